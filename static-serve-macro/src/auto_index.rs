@@ -0,0 +1,171 @@
+//! Generation of HTML pages listing the files of every embedded directory,
+//! like the autoindex of nginx
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write as _,
+    path::Path,
+};
+
+use proc_macro2::Span;
+use syn::LitByteStr;
+
+use crate::{
+    EmbeddedFileInfo, ImmutableAliases, OptionBytesSlice, OptionStr, StoredBody, error::Error,
+    etag::EtagScheme, options::IndexFiles, percent::decode_route, validators, with_charset,
+};
+
+/// The entries of a listed directory
+#[derive(Default)]
+struct Listing {
+    /// The names of the subdirectories
+    dirs: BTreeSet<String>,
+    /// The names and sizes of the files
+    files: BTreeMap<String, usize>,
+}
+
+/// Build the index pages of the directories of `assets`, served at the routes
+/// of the directories with a trailing slash, unless an asset is served there
+/// already (e.g. an `index.html` stripped by `strip_html_ext`) or the
+/// directory has one of the `index_files`
+pub(crate) fn index_assets(
+    assets: &[EmbeddedFileInfo],
+    index_files: &IndexFiles,
+    charset: Option<&str>,
+    last_modified_only: bool,
+    etag_scheme: EtagScheme<'_>,
+) -> Result<Vec<EmbeddedFileInfo>, Error> {
+    let mut listings: BTreeMap<String, Listing> = BTreeMap::new();
+    let mut file_paths: BTreeMap<String, Vec<&Path>> = BTreeMap::new();
+    for asset in assets {
+        // The protected, signed, hidden and gated files aren't listed on the public index pages
+        let Some(route) = asset.entry_path.as_ref().filter(|_| asset.is_public()) else {
+            continue;
+        };
+        let Some((dir, name)) = route.rsplit_once('/') else {
+            continue;
+        };
+        let dir = format!("{dir}/");
+        if !name.is_empty() {
+            listings
+                .entry(dir.clone())
+                .or_default()
+                .files
+                .insert(name.to_owned(), asset.lit_byte_str_contents.value().len());
+        }
+        if let Some(file_path) = &asset.file_path {
+            file_paths
+                .entry(dir.clone())
+                .or_default()
+                .push(Path::new(file_path));
+        }
+
+        // Register the directory in all of its ancestors
+        let mut child = dir;
+        while let Some((parent, name)) = child.trim_end_matches('/').rsplit_once('/') {
+            let parent = format!("{parent}/");
+            listings
+                .entry(parent.clone())
+                .or_default()
+                .dirs
+                .insert(name.to_owned());
+            child = parent;
+        }
+    }
+
+    let mut index_assets = Vec::new();
+    for (dir, listing) in listings {
+        if assets
+            .iter()
+            .any(|asset| asset.entry_path.as_deref() == Some(dir.as_str()))
+            || index_files.find(&dir, assets).is_some()
+        {
+            continue;
+        }
+
+        let html = index_page(&dir, &listing);
+        let (etag_str, last_modified) = validators(
+            html.as_bytes(),
+            file_paths.remove(&dir).unwrap_or_default(),
+            last_modified_only,
+            etag_scheme,
+        )?;
+        index_assets.push(EmbeddedFileInfo {
+            entry_path: Some(dir),
+            content_type: with_charset("text/html".to_owned(), charset),
+            etag_str: OptionStr(etag_str),
+            last_modified: OptionStr(last_modified),
+            lit_byte_str_contents: LitByteStr::new(html.as_bytes(), Span::call_site()),
+            maybe_gzip: OptionBytesSlice(None),
+            maybe_zstd: OptionBytesSlice(None),
+            maybe_brotli: OptionBytesSlice(None),
+            cache_busted: false,
+            immutable_aliases: ImmutableAliases::default(),
+            cache_control: OptionStr(None),
+            headers: Vec::new(),
+            stored_body: StoredBody::Identity,
+            image_variants: Vec::new(),
+            media_variants: Vec::new(),
+            integrity: OptionStr(None),
+            cors: Vec::new(),
+            encoding_preference: Vec::new(),
+            file_path: None,
+            stream_chunk_size: None,
+            basic_auth: None,
+            signed: false,
+            source_map_gate: None,
+            cfg_gate: None,
+        });
+    }
+    Ok(index_assets)
+}
+
+/// Render the index page of the directory at `route`
+///
+/// The routes and names are percent-encoded, the links keep them as is
+/// while the text shows them decoded.
+fn index_page(route: &str, listing: &Listing) -> String {
+    let title = format!("Index of {}", escape_html(&decode_route(route)));
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n</head>\n<body>\n<h1>{title}</h1>\n<table>\n"
+    );
+    if route != "/" {
+        html.push_str("<tr><td><a href=\"../\">../</a></td><td></td></tr>\n");
+    }
+    for dir in &listing.dirs {
+        let href = escape_html(dir);
+        let name = escape_html(&decode_route(dir));
+        writeln!(
+            html,
+            "<tr><td><a href=\"./{href}/\">{name}/</a></td><td>-</td></tr>"
+        )
+        .expect("writing to a String cannot fail");
+    }
+    for (file, size) in &listing.files {
+        let href = escape_html(file);
+        let name = escape_html(&decode_route(file));
+        writeln!(
+            html,
+            "<tr><td><a href=\"./{href}\">{name}</a></td><td>{size}</td></tr>"
+        )
+        .expect("writing to a String cannot fail");
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+/// Escape the characters of `value` with a special meaning in HTML
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,189 @@
+//! Enforcement of the size budgets of the embedded files,
+//! from `max_file_size` and `max_total_size`
+
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use proc_macro2::TokenStream;
+
+use syn::{
+    Lit, LitStr,
+    parse::{Parse, ParseStream},
+};
+
+use crate::{AssetEntry, compile_warning, error::Error};
+
+/// A size in bytes, such as `"8MB"`, `"512 KiB"` or `1048576`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct ByteSize(u64);
+
+impl Parse for ByteSize {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let size = match input.parse()? {
+            Lit::Int(bytes) => bytes.base10_parse()?,
+            Lit::Str(size) => parse_size(&size)?,
+            lit => {
+                return Err(syn::Error::new(
+                    lit.span(),
+                    "Expected a size such as `\"8MB\"`, or a number of bytes",
+                ));
+            }
+        };
+        Ok(Self(size))
+    }
+}
+
+impl ByteSize {
+    /// The size in bytes
+    pub(crate) fn bytes(self) -> u64 {
+        self.0
+    }
+}
+
+/// Parse a size such as `"8MB"`, `"1.5 GB"` or `"512KiB"`
+#[expect(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn parse_size(size: &LitStr) -> syn::Result<u64> {
+    let value = size.value();
+    let value = value.trim();
+    let unit_start = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(unit_start);
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        _ => {
+            return Err(syn::Error::new(
+                size.span(),
+                "The unit of the size must be `B`, `KB`, `MB`, `GB`, `KiB`, `MiB` or `GiB`",
+            ));
+        }
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_| syn::Error::new(size.span(), "Expected a size such as `\"8MB\"`"))?;
+    Ok((number * multiplier as f64).round() as u64)
+}
+
+/// The size budgets of the embedded files
+#[derive(Default)]
+pub(crate) struct SizeBudgets {
+    pub(crate) max_file_size: Option<ByteSize>,
+    pub(crate) max_total_size: Option<ByteSize>,
+    /// Warn about the files over budget rather than failing the compilation
+    pub(crate) warn: bool,
+}
+
+impl SizeBudgets {
+    /// Check the sizes of the files of `entries` and of their `variants`,
+    /// before compression, returning a warning to emit when over budget
+    /// in warning mode
+    pub(crate) fn check(
+        &self,
+        entries: &[AssetEntry],
+        variants: &[Vec<PathBuf>],
+    ) -> Result<Option<TokenStream>, Error> {
+        if self.max_file_size.is_none() && self.max_total_size.is_none() {
+            return Ok(None);
+        }
+
+        let mut files = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            files.push((entry.relative_path.clone(), file_size(&entry.path)?));
+            for variant in variants.get(index).into_iter().flatten() {
+                let relative_path = Path::new(&entry.relative_path)
+                    .with_file_name(variant.file_name().unwrap_or_default());
+                files.push((
+                    relative_path.to_string_lossy().into_owned(),
+                    file_size(variant)?,
+                ));
+            }
+        }
+
+        let mut report = String::new();
+        if let Some(ByteSize(max_file_size)) = self.max_file_size {
+            files.sort_by(|(path, size), (other_path, other_size)| {
+                other_size.cmp(size).then_with(|| path.cmp(other_path))
+            });
+            for (path, size) in files.iter().filter(|(_, size)| *size > max_file_size) {
+                writeln!(
+                    report,
+                    "  {path}: {} (max_file_size = {})",
+                    format_size(*size),
+                    format_size(max_file_size)
+                )
+                .expect("writing to a String cannot fail");
+            }
+        }
+        let total_size = files.iter().map(|(_, size)| size).sum();
+        if let Some(ByteSize(max_total_size)) = self.max_total_size
+            && total_size > max_total_size
+        {
+            writeln!(
+                report,
+                "  total: {} (max_total_size = {})",
+                format_size(total_size),
+                format_size(max_total_size)
+            )
+            .expect("writing to a String cannot fail");
+        }
+
+        if report.is_empty() {
+            return Ok(None);
+        }
+        let report = format!(
+            "The embedded files exceed their size budgets:\n{}",
+            report.trim_end()
+        );
+        if !self.warn {
+            return Err(Error::OverBudget(report));
+        }
+        Ok(Some(compile_warning("OVER_SIZE_BUDGET", &report)))
+    }
+}
+
+fn file_size(path: &Path) -> Result<u64, Error> {
+    fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .map_err(Error::CannotReadEntryContents)
+}
+
+/// Format `size` in bytes with decimal units, e.g. `1.5 MB`
+#[expect(clippy::cast_precision_loss)]
+fn format_size(size: u64) -> String {
+    const UNITS: [(&str, u64); 3] = [("GB", 1_000_000_000), ("MB", 1_000_000), ("KB", 1_000)];
+    UNITS.iter().find(|(_, unit)| size >= *unit).map_or_else(
+        || format!("{size} B"),
+        |(name, unit)| format!("{:.1} {name}", size as f64 / *unit as f64),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ByteSize, format_size};
+
+    #[test]
+    fn parses_and_formats_sizes() {
+        let size = |input: &str| syn::parse_str::<ByteSize>(input).unwrap().0;
+        assert_eq!(size(r#""8MB""#), 8_000_000);
+        assert_eq!(size(r#""1.5 GB""#), 1_500_000_000);
+        assert_eq!(size(r#""512KiB""#), 512 * 1024);
+        assert_eq!(size("1048576"), 1_048_576);
+        assert!(syn::parse_str::<ByteSize>(r#""8 parsecs""#).is_err());
+
+        assert_eq!(format_size(999), "999 B");
+        assert_eq!(format_size(1_500_000), "1.5 MB");
+    }
+}
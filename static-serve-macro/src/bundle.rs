@@ -0,0 +1,113 @@
+//! Storage of the embedded bytes in a packed bundle file written at
+//! expansion time, memory-mapped by the runtime, for `bundle`
+//!
+//! The bundle starts with the SHA-256 hash of its contents, followed by
+//! the bytes of the assets, each of them written once. The generated code
+//! holds the offset and length of every body, so that the routes and
+//! validation stay the same as with the bytes in the binary.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs,
+    path::{self, PathBuf},
+};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use sha2::{Digest as _, Sha256};
+
+use crate::error::Error;
+
+/// The size of the header of the bundle: the SHA-256 hash of its contents
+const HEADER_SIZE: usize = 32;
+
+/// A bundle file being filled with the embedded bytes
+pub(crate) struct Bundle {
+    path: PathBuf,
+    /// The bytes appended after the header so far
+    contents: RefCell<Vec<u8>>,
+    /// The offsets of the bytes in the bundle, by their hash
+    offsets: RefCell<HashMap<Vec<u8>, usize>>,
+}
+
+impl Bundle {
+    /// The bundle written to `path`, made absolute since the executables
+    /// don't run from the working directory of the compiler
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path: path::absolute(&path).unwrap_or(path),
+            contents: RefCell::default(),
+            offsets: RefCell::default(),
+        }
+    }
+
+    /// The `&'static [u8]` expression reading `bytes`
+    /// from the bundle, appending them unless already there
+    pub(crate) fn bytes(&self, bytes: &[u8]) -> TokenStream {
+        let mut contents = self.contents.borrow_mut();
+        let offset = *self
+            .offsets
+            .borrow_mut()
+            .entry(Sha256::digest(bytes).to_vec())
+            .or_insert_with(|| {
+                let offset = HEADER_SIZE + contents.len();
+                contents.extend_from_slice(bytes);
+                offset
+            });
+        let len = bytes.len();
+        quote! { __STATIC_SERVE_BUNDLE.slice(#offset, #len) }
+    }
+
+    /// Write the bundle, unless it's already up to date, and generate
+    /// the `__STATIC_SERVE_BUNDLE` static mapping it at runtime
+    pub(crate) fn write(&self) -> Result<TokenStream, Error> {
+        let contents = self.contents.borrow();
+        let hash = Sha256::digest(contents.as_slice());
+        let mut bundle = Vec::with_capacity(HEADER_SIZE + contents.len());
+        bundle.extend_from_slice(&hash);
+        bundle.extend_from_slice(&contents);
+
+        if fs::read(&self.path).ok().as_deref() != Some(bundle.as_slice()) {
+            if let Some(parent) = self.path.parent() {
+                fs::create_dir_all(parent).map_err(Error::CannotWriteBundle)?;
+            }
+            fs::write(&self.path, &bundle).map_err(Error::CannotWriteBundle)?;
+        }
+
+        let path = self.path.to_str().ok_or(Error::FilePathIsNotUtf8)?;
+        let file_name = self
+            .path
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .ok_or(Error::FilePathIsNotUtf8)?;
+        let hash = hash.iter();
+        Ok(quote! {
+            static __STATIC_SERVE_BUNDLE: ::static_serve::Bundle =
+                ::static_serve::Bundle::new(#file_name, #path, [#(#hash),*]);
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{Bundle, HEADER_SIZE};
+
+    #[test]
+    fn writes_each_body_once() {
+        let path = std::env::temp_dir().join("static-serve-bundle-test.bundle");
+        let bundle = Bundle::new(path.clone());
+
+        let first = bundle.bytes(b"first").to_string();
+        let second = bundle.bytes(b"second").to_string();
+        assert_eq!(bundle.bytes(b"first").to_string(), first);
+        assert!(first.contains(&format!("slice ({HEADER_SIZE}usize , 5usize)")));
+        assert!(second.contains(&format!("slice ({}usize , 6usize)", HEADER_SIZE + 5)));
+
+        bundle.write().unwrap();
+        let written = fs::read(&path).unwrap();
+        assert_eq!(&written[HEADER_SIZE..], b"firstsecond");
+    }
+}
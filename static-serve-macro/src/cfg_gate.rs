@@ -0,0 +1,174 @@
+//! Conditional embedding of the files under the `cfg` predicates of
+//! `cfg_gate`, e.g. the assets of an admin UI only embedded when a
+//! cargo feature is enabled
+//!
+//! The excluded assets keep their slot in the table of the assets, as
+//! empty placeholders, so that the indices computed at expansion time
+//! stay valid whatever the predicates evaluate to.
+
+use std::path::{Path, PathBuf};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    LitStr, Meta, Token, bracketed, parenthesized,
+    parse::{Parse, ParseStream},
+};
+
+use crate::{
+    EmbeddedFileInfo,
+    options::{AssetsDir, find_in_assets_dirs},
+};
+
+/// The subdirectories and files, relative to the assets
+/// directory, only embedded when their predicate holds
+#[derive(Default)]
+pub(crate) struct CfgGates(Vec<(PathBuf, LitStr, Meta)>);
+
+impl Parse for CfgGates {
+    /// Parse a list of tuples such as `[("admin", "feature = \"admin-ui\"")]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+
+        let mut gates = Vec::new();
+        while !inner_content.is_empty() {
+            let tuple_content;
+            parenthesized!(tuple_content in inner_content);
+            let path = tuple_content.parse::<LitStr>()?;
+            tuple_content.parse::<Token![,]>()?;
+            let predicate = tuple_content.parse::<LitStr>()?;
+            if !tuple_content.is_empty() {
+                tuple_content.parse::<Token![,]>()?;
+            }
+            let meta = predicate.parse::<Meta>().map_err(|_| {
+                syn::Error::new(
+                    predicate.span(),
+                    "Expected a `cfg` predicate, such as `\"feature = \\\"admin-ui\\\"\"`",
+                )
+            })?;
+            gates.push((PathBuf::from(path.value()), path, meta));
+
+            if !inner_content.is_empty() {
+                inner_content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(Self(gates))
+    }
+}
+
+impl CfgGates {
+    /// Check the gated paths exist in the assets directories
+    pub(crate) fn validate(&self, assets_dirs: &[AssetsDir]) -> syn::Result<()> {
+        for (path, lit, _) in &self.0 {
+            if find_in_assets_dirs(assets_dirs, path).is_err() {
+                return Err(syn::Error::new(
+                    lit.span(),
+                    format!("The gated path {} does not exist", path.display()),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The predicate of the file at `relative_path`, which must hold
+    /// for it to be embedded: those of all the gated paths containing it
+    pub(crate) fn predicate(&self, relative_path: &Path) -> Option<TokenStream> {
+        let predicates: Vec<&Meta> = self
+            .0
+            .iter()
+            .filter(|(path, ..)| relative_path.starts_with(path))
+            .map(|(.., predicate)| predicate)
+            .collect();
+        match predicates.as_slice() {
+            [] => None,
+            [predicate] => Some(quote! { #predicate }),
+            predicates => Some(quote! { all(#(#predicates),*) }),
+        }
+    }
+}
+
+/// Wrap the `static_asset` expression of an asset only embedded when
+/// `predicate` holds, replaced by an empty placeholder otherwise
+///
+/// The expression is built by a function, `const` unless the table of the
+/// assets is built at startup, so that the bodies of the excluded assets
+/// aren't compiled at all.
+pub(crate) fn gated_asset(
+    static_asset: &TokenStream,
+    predicate: &TokenStream,
+    constness: Option<&TokenStream>,
+) -> TokenStream {
+    quote! {{
+        #[cfg(#predicate)]
+        #constness fn __static_serve_gated() -> ::static_serve::StaticAsset {
+            #static_asset
+        }
+        #[cfg(not(#predicate))]
+        #constness fn __static_serve_gated() -> ::static_serve::StaticAsset {
+            ::static_serve::StaticAsset::EXCLUDED
+        }
+        __static_serve_gated()
+    }}
+}
+
+/// Generate `__STATIC_SERVE_INCLUDED`, whether each of the `assets`
+/// is embedded given the predicates of their gates
+pub(crate) fn included_flags(assets: &[EmbeddedFileInfo]) -> TokenStream {
+    let assets_len = assets.len();
+    let flags = assets.iter().map(|asset| {
+        asset
+            .cfg_gate
+            .as_ref()
+            .map_or_else(|| quote! { true }, |predicate| quote! { cfg!(#predicate) })
+    });
+    quote! {
+        const __STATIC_SERVE_INCLUDED: [bool; #assets_len] = [#(#flags),*];
+    }
+}
+
+/// The expression of a constant `&[&str]` holding the `routes` whose
+/// predicate holds, in order, or all of them without predicates
+pub(crate) fn gated_routes(routes: &[(&str, Option<&TokenStream>)]) -> TokenStream {
+    if routes.iter().all(|(_, predicate)| predicate.is_none()) {
+        let routes = routes.iter().map(|(route, _)| route);
+        return quote! { &[#(#routes),*] };
+    }
+
+    let routes_len = routes.len();
+    let routes = routes.iter().map(|(route, predicate)| {
+        predicate.map_or_else(
+            || quote! { (#route, true) },
+            |predicate| quote! { (#route, cfg!(#predicate)) },
+        )
+    });
+    quote! {{
+        const ROUTES: [(&str, bool); #routes_len] = [#(#routes),*];
+        const LEN: usize = {
+            let mut len = 0;
+            let mut index = 0;
+            while index < ROUTES.len() {
+                if ROUTES[index].1 {
+                    len += 1;
+                }
+                index += 1;
+            }
+            len
+        };
+        const INCLUDED: [&str; LEN] = {
+            let mut included = [""; LEN];
+            let mut len = 0;
+            let mut index = 0;
+            while index < ROUTES.len() {
+                if ROUTES[index].1 {
+                    included[len] = ROUTES[index].0;
+                    len += 1;
+                }
+                index += 1;
+            }
+            included
+        };
+        &INCLUDED
+    }}
+}
@@ -0,0 +1,59 @@
+//! Generation of the Content Security Policy hashes of the embedded
+//! scripts and styles, returned by the `csp_hashes` function
+
+use std::collections::BTreeSet;
+
+use base64::{Engine as _, prelude::BASE64_STANDARD};
+use proc_macro2::TokenStream;
+use quote::quote;
+use sha2::{Digest as _, Sha256};
+use syn::Visibility;
+
+use crate::{EmbeddedFileInfo, media_type};
+
+/// Generate the `csp_hashes` function, returning the `'sha256-...'` sources
+/// of the JavaScript and CSS `assets`, for `script-src` and `style-src`
+pub(crate) fn csp_hashes_fn(assets: &[EmbeddedFileInfo], vis: &Visibility) -> TokenStream {
+    let mut script_src = BTreeSet::new();
+    let mut style_src = BTreeSet::new();
+    for asset in assets {
+        let sources = match media_type(&asset.content_type) {
+            "text/javascript" | "application/javascript" => &mut script_src,
+            "text/css" => &mut style_src,
+            _ => continue,
+        };
+        sources.insert(hash_source(&asset.lit_byte_str_contents.value()));
+    }
+
+    quote! {
+        #[allow(dead_code)]
+        #vis fn csp_hashes() -> ::static_serve::CspHashes {
+            ::static_serve::CspHashes {
+                script_src: &[#(#script_src),*],
+                style_src: &[#(#style_src),*],
+            }
+        }
+    }
+}
+
+/// The `'sha256-...'` source of a CSP directive allowing `contents`
+fn hash_source(contents: &[u8]) -> String {
+    format!(
+        "'sha256-{}'",
+        BASE64_STANDARD.encode(Sha256::digest(contents))
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::hash_source;
+
+    #[test]
+    fn hashes_like_browsers() {
+        // The hash from the CSP specification's example of `alert('Hello, world.');`
+        assert_eq!(
+            hash_source(b"alert('Hello, world.');"),
+            "'sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng='"
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! Compression of the embedded bodies against a zstd dictionary shared
+//! by all the assets, decompressed at startup, for `zstd_dictionary = true`
+
+use proc_macro2::Span;
+use syn::LitByteStr;
+use zstd::bulk::Compressor;
+
+use crate::{
+    EmbeddedFileInfo, StoredBody,
+    error::{Error, ZstdType},
+    maybe_get_compressed,
+};
+
+/// The maximum size of the dictionary, the default of the zstd CLI
+const MAX_DICTIONARY_SIZE: usize = 112_640;
+
+/// Train a dictionary on the bodies of `assets`, and embed the bodies
+/// it compresses significantly in their compressed form instead
+pub(crate) fn compress_with_dictionary(
+    assets: &mut [EmbeddedFileInfo],
+    level: i32,
+) -> Result<LitByteStr, Error> {
+    let samples: Vec<Vec<u8>> = assets
+        .iter()
+        .map(|asset| asset.lit_byte_str_contents.value())
+        .collect();
+    let dictionary = zstd::dict::from_samples(&samples, MAX_DICTIONARY_SIZE)
+        .unwrap_or_else(|_| raw_dictionary(&samples));
+
+    let mut compressor = Compressor::with_dictionary(level, &dictionary)
+        .map_err(|e| Error::Zstd(ZstdType::Dictionary(e)))?;
+    for (asset, sample) in assets.iter_mut().zip(&samples) {
+        // The bodies already embedded compressed only
+        if !matches!(asset.stored_body, StoredBody::Identity) {
+            continue;
+        }
+        let compressed = compressor
+            .compress(sample)
+            .map_err(|e| Error::Zstd(ZstdType::EncoderWrite(e)))?;
+        if let Some(compressed) = maybe_get_compressed(&compressed, sample) {
+            asset.stored_body = StoredBody::Dictionary(compressed);
+        }
+    }
+
+    Ok(LitByteStr::new(&dictionary, Span::call_site()))
+}
+
+/// The training fails with too few samples, e.g. in small asset trees,
+/// but zstd can still use the end of their contents as a raw dictionary
+fn raw_dictionary(samples: &[Vec<u8>]) -> Vec<u8> {
+    let mut dictionary = samples.concat();
+    let excess = dictionary.len().saturating_sub(MAX_DICTIONARY_SIZE);
+    dictionary.drain(..excess);
+    dictionary
+}
@@ -0,0 +1,130 @@
+//! The environment variables read at expansion time, tracked with
+//! `option_env!` so that Cargo expands the macros again when they change
+
+use std::{cell::RefCell, collections::BTreeSet, env, ffi::OsString};
+
+use display_full_error::DisplayFullError;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+
+thread_local! {
+    /// The names of the variables read by the current invocation
+    static READ: RefCell<BTreeSet<String>> = const { RefCell::new(BTreeSet::new()) };
+}
+
+/// Start tracking the variables read by a new invocation
+pub(crate) fn reset() {
+    READ.with_borrow_mut(BTreeSet::clear);
+}
+
+/// Track the variable `name`, read by the current invocation
+pub(crate) fn track(name: &str) {
+    READ.with_borrow_mut(|read| read.insert(name.to_owned()));
+}
+
+/// The value of the variable `name`, tracked
+pub(crate) fn var_os(name: &str) -> Option<OsString> {
+    track(name);
+    env::var_os(name)
+}
+
+/// The items making Cargo expand the invocation again when one of the
+/// variables it read changes, as `option_env!` tracks them
+pub(crate) fn tracked() -> TokenStream {
+    READ.with_borrow_mut(|read| {
+        read.iter()
+            .map(|name| {
+                quote! {
+                    const _: ::core::option::Option<&str> = ::core::option_env!(#name);
+                }
+            })
+            .collect()
+    })
+}
+
+/// Replace `$VAR` and `${VAR}` with the values of the environment variables,
+/// such as `$OUT_DIR` for the files generated by a build script, and `$$`
+/// with a literal `$`
+pub(crate) fn expand(value: &str, span: Span) -> syn::Result<String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
+        if let Some(after) = rest.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+        let (name, after) = if let Some(braced) = rest.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| syn::Error::new(span, "Unclosed `${` in path"))?;
+            (&braced[..end], &braced[end + 1..])
+        } else {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            rest.split_at(end)
+        };
+        if name.is_empty() {
+            return Err(syn::Error::new(
+                span,
+                "Expected an environment variable name after `$` in path",
+            ));
+        }
+        let var = var_os(name)
+            .ok_or(env::VarError::NotPresent)
+            .and_then(|var| var.into_string().map_err(env::VarError::NotUnicode))
+            .map_err(|e| {
+                syn::Error::new(
+                    span,
+                    format!(
+                        "Cannot expand the environment variable {name} in path: {}",
+                        DisplayFullError(&e)
+                    ),
+                )
+            })?;
+        expanded.push_str(&var);
+        rest = after;
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod test {
+    use proc_macro2::Span;
+    use quote::quote;
+
+    use super::{expand, reset, tracked};
+
+    #[test]
+    fn expands_and_tracks_the_variables() {
+        reset();
+        let expanded = expand(
+            "$CARGO_PKG_NAME/${CARGO_PKG_NAME}/$$HOME",
+            Span::call_site(),
+        );
+        assert_eq!(
+            expanded.unwrap(),
+            "static-serve-macro/static-serve-macro/$HOME"
+        );
+        assert_eq!(
+            tracked().to_string(),
+            quote! {
+                const _: ::core::option::Option<&str> = ::core::option_env!("CARGO_PKG_NAME");
+            }
+            .to_string()
+        );
+
+        reset();
+        let error = expand("$STATIC_SERVE_SURELY_UNSET", Span::call_site()).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Cannot expand the environment variable STATIC_SERVE_SURELY_UNSET in path: environment variable not found"
+        );
+        assert!(tracked().to_string().contains("STATIC_SERVE_SURELY_UNSET"));
+        assert!(expand("$", Span::call_site()).is_err());
+    }
+}
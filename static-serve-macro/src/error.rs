@@ -2,9 +2,9 @@ use std::{
     ffi::{OsStr, OsString},
     fmt::{Display, Formatter},
     io,
+    path::PathBuf,
 };
 
-use glob::{GlobError, PatternError};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -23,22 +23,74 @@ pub(crate) enum Error {
     InvalidUnicodeInDirectoryName,
     #[error("Cannot canonicalize ignore path")]
     CannotCanonicalizeIgnorePath(#[source] io::Error),
-    #[error("Invalid unicode in entry name")]
-    InvalidUnicodeInEntryName,
     #[error("Error while compressing with gzip")]
     Gzip(#[from] GzipType),
     #[error("Error while compressing with zstd")]
     Zstd(#[from] ZstdType),
+    #[error("Error while compressing with Brotli")]
+    Brotli(#[from] BrotliType),
     #[error("Error while reading entry contents")]
     CannotReadEntryContents(#[source] io::Error),
-    #[error("Error while parsing glob pattern")]
-    Pattern(#[source] PatternError),
-    #[error("Error reading path for glob")]
-    Glob(#[source] GlobError),
-    #[error("Cannot get entry metadata")]
-    CannotGetMetadata(#[source] io::Error),
+    #[error("Cannot read assets directory")]
+    CannotReadDirectory(#[source] io::Error),
+    #[error("Path of asset file {} is not valid UTF-8, set `non_utf8_paths` to skip or encode it", .0.display())]
+    NonUtf8Path(PathBuf),
+    #[error("The symbolic link {} points to its ancestor directory {}", .link.display(), .ancestor.display())]
+    SymlinkLoop { link: PathBuf, ancestor: PathBuf },
     #[error("Cannot canonicalize directory for cache-busting")]
     CannotCanonicalizeCacheBustedDir(#[source] io::Error),
+    #[error("Cannot get the modification time of asset file")]
+    CannotGetModifiedTime(#[source] io::Error),
+    #[error("The modification time of asset file is before the UNIX epoch")]
+    ModifiedTimeBeforeUnixEpoch,
+    #[error("Path {0} is too long to be stored in the tarball")]
+    TarballPathTooLong(String),
+    #[error("Cannot write the route definitions file")]
+    CannotWriteRouteDefinitions(#[source] io::Error),
+    #[error("Cannot write the report file")]
+    CannotWriteReport(#[source] io::Error),
+    #[error("The fallback page is not embedded, is it in an ignored path?")]
+    FallbackPageNotEmbedded,
+    #[error("Cannot read the manifest file")]
+    CannotReadManifest(#[source] io::Error),
+    #[error("Invalid manifest: {0}")]
+    InvalidManifest(toml::de::Error),
+    #[error("Cannot write the embedded bytes to OUT_DIR")]
+    CannotWriteOutDir(#[source] io::Error),
+    #[error("Cannot write the bundle file")]
+    CannotWriteBundle(#[source] io::Error),
+    #[error("Cannot write the expanded code")]
+    CannotWriteExpansion(#[source] io::Error),
+    #[error("The pre-compressed file {} doesn't decompress to the original file", .0.display())]
+    PrecompressedMismatch(PathBuf),
+    #[error("The preloaded file {0} is not embedded, is it in an ignored path?")]
+    PreloadNotEmbedded(String),
+    #[error("The routes of the preloaded files cannot be sent in a `Link` header: {0}")]
+    InvalidPreloadLink(String),
+    #[error("The file {0} is in several assets directories")]
+    ConflictingAssets(String),
+    #[error("The placeholder {{{{{name}}}}} of {} has no value in `template_vars` nor in the environment", .path.display())]
+    UnresolvedPlaceholder { name: String, path: PathBuf },
+    #[error("{first} and {second} are both served at {route}")]
+    DuplicateRoute {
+        route: String,
+        first: String,
+        second: String,
+    },
+    #[error("{0}")]
+    OverBudget(String),
+    #[cfg(feature = "image-encoding")]
+    #[error("Cannot decode the image {} to encode its variants", .0.display())]
+    CannotDecodeImage(PathBuf, #[source] image::ImageError),
+    #[cfg(feature = "image-encoding")]
+    #[error("Cannot encode a variant of the image {}: {}", .0.display(), .1)]
+    CannotEncodeImage(PathBuf, String),
+    #[error("{first} and {second} both have the route constant `paths::{name}`")]
+    DuplicateConstant {
+        name: String,
+        first: String,
+        second: String,
+    },
 }
 
 struct UnknownFileExtension<'a>(Option<&'a OsStr>);
@@ -61,14 +113,30 @@ pub(crate) enum GzipType {
     CompressorWrite(#[source] io::Error),
     #[error("The encoder could not complete the `finish` procedure")]
     EncoderFinish(#[source] io::Error),
+    #[error("The compressed body does not decompress to the original contents")]
+    RoundTrip,
 }
 
 #[derive(Debug, Error)]
 pub(crate) enum ZstdType {
     #[error("The encoder could not write")]
     EncoderWrite(#[source] io::Error),
+    #[error("The shared dictionary could not be loaded")]
+    Dictionary(#[source] io::Error),
     #[error("The encoder could not complete the `finish` procedure")]
     EncoderFinish(#[source] io::Error),
+    #[error(
+        "The compressed body does not decompress to the original contents within the 8 MiB window of the browsers"
+    )]
+    RoundTrip,
+}
+
+#[derive(Debug, Error)]
+pub(crate) enum BrotliType {
+    #[error("The compressor could not write")]
+    CompressorWrite(#[source] io::Error),
+    #[error("The compressed body does not decompress to the original contents")]
+    RoundTrip,
 }
 
 #[cfg(test)]
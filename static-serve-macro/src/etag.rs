@@ -0,0 +1,178 @@
+//! Computation of the `ETag` of the assets, with the algorithm
+//! selected by the `etag` option and the `etag_salt`
+
+use std::ops::BitXor;
+
+use base64::{Engine as _, prelude::BASE64_STANDARD};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use syn::{
+    LitStr,
+    parse::{Parse, ParseStream},
+};
+
+/// The hash algorithm of the `ETag`s
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum EtagAlgorithm {
+    /// SHA-256 folded into 64 bits, the shortest header
+    #[default]
+    Sha256Folded,
+    /// SHA-1 folded into 64 bits, for the `ETag`s of older releases
+    Sha1Folded,
+    /// The full hashes, formatted as Subresource Integrity hashes
+    Sha256,
+    Sha384,
+    Sha512,
+    /// The full BLAKE3 hash, formatted like the other full hashes
+    /// but not listed as a Subresource Integrity hash
+    Blake3,
+}
+
+impl Parse for EtagAlgorithm {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let algorithm: LitStr = input.parse()?;
+        match algorithm.value().as_str() {
+            "sha256-64" => Ok(Self::Sha256Folded),
+            "sha1-64" => Ok(Self::Sha1Folded),
+            "sha256" => Ok(Self::Sha256),
+            "sha384" => Ok(Self::Sha384),
+            "sha512" => Ok(Self::Sha512),
+            "blake3" => Ok(Self::Blake3),
+            _ => Err(syn::Error::new(
+                algorithm.span(),
+                "Expected `\"sha256-64\"`, `\"sha1-64\"`, `\"sha256\"`, `\"sha384\"`, `\"sha512\"` or `\"blake3\"`",
+            )),
+        }
+    }
+}
+
+impl EtagAlgorithm {
+    /// The full `sha256-...` or `blake3-...` hash of `contents`,
+    /// mixed with `salt`, unless the hashes are folded
+    fn hash(self, salt: &str, contents: &[u8]) -> Option<String> {
+        fn sri<D: Digest>(prefix: &str, salt: &str, contents: &[u8]) -> String {
+            let mut hasher = D::new();
+            hasher.update(salt.as_bytes());
+            hasher.update(contents);
+            format!("{prefix}-{}", BASE64_STANDARD.encode(hasher.finalize()))
+        }
+
+        match self {
+            Self::Sha256Folded | Self::Sha1Folded => None,
+            Self::Sha256 => Some(sri::<Sha256>("sha256", salt, contents)),
+            Self::Sha384 => Some(sri::<Sha384>("sha384", salt, contents)),
+            Self::Sha512 => Some(sri::<Sha512>("sha512", salt, contents)),
+            Self::Blake3 => {
+                let mut hasher = blake3::Hasher::new();
+                hasher.update(salt.as_bytes());
+                hasher.update(contents);
+                Some(format!(
+                    "blake3-{}",
+                    BASE64_STANDARD.encode(hasher.finalize().as_bytes())
+                ))
+            }
+        }
+    }
+
+    /// The Subresource Integrity hash of `contents`, listed in the
+    /// manifest when the `ETag`s use the full SHA-2 hashes, the only
+    /// ones browsers check
+    pub(crate) fn integrity(self, contents: &[u8]) -> Option<String> {
+        match self {
+            Self::Sha256 | Self::Sha384 | Self::Sha512 => self.hash("", contents),
+            Self::Sha256Folded | Self::Sha1Folded | Self::Blake3 => None,
+        }
+    }
+}
+
+/// How the `ETag`s of the assets are computed
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct EtagScheme<'a> {
+    pub(crate) algorithm: EtagAlgorithm,
+    /// Mixed into every `ETag`, so they can be invalidated
+    /// without changing the contents of the files
+    pub(crate) salt: &'a str,
+}
+
+impl EtagScheme<'_> {
+    /// The quoted `ETag` of `contents`
+    pub(crate) fn etag(self, contents: &[u8]) -> String {
+        fn digest<D: Digest>(salt: &str, contents: &[u8]) -> Vec<u8> {
+            let mut hasher = D::new();
+            hasher.update(salt.as_bytes());
+            hasher.update(contents);
+            hasher.finalize().to_vec()
+        }
+
+        if let Some(hash) = self.algorithm.hash(self.salt, contents) {
+            return format!("\"{hash}\"");
+        }
+
+        let digest = match self.algorithm {
+            EtagAlgorithm::Sha1Folded => digest::<Sha1>(self.salt, contents),
+            _ => digest::<Sha256>(self.salt, contents),
+        };
+        format!("\"{:016x}\"", fold(&digest))
+    }
+}
+
+/// `digest` folded into 64 bits, by a XOR of its little-endian
+/// 8-byte words, the last one padded with zeros
+fn fold(digest: &[u8]) -> u64 {
+    digest
+        .chunks(8)
+        .map(|chunk| {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u64::from_le_bytes(word)
+        })
+        .fold(0, BitXor::bitxor)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{EtagAlgorithm, EtagScheme};
+
+    #[test]
+    fn formats_full_hashes_like_subresource_integrity() {
+        let scheme = EtagScheme {
+            algorithm: EtagAlgorithm::Sha256,
+            salt: "",
+        };
+        assert_eq!(
+            scheme.etag(b"abc"),
+            "\"sha256-ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=\""
+        );
+        assert_eq!(
+            EtagAlgorithm::Sha384.integrity(b"abc").unwrap(),
+            "sha384-ywB1P0WjXou1oD1pmsZQBycsMqsO3tFjGotgWkP/W+2AhgcroefMI1i67KE0yCWn"
+        );
+        assert_eq!(EtagAlgorithm::Sha256Folded.integrity(b"abc"), None);
+
+        let salted = EtagScheme {
+            salt: "v2",
+            ..scheme
+        };
+        assert_ne!(salted.etag(b"abc"), scheme.etag(b"abc"));
+        assert_eq!(EtagScheme::default().etag(b"abc").len(), 18);
+    }
+
+    #[test]
+    fn folds_or_spells_out_the_other_hashes() {
+        let etag = |algorithm| {
+            EtagScheme {
+                algorithm,
+                salt: "",
+            }
+            .etag(b"abc")
+        };
+        assert_eq!(etag(EtagAlgorithm::Sha256Folded), "\"f882b8b6a3c82aff\"");
+        assert_eq!(etag(EtagAlgorithm::Sha1Folded), "\"0643563fdac3778f\"");
+        assert_eq!(
+            etag(EtagAlgorithm::Blake3),
+            "\"blake3-ZDezrDhGUTP/tjt1JzqNtUjFWEZdedsD/TWcbNW9nYU=\""
+        );
+        // Browsers don't check BLAKE3 integrity hashes
+        assert_eq!(EtagAlgorithm::Blake3.integrity(b"abc"), None);
+    }
+}
@@ -0,0 +1,38 @@
+//! Font handling for `fonts = true`: the legacy formats of the fonts
+//! which have a WOFF2 sibling aren't embedded, and the fonts are served
+//! with an immutable `Cache-Control` and to all the origins
+
+use std::path::{Path, PathBuf};
+
+use crate::AssetEntry;
+
+/// The extensions of the font formats superseded by WOFF2
+const LEGACY_EXTENSIONS: [&str; 4] = ["woff", "ttf", "otf", "eot"];
+
+/// Whether the file at `path` is a font, judging by its extension
+pub(crate) fn is_font(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        extension.eq_ignore_ascii_case("woff2")
+            || LEGACY_EXTENSIONS
+                .iter()
+                .any(|ext| extension.eq_ignore_ascii_case(ext))
+    })
+}
+
+/// Remove the legacy fonts which have a `.woff2` sibling from `entries`,
+/// and cache-bust the remaining fonts
+pub(crate) fn apply(entries: &mut Vec<AssetEntry>) {
+    let paths: Vec<PathBuf> = entries.iter().map(|entry| entry.path.clone()).collect();
+    entries.retain(|entry| {
+        let is_legacy = entry.path.extension().is_some_and(|extension| {
+            LEGACY_EXTENSIONS
+                .iter()
+                .any(|ext| extension.eq_ignore_ascii_case(ext))
+        });
+        !(is_legacy && paths.contains(&entry.path.with_extension("woff2")))
+    });
+
+    for entry in entries.iter_mut().filter(|entry| is_font(&entry.path)) {
+        entry.cache_busted = true;
+    }
+}
@@ -0,0 +1,151 @@
+//! Modern encodings of the images for `image_variants = true`: the AVIF
+//! and WebP siblings of the PNG and JPEG images, e.g. `hero.avif` next
+//! to `hero.png`, are embedded as variants negotiated with `Accept`
+//!
+//! With the `image-encoding` feature, the variants without a
+//! sibling are encoded from the image at expansion time.
+
+use std::{
+    collections::HashSet,
+    mem,
+    path::{Path, PathBuf},
+};
+
+#[cfg(feature = "image-encoding")]
+use image::{DynamicImage, ExtendedColorType, ImageEncoder, codecs::avif::AvifEncoder};
+
+use crate::AssetEntry;
+#[cfg(feature = "image-encoding")]
+use crate::error::Error;
+
+/// The extensions of the images which can have variants
+const IMAGE_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// The extensions of the variants, in order of preference
+const VARIANT_EXTENSIONS: [&str; 2] = ["avif", "webp"];
+
+/// The quality of the encoded AVIF variants, from 1 to 100
+#[cfg(feature = "image-encoding")]
+const AVIF_QUALITY: u8 = 80;
+/// The speed of the AVIF encoder, from 1 (slowest) to 10
+#[cfg(feature = "image-encoding")]
+const AVIF_SPEED: u8 = 6;
+/// The quality of the encoded WebP variants, from 0 to 100
+#[cfg(feature = "image-encoding")]
+const WEBP_QUALITY: f32 = 80.0;
+
+/// A variant encoded from an image
+#[cfg(feature = "image-encoding")]
+pub(crate) struct EncodedVariant {
+    pub(crate) content_type: &'static str,
+    pub(crate) contents: Vec<u8>,
+}
+
+/// Remove the variants of the images from `entries`, and return
+/// the paths of the variants of each of the remaining entries
+pub(crate) fn remove_variants(entries: &mut Vec<AssetEntry>) -> Vec<Vec<PathBuf>> {
+    let variants: Vec<Vec<PathBuf>> = entries
+        .iter()
+        .map(|entry| {
+            if !is_image(&entry.path) {
+                return Vec::new();
+            }
+            VARIANT_EXTENSIONS
+                .iter()
+                .map(|ext| entry.path.with_extension(ext))
+                .filter(|variant| entries.iter().any(|entry| entry.path == *variant))
+                .collect()
+        })
+        .collect();
+
+    let removed: HashSet<&PathBuf> = variants.iter().flatten().collect();
+    let mut kept_entries = Vec::new();
+    let mut kept_variants = Vec::new();
+    for (entry, entry_variants) in mem::take(entries).into_iter().zip(&variants) {
+        if !removed.contains(&entry.path) {
+            kept_entries.push(entry);
+            kept_variants.push(entry_variants.clone());
+        }
+    }
+    *entries = kept_entries;
+    kept_variants
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| {
+        IMAGE_EXTENSIONS
+            .iter()
+            .any(|ext| extension.eq_ignore_ascii_case(ext))
+    })
+}
+
+/// Encode the variants of the image at `path` which have no sibling among
+/// `siblings`, keeping the ones smaller than its `original_size`
+///
+/// The lossy encodings only pay off for photos, the variants of
+/// flat graphics often being larger than their PNG.
+#[cfg(feature = "image-encoding")]
+pub(crate) fn encode_variants(
+    path: &Path,
+    siblings: &[PathBuf],
+    original_size: usize,
+) -> Result<Vec<EncodedVariant>, Error> {
+    let missing: Vec<&str> = VARIANT_EXTENSIONS
+        .into_iter()
+        .filter(|ext| !siblings.contains(&path.with_extension(ext)))
+        .collect();
+    if !is_image(path) || missing.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let image = image::open(path).map_err(|e| Error::CannotDecodeImage(path.to_owned(), e))?;
+    // Both encoders take 8-bit RGB(A) pixels
+    let image = if image.color().has_alpha() {
+        DynamicImage::ImageRgba8(image.into_rgba8())
+    } else {
+        DynamicImage::ImageRgb8(image.into_rgb8())
+    };
+
+    let mut variants = Vec::new();
+    for ext in missing {
+        let variant = match ext {
+            "avif" => EncodedVariant {
+                content_type: "image/avif",
+                contents: encode_avif(&image)
+                    .map_err(|e| Error::CannotEncodeImage(path.to_owned(), e.to_string()))?,
+            },
+            _ => EncodedVariant {
+                content_type: "image/webp",
+                contents: encode_webp(&image)
+                    .map_err(|e| Error::CannotEncodeImage(path.to_owned(), e.to_owned()))?,
+            },
+        };
+        if variant.contents.len() < original_size {
+            variants.push(variant);
+        }
+    }
+    Ok(variants)
+}
+
+#[cfg(feature = "image-encoding")]
+fn encode_avif(image: &DynamicImage) -> Result<Vec<u8>, image::ImageError> {
+    let color_type = if image.color().has_alpha() {
+        ExtendedColorType::Rgba8
+    } else {
+        ExtendedColorType::Rgb8
+    };
+    let mut avif = Vec::new();
+    AvifEncoder::new_with_speed_quality(&mut avif, AVIF_SPEED, AVIF_QUALITY).write_image(
+        image.as_bytes(),
+        image.width(),
+        image.height(),
+        color_type,
+    )?;
+    Ok(avif)
+}
+
+#[cfg(feature = "image-encoding")]
+fn encode_webp(image: &DynamicImage) -> Result<Vec<u8>, &str> {
+    let encoder = webp::Encoder::from_image(image)?;
+    Ok(encoder.encode(WEBP_QUALITY).to_vec())
+}
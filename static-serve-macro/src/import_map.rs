@@ -0,0 +1,145 @@
+//! Generation of an [import map](https://html.spec.whatwg.org/multipage/webappapis.html#import-maps)
+//! pointing the JavaScript modules to URLs which change with their contents
+
+use std::fs;
+
+use proc_macro2::Span;
+use syn::LitByteStr;
+
+use crate::{
+    AssetEntry, EmbeddedFileInfo, ImmutableAliases, OCTET_STREAM, OptionBytesSlice, OptionStr,
+    StoredBody,
+    error::Error,
+    etag::EtagScheme,
+    file_content_type,
+    options::{MimeOverrides, RouteMap},
+    route_definitions::string_literal,
+    validators,
+};
+
+/// Build the JSON import map of the JavaScript modules in `entries`
+///
+/// Every module is mapped to its route, or its custom one of `route_map`,
+/// under `prefix`, with the hash of its contents in the query string.
+/// Cache-busted modules are left out, since their file names are expected
+/// to be fingerprinted already.
+pub(crate) fn import_map(
+    entries: &[AssetEntry],
+    (prefix, route_map): (&str, &RouteMap),
+    etag_scheme: EtagScheme<'_>,
+) -> Result<String, Error> {
+    let mut imports = Vec::new();
+    for AssetEntry {
+        path,
+        relative_path: relative_entry,
+        route,
+        cache_busted,
+    } in entries
+    {
+        if *cache_busted
+            || file_content_type(path, Some(OCTET_STREAM), &MimeOverrides::default())?
+                != "text/javascript"
+        {
+            continue;
+        }
+
+        let route = route_map
+            .route(relative_entry)
+            .unwrap_or_else(|| route.clone());
+        let route = format!("{prefix}{route}");
+        let contents = fs::read(path).map_err(Error::CannotReadEntryContents)?;
+        let etag = etag_scheme.etag(&contents);
+        let hash = etag.trim_matches('"');
+        imports.push(format!(
+            "    {}: {}",
+            string_literal(&route),
+            string_literal(&format!("{route}?v={hash}"))
+        ));
+    }
+
+    if imports.is_empty() {
+        return Ok("{\n  \"imports\": {}\n}\n".to_owned());
+    }
+    Ok(format!(
+        "{{\n  \"imports\": {{\n{}\n  }}\n}}\n",
+        imports.join(",\n")
+    ))
+}
+
+/// Build the asset served at `route` with the JSON `import_map`
+///
+/// `entries` are the files the import map was built from.
+pub(crate) fn import_map_asset(
+    import_map: &str,
+    entries: &[AssetEntry],
+    route: String,
+    last_modified_only: bool,
+    etag_scheme: EtagScheme<'_>,
+) -> Result<EmbeddedFileInfo, Error> {
+    let (etag_str, last_modified) = validators(
+        import_map.as_bytes(),
+        entries.iter().map(|entry| entry.path.as_path()),
+        last_modified_only,
+        etag_scheme,
+    )?;
+
+    Ok(EmbeddedFileInfo {
+        entry_path: Some(route),
+        content_type: "application/importmap+json".to_owned(),
+        etag_str: OptionStr(etag_str),
+        last_modified: OptionStr(last_modified),
+        lit_byte_str_contents: LitByteStr::new(import_map.as_bytes(), Span::call_site()),
+        maybe_gzip: OptionBytesSlice(None),
+        maybe_zstd: OptionBytesSlice(None),
+        maybe_brotli: OptionBytesSlice(None),
+        cache_busted: false,
+        immutable_aliases: ImmutableAliases::default(),
+        cache_control: OptionStr(None),
+        headers: Vec::new(),
+        stored_body: StoredBody::Identity,
+        image_variants: Vec::new(),
+        media_variants: Vec::new(),
+        integrity: OptionStr(None),
+        cors: Vec::new(),
+        encoding_preference: Vec::new(),
+        file_path: None,
+        stream_chunk_size: None,
+        basic_auth: None,
+        signed: false,
+        source_map_gate: None,
+        cfg_gate: None,
+    })
+}
+
+/// Insert the `<script type="importmap">` element right after the opening
+/// `<head>` tag of `html`, so it precedes every module script
+///
+/// Documents without a `<head>` tag are left untouched.
+pub(crate) fn inject(html: &mut Vec<u8>, import_map: &str) {
+    let lowercase = html.to_ascii_lowercase();
+    let Some(head_start) = lowercase
+        .windows(b"<head".len())
+        .enumerate()
+        .find(|&(i, window)| {
+            // Don't match `<header>`
+            window == b"<head"
+                && matches!(
+                    lowercase.get(i + b"<head".len()),
+                    Some(b'>' | b' ' | b'\t' | b'\n' | b'\r')
+                )
+        })
+        .map(|(i, _)| i)
+    else {
+        return;
+    };
+    let Some(head_end) = lowercase[head_start..]
+        .iter()
+        .position(|&byte| byte == b'>')
+        .map(|position| head_start + position + 1)
+    else {
+        return;
+    };
+
+    let script = format!("<script type=\"importmap\">\n{import_map}</script>");
+    html.splice(head_end..head_end, script.into_bytes());
+}
@@ -2,69 +2,162 @@
 //! in a web server
 
 use std::{
+    collections::HashMap,
     convert::Into,
+    ffi::OsString,
+    fmt::Write as _,
     fs,
     io::{self, Write},
-    path::{Path, PathBuf},
+    num::NonZeroUsize,
+    panic,
+    path::{Component, Path, PathBuf, Prefix},
+    thread,
+    time::UNIX_EPOCH,
 };
 
 use display_full_error::DisplayFullError;
 use flate2::write::GzEncoder;
-use glob::glob;
 use proc_macro2::{Span, TokenStream};
-use quote::{ToTokens, quote};
-use sha2::{Digest as _, Sha256};
+use quote::{ToTokens, format_ident, quote};
 use syn::{
-    Ident, LitBool, LitByteStr, LitStr, Token, bracketed,
+    Ident, LitBool, LitByteStr, LitStr, Token, Visibility,
     parse::{Parse, ParseStream},
-    parse_macro_input,
+    parse_macro_input, parse_quote,
 };
 
+mod auto_index;
+mod budget;
+mod bundle;
+mod cfg_gate;
+mod csp;
+mod dictionary;
+mod env_vars;
 mod error;
-use error::{Error, GzipType, ZstdType};
+mod etag;
+mod fonts;
+// Shared with `static-serve`, which parses the dates
+#[path = "../../static-serve/src/http_date.rs"]
+#[cfg_attr(not(test), expect(dead_code))]
+mod http_date;
+mod images;
+mod import_map;
+mod lookup;
+mod manifest;
+mod media_variants;
+mod metadata;
+mod minify;
+mod options;
+mod out_dir;
+mod paths;
+// Shared with `static-serve`, which matches the requests with the routes
+#[path = "../../static-serve/src/percent.rs"]
+#[cfg_attr(not(test), expect(dead_code))]
+mod percent;
+mod precompressed;
+mod report;
+mod route_definitions;
+mod source_maps;
+mod tarball;
+mod template;
+use error::{BrotliType, Error, GzipType, ZstdType};
+use etag::{EtagAlgorithm, EtagScheme};
+use options::{
+    Algorithms, AssetsDir, AssetsDirs, CacheBustedPaths, CacheControls, Charset, CompressPolicy,
+    CompressionGroups, Cors, EmbedAssets, EncodingPreference, FallbackKind, GzipLevel, IgnorePaths,
+    ImportMapRoute, IndexFiles, IsCacheBusted, MaxAge, MimeOverrides, NonUtf8Paths, PathFilter,
+    Preload, RouteDefinitions, RouteMap, RoutePrefix, SecurityHeaders, ShouldCompress,
+    ShouldStripHtmlExt, StreamBodies, TarballRoute, TrailingSlash, ZstdLevel, flag,
+    is_header_value, is_manifest_relative, resolve_path, unknown_content_type,
+};
+use out_dir::BytesDir;
+use precompressed::Precompressed;
+use source_maps::SourceMapGate;
+use template::TemplateVars;
+use walkdir::WalkDir;
 
 #[proc_macro]
 /// Embed and optionally compress static assets for a web server
-///
-/// ```compile_fail,hidden
-/// # // The corresponding successful test is in static-serve/tests/tests.rs,
-/// # // where tests usually belong. It's called serves_unknown_attributes.
-/// # // But only doctests support the `compile_fail` attribute so the failing
-/// # // test is placed here.
-/// embed_assets!(
-///     "../static-serve/test_unknown_extensions",
-///     allow_unknown_extensions = false
-/// );
-/// ```
 pub fn embed_assets(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    let parsed = parse_macro_input!(input as EmbedAssets);
-    quote! { #parsed }.into()
+    env_vars::reset();
+    let parsed = parse_macro_input!(input as EmbedAssetsInput);
+    let expanded = quote! { #parsed };
+    let tracked_env = env_vars::tracked();
+    quote! {
+        #expanded
+        #tracked_env
+    }
+    .into()
+}
+
+#[proc_macro]
+/// Embed static assets like `embed_assets!`, evaluating to a
+/// `StaticAssetSet` which can be merged with other sets
+pub fn embed_asset_set(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    env_vars::reset();
+    let parsed = parse_macro_input!(input as EmbedAssetsInput);
+    let asset_set_fn = parsed.asset_set_fn();
+    let expanded = quote! { #parsed };
+    let tracked_env = env_vars::tracked();
+    quote! {{
+        #expanded
+        #tracked_env
+        #asset_set_fn()
+    }}
+    .into()
 }
 
 #[proc_macro]
 /// Embed and optionally compress a single static asset for a web server
 pub fn embed_asset(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    env_vars::reset();
     let parsed = parse_macro_input!(input as EmbedAsset);
-    quote! { #parsed }.into()
+    let expanded = quote! { #parsed };
+    let tracked_env = env_vars::tracked();
+    quote! {{
+        #tracked_env
+        #expanded
+    }}
+    .into()
 }
 
 struct EmbedAsset {
     asset_file: AssetFile,
     should_compress: ShouldCompress,
     cache_busted: IsCacheBusted,
-    allow_unknown_extensions: LitBool,
+    /// From `allow_unknown_extensions` and `unknown_mime`
+    unknown_content_type: Option<String>,
+    last_modified_only: LitBool,
+    etag_salt: Option<LitStr>,
+    etag_algorithm: EtagAlgorithm,
+    gzip_level: GzipLevel,
+    zstd_level: ZstdLevel,
+    max_age: Option<MaxAge>,
+    charset: Charset,
+    cors: Cors,
+    security_headers: SecurityHeaders,
 }
 
 struct AssetFile(LitStr);
 
 impl Parse for EmbedAsset {
+    #[expect(clippy::too_many_lines)]
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let asset_file: AssetFile = input.parse()?;
+        let asset_file = AssetFile::parse(input, is_manifest_relative(input)?)?;
 
         // Default to no compression, no cache-busting
         let mut maybe_should_compress = None;
         let mut maybe_is_cache_busted = None;
         let mut maybe_allow_unknown_extensions = None;
+        let mut maybe_last_modified_only = None;
+        let mut maybe_etag_salt = None;
+        let mut maybe_etag_algorithm = None;
+        let mut maybe_gzip_level = None;
+        let mut maybe_zstd_level = None;
+        let mut maybe_max_age = None;
+        let mut maybe_unknown_mime = None;
+        let mut maybe_charset = None;
+        let mut maybe_cors = None;
+        let mut maybe_security_headers: Option<LitBool> = None;
 
         while !input.is_empty() {
             input.parse::<Token![,]>()?;
@@ -84,49 +177,94 @@ impl Parse for EmbedAsset {
                     let value = input.parse()?;
                     maybe_allow_unknown_extensions = Some(value);
                 }
+                "last_modified_only" => {
+                    let value = input.parse()?;
+                    maybe_last_modified_only = Some(value);
+                }
+                "etag_salt" => {
+                    let value = input.parse()?;
+                    maybe_etag_salt = Some(value);
+                }
+                "etag" => {
+                    let value = input.parse()?;
+                    maybe_etag_algorithm = Some(value);
+                }
+                "gzip_level" => {
+                    let value = input.parse()?;
+                    maybe_gzip_level = Some(value);
+                }
+                "zstd_level" => {
+                    let value = input.parse()?;
+                    maybe_zstd_level = Some(value);
+                }
+                "max_age" => {
+                    let value = input.parse()?;
+                    maybe_max_age = Some(value);
+                }
+                "unknown_mime" => {
+                    let value = input.parse()?;
+                    maybe_unknown_mime = Some(value);
+                }
+                "charset" => {
+                    let value = input.parse()?;
+                    maybe_charset = Some(value);
+                }
+                "manifest_relative" => {
+                    // Already looked up by `is_manifest_relative`
+                    input.parse::<LitBool>()?;
+                }
+                "cors" => {
+                    let value = input.parse()?;
+                    maybe_cors = Some(value);
+                }
+                "security_headers" => {
+                    let value = input.parse()?;
+                    maybe_security_headers = Some(value);
+                }
                 _ => {
                     return Err(syn::Error::new(
                         key.span(),
                         format!(
-                            "Unknown key in `embed_asset!` macro. Expected `compress`, `cache_bust`, or `allow_unknown_extensions` but got {key}"
+                            "Unknown key in `embed_asset!` macro. Expected `compress`, `cache_bust`, `allow_unknown_extensions`, `last_modified_only`, `etag_salt`, `etag`, `gzip_level`, `zstd_level`, `max_age`, `unknown_mime`, `charset`, `manifest_relative`, `cors`, or `security_headers` but got {key}"
                         ),
                     ));
                 }
             }
         }
-        let should_compress = maybe_should_compress.unwrap_or_else(|| {
-            ShouldCompress(LitBool {
-                value: false,
-                span: Span::call_site(),
-            })
-        });
-        let cache_busted = maybe_is_cache_busted.unwrap_or_else(|| {
-            IsCacheBusted(LitBool {
-                value: false,
-                span: Span::call_site(),
-            })
-        });
-        let allow_unknown_extensions = maybe_allow_unknown_extensions.unwrap_or(LitBool {
-            value: false,
-            span: Span::call_site(),
-        });
+        let should_compress =
+            maybe_should_compress.unwrap_or(ShouldCompress(Algorithms::compress(false)));
+        let cache_busted = IsCacheBusted(flag(maybe_is_cache_busted, false));
+        let unknown_content_type = unknown_content_type(
+            &flag(maybe_allow_unknown_extensions, false),
+            maybe_unknown_mime.as_ref(),
+        );
+        let last_modified_only = flag(maybe_last_modified_only, false);
 
         Ok(Self {
             asset_file,
             should_compress,
             cache_busted,
-            allow_unknown_extensions,
+            unknown_content_type,
+            last_modified_only,
+            etag_salt: maybe_etag_salt,
+            etag_algorithm: maybe_etag_algorithm.unwrap_or_default(),
+            gzip_level: maybe_gzip_level.unwrap_or_default(),
+            zstd_level: maybe_zstd_level.unwrap_or_default(),
+            max_age: maybe_max_age,
+            charset: maybe_charset.unwrap_or_default(),
+            cors: maybe_cors.unwrap_or_default(),
+            security_headers: SecurityHeaders::new(maybe_security_headers.as_ref(), None, None)?,
         })
     }
 }
 
-impl Parse for AssetFile {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
+impl AssetFile {
+    fn parse(input: ParseStream, manifest_relative: bool) -> syn::Result<Self> {
         let input_span = input.span();
         let asset_file: LitStr = input.parse()?;
         let literal = asset_file.value();
-        let path = Path::new(&literal);
-        let metadata = match fs::metadata(path) {
+        let asset_file = resolve_path(&asset_file, manifest_relative)?;
+        let metadata = match fs::metadata(asset_file.value()) {
             Ok(meta) => meta,
             Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound) => {
                 return Err(syn::Error::new(
@@ -155,17 +293,7 @@ impl Parse for AssetFile {
 
 impl ToTokens for EmbedAsset {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let AssetFile(asset_file) = &self.asset_file;
-        let ShouldCompress(should_compress) = &self.should_compress;
-        let IsCacheBusted(cache_busted) = &self.cache_busted;
-        let allow_unknown_extensions = &self.allow_unknown_extensions;
-
-        let result = generate_static_handler(
-            asset_file,
-            should_compress,
-            cache_busted,
-            allow_unknown_extensions,
-        );
+        let result = generate_static_handler(self);
 
         match result {
             Ok(value) => {
@@ -181,116 +309,94 @@ impl ToTokens for EmbedAsset {
     }
 }
 
-struct EmbedAssets {
-    assets_dir: AssetsDir,
-    validated_ignore_paths: IgnorePaths,
-    should_compress: ShouldCompress,
-    should_strip_html_ext: ShouldStripHtmlExt,
-    cache_busted_paths: CacheBustedPaths,
-    allow_unknown_extensions: LitBool,
+/// `embed_assets!` embeds either a directory or the files listed in a manifest
+enum EmbedAssetsInput {
+    Directory(Box<EmbedAssets>),
+    Manifest(EmbedManifest),
 }
 
-impl Parse for EmbedAssets {
+impl Parse for EmbedAssetsInput {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let assets_dir: AssetsDir = input.parse()?;
+        // The assets directory is a string literal, the manifest a `manifest = "..."` pair
+        if input.peek(Ident) {
+            input.parse().map(Self::Manifest)
+        } else {
+            input.parse().map(Self::Directory)
+        }
+    }
+}
 
-        // Default to no compression
-        let mut maybe_should_compress = None;
-        let mut maybe_ignore_paths = None;
-        let mut maybe_should_strip_html_ext = None;
-        let mut maybe_cache_busted_paths = None;
-        let mut maybe_allow_unknown_extensions = None;
+impl EmbedAssetsInput {
+    /// The path of the generated `static_asset_set` function
+    fn asset_set_fn(&self) -> TokenStream {
+        match self {
+            Self::Directory(embed_assets) => embed_assets.fn_name.as_ref().map_or_else(
+                || quote! { static_asset_set },
+                |fn_name| quote! { #fn_name::static_asset_set },
+            ),
+            Self::Manifest(_) => quote! { static_asset_set },
+        }
+    }
+}
+
+impl ToTokens for EmbedAssetsInput {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            Self::Directory(embed_assets) => embed_assets.to_tokens(tokens),
+            Self::Manifest(embed_manifest) => embed_manifest.to_tokens(tokens),
+        }
+    }
+}
+
+struct EmbedManifest {
+    manifest_path: LitStr,
+}
 
+impl Parse for EmbedManifest {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key: Ident = input.parse()?;
+        if key != "manifest" {
+            return Err(syn::Error::new(
+                key.span(),
+                format!(
+                    "Expected the assets directory or `manifest = \"...\"` in `embed_assets!` macro, but got {key}"
+                ),
+            ));
+        }
+        input.parse::<Token![=]>()?;
+        let manifest_relative = is_manifest_relative(input)?;
+        let manifest_path = resolve_path(&input.parse()?, manifest_relative)?;
+
+        if !Path::new(&manifest_path.value()).is_file() {
+            return Err(syn::Error::new(
+                manifest_path.span(),
+                "The specified manifest file does not exist",
+            ));
+        }
         while !input.is_empty() {
             input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
             let key: Ident = input.parse()?;
-            input.parse::<Token![=]>()?;
-
-            match key.to_string().as_str() {
-                "compress" => {
-                    let value = input.parse()?;
-                    maybe_should_compress = Some(value);
-                }
-                "ignore_paths" => {
-                    let value = input.parse()?;
-                    maybe_ignore_paths = Some(value);
-                }
-                "strip_html_ext" => {
-                    let value = input.parse()?;
-                    maybe_should_strip_html_ext = Some(value);
-                }
-                "cache_busted_paths" => {
-                    let value = input.parse()?;
-                    maybe_cache_busted_paths = Some(value);
-                }
-                "allow_unknown_extensions" => {
-                    let value = input.parse()?;
-                    maybe_allow_unknown_extensions = Some(value);
-                }
-                _ => {
-                    return Err(syn::Error::new(
-                        key.span(),
-                        "Unknown key in embed_assets! macro. Expected `compress`, `ignore_paths`, `strip_html_ext`, `cache_busted_paths`, or `allow_unknown_extensions`",
-                    ));
-                }
+            if key != "manifest_relative" {
+                return Err(syn::Error::new(
+                    key.span(),
+                    "The manifest describes every embedded file, no other key than `manifest_relative` is accepted",
+                ));
             }
+            input.parse::<Token![=]>()?;
+            // Already looked up by `is_manifest_relative`
+            input.parse::<LitBool>()?;
         }
 
-        let should_compress = maybe_should_compress.unwrap_or_else(|| {
-            ShouldCompress(LitBool {
-                value: false,
-                span: Span::call_site(),
-            })
-        });
-
-        let should_strip_html_ext = maybe_should_strip_html_ext.unwrap_or_else(|| {
-            ShouldStripHtmlExt(LitBool {
-                value: false,
-                span: Span::call_site(),
-            })
-        });
-
-        let ignore_paths_with_span = maybe_ignore_paths.unwrap_or(IgnorePathsWithSpan(vec![]));
-        let validated_ignore_paths = validate_ignore_paths(ignore_paths_with_span, &assets_dir.0)?;
-
-        let maybe_cache_busted_paths =
-            maybe_cache_busted_paths.unwrap_or(CacheBustedPathsWithSpan(vec![]));
-        let cache_busted_paths =
-            validate_cache_busted_paths(maybe_cache_busted_paths, &assets_dir.0)?;
-
-        let allow_unknown_extensions = maybe_allow_unknown_extensions.unwrap_or(LitBool {
-            value: false,
-            span: Span::call_site(),
-        });
-
-        Ok(Self {
-            assets_dir,
-            validated_ignore_paths,
-            should_compress,
-            should_strip_html_ext,
-            cache_busted_paths,
-            allow_unknown_extensions,
-        })
+        Ok(Self { manifest_path })
     }
 }
 
-impl ToTokens for EmbedAssets {
+impl ToTokens for EmbedManifest {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let AssetsDir(assets_dir) = &self.assets_dir;
-        let ignore_paths = &self.validated_ignore_paths;
-        let ShouldCompress(should_compress) = &self.should_compress;
-        let ShouldStripHtmlExt(should_strip_html_ext) = &self.should_strip_html_ext;
-        let cache_busted_paths = &self.cache_busted_paths;
-        let allow_unknown_extensions = &self.allow_unknown_extensions;
-
-        let result = generate_static_routes(
-            assets_dir,
-            ignore_paths,
-            should_compress,
-            should_strip_html_ext,
-            cache_busted_paths,
-            allow_unknown_extensions.value,
-        );
+        let result = generate_manifest_routes(&self.manifest_path);
 
         match result {
             Ok(value) => {
@@ -299,349 +405,1655 @@ impl ToTokens for EmbedAssets {
                 });
             }
             Err(err_message) => {
-                let error = syn::Error::new(Span::call_site(), err_message);
+                let error = syn::Error::new(self.manifest_path.span(), err_message);
                 tokens.extend(error.to_compile_error());
             }
         }
     }
 }
 
-struct AssetsDir(LitStr);
+impl EmbedAssets {
+    /// The generated items, or the error of the expansion,
+    /// reported at the first assets directory
+    fn expand(&self) -> syn::Result<TokenStream> {
+        generate_static_routes(self)
+            .map_err(|err_message| syn::Error::new(self.assets_dirs.span(), err_message))
+    }
+}
 
-impl Parse for AssetsDir {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        let input_span = input.span();
-        let assets_dir: LitStr = input.parse()?;
-        let literal = assets_dir.value();
-        let path = Path::new(&literal);
-        let metadata = match fs::metadata(path) {
-            Ok(meta) => meta,
-            Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound) => {
-                return Err(syn::Error::new(
-                    input_span,
-                    "The specified assets directory does not exist",
-                ));
-            }
-            Err(e) => {
-                return Err(syn::Error::new(
-                    input_span,
-                    format!(
-                        "Error reading directory {literal}: {}",
-                        DisplayFullError(&e)
-                    ),
-                ));
+impl ToTokens for EmbedAssets {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self.expand() {
+            Ok(value) => {
+                tokens.extend(quote! {
+                    #value
+                });
             }
-        };
-
-        if !metadata.is_dir() {
-            return Err(syn::Error::new(
-                input_span,
-                "The specified assets directory is not a directory",
-            ));
+            Err(error) => tokens.extend(error.to_compile_error()),
         }
-
-        Ok(AssetsDir(assets_dir))
     }
 }
 
-struct IgnorePaths(Vec<PathBuf>);
-
-struct IgnorePathsWithSpan(Vec<(PathBuf, Span)>);
-
-impl Parse for IgnorePathsWithSpan {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        let dirs = parse_dirs(input)?;
+impl TrailingSlash {
+    /// Apply to the routes of `assets`, recording the
+    /// other forms of the routes in `extra_routes`
+    fn apply(self, assets: &mut [EmbeddedFileInfo], extra_routes: &mut ExtraRoutes) {
+        for (index, asset) in assets.iter_mut().enumerate() {
+            let Some(route) = &mut asset.entry_path else {
+                continue;
+            };
+            let is_page = asset.content_type.starts_with("text/html")
+                && route.len() > 1
+                && (route.ends_with('/')
+                    || !route
+                        .rsplit('/')
+                        .next()
+                        .is_some_and(|name| name.contains('.')));
+            if !is_page {
+                continue;
+            }
 
-        Ok(IgnorePathsWithSpan(dirs))
+            let other = match route.strip_suffix('/') {
+                Some(stripped) => stripped.to_owned(),
+                None => format!("{route}/"),
+            };
+            match self {
+                Self::Redirect if is_header_value(route) => {
+                    extra_routes.redirects.push((other, route.clone()));
+                }
+                Self::Redirect => {}
+                Self::Strip => {
+                    if route.ends_with('/') {
+                        *route = other;
+                    }
+                }
+                Self::Both => extra_routes.aliases.push((other, index)),
+            }
+        }
     }
 }
 
-fn validate_ignore_paths(
-    ignore_paths: IgnorePathsWithSpan,
-    assets_dir: &LitStr,
-) -> syn::Result<IgnorePaths> {
-    let mut valid_ignore_paths = Vec::new();
-    for (dir, span) in ignore_paths.0 {
-        let full_path = PathBuf::from(assets_dir.value()).join(&dir);
-        match fs::metadata(&full_path) {
-            Ok(_) => valid_ignore_paths.push(full_path),
-            Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound) => {
-                return Err(syn::Error::new(
-                    span,
-                    "The specified ignored path does not exist",
-                ));
+impl IndexFiles {
+    /// The position among `assets` of the index file of the
+    /// directory served at `dir`, ending with a slash
+    fn find(&self, dir: &str, assets: &[EmbeddedFileInfo]) -> Option<usize> {
+        self.0.iter().find_map(|name| {
+            let route = format!("{dir}{name}");
+            assets
+                .iter()
+                .position(|asset| asset.entry_path.as_deref() == Some(route.as_str()))
+        })
+    }
+
+    /// Serve the index files of `assets` at the routes of their directories,
+    /// unless an asset is served there already, handling the route without
+    /// the trailing slash like `trailing_slash`
+    fn add_aliases(
+        &self,
+        assets: &[EmbeddedFileInfo],
+        trailing_slash: Option<TrailingSlash>,
+        extra_routes: &mut ExtraRoutes,
+    ) {
+        let mut dirs: Vec<&str> = assets
+            .iter()
+            .filter_map(|asset| {
+                let (dir, _) = asset.entry_path.as_deref()?.rsplit_once('/')?;
+                Some(dir)
+            })
+            .collect();
+        dirs.sort_unstable();
+        dirs.dedup();
+
+        for dir in dirs {
+            let route = format!("{dir}/");
+            if assets
+                .iter()
+                .any(|asset| asset.entry_path.as_deref() == Some(route.as_str()))
+            {
+                continue;
             }
-            Err(e) => {
-                return Err(syn::Error::new(
-                    span,
-                    format!(
-                        "Error reading ignored path {}: {}",
-                        dir.to_string_lossy(),
-                        DisplayFullError(&e)
-                    ),
-                ));
+            let Some(index) = self.find(&route, assets) else {
+                continue;
+            };
+            // The root has no other form
+            let has_other_form = !dir.is_empty();
+            match trailing_slash {
+                Some(TrailingSlash::Redirect) if has_other_form && is_header_value(&route) => {
+                    extra_routes.redirects.push((dir.to_owned(), route.clone()));
+                    extra_routes.aliases.push((route, index));
+                }
+                Some(TrailingSlash::Strip) if has_other_form => {
+                    extra_routes.aliases.push((dir.to_owned(), index));
+                }
+                Some(TrailingSlash::Both) if has_other_form => {
+                    extra_routes.aliases.push((dir.to_owned(), index));
+                    extra_routes.aliases.push((route, index));
+                }
+                _ => extra_routes.aliases.push((route, index)),
             }
         }
     }
-    Ok(IgnorePaths(valid_ignore_paths))
 }
 
-struct ShouldCompress(LitBool);
-
-impl Parse for ShouldCompress {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        let lit = input.parse()?;
-        Ok(ShouldCompress(lit))
+/// Redirect the original routes of the HTML files of `entries`, stripped of
+/// their extension by `strip_html_ext`, to the routes of their `assets`
+///
+/// The `assets` start with the ones embedded from `entries`, in the same order.
+fn add_html_redirects(
+    entries: &[AssetEntry],
+    assets: &[EmbeddedFileInfo],
+    (prefix, route_map): (&str, &RouteMap),
+    extra_routes: &mut ExtraRoutes,
+) {
+    for (entry, asset) in entries.iter().zip(assets) {
+        let Some(route) = &asset.entry_path else {
+            continue;
+        };
+        let original = format!("{prefix}{}", entry.route);
+        if *route != original
+            && asset.content_type.starts_with("text/html")
+            && route_map.route(&entry.relative_path).is_none()
+            && is_header_value(route)
+        {
+            extra_routes.redirects.push((original, route.clone()));
+        }
     }
 }
 
-struct ShouldStripHtmlExt(LitBool);
-
-impl Parse for ShouldStripHtmlExt {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        let lit = input.parse()?;
-        Ok(ShouldStripHtmlExt(lit))
+/// Serve the `assets` at their `/_immutable/<hash>/<route>` alias too,
+/// for `immutable_aliases = true`, unless they're already cache-busted
+/// or have no `ETag` to put in the alias
+fn add_immutable_aliases(assets: &mut [EmbeddedFileInfo], extra_routes: &mut ExtraRoutes) {
+    for (index, asset) in assets.iter_mut().enumerate() {
+        if asset.cache_busted {
+            continue;
+        }
+        let Some(alias) = asset.immutable_alias_route() else {
+            continue;
+        };
+        asset.immutable_aliases.route = true;
+        for variant in asset
+            .image_variants
+            .iter_mut()
+            .chain(&mut asset.media_variants)
+        {
+            variant.immutable_aliases.route = true;
+        }
+        extra_routes.aliases.push((alias, index));
     }
 }
 
-struct IsCacheBusted(LitBool);
+/// The routes generated next to the ones of the assets
+#[derive(Default)]
+struct ExtraRoutes {
+    /// Tuples of a route and of the index of the asset it also serves
+    aliases: Vec<(String, usize)>,
+    /// Tuples of a route and of the one it redirects to
+    redirects: Vec<(String, String)>,
+}
+
+/// Send a `Link` header preloading the files of `preload` from their
+/// final routes with the HTML pages among `assets`, the first of which
+/// are embedded from `entries`
+fn add_preload_links(
+    entries: &[AssetEntry],
+    assets: &mut [EmbeddedFileInfo],
+    Preload(preload): &Preload,
+) -> Result<(), Error> {
+    if preload.is_empty() {
+        return Ok(());
+    }
 
-impl Parse for IsCacheBusted {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        let lit = input.parse()?;
-        Ok(IsCacheBusted(lit))
+    let mut links = Vec::new();
+    for path in preload {
+        let relative_path = normalize_web_path(&path.value());
+        let asset = entries
+            .iter()
+            .position(|entry| normalize_web_path(&entry.relative_path) == relative_path)
+            .and_then(|index| assets.get(index))
+            .ok_or_else(|| Error::PreloadNotEmbedded(path.value()))?;
+        let route = asset.entry_path.as_deref().unwrap_or_default();
+        let destination = match media_type(&asset.content_type) {
+            "text/javascript" | "application/javascript" => "script",
+            "text/css" => "style",
+            media_type if media_type.starts_with("font/") => "font; crossorigin",
+            media_type if media_type.starts_with("image/") => "image",
+            _ => "fetch; crossorigin",
+        };
+        links.push(format!("<{route}>; rel=preload; as={destination}"));
+    }
+    let link = links.join(", ");
+    if !is_header_value(&link) {
+        return Err(Error::InvalidPreloadLink(link));
     }
-}
 
-struct CacheBustedPaths {
-    dirs: Vec<PathBuf>,
-    files: Vec<PathBuf>,
+    for asset in assets {
+        if media_type(&asset.content_type) == "text/html" {
+            asset.headers.push(("link".to_owned(), link.clone()));
+        }
+    }
+    Ok(())
 }
-struct CacheBustedPathsWithSpan(Vec<(PathBuf, Span)>);
 
-impl Parse for CacheBustedPathsWithSpan {
-    fn parse(input: ParseStream) -> syn::Result<Self> {
-        let dirs = parse_dirs(input)?;
-        Ok(CacheBustedPathsWithSpan(dirs))
-    }
-}
-
-fn validate_cache_busted_paths(
-    tuples: CacheBustedPathsWithSpan,
-    assets_dir: &LitStr,
-) -> syn::Result<CacheBustedPaths> {
-    let mut valid_dirs = Vec::new();
-    let mut valid_files = Vec::new();
-    for (dir, span) in tuples.0 {
-        let full_path = PathBuf::from(assets_dir.value()).join(&dir);
-        match fs::metadata(&full_path) {
-            Ok(meta) => {
-                if meta.is_dir() {
-                    valid_dirs.push(full_path);
-                } else {
-                    valid_files.push(full_path);
-                }
-            }
-            Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound) => {
-                return Err(syn::Error::new(
-                    span,
-                    "The specified directory for cache busting does not exist",
-                ));
-            }
-            Err(e) => {
-                return Err(syn::Error::new(
-                    span,
-                    format!(
-                        "Error reading path {}: {}",
-                        dir.to_string_lossy(),
-                        DisplayFullError(&e)
-                    ),
-                ));
-            }
+impl StreamBodies {
+    /// Stream the body of `asset`, and of its variants, if large enough
+    fn apply(self, asset: &mut EmbeddedFileInfo) {
+        let size = asset.lit_byte_str_contents.value().len();
+        if u64::try_from(size).is_ok_and(|size| size >= self.min_size) {
+            asset.stream_chunk_size = Some(self.chunk_size);
+        }
+        for variant in asset
+            .image_variants
+            .iter_mut()
+            .chain(&mut asset.media_variants)
+        {
+            self.apply(variant);
         }
     }
-    Ok(CacheBustedPaths {
-        dirs: valid_dirs,
-        files: valid_files,
-    })
 }
 
-/// Helper function for turning an array of strs representing paths into
-/// a `Vec` containing tuples of each `PathBuf` and its `Span` in the `ParseStream`
-fn parse_dirs(input: ParseStream) -> syn::Result<Vec<(PathBuf, Span)>> {
-    let inner_content;
-    bracketed!(inner_content in input);
+/// The media type of `content_type`, without its parameters
+fn media_type(content_type: &str) -> &str {
+    content_type
+        .split_once(';')
+        .map_or(content_type, |(media_type, _)| media_type)
+}
 
-    let mut dirs = Vec::new();
-    while !inner_content.is_empty() {
-        let directory_span = inner_content.span();
-        let directory_str = inner_content.parse::<LitStr>()?;
-        let path = PathBuf::from(directory_str.value());
-        dirs.push((path, directory_span));
+/// Whether `content_type` is textual: `text/*`, JavaScript or JSON
+fn is_textual(content_type: &str) -> bool {
+    content_type.starts_with("text/")
+        || content_type == "application/javascript"
+        || content_type == "application/json"
+}
 
-        if !inner_content.is_empty() {
-            inner_content.parse::<Token![,]>()?;
+/// Append `charset` to the textual `content_type`: `text/*`,
+/// JavaScript and JSON, unless it has parameters already
+fn with_charset(content_type: String, charset: Option<&str>) -> String {
+    match charset {
+        Some(charset) if is_textual(&content_type) && !content_type.contains(';') => {
+            format!("{content_type}; charset={charset}")
         }
+        _ => content_type,
     }
-    Ok(dirs)
 }
 
-fn generate_static_routes(
-    assets_dir: &LitStr,
-    ignore_paths: &IgnorePaths,
-    should_compress: &LitBool,
-    should_strip_html_ext: &LitBool,
-    cache_busted_paths: &CacheBustedPaths,
-    allow_unknown_extensions: bool,
-) -> Result<TokenStream, error::Error> {
-    let assets_dir_abs = Path::new(&assets_dir.value())
-        .canonicalize()
-        .map_err(Error::CannotCanonicalizeDirectory)?;
-    let assets_dir_abs_str = assets_dir_abs
-        .to_str()
-        .ok_or(Error::InvalidUnicodeInDirectoryName)?;
-    let canon_ignore_paths = ignore_paths
-        .0
+/// The content type of the files with unknown extensions
+const OCTET_STREAM: &str = "application/octet-stream";
+
+#[expect(clippy::too_many_lines)]
+fn generate_static_routes(embed_assets: &EmbedAssets) -> Result<TokenStream, error::Error> {
+    let EmbedAssets {
+        assets_dirs: AssetsDirs(assets_dirs),
+        deny_conflicts,
+        non_utf8_paths,
+        auto_index,
+        zstd_dictionary,
+        identity,
+        lazy_decompression,
+        minify,
+        fonts,
+        image_variants,
+        accept_variants,
+        template_vars,
+        trailing_slash,
+        index_files,
+        html_redirects,
+        validated_ignore_paths: ignore_paths,
+        path_filter,
+        should_compress: ShouldCompress(should_compress),
+        should_strip_html_ext: ShouldStripHtmlExt(should_strip_html_ext),
+        cache_busted_paths,
+        cache_bust_query,
+        immutable_aliases,
+        unknown_content_type,
+        last_modified_only,
+        tarball_route,
+        route_definitions,
+        report,
+        import_map_route,
+        inject_import_map,
+        robots_txt,
+        security_txt,
+        fallback_page,
+        compression_groups,
+        etag_salt,
+        etag_algorithm,
+        expand_to,
+        gzip_level: GzipLevel(gzip_level),
+        zstd_level: ZstdLevel(zstd_level),
+        use_precompressed,
+        debug_from_disk,
+        fall_through_methods,
+        rocket_routes,
+        poem_routes,
+        salvo_router,
+        wrap,
+        prefix: RoutePrefix(prefix),
+        cache_controls,
+        cfg_gates,
+        extra_headers,
+        protected,
+        signed,
+        source_map_gate,
+        preload,
+        route_map,
+        max_age,
+        mime_overrides,
+        compress_policy,
+        size_budgets,
+        stream_bodies,
+        shard_size,
+        link_section,
+        bundle,
+        charset: Charset(charset),
+        cors: Cors(cors),
+        encoding_preference: EncodingPreference(encoding_preference),
+        security_headers,
+        fn_name,
+        vis,
+    } = embed_assets;
+    let last_modified_only = last_modified_only.value;
+    let etag_salt = etag_salt.as_ref().map(LitStr::value);
+    let etag_scheme = EtagScheme {
+        algorithm: *etag_algorithm,
+        salt: etag_salt.as_deref().unwrap_or_default(),
+    };
+
+    let mut entries = Vec::new();
+    let mut skipped_paths = Vec::new();
+    for AssetsDir(assets_dir) in assets_dirs {
+        let assets_dir_abs = canonicalize(Path::new(&assets_dir.value()))
+            .map_err(Error::CannotCanonicalizeDirectory)?;
+        let assets_dir_abs_str = assets_dir_abs
+            .to_str()
+            .ok_or(Error::InvalidUnicodeInDirectoryName)?;
+        let dir_entries = asset_entries(
+            assets_dir_abs_str,
+            (ignore_paths, path_filter),
+            cache_busted_paths,
+            (*non_utf8_paths, &mut skipped_paths),
+        )?;
+        merge_entries(&mut entries, dir_entries, deny_conflicts.value)?;
+    }
+    if use_precompressed.value {
+        precompressed::remove_siblings(&mut entries);
+    }
+    if fonts.value {
+        fonts::apply(&mut entries);
+    }
+    let mut media_variants = accept_variants.remove_variants(&mut entries);
+    let image_variants = if image_variants.value {
+        images::remove_variants(&mut entries)
+    } else {
+        Vec::new()
+    };
+    let media_variants: Vec<Vec<PathBuf>> = entries
         .iter()
-        .map(|d| {
-            d.canonicalize()
-                .map_err(Error::CannotCanonicalizeIgnorePath)
-        })
-        .collect::<Result<Vec<_>, _>>()?;
-    let canon_cache_busted_dirs = cache_busted_paths
-        .dirs
+        .map(|entry| media_variants.remove(&entry.path).unwrap_or_default())
+        .collect();
+    let variants: Vec<Vec<PathBuf>> = media_variants
         .iter()
-        .map(|d| {
-            d.canonicalize()
-                .map_err(Error::CannotCanonicalizeCacheBustedDir)
+        .enumerate()
+        .map(|(index, media)| {
+            let images = image_variants.get(index).into_iter().flatten();
+            images.chain(media).cloned().collect()
         })
-        .collect::<Result<Vec<_>, _>>()?;
-    let canon_cache_busted_files = cache_busted_paths
-        .files
-        .iter()
-        .map(|file| file.canonicalize().map_err(Error::CannotCanonicalizeFile))
-        .collect::<Result<Vec<_>, _>>()?;
-
-    let mut routes = Vec::new();
-    for entry in glob(&format!("{assets_dir_abs_str}/**/*")).map_err(Error::Pattern)? {
-        let entry = entry.map_err(Error::Glob)?;
-        let metadata = entry.metadata().map_err(Error::CannotGetMetadata)?;
-        if metadata.is_dir() {
-            continue;
-        }
+        .collect();
+    let over_budget_warning = size_budgets.check(&entries, &variants)?;
+    let skipped_paths_warning = NonUtf8Paths::skipped_warning(&skipped_paths);
+
+    let import_map = import_map_route
+        .as_ref()
+        .map(|_| import_map::import_map(&entries, (prefix, route_map), etag_scheme))
+        .transpose()?;
+    let file_options = FileOptions {
+        should_strip_html_ext: should_strip_html_ext.value,
+        unknown_content_type: unknown_content_type.as_deref(),
+        charset: charset.as_deref(),
+        last_modified_only,
+        injected_import_map: import_map.as_deref().filter(|_| inject_import_map.value),
+        etag_scheme,
+        gzip_level: *gzip_level,
+        zstd_level: *zstd_level,
+        use_precompressed: use_precompressed.value,
+        minify: minify.value,
+        template_vars: template_vars.as_ref(),
+        max_age: max_age.as_ref().map(|MaxAge(max_age)| *max_age),
+        mime_overrides,
+        compress_policy,
+    };
 
-        // Skip `entry`s which are located in ignored paths
-        if canon_ignore_paths
+    let mut assets = embed_entries(
+        &entries,
+        (compression_groups, *should_compress),
+        (cache_controls, route_map),
+        &file_options,
+    )?;
+    for ((asset, entry), variants) in assets.iter_mut().zip(&entries).zip(&image_variants) {
+        asset.image_variants = variants
             .iter()
-            .any(|ignore_path| entry.starts_with(ignore_path))
-        {
-            continue;
+            .map(|path| {
+                let mut variant = EmbeddedFileInfo::from_path(
+                    path,
+                    None,
+                    Algorithms::default(),
+                    entry.cache_busted,
+                    &file_options,
+                )?;
+                variant.cache_control = OptionStr(asset.cache_control.0.clone());
+                Ok(variant)
+            })
+            .collect::<Result<_, Error>>()?;
+        #[cfg(feature = "image-encoding")]
+        for encoded in images::encode_variants(
+            &entry.path,
+            variants,
+            asset.lit_byte_str_contents.value().len(),
+        )? {
+            let (etag_str, last_modified) = validators(
+                &encoded.contents,
+                [entry.path.as_path()],
+                last_modified_only,
+                etag_scheme,
+            )?;
+            let bodies = FileBodies {
+                content_type: encoded.content_type.to_owned(),
+                contents: encoded.contents,
+                gzip: None,
+                zstd: None,
+                brotli: None,
+                etag_str,
+                last_modified,
+            };
+            let mut variant = EmbeddedFileInfo::from_bodies(
+                &entry.path,
+                bodies,
+                None,
+                entry.cache_busted,
+                &file_options,
+            );
+            variant.cache_control = OptionStr(asset.cache_control.0.clone());
+            asset.image_variants.push(variant);
         }
+        // AVIF is preferred whether it's a sibling or encoded
+        asset
+            .image_variants
+            .sort_by_key(|variant| variant.content_type != "image/avif");
+    }
+    for ((asset, entry), variants) in assets.iter_mut().zip(&entries).zip(&media_variants) {
+        asset.media_variants = variants
+            .iter()
+            .map(|path| {
+                let relative_path = Path::new(&entry.relative_path)
+                    .with_file_name(path.file_name().unwrap_or_default());
+                let algorithms = compression_groups.algorithms(
+                    &normalize_web_path(&relative_path.to_string_lossy())[1..],
+                    *should_compress,
+                );
+                let mut variant = EmbeddedFileInfo::from_path(
+                    path,
+                    None,
+                    algorithms,
+                    entry.cache_busted,
+                    &file_options,
+                )?;
+                variant.cache_control = OptionStr(asset.cache_control.0.clone());
+                Ok(variant)
+            })
+            .collect::<Result<_, Error>>()?;
+    }
 
-        let mut is_entry_cache_busted = false;
-        if canon_cache_busted_dirs
+    for (asset, entry) in assets.iter_mut().zip(&entries) {
+        asset.basic_auth = protected.basic_auth(Path::new(&entry.relative_path));
+        asset.signed = signed.contains(Path::new(&entry.relative_path));
+        asset.source_map_gate = source_map_gate
+            .clone()
+            .filter(|_| source_maps::is_source_map(&entry.relative_path));
+        asset.cfg_gate = cfg_gates.predicate(Path::new(&entry.relative_path));
+    }
+
+    if let (Some(ImportMapRoute(import_map_route)), Some(import_map)) =
+        (import_map_route, &import_map)
+    {
+        assets.push(import_map::import_map_asset(
+            import_map,
+            &entries,
+            import_map_route.value(),
+            last_modified_only,
+            etag_scheme,
+        )?);
+    }
+
+    assets.extend(metadata::metadata_assets(
+        robots_txt.as_ref(),
+        security_txt.as_ref(),
+        etag_scheme,
+    ));
+
+    if let Some(TarballRoute(tarball_route)) = tarball_route {
+        // The protected, signed, hidden and gated files aren't archived with the public ones
+        let public_entries: Vec<&AssetEntry> = entries
             .iter()
-            .any(|dir| entry.starts_with(dir))
-            || canon_cache_busted_files.contains(&entry)
+            .zip(&assets)
+            .filter(|(_, asset)| asset.is_public())
+            .map(|(entry, _)| entry)
+            .collect();
+        let tarball = tarball::tarball_asset(
+            &public_entries,
+            tarball_route.value(),
+            last_modified_only,
+            etag_scheme,
+            file_options.gzip_level,
+        )?;
+        assets.push(tarball);
+    }
+
+    if auto_index.value {
+        let index_assets = auto_index::index_assets(
+            &assets,
+            index_files,
+            file_options.charset,
+            last_modified_only,
+            etag_scheme,
+        )?;
+        assets.extend(index_assets);
+    }
+    for (index, asset) in assets.iter_mut().enumerate() {
+        let entry = entries.get(index);
+        let relative_path = entry.map(|entry| Path::new(&entry.relative_path));
+        asset.headers = extra_headers.headers(relative_path);
+        security_headers.add_to(&mut asset.headers);
+        asset.cors.clone_from(cors);
+        asset.encoding_preference.clone_from(encoding_preference);
+        // Browsers only load cross-origin fonts with CORS
+        if fonts.value && entry.is_some_and(|entry| fonts::is_font(&entry.path)) {
+            asset.cors = vec!["*".to_owned()];
+        }
+        for variant in asset
+            .image_variants
+            .iter_mut()
+            .chain(&mut asset.media_variants)
         {
-            is_entry_cache_busted = true;
+            variant.headers.clone_from(&asset.headers);
+            variant.cors.clone_from(&asset.cors);
+            variant
+                .encoding_preference
+                .clone_from(&asset.encoding_preference);
+            variant.basic_auth.clone_from(&asset.basic_auth);
+            variant.signed = asset.signed;
+            variant.source_map_gate.clone_from(&asset.source_map_gate);
         }
+    }
 
-        let entry = entry
-            .canonicalize()
-            .map_err(Error::CannotCanonicalizeFile)?;
-        let entry_str = entry.to_str().ok_or(Error::FilePathIsNotUtf8)?;
-        let EmbeddedFileInfo {
-            entry_path,
-            content_type,
-            etag_str,
-            lit_byte_str_contents,
-            maybe_gzip,
-            maybe_zstd,
-            cache_busted,
-        } = EmbeddedFileInfo::from_path(
-            &entry,
-            Some(assets_dir_abs_str),
+    for asset in &mut assets {
+        asset.entry_path = asset.entry_path.take().map(|path| prefix.clone() + &path);
+    }
+    add_preload_links(&entries, &mut assets, preload)?;
+    let mut extra_routes = ExtraRoutes::default();
+    if let Some(trailing_slash) = trailing_slash {
+        trailing_slash.apply(&mut assets, &mut extra_routes);
+    }
+    index_files.add_aliases(&assets, *trailing_slash, &mut extra_routes);
+    if html_redirects.value {
+        add_html_redirects(&entries, &assets, (prefix, route_map), &mut extra_routes);
+    }
+    if immutable_aliases.value {
+        add_immutable_aliases(&mut assets, &mut extra_routes);
+    }
+    check_duplicate_routes(&assets, &extra_routes)?;
+    for asset in &mut assets {
+        asset.hash_integrity(*etag_algorithm);
+        if let Some(stream_bodies) = stream_bodies {
+            stream_bodies.apply(asset);
+        }
+        if cache_bust_query.value {
+            asset.bust_cache_with_query();
+        }
+    }
+    if lazy_decompression.value {
+        assets
+            .iter_mut()
+            .for_each(EmbeddedFileInfo::decompress_lazily);
+    } else if !identity.value {
+        assets.iter_mut().for_each(EmbeddedFileInfo::drop_identity);
+    }
+    let dictionary = zstd_dictionary
+        .value
+        .then(|| dictionary::compress_with_dictionary(&mut assets, *zstd_level))
+        .transpose()?;
+
+    if let Some(RouteDefinitions { path, format }) = route_definitions {
+        route_definitions::write(Path::new(&path.value()), *format, &assets)?;
+    }
+    if let Some(report) = report {
+        report.write(&assets)?;
+    }
+
+    let fallback = fallback_page
+        .as_ref()
+        .map(|(page, kind)| Ok::<_, Error>((fallback_index(page, &assets)?, *kind)))
+        .transpose()?;
+
+    let inner_vis = match fn_name {
+        Some(_) => parse_quote!(pub),
+        None => vis.clone(),
+    };
+    // The items of `fn_name` are in a module, where `wrap` is resolved from its parent
+    let wrap = wrap.as_ref().map(|wrap| match fn_name {
+        Some(_) => path_from_parent(wrap),
+        None => wrap.clone(),
+    });
+    let bytes_dir = BytesDir::from_env()
+        .with_link_section(link_section.as_ref().map(LitStr::value))
+        .with_bundle(bundle.as_ref().map(|bundle| PathBuf::from(bundle.value())));
+    let mut items = generated_items(
+        (&assets, &extra_routes, dictionary.as_ref()),
+        (*shard_size, &bytes_dir),
+        fallback,
+        (debug_from_disk.value, fall_through_methods.value),
+        (
+            wrap.as_ref(),
+            [rocket_routes.value, poem_routes.value, salvo_router.value],
+        ),
+        &inner_vis,
+    )?;
+    items.extend(paths::paths_module(&entries, &assets, &inner_vis)?);
+    if let Some(fn_name) = fn_name {
+        items = named_items(fn_name, vis, &items);
+    }
+    items.extend(over_budget_warning);
+    items.extend(skipped_paths_warning);
+    if let Some(template_vars) = template_vars {
+        template_vars.track_env_reads();
+    }
+    if let Some(expand_to) = expand_to {
+        write_expansion(Path::new(&expand_to.value()), &items)?;
+    }
+
+    Ok(items)
+}
+
+/// Apply `f` to the `items` on all the available cores, as compressing large
+/// asset trees with the highest levels is slow, and keep their order
+fn in_parallel<T, U, F>(items: &[T], f: F) -> Result<Vec<U>, Error>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> Result<U, Error> + Sync,
+{
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let chunk_size = items.len().div_ceil(threads).max(1);
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(|| chunk.iter().map(&f).collect::<Result<Vec<_>, _>>()))
+            .collect();
+        let mut results = Vec::with_capacity(items.len());
+        for handle in handles {
+            match handle.join() {
+                Ok(chunk_results) => results.extend(chunk_results?),
+                Err(panic) => panic::resume_unwind(panic),
+            }
+        }
+        Ok(results)
+    })
+}
+
+/// Embed the files of the assets directories, compressed with the algorithms
+/// of their `compression_groups`, or `should_compress` otherwise
+fn embed_entries(
+    entries: &[AssetEntry],
+    compression: (&CompressionGroups, Algorithms),
+    (cache_controls, route_map): (&CacheControls, &RouteMap),
+    file_options: &FileOptions,
+) -> Result<Vec<EmbeddedFileInfo>, Error> {
+    let (compression_groups, should_compress) = compression;
+    let bodies = in_parallel(entries, |entry| {
+        let algorithms = compression_groups.algorithms(
+            &normalize_web_path(&entry.relative_path)[1..],
             should_compress,
-            should_strip_html_ext,
-            is_entry_cache_busted,
-            allow_unknown_extensions,
+        );
+        FileBodies::read(&entry.path, algorithms, file_options)
+    })?;
+
+    let mut assets = Vec::new();
+    for (
+        AssetEntry {
+            path,
+            relative_path: relative_entry,
+            route,
+            cache_busted,
+        },
+        bodies,
+    ) in entries.iter().zip(bodies)
+    {
+        let mut embedded_file_info =
+            EmbeddedFileInfo::from_bodies(path, bodies, Some(route), *cache_busted, file_options);
+        if let Some(cache_control) = cache_controls.value(Path::new(relative_entry)) {
+            embedded_file_info.cache_control = OptionStr(Some(cache_control));
+        }
+        if let Some(route) = route_map.route(relative_entry) {
+            embedded_file_info.entry_path = Some(route);
+        }
+
+        assets.push(embedded_file_info);
+    }
+
+    Ok(assets)
+}
+
+/// Check no two `assets`, aliases or redirects are served at the same route,
+/// which would make the router panic at runtime, e.g. `about.html` and
+/// `about.htm` with `strip_html_ext`, or a custom route of `route_map`
+/// taken by another file
+fn check_duplicate_routes(
+    assets: &[EmbeddedFileInfo],
+    extra_routes: &ExtraRoutes,
+) -> Result<(), Error> {
+    let asset_routes = assets.iter().filter_map(|asset| {
+        let name = asset
+            .file_path
+            .clone()
+            .unwrap_or_else(|| "the generated asset".to_owned());
+        Some((asset.entry_path.as_deref()?, name))
+    });
+    let alias_routes = extra_routes.aliases.iter().map(|(alias, index)| {
+        let route = assets[*index].entry_path.as_deref().unwrap_or_default();
+        (alias.as_str(), format!("the alias of {route}"))
+    });
+    let redirect_routes = extra_routes
+        .redirects
+        .iter()
+        .map(|(from, to)| (from.as_str(), format!("the redirect to {to}")));
+
+    let mut routes = HashMap::new();
+    for (route, name) in asset_routes.chain(alias_routes).chain(redirect_routes) {
+        if let Some(first) = routes.insert(route, name.clone()) {
+            return Err(Error::DuplicateRoute {
+                route: route.to_owned(),
+                first,
+                second: name,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The index of the fallback `page` among the embedded `assets`
+fn fallback_index(page: &Path, assets: &[EmbeddedFileInfo]) -> Result<usize, Error> {
+    let page = canonicalize(page).map_err(Error::CannotCanonicalizeFile)?;
+    assets
+        .iter()
+        .position(|asset| asset.file_path.is_some() && asset.file_path.as_deref() == page.to_str())
+        .ok_or(Error::FallbackPageNotEmbedded)
+}
+
+/// Write the generated `items` to `path`, to inspect the expansion
+/// of large trees without expanding the whole crate
+///
+/// The code is written on a single line, `rustfmt` can format it.
+fn write_expansion(path: &Path, items: &TokenStream) -> Result<(), Error> {
+    let contents = format!("// Generated by static-serve, do not edit.\n{items}\n");
+    if fs::read_to_string(path).is_ok_and(|existing| existing == contents) {
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(Error::CannotWriteExpansion)?;
+    }
+    fs::write(path, contents).map_err(Error::CannotWriteExpansion)
+}
+
+fn generate_manifest_routes(manifest_path: &LitStr) -> Result<TokenStream, error::Error> {
+    let manifest_path = PathBuf::from(manifest_path.value());
+    let manifest_dir = manifest_path.parent().unwrap_or(Path::new(""));
+
+    let mut assets = Vec::new();
+    for manifest_asset in manifest::read(&manifest_path)? {
+        let path = canonicalize(&manifest_dir.join(&manifest_asset.path))
+            .map_err(Error::CannotCanonicalizeFile)?;
+        let mut embedded_file_info = EmbeddedFileInfo::from_path(
+            &path,
+            None,
+            manifest_asset.compress,
+            manifest_asset.cache_bust,
+            &FileOptions {
+                should_strip_html_ext: false,
+                // An explicit content type makes the extension irrelevant
+                unknown_content_type: manifest_asset
+                    .content_type
+                    .is_some()
+                    .then_some(OCTET_STREAM),
+                charset: Charset::default().0.as_deref(),
+                last_modified_only: false,
+                injected_import_map: None,
+                etag_scheme: EtagScheme::default(),
+                gzip_level: GzipLevel::default().0,
+                zstd_level: ZstdLevel::default().0,
+                use_precompressed: false,
+                minify: false,
+                template_vars: None,
+                max_age: None,
+                mime_overrides: &MimeOverrides::default(),
+                compress_policy: &CompressPolicy::default(),
+            },
         )?;
+        embedded_file_info.entry_path = Some(
+            manifest_asset
+                .route
+                .unwrap_or_else(|| percent::web_route(Path::new(&manifest_asset.path))),
+        );
+        if let Some(content_type) = manifest_asset.content_type {
+            embedded_file_info.content_type = content_type;
+        }
 
-        routes.push(quote! {
-            router = ::static_serve::static_route(
-                router,
-                #entry_path,
-                #content_type,
-                #etag_str,
-                {
-                    // Poor man's `tracked_path`
-                    // https://github.com/rust-lang/rust/issues/99515
-                    const _: &[u8] = include_bytes!(#entry_str);
-                        #lit_byte_str_contents
-                },
-                #maybe_gzip,
-                #maybe_zstd,
-                #cache_busted
-            );
+        assets.push(embedded_file_info);
+    }
+
+    generated_items(
+        (&assets, &ExtraRoutes::default(), None),
+        (DEFAULT_SHARD_SIZE, &BytesDir::from_env()),
+        None,
+        (false, false),
+        (None, [false; 3]),
+        &parse_quote!(pub),
+    )
+}
+
+impl NonUtf8Paths {
+    /// The warning listing the `skipped_paths`, if any
+    fn skipped_warning(skipped_paths: &[PathBuf]) -> Option<TokenStream> {
+        if skipped_paths.is_empty() {
+            return None;
+        }
+        let mut note = String::from("Skipped the files whose paths aren't valid UTF-8:");
+        for path in skipped_paths {
+            note.push_str("\n  ");
+            note.push_str(&path.to_string_lossy());
+        }
+        Some(compile_warning("SKIPPED_NON_UTF8_PATHS", &note))
+    }
+}
+
+/// A warning shown by the compiler with `note`, named `name`
+///
+/// Proc macros cannot emit warnings on stable Rust,
+/// but the compiler warns about deprecated items.
+fn compile_warning(name: &str, note: &str) -> TokenStream {
+    let name = Ident::new(name, Span::call_site());
+    quote! {
+        const _: () = {
+            #[deprecated(note = #note)]
+            const #name: () = ();
+            #name
+        };
+    }
+}
+
+/// Percent-encode the bytes of `path` which aren't valid UTF-8,
+/// keeping the valid parts as they are, with `/` separators
+fn percent_encode_invalid_utf8(path: &Path) -> String {
+    let mut encoded = String::new();
+    for (i, component) in path.components().enumerate() {
+        if i > 0 {
+            encoded.push('/');
+        }
+        for chunk in component.as_os_str().as_encoded_bytes().utf8_chunks() {
+            encoded.push_str(chunk.valid());
+            for byte in chunk.invalid() {
+                write!(encoded, "%{byte:02X}").expect("writing to a String cannot fail");
+            }
+        }
+    }
+    encoded
+}
+
+/// The relative `path` with `/` separators, rather than the `\` of Windows,
+/// or `None` when it isn't valid UTF-8
+fn slash_separated(path: &Path) -> Option<String> {
+    let components = path
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<Vec<_>>>()?;
+    Some(components.join("/"))
+}
+
+/// Canonicalize `path`, without the `\\?\` prefix of the verbatim paths
+/// returned on Windows when the path is still valid without it, so that
+/// the errors and the `include_bytes!` of the expanded code show the usual
+/// `C:\assets\app.js` form
+fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    path.canonicalize().map(|path| strip_verbatim_prefix(&path))
+}
+
+/// `path` without its `\\?\` or `\\?\UNC\` prefix, unless the path
+/// needs it: too long for the legacy APIs, or with components ending
+/// with a dot or a space, which Windows strips otherwise
+fn strip_verbatim_prefix(path: &Path) -> PathBuf {
+    /// The maximum length of the paths of the legacy Windows APIs
+    const MAX_PATH: usize = 260;
+
+    let mut components = path.components();
+    let Some(Component::Prefix(prefix)) = components.next() else {
+        return path.to_owned();
+    };
+    let mut stripped = match prefix.kind() {
+        Prefix::VerbatimDisk(disk) => PathBuf::from(format!("{}:", char::from(disk))),
+        Prefix::VerbatimUNC(server, share) => {
+            let mut unc = OsString::from(r"\\");
+            unc.push(server);
+            unc.push(r"\");
+            unc.push(share);
+            PathBuf::from(unc)
+        }
+        _ => return path.to_owned(),
+    };
+    stripped.push(components.as_path());
+    let needs_prefix = stripped.as_os_str().len() >= MAX_PATH
+        || components.any(|component| {
+            let name = component.as_os_str().as_encoded_bytes();
+            name.ends_with(b".") || name.ends_with(b" ")
         });
+    if needs_prefix {
+        path.to_owned()
+    } else {
+        stripped
     }
+}
 
+/// A file of the assets directories to embed
+struct AssetEntry {
+    /// The canonical path of the file
+    path: PathBuf,
+    /// The path of the file relative to its assets directory
+    relative_path: String,
+    /// The route derived from the relative path, percent-encoded
+    route: String,
+    cache_busted: bool,
+}
+
+/// Add the `dir_entries` of an assets directory to the `entries` of the
+/// previous ones, replacing the files at the same relative paths, unless
+/// `deny_conflicts` is set
+fn merge_entries(
+    entries: &mut Vec<AssetEntry>,
+    dir_entries: Vec<AssetEntry>,
+    deny_conflicts: bool,
+) -> Result<(), Error> {
+    for entry in dir_entries {
+        let web_path = normalize_web_path(&entry.relative_path);
+        let conflicting = entries
+            .iter_mut()
+            .find(|existing| normalize_web_path(&existing.relative_path) == web_path);
+        match conflicting {
+            Some(_) if deny_conflicts => return Err(Error::ConflictingAssets(web_path)),
+            Some(existing) => *existing = entry,
+            None => entries.push(entry),
+        }
+    }
+    Ok(())
+}
+
+/// Collect the files of the assets directory, skipping the ignored paths
+/// and the ones rejected by the `include` and `exclude` patterns
+///
+/// The files whose paths aren't valid UTF-8 are handled according to
+/// `non_utf8_paths`, the skipped ones being added to `skipped_paths`.
+fn asset_entries(
+    assets_dir_abs_str: &str,
+    (ignore_paths, path_filter): (&IgnorePaths, &PathFilter),
+    cache_busted_paths: &CacheBustedPaths,
+    (non_utf8_paths, skipped_paths): (NonUtf8Paths, &mut Vec<PathBuf>),
+) -> Result<Vec<AssetEntry>, error::Error> {
+    let canon_ignore_paths = ignore_paths
+        .0
+        .iter()
+        .map(|d| canonicalize(d).map_err(Error::CannotCanonicalizeIgnorePath))
+        .collect::<Result<Vec<_>, _>>()?;
+    let canon_cache_busted_dirs = cache_busted_paths
+        .dirs
+        .iter()
+        .map(|d| canonicalize(d).map_err(Error::CannotCanonicalizeCacheBustedDir))
+        .collect::<Result<Vec<_>, _>>()?;
+    let canon_cache_busted_files = cache_busted_paths
+        .files
+        .iter()
+        .map(|file| canonicalize(file).map_err(Error::CannotCanonicalizeFile))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut entries = Vec::new();
+    // Rather than a `**/*` glob, which silently skips the files
+    // whose names aren't valid UTF-8
+    for entry in walk_files(Path::new(assets_dir_abs_str))? {
+        // Skip `entry`s which are located in ignored paths
+        if canon_ignore_paths
+            .iter()
+            .any(|ignore_path| entry.starts_with(ignore_path))
+        {
+            continue;
+        }
+
+        let relative_route = entry
+            .strip_prefix(assets_dir_abs_str)
+            .expect("the files are walked from the assets directory");
+        let relative_entry = match (slash_separated(relative_route), non_utf8_paths) {
+            (Some(relative_entry), _) => relative_entry,
+            (None, NonUtf8Paths::Error) => return Err(Error::NonUtf8Path(entry)),
+            (None, NonUtf8Paths::Skip) => {
+                skipped_paths.push(entry);
+                continue;
+            }
+            (None, NonUtf8Paths::Encode) => percent_encode_invalid_utf8(relative_route),
+        };
+        if !path_filter.allows(&normalize_web_path(&relative_entry)[1..]) {
+            continue;
+        }
+
+        let mut is_entry_cache_busted = false;
+        if canon_cache_busted_dirs
+            .iter()
+            .any(|dir| entry.starts_with(dir))
+            || canon_cache_busted_files.contains(&entry)
+        {
+            is_entry_cache_busted = true;
+        }
+
+        let entry = canonicalize(&entry).map_err(Error::CannotCanonicalizeFile)?;
+        entries.push(AssetEntry {
+            route: percent::web_route(relative_route),
+            path: entry,
+            relative_path: relative_entry,
+            cache_busted: is_entry_cache_busted,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// The files under `dir`, following symbolic links, in the order of their
+/// names, failing on the links to their own ancestors, which would make
+/// the walk loop forever
+fn walk_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(dir).follow_links(true).sort_by_file_name() {
+        let entry = entry.map_err(|error| match error.loop_ancestor() {
+            Some(ancestor) => Error::SymlinkLoop {
+                link: error.path().unwrap_or(ancestor).to_owned(),
+                ancestor: ancestor.to_owned(),
+            },
+            None => Error::CannotReadDirectory(error.into()),
+        })?;
+        if !entry.file_type().is_dir() {
+            files.push(entry.into_path());
+        }
+    }
+    Ok(files)
+}
+
+/// The `Option<Fallback>` expression of the fallback page, with `asset`
+/// the expression of the asset at an index, served unless it's excluded by `cfg_gate`
+fn fallback_tokens(
+    fallback: Option<(usize, FallbackKind)>,
+    asset: impl Fn(usize) -> TokenStream,
+) -> TokenStream {
+    let Some((index, kind)) = fallback else {
+        return quote! { ::std::option::Option::None };
+    };
+    let asset = asset(index);
+    let variant = match kind {
+        FallbackKind::NotFound => quote! { NotFound },
+        FallbackKind::Spa => quote! { Spa },
+    };
+    quote! {
+        __STATIC_SERVE_INCLUDED[#index].then(|| ::static_serve::Fallback::#variant(#asset))
+    }
+}
+
+/// The `rocket_routes()`, poem `static_routes()` and `salvo_router()`
+/// functions, only generated on demand since they depend on the `rocket`,
+/// `poem` and `salvo` crates
+fn framework_items(
+    vis: &Visibility,
+    [rocket_routes, poem_routes, salvo_router]: [bool; 3],
+) -> TokenStream {
+    let rocket_items = rocket_routes.then(|| {
+        quote! {
+            ::static_serve::__rocket_items! {
+                /// The Rocket routes of the embedded assets, to mount under any base
+                #[allow(dead_code)]
+                #vis fn rocket_routes() -> ::std::vec::Vec<::rocket::Route> {
+                    ::static_serve::__rocket_routes!(rocket, static_service())
+                }
+            }
+        }
+    });
+    let poem_items = poem_routes.then(|| {
+        quote! {
+            ::static_serve::__poem_items! {
+                /// The poem route of the embedded assets, to nest under any path
+                #[allow(dead_code)]
+                #vis fn static_routes() -> ::poem::Route {
+                    ::static_serve::__poem_routes!(poem, static_service())
+                }
+            }
+        }
+    });
+    let salvo_items = salvo_router.then(|| {
+        quote! {
+            ::static_serve::__salvo_items! {
+                /// The salvo router of the embedded assets, to push under any path
+                #[allow(dead_code)]
+                #vis fn salvo_router() -> ::salvo::Router {
+                    ::static_serve::__salvo_routes!(salvo, static_service())
+                }
+            }
+        }
+    });
+    quote! {
+        #rocket_items
+
+        #poem_items
+
+        #salvo_items
+    }
+}
+
+/// Generate the items emitted by `embed_assets!`: the table of embedded
+/// `assets` (tuples of route and `StaticAsset`), the tables of their
+/// `extra_routes`, and the functions using them
+///
+/// The table is built by functions of `shard_size` assets each, with
+/// the bytes embedded by `bytes_dir`. `fallback` is the index of the asset served for the unmatched routes, and how.
+/// With `debug_from_disk`, debug builds read the files at request time, and
+/// with `fall_through_methods`, the other methods than `GET` and `HEAD` are
+/// left to the other routes. The method router of every route is passed to
+/// `wrap`, if any. The functions are declared with the visibility `vis`.
+fn generated_items(
+    (assets, extra_routes, dictionary): (&[EmbeddedFileInfo], &ExtraRoutes, Option<&LitByteStr>),
+    (shard_size, bytes_dir): (NonZeroUsize, &BytesDir),
+    fallback: Option<(usize, FallbackKind)>,
+    (debug_from_disk, fall_through_methods): (bool, bool),
+    (wrap, frameworks): (Option<&syn::Path>, [bool; 3]),
+    vis: &Visibility,
+) -> Result<TokenStream, Error> {
+    let assets_table = assets_table(assets, dictionary, shard_size, bytes_dir)?;
+    let route_tables = route_tables(
+        assets,
+        extra_routes,
+        (debug_from_disk, fall_through_methods),
+    );
+    let get_asset = lookup::get_asset_fn(assets, vis);
+    let asset_url = paths::asset_url_fn(assets, vis);
+    let included_flags = cfg_gate::included_flags(assets);
+    let all_routes = cfg_gate::gated_routes(&static_routes(assets, extra_routes));
+    let csp_hashes = csp::csp_hashes_fn(assets, vis);
+    let embedded_fallback = fallback_tokens(
+        fallback,
+        |index| quote! { &__static_serve_assets()[#index].1 },
+    );
+    let overridden_fallback = fallback_tokens(fallback, |index| quote! { overridden[#index].1 });
+    let framework_items = framework_items(vis, frameworks);
+    let axum_items = axum_items(
+        vis,
+        (&embedded_fallback, &overridden_fallback),
+        fall_through_methods,
+        wrap,
+    );
     Ok(quote! {
-    pub fn static_router<S>() -> ::axum::Router<S>
-        where S: ::std::clone::Clone + ::std::marker::Send + ::std::marker::Sync + 'static {
-            let mut router = ::axum::Router::<S>::new();
-            #(#routes)*
-            router
+        #assets_table
+
+        #included_flags
+
+        #route_tables
+
+        #[allow(dead_code)]
+        #vis fn static_router_for<B: ::static_serve::StaticAssetBackend>() -> B::Router {
+            let routes = __static_serve_routes();
+            B::router(routes, __static_serve_redirects(), #embedded_fallback)
+        }
+
+        /// The embedded assets as a tower `Service`, built once and shared
+        #[allow(dead_code)]
+        #vis fn static_service() -> ::static_serve::StaticService {
+            static SERVICE: ::std::sync::OnceLock<::static_serve::StaticService> =
+                ::std::sync::OnceLock::new();
+            SERVICE
+                .get_or_init(static_router_for::<::static_serve::StaticService>)
+                .clone()
+        }
+
+        #framework_items
+
+        #get_asset
+
+        #asset_url
+
+        /// The embedded assets, as a value which can be merged with other sets
+        #[allow(dead_code)]
+        #vis fn static_asset_set() -> ::static_serve::StaticAssetSet {
+            ::static_serve::StaticAssetSet::new(
+                __static_serve_routes(),
+                __static_serve_redirects(),
+                #embedded_fallback,
+            )
+        }
+
+        /// Every route answered by `static_router()`, sorted,
+        /// including the aliases and the redirected routes
+        #[allow(dead_code)]
+        #vis const STATIC_ROUTES: &[&str] = #all_routes;
+
+        #[allow(dead_code)]
+        #vis fn asset_manifest() -> &'static [::static_serve::AssetInfo] {
+            static MANIFEST: ::std::sync::OnceLock<::std::vec::Vec<::static_serve::AssetInfo>> =
+                ::std::sync::OnceLock::new();
+            MANIFEST.get_or_init(|| {
+                let mut manifest = ::static_serve::asset_manifest(__static_serve_assets());
+                let mut included = __STATIC_SERVE_INCLUDED.iter();
+                manifest.retain(|_| included.next().copied().unwrap_or(true));
+                manifest
+            })
+        }
+
+        #csp_hashes
+
+        #[allow(dead_code)]
+        #vis fn debug_verify() -> ::std::result::Result<(), ::static_serve::VerifyError> {
+            ::static_serve::verify_assets(__static_serve_assets())
+        }
+
+        /// Write the embedded assets to `dir`, under their routes
+        #[allow(dead_code)]
+        #vis fn write_assets_to(dir: &::std::path::Path) -> ::std::io::Result<()> {
+            write_assets_to_with(dir, ::static_serve::WriteOptions::new()).map(|_| ())
+        }
+
+        /// Write the embedded assets to `dir` with `options`, returning
+        /// the routes and the paths of the files written for them
+        #[allow(dead_code)]
+        #vis fn write_assets_to_with(
+            dir: &::std::path::Path,
+            options: ::static_serve::WriteOptions,
+        ) -> ::std::io::Result<::std::vec::Vec<(&'static str, ::std::path::PathBuf)>> {
+            ::static_serve::write_assets(__static_serve_assets(), &__STATIC_SERVE_INCLUDED, dir, options)
+        }
+
+        #axum_items
+    })
+}
+
+/// The items generated by `embed_assets!` which depend on axum, whose routers
+/// fall back to `embedded_fallback`, or to `overridden_fallback` in
+/// `static_router_with_overrides`
+fn axum_items(
+    vis: &Visibility,
+    (embedded_fallback, overridden_fallback): (&TokenStream, &TokenStream),
+    fall_through_methods: bool,
+    wrap: Option<&syn::Path>,
+) -> TokenStream {
+    let embedded_router = axum_router(&quote! { __static_serve_routes() }, embedded_fallback, wrap);
+    let overridden_router = axum_router(&quote! { &routes }, overridden_fallback, wrap);
+    let configured_wrap = wrap.map_or_else(
+        || quote! { |_, method_router| method_router },
+        |wrap| quote! { #wrap },
+    );
+    quote! {
+        ::static_serve::__axum_items! {
+            #vis fn static_router<S>() -> ::axum::Router<S>
+            where S: ::std::clone::Clone + ::std::marker::Send + ::std::marker::Sync + 'static {
+                #embedded_router
+            }
+
+            // The replaced contents are leaked, so only the tests enable it
+            ::static_serve::__overrides_items! {
+                #[allow(dead_code)]
+                #vis fn static_router_with_overrides<S>(
+                    overrides: &::static_serve::AssetOverrides,
+                ) -> ::axum::Router<S>
+                where S: ::std::clone::Clone + ::std::marker::Send + ::std::marker::Sync + 'static {
+                    let overridden = ::static_serve::apply_overrides(__static_serve_assets(), overrides);
+                    let mut routes: ::std::vec::Vec<::static_serve::Route> = overridden
+                        .iter()
+                        .map(|&(web_path, asset)| ::static_serve::Route {
+                            web_path,
+                            asset,
+                            file_path: ::std::option::Option::None,
+                            fall_through_methods: #fall_through_methods,
+                        })
+                        .collect();
+                    ::static_serve::add_aliases(&mut routes, __static_serve_aliases());
+                    ::static_serve::remove_excluded(&mut routes, __static_serve_aliases(), &__STATIC_SERVE_INCLUDED);
+                    #overridden_router
+                }
+            }
+
+            #[allow(dead_code)]
+            #vis fn static_router_with_config<S>(
+                config: &::static_serve::StaticServeConfig,
+            ) -> ::std::result::Result<::axum::Router<S>, ::static_serve::ConfigError>
+            where S: ::std::clone::Clone + ::std::marker::Send + ::std::marker::Sync + 'static {
+                ::static_serve::Axum::<S>::configured_router(
+                    __static_serve_routes(),
+                    __static_serve_redirects(),
+                    #embedded_fallback,
+                    config,
+                    #configured_wrap,
+                )
+            }
+        }
+    }
+}
+
+/// The expression of the axum router of `routes` and `fallback`,
+/// whose method routers are passed to `wrap`, if any
+fn axum_router(
+    routes: &TokenStream,
+    fallback: &TokenStream,
+    wrap: Option<&syn::Path>,
+) -> TokenStream {
+    let Some(wrap) = wrap else {
+        return quote! {
+            <::static_serve::Axum<S> as ::static_serve::StaticAssetBackend>::router(
+                #routes,
+                __static_serve_redirects(),
+                #fallback,
+            )
+        };
+    };
+    quote! {
+        ::static_serve::Axum::<S>::wrapped_router(#routes, __static_serve_redirects(), #fallback, #wrap)
+    }
+}
+
+/// Every route of the `assets` and of the `extra_routes`, sorted,
+/// with the `cfg_gate` predicate of the asset it serves, if any
+fn static_routes<'a>(
+    assets: &'a [EmbeddedFileInfo],
+    extra_routes: &'a ExtraRoutes,
+) -> Vec<(&'a str, Option<&'a TokenStream>)> {
+    let mut routes: Vec<(&str, Option<&TokenStream>)> = assets
+        .iter()
+        .filter_map(|asset| Some((asset.entry_path.as_deref()?, asset.cfg_gate.as_ref())))
+        .chain(
+            extra_routes
+                .aliases
+                .iter()
+                .map(|(alias, index)| (alias.as_str(), assets[*index].cfg_gate.as_ref())),
+        )
+        .chain(
+            extra_routes
+                .redirects
+                .iter()
+                .map(|(from, _)| (from.as_str(), None)),
+        )
+        .collect();
+    routes.sort_unstable_by_key(|&(route, _)| route);
+    routes
+}
+
+/// The number of assets per function of the table of the assets by default
+const DEFAULT_SHARD_SIZE: NonZeroUsize = NonZeroUsize::new(256).unwrap();
+
+/// Generate `__static_serve_assets`, the table of the embedded `assets`,
+/// built at startup when some of their bodies are embedded compressed
+///
+/// Beyond `shard_size` assets, the table is built by functions returning
+/// `shard_size` consecutive assets each, so that rustc processes several
+/// small bodies rather than a single huge one with thousands of files.
+/// The assets are ordered by path, so the shards follow the directories.
+fn assets_table(
+    assets_info: &[EmbeddedFileInfo],
+    dictionary: Option<&LitByteStr>,
+    shard_size: NonZeroUsize,
+    bytes_dir: &BytesDir,
+) -> Result<TokenStream, Error> {
+    let assets_len = assets_info.len();
+    let is_decompressed_at_startup = assets_info
+        .iter()
+        .any(|asset| asset.stored_body.is_decompressed_at_startup());
+    // The bodies decompressed at startup or read from
+    // the bundle can't be built at compile time
+    let is_built_at_startup = is_decompressed_at_startup || bytes_dir.is_bundled();
+    let constness = (!is_built_at_startup).then(|| quote! { const });
+    let assets = assets_info
+        .iter()
+        .map(|asset| {
+            let entry_path = &asset.entry_path;
+            let mut static_asset = asset.static_asset(bytes_dir)?;
+            if let Some(predicate) = &asset.cfg_gate {
+                static_asset = cfg_gate::gated_asset(&static_asset, predicate, constness.as_ref());
+            }
+            Ok(quote! {
+                (#entry_path, #static_asset)
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let (shards, assets) = if assets.len() > shard_size.get() {
+        let mut shard_fns = Vec::new();
+        let mut shard_calls = Vec::new();
+        let mut bindings = Vec::new();
+        for (index, shard) in assets.chunks(shard_size.get()).enumerate() {
+            let shard_fn = format_ident!("__static_serve_shard_{index}");
+            let shard_len = shard.len();
+            let shard_bindings: Vec<_> = (bindings.len()..bindings.len() + shard_len)
+                .map(|index| format_ident!("asset_{index}"))
+                .collect();
+            shard_fns.push(quote! {
+                #constness fn #shard_fn() -> [(&'static str, ::static_serve::StaticAsset); #shard_len] {
+                    [#(#shard),*]
+                }
+            });
+            shard_calls.push(quote! { let [#(#shard_bindings),*] = #shard_fn(); });
+            bindings.extend(shard_bindings);
+        }
+        (
+            quote! { #(#shard_fns)* },
+            quote! {{
+                #(#shard_calls)*
+                [#(#bindings),*]
+            }},
+        )
+    } else {
+        (TokenStream::new(), quote! { [#(#assets),*] })
+    };
+
+    Ok(if is_built_at_startup {
+        let dictionary = dictionary
+            .map(|dictionary| {
+                let dictionary = bytes_dir.bytes(dictionary)?;
+                Ok::<_, Error>(quote! { static __STATIC_SERVE_DICTIONARY: &[u8] = #dictionary; })
+            })
+            .transpose()?;
+        let bundle = bytes_dir.write_bundle()?;
+        quote! {
+            fn __static_serve_assets() -> &'static [(&'static str, ::static_serve::StaticAsset)] {
+                #dictionary
+                #bundle
+                #shards
+                static ASSETS: ::std::sync::OnceLock<[(&str, ::static_serve::StaticAsset); #assets_len]> =
+                    ::std::sync::OnceLock::new();
+                ASSETS.get_or_init(|| #assets)
+            }
+        }
+    } else {
+        quote! {
+            fn __static_serve_assets() -> &'static [(&'static str, ::static_serve::StaticAsset)] {
+                #shards
+                static ASSETS: [(&str, ::static_serve::StaticAsset); #assets_len] = #assets;
+                &ASSETS
+            }
         }
     })
 }
 
-fn generate_static_handler(
-    asset_file: &LitStr,
-    should_compress: &LitBool,
-    cache_busted: &LitBool,
-    allow_unknown_extensions: &LitBool,
-) -> Result<TokenStream, error::Error> {
-    let asset_file_abs = Path::new(&asset_file.value())
-        .canonicalize()
-        .map_err(Error::CannotCanonicalizeFile)?;
-    let asset_file_abs_str = asset_file_abs.to_str().ok_or(Error::FilePathIsNotUtf8)?;
-
-    let EmbeddedFileInfo {
-        entry_path: _,
-        content_type,
-        etag_str,
-        lit_byte_str_contents,
-        maybe_gzip,
-        maybe_zstd,
-        cache_busted,
-    } = EmbeddedFileInfo::from_path(
+/// Generate the tables of the `extra_routes` of the `assets`,
+/// and the function listing all the routes
+///
+/// With `debug_from_disk`, the routes of debug builds read the files, and
+/// with `fall_through_methods`, they leave the other methods than `GET`
+/// and `HEAD` to the other routes.
+fn route_tables(
+    assets: &[EmbeddedFileInfo],
+    extra_routes: &ExtraRoutes,
+    (debug_from_disk, fall_through_methods): (bool, bool),
+) -> TokenStream {
+    let assets_len = assets.len();
+    let file_paths = if debug_from_disk {
+        let file_paths = assets
+            .iter()
+            .map(|asset| OptionStr(asset.file_path.clone()));
+        quote! {
+            // The paths of the files are only embedded in debug builds
+            #[cfg(debug_assertions)]
+            let file_paths: [::std::option::Option<&'static str>; #assets_len] = [#(#file_paths),*];
+            #[cfg(not(debug_assertions))]
+            let file_paths = [::std::option::Option::None; #assets_len];
+        }
+    } else {
+        quote! {
+            let file_paths = [::std::option::Option::None; #assets_len];
+        }
+    };
+    let aliases = extra_routes
+        .aliases
+        .iter()
+        .map(|(alias, index)| quote! { (#alias, #index) });
+    let redirects_len = extra_routes.redirects.len();
+    let redirects = extra_routes.redirects.iter().map(|(from, to)| {
+        quote! { ::static_serve::Redirect { from: #from, to: #to } }
+    });
+    quote! {
+        fn __static_serve_aliases() -> &'static [(&'static str, usize)] {
+            &[#(#aliases),*]
+        }
+
+        fn __static_serve_redirects() -> &'static [::static_serve::Redirect] {
+            static REDIRECTS: [::static_serve::Redirect; #redirects_len] = [#(#redirects),*];
+            &REDIRECTS
+        }
+
+        fn __static_serve_routes() -> &'static [::static_serve::Route] {
+            static ROUTES: ::std::sync::OnceLock<::std::vec::Vec<::static_serve::Route>> =
+                ::std::sync::OnceLock::new();
+            ROUTES.get_or_init(|| {
+                #file_paths
+                let mut routes: ::std::vec::Vec<::static_serve::Route> = __static_serve_assets()
+                    .iter()
+                    .zip(file_paths)
+                    .map(|((web_path, asset), file_path)| ::static_serve::Route {
+                        web_path,
+                        asset,
+                        file_path,
+                        fall_through_methods: #fall_through_methods,
+                    })
+                    .collect();
+                ::static_serve::add_aliases(&mut routes, __static_serve_aliases());
+                ::static_serve::remove_excluded(&mut routes, __static_serve_aliases(), &__STATIC_SERVE_INCLUDED);
+                routes
+            })
+        }
+    }
+}
+
+/// The `path` of an item of the module invoking `embed_assets!`,
+/// as seen from the module of `fn_name`
+fn path_from_parent(path: &syn::Path) -> syn::Path {
+    let is_absolute = path.leading_colon.is_some()
+        || path
+            .segments
+            .first()
+            .is_some_and(|segment| segment.ident == "crate");
+    if is_absolute {
+        return path.clone();
+    }
+    let mut from_parent: syn::Path = parse_quote!(super);
+    let mut segments = path.segments.iter().peekable();
+    // `self::wrap` is `super::wrap`
+    if segments
+        .peek()
+        .is_some_and(|segment| segment.ident == "self")
+    {
+        segments.next();
+    }
+    from_parent.segments.extend(segments.cloned());
+    from_parent
+}
+
+/// Wrap the generated `items` in the module `fn_name`, next to the function
+/// `fn_name` building their router, so that several invocations can
+/// coexist in the same module
+fn named_items(fn_name: &Ident, vis: &Visibility, items: &TokenStream) -> TokenStream {
+    quote! {
+        #vis mod #fn_name {
+            #items
+        }
+
+        ::static_serve::__axum_items! {
+            #vis fn #fn_name<S>() -> ::axum::Router<S>
+            where S: ::std::clone::Clone + ::std::marker::Send + ::std::marker::Sync + 'static {
+                #fn_name::static_router()
+            }
+        }
+    }
+}
+
+fn generate_static_handler(embed_asset: &EmbedAsset) -> Result<TokenStream, error::Error> {
+    let EmbedAsset {
+        asset_file: AssetFile(asset_file),
+        should_compress: ShouldCompress(algorithms),
+        cache_busted: IsCacheBusted(cache_busted),
+        unknown_content_type,
+        last_modified_only,
+        etag_salt,
+        etag_algorithm,
+        gzip_level: GzipLevel(gzip_level),
+        zstd_level: ZstdLevel(zstd_level),
+        max_age,
+        charset: Charset(charset),
+        cors: Cors(cors),
+        security_headers,
+    } = embed_asset;
+    let etag_salt = etag_salt.as_ref().map(LitStr::value);
+
+    let asset_file_abs =
+        canonicalize(Path::new(&asset_file.value())).map_err(Error::CannotCanonicalizeFile)?;
+
+    let mut static_asset = EmbeddedFileInfo::from_path(
         &asset_file_abs,
         None,
-        should_compress,
-        &LitBool {
-            value: false,
-            span: Span::call_site(),
-        },
+        *algorithms,
         cache_busted.value(),
-        allow_unknown_extensions.value(),
+        &FileOptions {
+            should_strip_html_ext: false,
+            unknown_content_type: unknown_content_type.as_deref(),
+            charset: charset.as_deref(),
+            last_modified_only: last_modified_only.value(),
+            injected_import_map: None,
+            etag_scheme: EtagScheme {
+                algorithm: *etag_algorithm,
+                salt: etag_salt.as_deref().unwrap_or_default(),
+            },
+            gzip_level: *gzip_level,
+            zstd_level: *zstd_level,
+            use_precompressed: false,
+            minify: false,
+            template_vars: None,
+            max_age: max_age.as_ref().map(|MaxAge(max_age)| *max_age),
+            mime_overrides: &MimeOverrides::default(),
+            compress_policy: &CompressPolicy::default(),
+        },
     )?;
+    static_asset.cors.clone_from(cors);
+    security_headers.add_to(&mut static_asset.headers);
+    static_asset.hash_integrity(*etag_algorithm);
+    let static_asset = static_asset.static_asset(&BytesDir::from_env())?;
 
     let route = quote! {
-        ::static_serve::static_method_router(
-            #content_type,
-            #etag_str,
-            {
-                // Poor man's `tracked_path`
-                // https://github.com/rust-lang/rust/issues/99515
-                const _: &[u8] = include_bytes!(#asset_file_abs_str);
-                #lit_byte_str_contents
-            },
-            #maybe_gzip,
-            #maybe_zstd,
-            #cache_busted
-        )
+        {
+            static ASSET: ::static_serve::StaticAsset = #static_asset;
+            ::static_serve::static_method_router(&ASSET)
+        }
     };
 
     Ok(route)
@@ -658,50 +2070,255 @@ impl ToTokens for OptionBytesSlice {
     }
 }
 
+struct OptionStr(Option<String>);
+impl ToTokens for OptionStr {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        tokens.extend(if let Some(inner) = &self.0.as_ref() {
+            quote! { ::std::option::Option::Some(#inner) }
+        } else {
+            quote! { ::std::option::Option::None }
+        });
+    }
+}
+
 struct EmbeddedFileInfo {
     /// When creating a `Router`, we need the API path/route to the
     /// target file. If creating a `Handler`, this is not needed since
     /// the router is responsible for the file's path on the server.
     entry_path: Option<String>,
     content_type: String,
-    etag_str: String,
+    /// Omitted when conditional requests rely on `Last-Modified` only
+    etag_str: OptionStr,
+    /// The file's modification time as an HTTP-date
+    last_modified: OptionStr,
     lit_byte_str_contents: LitByteStr,
     maybe_gzip: OptionBytesSlice,
     maybe_zstd: OptionBytesSlice,
+    maybe_brotli: OptionBytesSlice,
     cache_busted: bool,
+    /// The other requests the asset is immutable at
+    immutable_aliases: ImmutableAliases,
+    /// An explicit `Cache-Control` value, overriding `cache_busted`
+    cache_control: OptionStr,
+    /// Extra headers from `headers`, as lowercase names and values
+    headers: Vec<(String, String)>,
+    /// How the body is embedded
+    stored_body: StoredBody,
+    /// The AVIF and WebP variants of an image, from `image_variants`
+    image_variants: Vec<EmbeddedFileInfo>,
+    /// The other representations of a data endpoint, from `accept_variants`
+    media_variants: Vec<EmbeddedFileInfo>,
+    /// The Subresource Integrity hash of the body, with a full `etag` algorithm
+    integrity: OptionStr,
+    /// The origins allowed by `cors`
+    cors: Vec<String>,
+    /// The names of the `ContentEncoding` variants in order
+    /// of preference, from `encoding_preference`
+    encoding_preference: Vec<&'static str>,
+    /// The size of the chunks of the body, from `stream_min_size`
+    stream_chunk_size: Option<NonZeroUsize>,
+    /// The realm and the credentials variable of the
+    /// Basic authentication of the asset, from `protected`
+    basic_auth: Option<(String, String)>,
+    /// Whether the asset is only served to signed URLs, from `signed`
+    signed: bool,
+    /// The header or the cookie the asset is only served with,
+    /// for the source maps hidden by `source_maps = "header-only"`
+    source_map_gate: Option<SourceMapGate>,
+    /// The `cfg` predicate which must hold for the asset to be embedded,
+    /// from `cfg_gate`
+    cfg_gate: Option<TokenStream>,
+    /// The file the asset was read from, tracked so that changes
+    /// to it trigger a rebuild. Assets generated at expansion time
+    /// have no file of their own.
+    file_path: Option<String>,
+}
+
+/// The requests an asset is immutable at, besides its own route when cache-busted
+#[derive(Clone, Copy, Default)]
+struct ImmutableAliases {
+    /// With the `v` query parameter, from `cache_bust_query`
+    query: bool,
+    /// At its `/_immutable/` alias, from `immutable_aliases`
+    route: bool,
+}
+
+/// Struct of the settings of `EmbeddedFileInfo::from_path` shared by
+/// all the files of an invocation (to avoid `clippy::too_many_arguments`)
+#[expect(clippy::struct_excessive_bools)]
+struct FileOptions<'a> {
+    should_strip_html_ext: bool,
+    /// The content type of the files with unknown extensions,
+    /// which fail the compilation when `None`
+    unknown_content_type: Option<&'a str>,
+    /// Appended to the textual content types
+    charset: Option<&'a str>,
+    last_modified_only: bool,
+    /// The import map to inject into HTML files
+    injected_import_map: Option<&'a str>,
+    etag_scheme: EtagScheme<'a>,
+    gzip_level: u32,
+    zstd_level: i32,
+    /// Embed the `.gz`, `.zst` and `.br` siblings of the files
+    /// rather than compressing them
+    use_precompressed: bool,
+    /// Minify the HTML, CSS and JavaScript files without such siblings
+    minify: bool,
+    /// The values of the placeholders of the textual files, if substituted
+    template_vars: Option<&'a TemplateVars>,
+    /// The `max-age` of cache-busted files, in seconds
+    max_age: Option<u64>,
+    mime_overrides: &'a MimeOverrides,
+    compress_policy: &'a CompressPolicy,
+}
+
+/// The contents of a file, with their compressed variants and validators
+///
+/// Unlike `EmbeddedFileInfo`, they hold no tokens, so
+/// several files can be read and compressed in parallel.
+struct FileBodies {
+    content_type: String,
+    contents: Vec<u8>,
+    gzip: Option<Vec<u8>>,
+    zstd: Option<Vec<u8>>,
+    brotli: Option<Vec<u8>>,
+    etag_str: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl FileBodies {
+    /// Read the file at `pathbuf`, and compress it with `algorithms`
+    /// unless its pre-compressed siblings are used
+    fn read(pathbuf: &Path, algorithms: Algorithms, options: &FileOptions) -> Result<Self, Error> {
+        let &FileOptions {
+            unknown_content_type,
+            last_modified_only,
+            injected_import_map,
+            etag_scheme,
+            gzip_level,
+            zstd_level,
+            use_precompressed,
+            minify,
+            template_vars,
+            mime_overrides,
+            compress_policy,
+            ..
+        } = options;
+        let mut contents = fs::read(pathbuf).map_err(Error::CannotReadEntryContents)?;
+        let content_type = file_content_type(pathbuf, unknown_content_type, mime_overrides)?;
+
+        if let Some(import_map) = injected_import_map
+            && content_type == "text/html"
+        {
+            import_map::inject(&mut contents, import_map);
+        }
+
+        if let Some(template_vars) = template_vars
+            && is_textual(media_type(&content_type))
+        {
+            contents = template::substitute(&contents, template_vars, pathbuf)?;
+        }
+
+        let precompressed = if use_precompressed {
+            precompressed::read(pathbuf, &contents)?
+        } else {
+            Precompressed::default()
+        };
+        // The siblings were compressed from the original contents
+        if minify
+            && precompressed.is_empty()
+            && let Some(minified) = minify::minify(&content_type, &contents)
+        {
+            contents = minified;
+        }
+
+        // Optionally compress files, unless they have been already
+        let algorithms = if compress_policy.applies_to(&content_type, contents.len()) {
+            algorithms
+        } else {
+            Algorithms::default()
+        };
+        let gzip = match precompressed.gzip {
+            Some(gzip) => Some(gzip),
+            None if algorithms.gzip => gzip_compress(&contents, gzip_level)?,
+            None => None,
+        };
+        let zstd = match precompressed.zstd {
+            Some(zstd) => Some(zstd),
+            None if algorithms.zstd => zstd_compress(&contents, zstd_level)?,
+            None => None,
+        };
+        let brotli = match precompressed.brotli {
+            Some(brotli) => Some(brotli),
+            None if algorithms.brotli => brotli_compress(&contents)?,
+            None => None,
+        };
+
+        let (etag_str, last_modified) =
+            validators(&contents, [pathbuf], last_modified_only, etag_scheme)?;
+
+        Ok(Self {
+            content_type,
+            contents,
+            gzip,
+            zstd,
+            brotli,
+            etag_str,
+            last_modified,
+        })
+    }
 }
 
 impl EmbeddedFileInfo {
     fn from_path(
-        pathbuf: &PathBuf,
-        assets_dir_abs_str: Option<&str>,
-        should_compress: &LitBool,
-        should_strip_html_ext: &LitBool,
+        pathbuf: &Path,
+        route: Option<&str>,
+        algorithms: Algorithms,
         cache_busted: bool,
-        allow_unknown_extensions: bool,
+        options: &FileOptions,
     ) -> Result<Self, Error> {
-        let contents = fs::read(pathbuf).map_err(Error::CannotReadEntryContents)?;
-
-        // Optionally compress files
-        let (maybe_gzip, maybe_zstd) = if should_compress.value {
-            let gzip = gzip_compress(&contents)?;
-            let zstd = zstd_compress(&contents)?;
-            (gzip, zstd)
-        } else {
-            (None, None)
-        };
+        let bodies = FileBodies::read(pathbuf, algorithms, options)?;
+        Ok(Self::from_bodies(
+            pathbuf,
+            bodies,
+            route,
+            cache_busted,
+            options,
+        ))
+    }
 
-        let content_type = file_content_type(pathbuf, allow_unknown_extensions)?;
+    /// Build the asset of the file at `pathbuf` from its `bodies`,
+    /// served at `route`
+    fn from_bodies(
+        pathbuf: &Path,
+        bodies: FileBodies,
+        route: Option<&str>,
+        cache_busted: bool,
+        options: &FileOptions,
+    ) -> Self {
+        let &FileOptions {
+            should_strip_html_ext,
+            charset,
+            max_age,
+            ..
+        } = options;
+        let FileBodies {
+            content_type,
+            contents,
+            gzip,
+            zstd,
+            brotli,
+            etag_str,
+            last_modified,
+        } = bodies;
+        // Files embedded with `non_utf8_paths = "encode"` aren't tracked
+        let file_path = pathbuf.to_str().map(ToOwned::to_owned);
+        let to_lit_byte_str = |bytes: Vec<u8>| LitByteStr::new(&bytes, Span::call_site());
 
         // entry_path is only needed for the router (embed_assets!)
-        let entry_path = if let Some(dir) = assets_dir_abs_str {
-            let relative_entry = pathbuf
-                .strip_prefix(dir)
-                .ok()
-                .and_then(|p| p.to_str())
-                .ok_or(Error::InvalidUnicodeInEntryName)?;
-            let mut web_path = normalize_web_path(relative_entry);
-            if should_strip_html_ext.value && content_type == "text/html" {
+        let entry_path = if let Some(route) = route {
+            let mut web_path = route.to_owned();
+            if should_strip_html_ext && content_type == "text/html" {
                 strip_html_ext(&mut web_path);
             }
 
@@ -710,25 +2327,360 @@ impl EmbeddedFileInfo {
             None
         };
 
-        let etag_str = etag(&contents);
+        let etag_str = OptionStr(etag_str);
+        let last_modified = OptionStr(last_modified);
         let lit_byte_str_contents = LitByteStr::new(&contents, Span::call_site());
-        let maybe_gzip = OptionBytesSlice(maybe_gzip);
-        let maybe_zstd = OptionBytesSlice(maybe_zstd);
+        let maybe_gzip = OptionBytesSlice(gzip.map(to_lit_byte_str));
+        let maybe_zstd = OptionBytesSlice(zstd.map(to_lit_byte_str));
+        let maybe_brotli = OptionBytesSlice(brotli.map(to_lit_byte_str));
 
-        Ok(Self {
+        Self {
             entry_path,
+            content_type: with_charset(content_type, charset),
+            etag_str,
+            last_modified,
+            lit_byte_str_contents,
+            maybe_gzip,
+            maybe_zstd,
+            maybe_brotli,
+            cache_busted,
+            immutable_aliases: ImmutableAliases::default(),
+            cache_control: OptionStr(
+                max_age
+                    .filter(|_| cache_busted)
+                    .map(|max_age| format!("public, max-age={max_age}, immutable")),
+            ),
+            headers: Vec::new(),
+            stored_body: StoredBody::Identity,
+            image_variants: Vec::new(),
+            media_variants: Vec::new(),
+            integrity: OptionStr(None),
+            cors: Vec::new(),
+            encoding_preference: Vec::new(),
+            stream_chunk_size: None,
+            basic_auth: None,
+            signed: false,
+            source_map_gate: None,
+            cfg_gate: None,
+            file_path,
+        }
+    }
+
+    /// Build the `StaticAsset` expression embedding the file, with
+    /// the large bodies stored in `bytes_dir` when there is one
+    #[expect(clippy::too_many_lines)]
+    fn static_asset(&self, bytes_dir: &BytesDir) -> Result<TokenStream, Error> {
+        let Self {
+            entry_path: _,
             content_type,
             etag_str,
+            last_modified,
             lit_byte_str_contents,
             maybe_gzip,
             maybe_zstd,
+            maybe_brotli,
             cache_busted,
+            immutable_aliases:
+                ImmutableAliases {
+                    query: cache_bust_query,
+                    route: immutable_alias,
+                },
+            cache_control,
+            headers,
+            stored_body,
+            image_variants,
+            media_variants,
+            integrity,
+            cors,
+            encoding_preference,
+            stream_chunk_size,
+            basic_auth,
+            signed,
+            source_map_gate,
+            cfg_gate: _,
+            file_path,
+        } = self;
+        let (header_names, header_values): (Vec<_>, Vec<_>) = headers.iter().cloned().unzip();
+        let encoding_preference = encoding_preference
+            .iter()
+            .map(|variant| format_ident!("{variant}"));
+        let size = lit_byte_str_contents.value().len();
+        let stream_chunk_size = stream_chunk_size.map_or_else(
+            || quote! { ::std::option::Option::None },
+            |chunk_size| {
+                let chunk_size = chunk_size.get();
+                quote! { ::std::num::NonZeroUsize::new(#chunk_size) }
+            },
+        );
+        let basic_auth = basic_auth.as_ref().map_or_else(
+            || quote! { ::std::option::Option::None },
+            |(realm, credentials_var)| {
+                quote! {
+                    ::std::option::Option::Some(::static_serve::BasicAuth {
+                        realm: #realm,
+                        credentials_var: #credentials_var,
+                    })
+                }
+            },
+        );
+        let source_map_gate = SourceMapGate::tokens(source_map_gate.as_ref());
+        let mut maybe_gzip = bytes_dir.option_bytes(maybe_gzip)?;
+        let mut maybe_zstd = bytes_dir.option_bytes(maybe_zstd)?;
+        let maybe_brotli = bytes_dir.option_bytes(maybe_brotli)?;
+        // The variant the body is decompressed from, embedded once
+        let mut compressed_variant = None;
+        let mut lazy_body = quote! { ::std::option::Option::None };
+        let body = match (stored_body, &self.maybe_zstd.0, &self.maybe_gzip.0) {
+            (StoredBody::Dictionary(compressed), _, _) => {
+                let compressed = bytes_dir.bytes(compressed)?;
+                quote! {
+                    ::static_serve::decompress_with_dictionary(#compressed, __STATIC_SERVE_DICTIONARY, #size)
+                }
+            }
+            (StoredBody::Zstd, Some(compressed), _) => {
+                let compressed = bytes_dir.bytes(compressed)?;
+                compressed_variant = Some(quote! { static COMPRESSED: &[u8] = #compressed; });
+                maybe_zstd = quote! { ::std::option::Option::Some(COMPRESSED) };
+                quote! { ::static_serve::decompress_zstd(COMPRESSED, #size) }
+            }
+            (StoredBody::Gzip, _, Some(compressed)) => {
+                let compressed = bytes_dir.bytes(compressed)?;
+                compressed_variant = Some(quote! { static COMPRESSED: &[u8] = #compressed; });
+                maybe_gzip = quote! { ::std::option::Option::Some(COMPRESSED) };
+                quote! { ::static_serve::decompress_gzip(COMPRESSED, #size) }
+            }
+            (StoredBody::Lazy { gzip }, Some(compressed), _) => {
+                let compressed = bytes_dir.bytes(compressed)?;
+                compressed_variant = Some(quote! {
+                    static COMPRESSED: &[u8] = #compressed;
+                    static LAZY_BODY: ::static_serve::LazyBody =
+                        ::static_serve::LazyBody::new(COMPRESSED, #size, #gzip);
+                });
+                maybe_zstd = quote! { ::std::option::Option::Some(COMPRESSED) };
+                maybe_gzip = quote! { ::std::option::Option::None };
+                lazy_body = quote! { ::std::option::Option::Some(&LAZY_BODY) };
+                quote! { &[] }
+            }
+            _ => bytes_dir.bytes(lit_byte_str_contents)?,
+        };
+
+        // Poor man's `tracked_path`
+        // https://github.com/rust-lang/rust/issues/99515
+        let tracked_path = file_path.as_ref().map(|file_path| {
+            quote! {
+                const _: &[u8] = include_bytes!(#file_path);
+            }
+        });
+
+        let (image_variants_static, image_variants) =
+            variants_static(&format_ident!("IMAGE_VARIANTS"), image_variants, bytes_dir)?;
+        let (media_variants_static, media_variants) =
+            variants_static(&format_ident!("MEDIA_VARIANTS"), media_variants, bytes_dir)?;
+
+        Ok(quote! {{
+            #compressed_variant
+            #image_variants_static
+            #media_variants_static
+            ::static_serve::StaticAsset::from_fields(::static_serve::StaticAssetFields {
+                content_type: #content_type,
+                etag: #etag_str,
+                last_modified: #last_modified,
+                body: {
+                    #tracked_path
+                    #body
+                },
+                body_gz: #maybe_gzip,
+                body_zst: #maybe_zstd,
+                body_br: #maybe_brotli,
+                cache_busted: #cache_busted,
+                immutable_aliases: ::static_serve::ImmutableAliases {
+                    query: #cache_bust_query,
+                    route: #immutable_alias,
+                },
+                cache_control: #cache_control,
+                headers: &[#((#header_names, #header_values)),*],
+                cors: &[#(#cors),*],
+                lazy_body: #lazy_body,
+                image_variants: #image_variants,
+                media_variants: #media_variants,
+                integrity: #integrity,
+                stream_chunk_size: #stream_chunk_size,
+                basic_auth: #basic_auth,
+                signed: #signed,
+                encoding_preference: &[#(::static_serve::headers::ContentEncoding::#encoding_preference),*],
+                source_map_gate: #source_map_gate,
+            })
+        }})
+    }
+
+    /// Whether the asset is served to anyone in every build: neither
+    /// protected, signed, a hidden source map nor excluded by `cfg_gate`
+    fn is_public(&self) -> bool {
+        self.basic_auth.is_none()
+            && !self.signed
+            && self.source_map_gate.is_none()
+            && self.cfg_gate.is_none()
+    }
+
+    /// Serve the asset and its variants as immutable when requested with
+    /// the `v` query parameter, for `cache_bust_query = true`, unless it's
+    /// already cache-busted or has no `ETag` to put in the query
+    fn bust_cache_with_query(&mut self) {
+        if self.cache_busted || self.etag_str.0.is_none() {
+            return;
+        }
+        self.immutable_aliases.query = true;
+        for variant in self
+            .image_variants
+            .iter_mut()
+            .chain(&mut self.media_variants)
+        {
+            variant.immutable_aliases.query = true;
+        }
+    }
+
+    /// The `/_immutable/<hash>/<route>` alias of the asset, with the
+    /// first 16 alphanumeric characters of its `ETag` as the hash
+    fn immutable_alias_route(&self) -> Option<String> {
+        let route = self.entry_path.as_ref()?;
+        let hash: String = self
+            .etag_str
+            .0
+            .as_ref()?
+            .chars()
+            .filter(char::is_ascii_alphanumeric)
+            .take(16)
+            .collect();
+        Some(format!("/_immutable/{hash}{route}"))
+    }
+
+    /// The URL linking to the asset: its route, with the hash of
+    /// its contents in the `v` query parameter with `cache_bust_query`,
+    /// or its alias with `immutable_aliases`
+    fn url(&self) -> Option<String> {
+        if self.immutable_aliases.route {
+            return self.immutable_alias_route();
+        }
+        let route = self.entry_path.as_ref()?;
+        Some(match &self.etag_str.0 {
+            Some(etag) if self.immutable_aliases.query => {
+                format!("{route}?v={}", etag.trim_matches('"'))
+            }
+            _ => route.clone(),
         })
     }
+
+    /// Compute the Subresource Integrity hashes of the body and of the
+    /// variants, listed in the manifest with a full `etag` algorithm
+    fn hash_integrity(&mut self, algorithm: EtagAlgorithm) {
+        self.integrity = OptionStr(algorithm.integrity(&self.lit_byte_str_contents.value()));
+        for variant in self
+            .image_variants
+            .iter_mut()
+            .chain(&mut self.media_variants)
+        {
+            variant.hash_integrity(algorithm);
+        }
+    }
+
+    /// Embed only the zstd or else gzip variant of the body, when there
+    /// is one, for `identity = false`
+    fn drop_identity(&mut self) {
+        if self.maybe_zstd.0.is_some() {
+            self.stored_body = StoredBody::Zstd;
+        } else if self.maybe_gzip.0.is_some() {
+            self.stored_body = StoredBody::Gzip;
+        }
+    }
+
+    /// Embed only the zstd variant of the body, when there is one,
+    /// for `lazy_decompression = true`
+    fn decompress_lazily(&mut self) {
+        if self.maybe_zstd.0.is_some() {
+            self.stored_body = StoredBody::Lazy {
+                gzip: self.maybe_gzip.0.is_some(),
+            };
+        }
+    }
+}
+
+/// The static named `name` holding the `variants` of an asset, if any, and
+/// the `&'static [StaticAsset]` expression referencing it
+///
+/// The variants read from the bundle can't be built at compile
+/// time, so they're built on first access instead.
+fn variants_static(
+    name: &Ident,
+    variants: &[EmbeddedFileInfo],
+    bytes_dir: &BytesDir,
+) -> Result<(Option<TokenStream>, TokenStream), Error> {
+    if variants.is_empty() {
+        return Ok((None, quote! { &[] }));
+    }
+    let variants_len = variants.len();
+    let variants = variants
+        .iter()
+        .map(|variant| variant.static_asset(bytes_dir))
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(if bytes_dir.is_bundled() {
+        (
+            Some(quote! {
+                static #name: ::std::sync::LazyLock<[::static_serve::StaticAsset; #variants_len]> =
+                    ::std::sync::LazyLock::new(|| [#(#variants),*]);
+            }),
+            quote! { &*#name },
+        )
+    } else {
+        (
+            Some(quote! {
+                static #name: [::static_serve::StaticAsset; #variants_len] = [#(#variants),*];
+            }),
+            quote! { &#name },
+        )
+    })
+}
+
+/// How the body of an asset is embedded
+enum StoredBody {
+    /// As is
+    Identity,
+    /// Compressed with the shared dictionary of `zstd_dictionary`
+    Dictionary(LitByteStr),
+    /// Only as its zstd variant, with `identity = false`
+    Zstd,
+    /// Only as its gzip variant, with `identity = false`
+    Gzip,
+    /// Only as its zstd variant, decompressed on first access
+    /// with `lazy_decompression = true`, and whether the gzip
+    /// variant is derived from it
+    Lazy { gzip: bool },
+}
+
+impl StoredBody {
+    /// Whether the body is decompressed when the table of the assets is built
+    fn is_decompressed_at_startup(&self) -> bool {
+        matches!(self, Self::Dictionary(_) | Self::Zstd | Self::Gzip)
+    }
 }
 
-fn gzip_compress(contents: &[u8]) -> Result<Option<LitByteStr>, Error> {
-    let mut compressor = GzEncoder::new(Vec::new(), flate2::Compression::best());
+/// The base 2 logarithm of the window of the zstd variants, the largest
+/// one the browsers are required to support (RFC 8878, section 3)
+const ZSTD_WINDOW_LOG: u32 = 23;
+
+/// The quality of the Brotli variants, the highest one
+const BROTLI_QUALITY: u32 = 11;
+/// The base 2 logarithm of the window of the Brotli variants, the
+/// largest one of the format without the large window extension
+const BROTLI_WINDOW_BITS: u32 = 22;
+/// The size of the internal buffer of the Brotli encoder and decoder
+pub(crate) const BROTLI_BUFFER_SIZE: usize = 4096;
+
+/// The gzip variant of `contents`, if significantly smaller
+///
+/// It's decompressed again and compared with `contents`, failing the
+/// compilation rather than embedding a body the clients can't decode.
+fn gzip_compress(contents: &[u8], level: u32) -> Result<Option<Vec<u8>>, Error> {
+    let mut compressor = GzEncoder::new(Vec::new(), flate2::Compression::new(level));
     compressor
         .write_all(contents)
         .map_err(|e| Error::Gzip(GzipType::CompressorWrite(e)))?;
@@ -736,11 +2688,20 @@ fn gzip_compress(contents: &[u8]) -> Result<Option<LitByteStr>, Error> {
         .finish()
         .map_err(|e| Error::Gzip(GzipType::EncoderFinish(e)))?;
 
-    Ok(maybe_get_compressed(&compressed, contents))
+    if !is_compression_significant(compressed.len(), contents.len()) {
+        return Ok(None);
+    }
+    if !precompressed::gzip_decompresses_to(&compressed, contents) {
+        return Err(Error::Gzip(GzipType::RoundTrip));
+    }
+    Ok(Some(compressed))
 }
 
-fn zstd_compress(contents: &[u8]) -> Result<Option<LitByteStr>, Error> {
-    let level = *zstd::compression_level_range().end();
+/// The zstd variant of `contents`, if significantly smaller
+///
+/// Like the gzip one, it's decompressed again and compared with `contents`,
+/// also checking its window fits within `ZSTD_WINDOW_LOG`.
+fn zstd_compress(contents: &[u8], level: i32) -> Result<Option<Vec<u8>>, Error> {
     let mut encoder = zstd::Encoder::new(Vec::new(), level).unwrap();
     write_to_zstd_encoder(&mut encoder, contents)
         .map_err(|e| Error::Zstd(ZstdType::EncoderWrite(e)))?;
@@ -749,7 +2710,37 @@ fn zstd_compress(contents: &[u8]) -> Result<Option<LitByteStr>, Error> {
         .finish()
         .map_err(|e| Error::Zstd(ZstdType::EncoderFinish(e)))?;
 
-    Ok(maybe_get_compressed(&compressed, contents))
+    if !is_compression_significant(compressed.len(), contents.len()) {
+        return Ok(None);
+    }
+    if !precompressed::zstd_decompresses_to(&compressed, contents) {
+        return Err(Error::Zstd(ZstdType::RoundTrip));
+    }
+    Ok(Some(compressed))
+}
+
+/// The Brotli variant of `contents`, if significantly smaller
+///
+/// Like the gzip one, it's decompressed again and compared with `contents`.
+fn brotli_compress(contents: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+    let mut compressor = brotli::CompressorWriter::new(
+        Vec::new(),
+        BROTLI_BUFFER_SIZE,
+        BROTLI_QUALITY,
+        BROTLI_WINDOW_BITS,
+    );
+    compressor
+        .write_all(contents)
+        .map_err(|e| Error::Brotli(BrotliType::CompressorWrite(e)))?;
+    let compressed = compressor.into_inner();
+
+    if !is_compression_significant(compressed.len(), contents.len()) {
+        return Ok(None);
+    }
+    if !precompressed::brotli_decompresses_to(&compressed, contents) {
+        return Err(Error::Brotli(BrotliType::RoundTrip));
+    }
+    Ok(Some(compressed))
 }
 
 fn write_to_zstd_encoder(
@@ -762,7 +2753,7 @@ fn write_to_zstd_encoder(
             .try_into()
             .expect("contents size should fit into u64"),
     ))?;
-    encoder.window_log(23)?;
+    encoder.window_log(ZSTD_WINDOW_LOG)?;
     encoder.include_checksum(false)?;
     encoder.include_contentsize(false)?;
     encoder.long_distance_matching(false)?;
@@ -784,43 +2775,68 @@ fn maybe_get_compressed(compressed: &[u8], contents: &[u8]) -> Option<LitByteStr
 /// Use `mime_guess` to get the best guess of the file's MIME type
 /// by looking at its extension, or return an error if unable.
 ///
-/// If the `allow_unknown_extensions` parameter is true, an unknown ext
-/// will not produce an error, but application/octet-stream.
+/// If `unknown_content_type` is set, an unknown ext
+/// will not produce an error, but that content type.
+/// The extensions of `mime_overrides` take precedence over the guess.
 ///
 /// We accept the first guess because [`mime_guess` updates the order
 /// according to the latest IETF RTC](https://docs.rs/mime_guess/2.0.5/mime_guess/struct.MimeGuess.html#note-ordering)
-fn file_content_type(path: &Path, allow_unknown_extensions: bool) -> Result<String, error::Error> {
+fn file_content_type(
+    path: &Path,
+    unknown_content_type: Option<&str>,
+    mime_overrides: &MimeOverrides,
+) -> Result<String, error::Error> {
     let Some(ext) = path.extension() else {
-        return if allow_unknown_extensions {
-            Ok(mime_guess::mime::APPLICATION_OCTET_STREAM.to_string())
-        } else {
-            Err(error::Error::UnknownFileExtension(None))
-        };
+        return unknown_content_type
+            .map(ToOwned::to_owned)
+            .ok_or(error::Error::UnknownFileExtension(None));
     };
 
     let ext = ext
         .to_str()
         .ok_or(error::Error::InvalidFileExtension(path.into()))?;
 
-    let guess = mime_guess::MimeGuess::from_ext(ext);
-
-    if allow_unknown_extensions {
-        return Ok(guess.first_or_octet_stream().to_string());
+    if let Some(content_type) = mime_overrides.content_type(ext) {
+        return Ok(content_type);
     }
 
-    guess
+    mime_guess::MimeGuess::from_ext(ext)
         .first_raw()
+        .or(unknown_content_type)
         .map(ToOwned::to_owned)
         .ok_or(error::Error::UnknownFileExtension(Some(ext.into())))
 }
 
-fn etag(contents: &[u8]) -> String {
-    let sha256 = Sha256::digest(contents);
-    let hash = u64::from_le_bytes(sha256[..8].try_into().unwrap())
-        ^ u64::from_le_bytes(sha256[8..16].try_into().unwrap())
-        ^ u64::from_le_bytes(sha256[16..24].try_into().unwrap())
-        ^ u64::from_le_bytes(sha256[24..32].try_into().unwrap());
-    format!("\"{hash:016x}\"")
+/// The validators of an asset generated at expansion time from the files
+/// at `paths`: an `ETag` computed from its `contents`, unless conditional
+/// requests rely on `Last-Modified` only, and the latest modification time
+/// of the files, since the asset changes whenever any of them does
+fn validators<'a>(
+    contents: &[u8],
+    paths: impl IntoIterator<Item = &'a Path>,
+    last_modified_only: bool,
+    etag_scheme: EtagScheme<'_>,
+) -> Result<(Option<String>, Option<String>), Error> {
+    let etag = (!last_modified_only).then(|| etag_scheme.etag(contents));
+
+    let mut latest_modified = 0;
+    for path in paths {
+        latest_modified = latest_modified.max(modified_secs(path)?);
+    }
+    Ok((etag, Some(http_date::format(latest_modified))))
+}
+
+/// Get the modification time of the file in seconds since the UNIX epoch
+fn modified_secs(path: &Path) -> Result<u64, Error> {
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(Error::CannotGetModifiedTime)?;
+    let secs_since_epoch = modified
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| Error::ModifiedTimeBeforeUnixEpoch)?
+        .as_secs();
+
+    Ok(secs_since_epoch)
 }
 
 /// Convert a relative filesystem-style path into a rooted web route.
@@ -832,7 +2848,7 @@ fn normalize_web_path(relative_path: &str) -> String {
     let normalized = Path::new(relative_path)
         .components()
         .filter_map(|component| match component {
-            std::path::Component::Normal(segment) => segment.to_str(),
+            Component::Normal(segment) => segment.to_str(),
             _ => None,
         })
         .collect::<Vec<_>>()
@@ -856,3 +2872,228 @@ fn strip_html_ext(path: &mut String) {
         path.truncate(1);
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use proc_macro2::TokenStream;
+
+    use super::{
+        AssetEntry, CacheBustedPaths, EmbedAssets, Error, IgnorePaths, NonUtf8Paths, PathFilter,
+        asset_entries, canonicalize, walk_files,
+    };
+
+    /// The entries of the assets directory `dir`, with the default options
+    fn entries(
+        dir: &Path,
+        non_utf8_paths: NonUtf8Paths,
+    ) -> Result<(Vec<AssetEntry>, Vec<String>), Error> {
+        let path_filter = PathFilter {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            skip_hidden: false,
+            keep_hidden: Vec::new(),
+            skip_source_maps: false,
+        };
+        let cache_busted_paths = CacheBustedPaths {
+            dirs: Vec::new(),
+            files: Vec::new(),
+        };
+        let mut skipped_paths = Vec::new();
+        let entries = asset_entries(
+            dir.to_str().unwrap(),
+            (&IgnorePaths(Vec::new()), &path_filter),
+            &cache_busted_paths,
+            (non_utf8_paths, &mut skipped_paths),
+        )?;
+        let skipped_paths = skipped_paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        Ok((entries, skipped_paths))
+    }
+
+    #[test]
+    fn uses_slash_separators_on_every_platform() {
+        let dir = std::env::temp_dir().join("static-serve-separators-test");
+        fs::create_dir_all(dir.join("sub").join("nested")).unwrap();
+        fs::write(dir.join("sub").join("nested").join("app.js"), "").unwrap();
+        let dir = canonicalize(&dir).unwrap();
+        assert!(!dir.to_string_lossy().starts_with(r"\\?\"));
+
+        let Ok((entries, _)) = entries(&dir, NonUtf8Paths::Error) else {
+            panic!("the assets directory should be readable");
+        };
+        assert_eq!(entries[0].relative_path, "sub/nested/app.js");
+        assert_eq!(entries[0].route, "/sub/nested/app.js");
+        assert!(entries[0].path.starts_with(&dir));
+    }
+
+    /// The items generated by `embed_assets!` for the assets
+    /// directory `dir` with the `options`, or the error of the expansion
+    fn expand(dir: &Path, options: &str) -> syn::Result<TokenStream> {
+        let embed_assets: EmbedAssets =
+            syn::parse_str(&format!("{:?}, {options}", dir.to_str().unwrap()))?;
+        embed_assets.expand()
+    }
+
+    /// The test assets directory `name` at the root of the workspace
+    fn test_dir(name: &str) -> std::path::PathBuf {
+        canonicalize(&Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(name)).unwrap()
+    }
+
+    #[test]
+    fn rejects_unknown_extensions() {
+        // The successful expansion is tested by
+        // serves_unknown_extensions in static-serve/tests
+        let dir = test_dir("test_unknown_extensions");
+        let Err(error) = expand(&dir, "allow_unknown_extensions = false") else {
+            panic!("the unknown extension should fail the expansion");
+        };
+        assert_eq!(
+            error.to_string(),
+            "Unknown file extension in directory of static assets: wtf"
+        );
+    }
+
+    #[test]
+    fn rejects_assets_over_budget() {
+        // The successful expansions are tested by
+        // enforces_size_budgets in static-serve/tests
+        let dir = test_dir("test_assets/big");
+        let Err(error) = expand(&dir, "max_file_size = \"500B\", max_total_size = 1000") else {
+            panic!("the files over budget should fail the expansion");
+        };
+        assert_eq!(
+            error.to_string(),
+            "The embedded files exceed their size budgets:\n  app.js: 600 B (max_file_size = 500 B)\n  immutable/app.js: 600 B (max_file_size = 500 B)\n  total: 1.9 KB (max_total_size = 1.0 KB)"
+        );
+        assert!(expand(&dir, "max_file_size = \"1MB\"").is_ok());
+    }
+
+    #[test]
+    fn rejects_duplicate_routes() {
+        let dir = std::env::temp_dir().join("static-serve-duplicate-routes-test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("about.htm"), "<p>htm</p>").unwrap();
+        fs::write(dir.join("about.html"), "<p>html</p>").unwrap();
+        let dir = canonicalize(&dir).unwrap();
+        let Err(error) = expand(&dir, "strip_html_ext = true") else {
+            panic!("the duplicate route should fail the expansion");
+        };
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "{} and {} are both served at /about",
+                dir.join("about.htm").display(),
+                dir.join("about.html").display()
+            )
+        );
+        // The error points at the assets directory
+        let (start, end) = (error.span().start(), error.span().end());
+        assert_eq!((start.line, start.column), (1, 0));
+        let literal = format!("{:?}", dir.to_str().unwrap());
+        assert_eq!((end.line, end.column), (1, literal.len()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn follows_symbolic_links_without_looping() {
+        use std::os::unix::fs::symlink;
+
+        let dir = std::env::temp_dir().join("static-serve-symlinks-test");
+        if dir.exists() {
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        fs::create_dir_all(dir.join("assets")).unwrap();
+        fs::create_dir_all(dir.join("shared")).unwrap();
+        fs::write(dir.join("assets").join("app.js"), "").unwrap();
+        fs::write(dir.join("shared").join("lib.js"), "").unwrap();
+        let dir = canonicalize(&dir).unwrap();
+        let assets = dir.join("assets");
+        symlink(dir.join("shared"), assets.join("shared")).unwrap();
+        assert_eq!(
+            walk_files(&assets).unwrap(),
+            [assets.join("app.js"), assets.join("shared").join("lib.js")]
+        );
+
+        symlink(&assets, assets.join("shared").join("loop")).unwrap();
+        let Err(error) = walk_files(&assets) else {
+            panic!("the symbolic link to an ancestor should fail the walk");
+        };
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "The symbolic link {} points to its ancestor directory {}",
+                assets.join("shared").join("loop").display(),
+                assets.display()
+            )
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn strips_verbatim_prefixes() {
+        use std::path::PathBuf;
+
+        use super::strip_verbatim_prefix;
+
+        let strip = |path: &str| strip_verbatim_prefix(Path::new(path));
+        assert_eq!(
+            strip(r"\\?\C:\assets\app.js"),
+            PathBuf::from(r"C:\assets\app.js")
+        );
+        assert_eq!(
+            strip(r"\\?\UNC\server\share\app.js"),
+            PathBuf::from(r"\\server\share\app.js")
+        );
+        assert_eq!(
+            strip(r"C:\assets\app.js"),
+            PathBuf::from(r"C:\assets\app.js")
+        );
+        // Windows would strip the trailing dot without the prefix
+        assert_eq!(
+            strip(r"\\?\C:\assets.\app.js"),
+            PathBuf::from(r"\\?\C:\assets.\app.js")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn handles_non_utf8_paths() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt as _};
+
+        let dir = std::env::temp_dir().join("static-serve-non-utf8-test");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("app.js"), "console.log(1);").unwrap();
+        fs::write(dir.join(OsStr::from_bytes(b"caf\xe9.txt")), "latin-1").unwrap();
+        let dir = dir.canonicalize().unwrap();
+        let relative_paths = |entries: Vec<AssetEntry>| {
+            entries
+                .into_iter()
+                .map(|entry| entry.relative_path)
+                .collect::<Vec<_>>()
+        };
+
+        let Err(error) = entries(&dir, NonUtf8Paths::Error) else {
+            panic!("the non-UTF-8 path should fail the expansion");
+        };
+        assert!(error.to_string().contains("caf\u{fffd}.txt"));
+
+        let Ok((skipped, skipped_paths)) = entries(&dir, NonUtf8Paths::Skip) else {
+            panic!("the non-UTF-8 path should be skipped");
+        };
+        assert_eq!(relative_paths(skipped), ["app.js"]);
+        assert_eq!(
+            skipped_paths,
+            [dir.join("caf\u{fffd}.txt").to_string_lossy()]
+        );
+
+        let Ok((encoded, _)) = entries(&dir, NonUtf8Paths::Encode) else {
+            panic!("the non-UTF-8 path should be encoded");
+        };
+        assert_eq!(encoded[1].route, "/caf%E9.txt");
+        assert_eq!(relative_paths(encoded), ["app.js", "caf%E9.txt"]);
+    }
+}
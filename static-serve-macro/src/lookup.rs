@@ -0,0 +1,143 @@
+//! Generation of the `get_asset` function, looking the assets up
+//! by route in a perfect hash table built at compile time
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Visibility;
+
+use crate::EmbeddedFileInfo;
+
+/// The average number of routes per bucket of displacements
+const BUCKET_SIZE: usize = 4;
+/// The displacements tried for a bucket before trying another seed
+const MAX_DISPLACEMENT: u32 = 1 << 20;
+
+/// Generate the `get_asset` function, returning the asset served at a route
+pub(crate) fn get_asset_fn(assets: &[EmbeddedFileInfo], vis: &Visibility) -> TokenStream {
+    let routes: Vec<_> = assets
+        .iter()
+        .enumerate()
+        .filter_map(|(index, asset)| Some((index, asset.entry_path.as_deref()?)))
+        .collect();
+    let PerfectHash {
+        seed,
+        displacements,
+        slots,
+    } = PerfectHash::build(&routes);
+    let displacements_len = displacements.len();
+    let slots_len = slots.len();
+
+    quote! {
+        #[allow(dead_code)]
+        #vis fn get_asset(web_path: &str) -> ::std::option::Option<&'static ::static_serve::StaticAsset> {
+            static DISPLACEMENTS: [u32; #displacements_len] = [#(#displacements),*];
+            static SLOTS: [usize; #slots_len] = [#(#slots),*];
+            // Decoded or encoded, in the spelling of the routes
+            let web_path = ::static_serve::canonical_path(web_path);
+            let index = ::static_serve::perfect_hash_index(&web_path, #seed, &DISPLACEMENTS, &SLOTS)?;
+            let (path, asset) = &__static_serve_assets()[index];
+            (*path == web_path && __STATIC_SERVE_INCLUDED[index]).then_some(asset)
+        }
+    }
+}
+
+/// A perfect hash table of routes, built with the hash and displace
+/// algorithm: each route is hashed into a bucket, whose displacement
+/// is chosen so that all its routes land in free slots
+#[derive(Debug, PartialEq)]
+struct PerfectHash {
+    seed: u64,
+    /// The displacement of each bucket
+    displacements: Vec<u32>,
+    /// The index of the asset of each slot
+    slots: Vec<usize>,
+}
+
+impl PerfectHash {
+    /// Build the table of the `routes`, given with the indices of their assets
+    fn build(routes: &[(usize, &str)]) -> Self {
+        (0..)
+            .find_map(|seed| Self::build_with_seed(routes, seed))
+            .expect("a seed should place all the routes")
+    }
+
+    /// Build the table of the `routes` hashed with `seed`, if they can be placed
+    #[expect(clippy::cast_possible_truncation)]
+    fn build_with_seed(routes: &[(usize, &str)], seed: u64) -> Option<Self> {
+        let buckets_len = routes.len().div_ceil(BUCKET_SIZE);
+        let mut buckets = vec![Vec::new(); buckets_len];
+        for &(index, route) in routes {
+            let hash = route_hash(route, seed);
+            buckets[(hash % buckets_len as u64) as usize].push((index, hash));
+        }
+        let mut order: Vec<_> = (0..buckets_len).collect();
+        order.sort_by_key(|&bucket| usize::MAX - buckets[bucket].len());
+
+        let mut slots = vec![None; routes.len()];
+        let mut displacements = vec![0; buckets_len];
+        let mut positions = Vec::with_capacity(BUCKET_SIZE);
+        for bucket in order {
+            let displacement = (0..MAX_DISPLACEMENT).find(|&displacement| {
+                positions.clear();
+                buckets[bucket].iter().all(|(_, hash)| {
+                    let position =
+                        (mix(hash ^ u64::from(displacement)) % slots.len() as u64) as usize;
+                    let is_free = slots[position].is_none() && !positions.contains(&position);
+                    positions.push(position);
+                    is_free
+                })
+            })?;
+            displacements[bucket] = displacement;
+            for (&(index, _), &position) in buckets[bucket].iter().zip(&positions) {
+                slots[position] = Some(index);
+            }
+        }
+
+        Some(Self {
+            seed,
+            displacements,
+            slots: slots.into_iter().collect::<Option<_>>()?,
+        })
+    }
+}
+
+/// The hash of `route` with `seed`
+///
+/// Must stay in sync with the one of `static-serve`,
+/// which looks the routes up at runtime.
+fn route_hash(route: &str, seed: u64) -> u64 {
+    let hash = route
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325 ^ seed, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    mix(hash)
+}
+
+/// The splitmix64 finalizer, spreading the bits of `hash`
+const fn mix(mut hash: u64) -> u64 {
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PerfectHash, mix, route_hash};
+
+    #[test]
+    #[expect(clippy::cast_possible_truncation)]
+    fn places_every_route_in_its_own_slot() {
+        let routes: Vec<_> = (0..1000).map(|i| format!("/assets/{i}.js")).collect();
+        let routes: Vec<_> = routes.iter().map(String::as_str).enumerate().collect();
+        let table = PerfectHash::build(&routes);
+        assert_eq!(table.slots.len(), routes.len());
+        for (index, route) in routes {
+            let hash = route_hash(route, table.seed);
+            let displacement =
+                table.displacements[(hash % table.displacements.len() as u64) as usize];
+            let slot = (mix(hash ^ u64::from(displacement)) % table.slots.len() as u64) as usize;
+            assert_eq!(table.slots[slot], index);
+        }
+    }
+}
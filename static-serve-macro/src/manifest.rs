@@ -0,0 +1,204 @@
+//! Parsing of the manifest listing the files embedded by
+//! `embed_assets!(manifest = "...")`
+//!
+//! The manifest is a TOML array of `[[asset]]` tables:
+//!
+//! ```toml
+//! [[asset]]
+//! path = "dist/app.js"
+//! route = "/app.js"
+//! content_type = "text/javascript"
+//! compress = ["gzip", "zstd"]
+//! cache_bust = false
+//! ```
+
+use std::{fmt, fs, path::Path};
+
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, SeqAccess, Visitor},
+};
+
+use crate::{error::Error, options::Algorithms};
+
+/// The tables of the manifest
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(default)]
+    asset: Vec<ManifestAsset>,
+}
+
+/// A file listed in the manifest
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ManifestAsset {
+    /// The path of the file, relative to the manifest
+    pub(crate) path: String,
+    /// Defaults to the path of the file
+    #[serde(default, deserialize_with = "route")]
+    pub(crate) route: Option<String>,
+    /// Defaults to the guess based on the extension of the file
+    #[serde(default)]
+    pub(crate) content_type: Option<String>,
+    /// Either a boolean, for gzip and zstd, or a list of algorithms
+    #[serde(default, deserialize_with = "compress")]
+    pub(crate) compress: Algorithms,
+    #[serde(default)]
+    pub(crate) cache_bust: bool,
+}
+
+/// Read and parse the manifest at `path`
+pub(crate) fn read(path: &Path) -> Result<Vec<ManifestAsset>, Error> {
+    let manifest = fs::read_to_string(path).map_err(Error::CannotReadManifest)?;
+    parse(&manifest)
+}
+
+/// Parse the `manifest`, whose errors point at the invalid
+/// line and column
+fn parse(manifest: &str) -> Result<Vec<ManifestAsset>, Error> {
+    let Manifest { asset } = toml::from_str(manifest).map_err(Error::InvalidManifest)?;
+    Ok(asset)
+}
+
+/// A route, which must start with `/`
+fn route<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let route = String::deserialize(deserializer)?;
+    if !route.starts_with('/') {
+        return Err(de::Error::custom("the route must start with `/`"));
+    }
+    Ok(Some(route))
+}
+
+/// A compression algorithm named in a `compress` array
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Algorithm {
+    Gzip,
+    Zstd,
+    Br,
+}
+
+/// `compress = true`, `compress = false` or `compress = ["gzip", "br"]`
+fn compress<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Algorithms, D::Error> {
+    struct CompressVisitor;
+
+    impl<'de> Visitor<'de> for CompressVisitor {
+        type Value = Algorithms;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+            formatter.write_str("a boolean or an array of algorithms")
+        }
+
+        fn visit_bool<E: de::Error>(self, compress: bool) -> Result<Algorithms, E> {
+            Ok(Algorithms::compress(compress))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Algorithms, A::Error> {
+            let mut algorithms = Algorithms::compress(false);
+            while let Some(algorithm) = seq.next_element()? {
+                match algorithm {
+                    Algorithm::Gzip => algorithms.gzip = true,
+                    Algorithm::Zstd => algorithms.zstd = true,
+                    Algorithm::Br => algorithms.brotli = true,
+                }
+            }
+            Ok(algorithms)
+        }
+    }
+
+    deserializer.deserialize_any(CompressVisitor)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ManifestAsset, parse};
+    use crate::options::Algorithms;
+
+    #[test]
+    fn parses_manifest() {
+        let manifest = r#"
+# Reviewed list of the embedded files
+[[asset]]
+path = "dist/app.js" # the bundle
+route = "/app.js"
+compress = true
+
+[[asset]]
+path = 'dist/#index.html'
+content_type = "text/html; charset=\"utf-8\""
+cache_bust = false
+"#;
+        assert_eq!(
+            parse(manifest).unwrap(),
+            vec![
+                ManifestAsset {
+                    path: "dist/app.js".to_owned(),
+                    route: Some("/app.js".to_owned()),
+                    content_type: None,
+                    compress: Algorithms::compress(true),
+                    cache_bust: false,
+                },
+                ManifestAsset {
+                    path: "dist/#index.html".to_owned(),
+                    route: None,
+                    content_type: Some("text/html; charset=\"utf-8\"".to_owned()),
+                    compress: Algorithms::compress(false),
+                    cache_bust: false,
+                },
+            ]
+        );
+
+        assert!(parse("path = \"app.js\"").is_err());
+        assert!(parse("[[asset]]\nroute = \"/app.js\"").is_err());
+        assert!(parse("[[asset]]\npath = \"app.js\"\npath = \"app.js\"").is_err());
+        assert!(parse("[[asset]]\npath = \"app.js\"\ncompress = \"yes\"").is_err());
+        assert!(parse("[assets]").is_err());
+    }
+
+    #[test]
+    fn parses_compression_algorithms() {
+        let manifest = r#"
+[[asset]]
+path = "app.wasm"
+compress = ["zstd", 'br',]
+
+[[asset]]
+path = "app.js"
+compress = []
+"#;
+        let assets = parse(manifest).unwrap();
+        assert_eq!(
+            assets[0].compress,
+            Algorithms {
+                gzip: false,
+                zstd: true,
+                brotli: true,
+            }
+        );
+        assert_eq!(assets[1].compress, Algorithms::compress(false));
+
+        assert!(parse("[[asset]]\npath = \"app.js\"\ncompress = [\"lz4\"]").is_err());
+        assert!(parse("[[asset]]\npath = \"app.js\"\ncompress = [\"gzip\"").is_err());
+        assert!(parse("[[asset]]\npath = [\"app.js\"]").is_err());
+    }
+
+    #[test]
+    fn reports_the_position_of_errors() {
+        let error = parse("[[asset]]\npath = \"app.js\"\ncompress = [\"lz4\"]").unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("line 3, column 13"), "{message}");
+        assert!(
+            message.contains("unknown variant `lz4`, expected one of `gzip`, `zstd`, `br`"),
+            "{message}"
+        );
+
+        let error = parse("[[asset]]\npath = \"app.js\"\nroute = \"app.js\"").unwrap_err();
+        let message = error.to_string();
+        assert!(message.contains("line 3, column 9"), "{message}");
+        assert!(
+            message.contains("the route must start with `/`"),
+            "{message}"
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Representations of the data endpoints for `accept_variants`: the files
+//! with the listed extensions, e.g. `data.json` and `data.xml`, are served
+//! together at their route without extension, negotiated with `Accept`
+
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    path::PathBuf,
+};
+
+use syn::{
+    LitStr, Token, bracketed,
+    parse::{Parse, ParseStream},
+};
+
+use crate::AssetEntry;
+
+/// The extensions of the representations, without their leading dot,
+/// in order of preference
+#[derive(Default)]
+pub(crate) struct AcceptVariants(Vec<String>);
+
+impl Parse for AcceptVariants {
+    /// Parse a list of extensions such as `["json", "xml"]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+        let extensions = inner_content.parse_terminated(<LitStr as Parse>::parse, Token![,])?;
+        extensions
+            .into_iter()
+            .map(|extension| {
+                let value = extension.value();
+                if value.is_empty() || value.contains(['.', '/', '\\']) {
+                    return Err(syn::Error::new(
+                        extension.span(),
+                        "Expected a file extension without its leading dot, such as `\"json\"`",
+                    ));
+                }
+                Ok(value)
+            })
+            .collect::<syn::Result<_>>()
+            .map(Self)
+    }
+}
+
+impl AcceptVariants {
+    /// The position of the extension of `path` among the listed ones
+    fn rank(&self, path: &std::path::Path) -> Option<usize> {
+        let extension = path.extension()?;
+        self.0
+            .iter()
+            .position(|listed| extension.eq_ignore_ascii_case(listed))
+    }
+
+    /// Serve the files of `entries` with the listed extensions at their
+    /// routes without extension, and remove their other representations,
+    /// returned by the path of the preferred one, in order of preference
+    pub(crate) fn remove_variants(
+        &self,
+        entries: &mut Vec<AssetEntry>,
+    ) -> HashMap<PathBuf, Vec<PathBuf>> {
+        // The representations of each endpoint, by path without extension
+        let mut endpoints: HashMap<PathBuf, Vec<(usize, PathBuf)>> = HashMap::new();
+        for entry in entries.iter() {
+            if let Some(rank) = self.rank(&entry.path) {
+                endpoints
+                    .entry(entry.path.with_extension(""))
+                    .or_default()
+                    .push((rank, entry.path.clone()));
+            }
+        }
+
+        let mut variants = HashMap::new();
+        for mut representations in endpoints.into_values() {
+            representations.sort_unstable();
+            let mut representations = representations.into_iter().map(|(_, path)| path);
+            let Some(preferred) = representations.next() else {
+                continue;
+            };
+            variants.insert(preferred, representations.collect::<Vec<_>>());
+        }
+
+        let removed: HashSet<&PathBuf> = variants.values().flatten().collect();
+        let mut kept_entries = Vec::new();
+        for mut entry in mem::take(entries) {
+            if removed.contains(&entry.path) {
+                continue;
+            }
+            if variants.contains_key(&entry.path)
+                && let Some(extension_dot) = entry.route.rfind('.')
+            {
+                entry.route.truncate(extension_dot);
+            }
+            kept_entries.push(entry);
+        }
+        *entries = kept_entries;
+        variants
+    }
+}
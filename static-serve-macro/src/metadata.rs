@@ -0,0 +1,193 @@
+//! Generation of the `robots.txt` and `security.txt` metadata files
+//! from the macro options, for `robots_txt` and `security_txt`
+
+use std::fmt::Write as _;
+
+use proc_macro2::Span;
+use syn::{
+    LitByteStr, LitStr, Token, bracketed, parenthesized,
+    parse::{Parse, ParseStream},
+};
+
+use crate::{
+    EmbeddedFileInfo, ImmutableAliases, OptionBytesSlice, OptionStr, StoredBody, etag::EtagScheme,
+};
+
+/// The route of the `robots.txt` file
+const ROBOTS_TXT_ROUTE: &str = "/robots.txt";
+/// The route of the `security.txt` file, as required by RFC 9116
+const SECURITY_TXT_ROUTE: &str = "/.well-known/security.txt";
+/// Crawlers may cache `robots.txt` for up to a day (RFC 9309)
+const CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// The contents of the `robots.txt` file
+pub(crate) struct RobotsTxt(String);
+
+impl Parse for RobotsTxt {
+    /// Parse either the contents of the file, or a list of the
+    /// paths disallowed to all crawlers, such as `["/admin", "/api"]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            return Ok(Self(input.parse::<LitStr>()?.value()));
+        }
+
+        let inner_content;
+        bracketed!(inner_content in input);
+        let paths = inner_content.parse_terminated(<LitStr as Parse>::parse, Token![,])?;
+        let mut contents = "User-agent: *\n".to_owned();
+        if paths.is_empty() {
+            contents.push_str("Disallow:\n");
+        }
+        for path in paths {
+            if !path.value().starts_with('/') {
+                return Err(syn::Error::new(
+                    path.span(),
+                    "The disallowed paths must start with `/`",
+                ));
+            }
+            writeln!(contents, "Disallow: {}", path.value())
+                .expect("writing to a String cannot fail");
+        }
+        Ok(Self(contents))
+    }
+}
+
+/// The contents of the `security.txt` file
+pub(crate) struct SecurityTxt(String);
+
+impl Parse for SecurityTxt {
+    /// Parse either the contents of the file, or a list of its fields,
+    /// such as `[("Contact", "mailto:security@example.com"), ("Expires", "2030-01-01T00:00:00Z")]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let span = input.span();
+        let contents = if input.peek(LitStr) {
+            input.parse::<LitStr>()?.value()
+        } else {
+            let inner_content;
+            bracketed!(inner_content in input);
+            let mut contents = String::new();
+            while !inner_content.is_empty() {
+                let tuple_content;
+                parenthesized!(tuple_content in inner_content);
+                let name = tuple_content.parse::<LitStr>()?;
+                tuple_content.parse::<Token![,]>()?;
+                let value = tuple_content.parse::<LitStr>()?;
+                if !tuple_content.is_empty() {
+                    tuple_content.parse::<Token![,]>()?;
+                }
+
+                if name.value().is_empty()
+                    || !name
+                        .value()
+                        .bytes()
+                        .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-')
+                {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        "The field name must be made of ASCII letters, digits and `-`",
+                    ));
+                }
+                writeln!(contents, "{}: {}", name.value(), value.value())
+                    .expect("writing to a String cannot fail");
+
+                if !inner_content.is_empty() {
+                    inner_content.parse::<Token![,]>()?;
+                }
+            }
+            contents
+        };
+
+        for field in ["Contact", "Expires"] {
+            if !has_field(&contents, field) {
+                return Err(syn::Error::new(
+                    span,
+                    format!("`security_txt` requires the `{field}` field (RFC 9116)"),
+                ));
+            }
+        }
+        Ok(Self(contents))
+    }
+}
+
+/// Whether the `contents` of a `security.txt` file have the field `name`
+fn has_field(contents: &str, name: &str) -> bool {
+    contents.lines().any(|line| {
+        line.split_once(':')
+            .is_some_and(|(field, _)| field.trim().eq_ignore_ascii_case(name))
+    })
+}
+
+/// Build the assets of the `robots_txt` and `security_txt` files
+pub(crate) fn metadata_assets(
+    robots_txt: Option<&RobotsTxt>,
+    security_txt: Option<&SecurityTxt>,
+    etag_scheme: EtagScheme<'_>,
+) -> Vec<EmbeddedFileInfo> {
+    let robots_txt = robots_txt.map(|RobotsTxt(contents)| (ROBOTS_TXT_ROUTE, contents));
+    let security_txt = security_txt.map(|SecurityTxt(contents)| (SECURITY_TXT_ROUTE, contents));
+    robots_txt
+        .into_iter()
+        .chain(security_txt)
+        .map(|(route, contents)| text_asset(route, contents, etag_scheme))
+        .collect()
+}
+
+/// Build the plain text asset served at `route` with `contents`
+///
+/// As they have no modification time, they are always given an `ETag`.
+fn text_asset(route: &str, contents: &str, etag_scheme: EtagScheme<'_>) -> EmbeddedFileInfo {
+    EmbeddedFileInfo {
+        entry_path: Some(route.to_owned()),
+        content_type: "text/plain; charset=utf-8".to_owned(),
+        etag_str: OptionStr(Some(etag_scheme.etag(contents.as_bytes()))),
+        last_modified: OptionStr(None),
+        lit_byte_str_contents: LitByteStr::new(contents.as_bytes(), Span::call_site()),
+        maybe_gzip: OptionBytesSlice(None),
+        maybe_zstd: OptionBytesSlice(None),
+        maybe_brotli: OptionBytesSlice(None),
+        cache_busted: false,
+        immutable_aliases: ImmutableAliases::default(),
+        cache_control: OptionStr(Some(CACHE_CONTROL.to_owned())),
+        headers: Vec::new(),
+        stored_body: StoredBody::Identity,
+        image_variants: Vec::new(),
+        media_variants: Vec::new(),
+        integrity: OptionStr(None),
+        cors: Vec::new(),
+        encoding_preference: Vec::new(),
+        file_path: None,
+        stream_chunk_size: None,
+        basic_auth: None,
+        signed: false,
+        source_map_gate: None,
+        cfg_gate: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RobotsTxt, SecurityTxt};
+
+    #[test]
+    fn builds_metadata_files_from_config() {
+        let RobotsTxt(robots_txt) = syn::parse_str(r#"["/admin", "/api"]"#).unwrap();
+        assert_eq!(
+            robots_txt,
+            "User-agent: *\nDisallow: /admin\nDisallow: /api\n"
+        );
+        let RobotsTxt(robots_txt) = syn::parse_str("[]").unwrap();
+        assert_eq!(robots_txt, "User-agent: *\nDisallow:\n");
+
+        let SecurityTxt(security_txt) = syn::parse_str(
+            r#"[("Contact", "mailto:security@example.com"), ("Expires", "2030-01-01T00:00:00Z")]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            security_txt,
+            "Contact: mailto:security@example.com\nExpires: 2030-01-01T00:00:00Z\n"
+        );
+        assert!(
+            syn::parse_str::<SecurityTxt>(r#""Contact: mailto:security@example.com""#).is_err()
+        );
+    }
+}
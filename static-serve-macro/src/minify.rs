@@ -0,0 +1,116 @@
+//! Minification of the embedded HTML, CSS and JavaScript for `minify = true`,
+//! with minify-html, lightningcss and minify-js, which parse what they minify
+
+use lightningcss::stylesheet::{MinifyOptions, ParserOptions, PrinterOptions, StyleSheet};
+use minify_html::Cfg;
+use minify_js::{Session, TopLevelMode};
+
+use crate::media_type;
+
+/// The minified `contents` of a file served as `content_type`, or `None`
+/// when it isn't UTF-8 HTML, CSS or JavaScript, or doesn't parse as such
+pub(crate) fn minify(content_type: &str, contents: &[u8]) -> Option<Vec<u8>> {
+    let source = str::from_utf8(contents).ok()?;
+    match media_type(content_type).trim() {
+        "text/html" => Some(html(source)),
+        "text/css" => css(source),
+        "text/javascript" | "application/javascript" => js(source),
+        _ => None,
+    }
+}
+
+/// Minify HTML, including the inline scripts and styles, keeping
+/// the doctype, the optional tags and the spaces the specification requires
+fn html(source: &str) -> Vec<u8> {
+    let cfg = Cfg {
+        do_not_minify_doctype: true,
+        ensure_spec_compliant_unquoted_attribute_values: true,
+        keep_closing_tags: true,
+        keep_html_and_head_opening_tags: true,
+        keep_spaces_between_attributes: true,
+        minify_css: true,
+        minify_js: true,
+        ..Cfg::new()
+    };
+    minify_html::minify(source.as_bytes(), &cfg)
+}
+
+/// Minify CSS, or `None` when lightningcss can't parse it
+fn css(source: &str) -> Option<Vec<u8>> {
+    let mut stylesheet = StyleSheet::parse(source, ParserOptions::default()).ok()?;
+    stylesheet.minify(MinifyOptions::default()).ok()?;
+    let printed = stylesheet
+        .to_css(PrinterOptions {
+            minify: true,
+            ..PrinterOptions::default()
+        })
+        .ok()?;
+    Some(printed.code.into_bytes())
+}
+
+/// Minify JavaScript, or `None` when minify-js can't parse it
+///
+/// The files are parsed as classic scripts, whose top-level declarations
+/// are globals which mustn't be renamed, and as modules when they don't
+/// parse as scripts, e.g. because of their `import` declarations.
+fn js(source: &str) -> Option<Vec<u8>> {
+    [TopLevelMode::Global, TopLevelMode::Module]
+        .into_iter()
+        .find_map(|mode| {
+            let mut minified = Vec::new();
+            minify_js::minify(&Session::new(), mode, source.as_bytes(), &mut minified)
+                .ok()
+                .map(|()| minified)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::minify;
+
+    /// The minified `source`, served as `content_type`, as a string
+    fn minified(content_type: &str, source: &str) -> String {
+        String::from_utf8(minify(content_type, source.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn minifies_css() {
+        assert_eq!(
+            minified(
+                "text/css",
+                "/* comment */\na > b,\nc :hover {\n  color: #ff0000;\n  content: \"a  /* b */\";\n}\n"
+            ),
+            "a>b,c :hover{color:red;content:\"a  /* b */\"}"
+        );
+        assert!(minify("text/css", b"}").is_none());
+    }
+
+    #[test]
+    fn minifies_js() {
+        // The slashes after `)` and `++` are divisions or regular expressions
+        // depending on the syntax, which a tokenizer alone can't tell apart
+        for source in [
+            "if (ready) /a b/g.test(text) && run()\n",
+            "let i = 2; i++ / 2 / i\n",
+            "const text = `a  ${ `b  c` }`; // comment\n",
+        ] {
+            let minified = minified("text/javascript", source);
+            assert!(minified.len() < source.len(), "{minified}");
+        }
+        assert!(minified("text/javascript", "if (ready) /a b/g.test(text)").contains("/a b/g"));
+        assert!(minified("text/javascript", "var global = 1").contains("global"));
+        assert!(minified("text/javascript", "import a from \"./a.js\"; a()").contains("./a.js"));
+        assert!(minify("text/javascript", b"function (").is_none());
+    }
+
+    #[test]
+    fn minifies_html() {
+        assert_eq!(
+            minified(
+                "text/html; charset=utf-8",
+                "<!DOCTYPE html>\n<html>\n<head>\n<!-- comment -->\n<style>\n  a { color: red; }\n</style>\n</head>\n<body>\n<p  class=\"a  b\" >\n  Hello,   <b>world</b>!\n</p>\n<pre>  keep\n  this  </pre>\n<script>\n  const a = 1;\n</script>\n</body>\n</html>\n"
+            ),
+            "<!doctype html><html><head><style>a{color:red}</style></head><body><p class=\"a b\">Hello, <b>world</b>!</p><pre>  keep\n  this  </pre><script>const a=1</script></body></html>"
+        );
+    }
+}
@@ -0,0 +1,2040 @@
+//! The options of `embed_assets!`, parsed and validated
+//! into the settings of the expansion
+
+use std::{
+    env, fs, io,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+
+use display_full_error::DisplayFullError;
+use glob::Pattern;
+use proc_macro2::{Span, TokenStream, TokenTree};
+use syn::{
+    Ident, LitBool, LitInt, LitStr, Token, Visibility, braced, bracketed, parenthesized,
+    parse::{Parse, ParseStream},
+    parse_quote,
+};
+
+use crate::{
+    DEFAULT_SHARD_SIZE, OCTET_STREAM,
+    budget::{ByteSize, SizeBudgets},
+    cfg_gate::CfgGates,
+    env_vars,
+    etag::EtagAlgorithm,
+    media_type,
+    media_variants::AcceptVariants,
+    metadata::{RobotsTxt, SecurityTxt},
+    normalize_web_path,
+    report::{Report, ReportOption},
+    route_definitions, source_maps,
+    source_maps::{SourceMapGate, SourceMaps},
+    template::TemplateVars,
+};
+
+/// The value of a boolean option, or `default`
+/// spanning the call site when it isn't set
+pub(crate) fn flag(value: Option<LitBool>, default: bool) -> LitBool {
+    value.unwrap_or_else(|| LitBool::new(default, Span::call_site()))
+}
+
+pub(crate) struct EmbedAssets {
+    pub(crate) assets_dirs: AssetsDirs,
+    pub(crate) deny_conflicts: LitBool,
+    pub(crate) non_utf8_paths: NonUtf8Paths,
+    pub(crate) auto_index: LitBool,
+    pub(crate) zstd_dictionary: LitBool,
+    /// Whether the bodies which have a compressed variant are embedded as is
+    pub(crate) identity: LitBool,
+    /// Whether the bodies which have a zstd variant are decompressed on first access
+    pub(crate) lazy_decompression: LitBool,
+    pub(crate) minify: LitBool,
+    /// Whether to drop the legacy fonts superseded by WOFF2, and to
+    /// serve the fonts cache-busted and to all the origins
+    pub(crate) fonts: LitBool,
+    pub(crate) image_variants: LitBool,
+    pub(crate) accept_variants: AcceptVariants,
+    /// The values of the placeholders of the textual files, if substituted
+    pub(crate) template_vars: Option<TemplateVars>,
+    pub(crate) trailing_slash: Option<TrailingSlash>,
+    pub(crate) index_files: IndexFiles,
+    pub(crate) html_redirects: LitBool,
+    pub(crate) validated_ignore_paths: IgnorePaths,
+    pub(crate) path_filter: PathFilter,
+    pub(crate) should_compress: ShouldCompress,
+    pub(crate) should_strip_html_ext: ShouldStripHtmlExt,
+    pub(crate) cache_busted_paths: CacheBustedPaths,
+    /// Whether the routes are linked with the hash of the contents in
+    /// their `v` query parameter, and immutable when requested with it
+    pub(crate) cache_bust_query: LitBool,
+    /// Whether the assets are also served, and linked, at
+    /// `/_immutable/<hash>/<route>`, with an immutable `Cache-Control`
+    pub(crate) immutable_aliases: LitBool,
+    /// From `allow_unknown_extensions` and `unknown_mime`
+    pub(crate) unknown_content_type: Option<String>,
+    pub(crate) last_modified_only: LitBool,
+    pub(crate) tarball_route: Option<TarballRoute>,
+    pub(crate) route_definitions: Option<RouteDefinitions>,
+    pub(crate) report: Option<Report>,
+    pub(crate) import_map_route: Option<ImportMapRoute>,
+    pub(crate) inject_import_map: LitBool,
+    pub(crate) robots_txt: Option<RobotsTxt>,
+    pub(crate) security_txt: Option<SecurityTxt>,
+    pub(crate) fallback_page: Option<(PathBuf, FallbackKind)>,
+    pub(crate) compression_groups: CompressionGroups,
+    pub(crate) etag_salt: Option<LitStr>,
+    pub(crate) etag_algorithm: EtagAlgorithm,
+    pub(crate) expand_to: Option<LitStr>,
+    pub(crate) gzip_level: GzipLevel,
+    pub(crate) zstd_level: ZstdLevel,
+    pub(crate) use_precompressed: LitBool,
+    pub(crate) debug_from_disk: LitBool,
+    /// Whether the methods other than `GET` and `HEAD`
+    /// are left to the other routes of the paths
+    pub(crate) fall_through_methods: LitBool,
+    /// Whether a `rocket_routes()` function is generated
+    pub(crate) rocket_routes: LitBool,
+    /// Whether a poem `static_routes()` function is generated
+    pub(crate) poem_routes: LitBool,
+    /// Whether a `salvo_router()` function is generated
+    pub(crate) salvo_router: LitBool,
+    /// The function wrapping the method router of every route, if any
+    pub(crate) wrap: Option<syn::Path>,
+    pub(crate) prefix: RoutePrefix,
+    pub(crate) cache_controls: CacheControls,
+    /// The `cfg` predicates of the files only embedded when they hold
+    pub(crate) cfg_gates: CfgGates,
+    pub(crate) extra_headers: ExtraHeaders,
+    pub(crate) protected: Protected,
+    pub(crate) signed: Signed,
+    /// The header or the cookie the source maps are only served with,
+    /// for `source_maps = "header-only"`
+    pub(crate) source_map_gate: Option<SourceMapGate>,
+    pub(crate) preload: Preload,
+    pub(crate) route_map: RouteMap,
+    pub(crate) max_age: Option<MaxAge>,
+    pub(crate) mime_overrides: MimeOverrides,
+    pub(crate) compress_policy: CompressPolicy,
+    pub(crate) size_budgets: SizeBudgets,
+    pub(crate) stream_bodies: Option<StreamBodies>,
+    /// The number of assets per function of the table of the assets
+    pub(crate) shard_size: NonZeroUsize,
+    /// The linker section of the embedded bytes, if any
+    pub(crate) link_section: Option<LitStr>,
+    /// The bundle file the bytes are written to rather than embedded, if any
+    pub(crate) bundle: Option<LitStr>,
+    pub(crate) charset: Charset,
+    pub(crate) cors: Cors,
+    pub(crate) encoding_preference: EncodingPreference,
+    pub(crate) security_headers: SecurityHeaders,
+    pub(crate) fn_name: Option<Ident>,
+    pub(crate) vis: Visibility,
+}
+
+/// The options of `embed_assets!` as written, by key,
+/// before their defaults are applied and they're validated
+#[derive(Default)]
+struct Options {
+    compress: Option<ShouldCompress>,
+    ignore_paths: Option<IgnorePathsWithSpan>,
+    strip_html_ext: Option<LitBool>,
+    cache_busted_paths: Option<CacheBustedPathsWithSpan>,
+    cache_bust_query: Option<LitBool>,
+    immutable_aliases: Option<LitBool>,
+    allow_unknown_extensions: Option<LitBool>,
+    last_modified_only: Option<LitBool>,
+    tarball: Option<TarballRoute>,
+    route_definitions: Option<RouteDefinitions>,
+    report: Option<ReportOption>,
+    import_map: Option<ImportMapRoute>,
+    inject_import_map: Option<LitBool>,
+    robots_txt: Option<RobotsTxt>,
+    security_txt: Option<SecurityTxt>,
+    not_found: Option<LitStr>,
+    spa_fallback: Option<LitStr>,
+    compression_groups: Option<CompressionGroups>,
+    etag_salt: Option<LitStr>,
+    etag: Option<EtagAlgorithm>,
+    expand_to: Option<LitStr>,
+    gzip_level: Option<GzipLevel>,
+    zstd_level: Option<ZstdLevel>,
+    zstd_dictionary: Option<LitBool>,
+    identity: Option<LitBool>,
+    lazy_decompression: Option<LitBool>,
+    minify: Option<LitBool>,
+    fonts: Option<LitBool>,
+    image_variants: Option<LitBool>,
+    accept_variants: Option<AcceptVariants>,
+    templates: Option<LitBool>,
+    template_vars: Option<TemplateVars>,
+    use_precompressed: Option<LitBool>,
+    debug_from_disk: Option<LitBool>,
+    fall_through_methods: Option<LitBool>,
+    rocket_routes: Option<LitBool>,
+    poem_routes: Option<LitBool>,
+    salvo_router: Option<LitBool>,
+    wrap: Option<syn::Path>,
+    prefix: Option<RoutePrefix>,
+    cache_control: Option<CacheControls>,
+    cfg_gate: Option<CfgGates>,
+    headers: Option<ExtraHeaders>,
+    protected: Option<Protected>,
+    signed: Option<Signed>,
+    source_maps: Option<SourceMaps>,
+    source_maps_header: Option<LitStr>,
+    source_maps_cookie: Option<LitStr>,
+    cors: Option<Cors>,
+    encoding_preference: Option<EncodingPreference>,
+    security_headers: Option<LitBool>,
+    referrer_policy: Option<LitStr>,
+    cross_origin_opener_policy: Option<LitStr>,
+    preload: Option<Preload>,
+    max_age: Option<MaxAge>,
+    mime_overrides: Option<MimeOverrides>,
+    compress_min_size: Option<usize>,
+    compress_types: Option<CompressTypes>,
+    max_file_size: Option<ByteSize>,
+    max_total_size: Option<ByteSize>,
+    warn_over_budget: Option<LitBool>,
+    stream_min_size: Option<ByteSize>,
+    stream_chunk_size: Option<(ByteSize, Span)>,
+    shard_size: Option<NonZeroUsize>,
+    link_section: Option<LitStr>,
+    bundle: Option<LitStr>,
+    unknown_mime: Option<UnknownMime>,
+    charset: Option<Charset>,
+    include: Option<Vec<Pattern>>,
+    exclude: Option<Vec<Pattern>>,
+    skip_hidden: Option<LitBool>,
+    keep_hidden: Option<Vec<Pattern>>,
+    route_map: Option<RouteMap>,
+    fn_name: Option<Ident>,
+    vis: Option<Visibility>,
+    deny_conflicts: Option<LitBool>,
+    non_utf8_paths: Option<NonUtf8Paths>,
+    auto_index: Option<LitBool>,
+    trailing_slash: Option<TrailingSlash>,
+    html_redirects: Option<LitBool>,
+    index_files: Option<IndexFiles>,
+}
+
+impl Options {
+    /// Parse the `key = value` pairs following the assets directories,
+    /// the paths being resolved like the directories
+    #[expect(clippy::too_many_lines)]
+    fn parse(input: ParseStream, manifest_relative: bool) -> syn::Result<Self> {
+        let mut options = Self::default();
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+
+            match key.to_string().as_str() {
+                "compress" => options.compress = Some(input.parse()?),
+                "ignore_paths" => options.ignore_paths = Some(input.parse()?),
+                "strip_html_ext" => options.strip_html_ext = Some(input.parse()?),
+                "cache_busted_paths" => options.cache_busted_paths = Some(input.parse()?),
+                "cache_bust_query" => options.cache_bust_query = Some(input.parse()?),
+                "immutable_aliases" => options.immutable_aliases = Some(input.parse()?),
+                "allow_unknown_extensions" => {
+                    options.allow_unknown_extensions = Some(input.parse()?);
+                }
+                "last_modified_only" => options.last_modified_only = Some(input.parse()?),
+                "tarball" => options.tarball = Some(input.parse()?),
+                "route_definitions" => {
+                    options.route_definitions =
+                        Some(RouteDefinitions::parse(input, manifest_relative)?);
+                }
+                "report" => options.report = Some(ReportOption::parse(input, manifest_relative)?),
+                "manifest_relative" => {
+                    // Already looked up by `is_manifest_relative`
+                    input.parse::<LitBool>()?;
+                }
+                "import_map" => options.import_map = Some(input.parse()?),
+                "inject_import_map" => options.inject_import_map = Some(input.parse()?),
+                "robots_txt" => options.robots_txt = Some(input.parse()?),
+                "security_txt" => options.security_txt = Some(input.parse()?),
+                "not_found" => options.not_found = Some(input.parse()?),
+                "spa_fallback" => options.spa_fallback = Some(input.parse()?),
+                "compression_groups" => options.compression_groups = Some(input.parse()?),
+                "etag_salt" => options.etag_salt = Some(input.parse()?),
+                "etag" => options.etag = Some(input.parse()?),
+                "expand_to" => {
+                    options.expand_to = Some(resolve_path(&input.parse()?, manifest_relative)?);
+                }
+                "gzip_level" => options.gzip_level = Some(input.parse()?),
+                "zstd_level" => options.zstd_level = Some(input.parse()?),
+                "use_precompressed" => options.use_precompressed = Some(input.parse()?),
+                "debug_from_disk" => options.debug_from_disk = Some(input.parse()?),
+                "fall_through_methods" => options.fall_through_methods = Some(input.parse()?),
+                "rocket_routes" => options.rocket_routes = Some(input.parse()?),
+                "poem_routes" => options.poem_routes = Some(input.parse()?),
+                "salvo_router" => options.salvo_router = Some(input.parse()?),
+                "wrap" => options.wrap = Some(input.parse()?),
+                "prefix" => options.prefix = Some(input.parse()?),
+                "cache_control" => options.cache_control = Some(input.parse()?),
+                "cfg_gate" => options.cfg_gate = Some(input.parse()?),
+                "headers" => options.headers = Some(input.parse()?),
+                "protected" => options.protected = Some(input.parse()?),
+                "signed" => options.signed = Some(input.parse()?),
+                "source_maps" => options.source_maps = Some(input.parse()?),
+                "source_maps_header" => options.source_maps_header = Some(input.parse()?),
+                "source_maps_cookie" => options.source_maps_cookie = Some(input.parse()?),
+                "preload" => options.preload = Some(input.parse()?),
+                "cors" => options.cors = Some(input.parse()?),
+                "encoding_preference" => options.encoding_preference = Some(input.parse()?),
+                "security_headers" => options.security_headers = Some(input.parse()?),
+                "referrer_policy" => options.referrer_policy = Some(input.parse()?),
+                "cross_origin_opener_policy" => {
+                    options.cross_origin_opener_policy = Some(input.parse()?);
+                }
+                "max_age" => options.max_age = Some(input.parse()?),
+                "mime_overrides" => options.mime_overrides = Some(input.parse()?),
+                "compress_min_size" => {
+                    options.compress_min_size = Some(input.parse::<LitInt>()?.base10_parse()?);
+                }
+                "compress_types" => options.compress_types = Some(input.parse()?),
+                "max_file_size" => options.max_file_size = Some(input.parse()?),
+                "max_total_size" => options.max_total_size = Some(input.parse()?),
+                "warn_over_budget" => options.warn_over_budget = Some(input.parse()?),
+                "stream_min_size" => options.stream_min_size = Some(input.parse()?),
+                "stream_chunk_size" => {
+                    let span = input.span();
+                    options.stream_chunk_size = Some((input.parse()?, span));
+                }
+                "shard_size" => {
+                    options.shard_size = Some(input.parse::<LitInt>()?.base10_parse()?);
+                }
+                "link_section" => {
+                    let value: LitStr = input.parse()?;
+                    if value.value().is_empty() {
+                        return Err(syn::Error::new(
+                            value.span(),
+                            "Expected the name of a linker section, such as `\".static_serve\"`",
+                        ));
+                    }
+                    options.link_section = Some(value);
+                }
+                "bundle" => {
+                    options.bundle = Some(resolve_path(&input.parse()?, manifest_relative)?);
+                }
+                "unknown_mime" => options.unknown_mime = Some(input.parse()?),
+                "charset" => options.charset = Some(input.parse()?),
+                "include" => options.include = Some(parse_patterns(input)?),
+                "exclude" => options.exclude = Some(parse_patterns(input)?),
+                "skip_hidden" => options.skip_hidden = Some(input.parse()?),
+                "keep_hidden" => options.keep_hidden = Some(parse_patterns(input)?),
+                "route_map" => options.route_map = Some(input.parse()?),
+                "non_utf8_paths" => options.non_utf8_paths = Some(input.parse()?),
+                "deny_conflicts" => options.deny_conflicts = Some(input.parse()?),
+                "auto_index" => options.auto_index = Some(input.parse()?),
+                "zstd_dictionary" => options.zstd_dictionary = Some(input.parse()?),
+                "identity" => options.identity = Some(input.parse()?),
+                "lazy_decompression" => options.lazy_decompression = Some(input.parse()?),
+                "minify" => options.minify = Some(input.parse()?),
+                "fonts" => options.fonts = Some(input.parse()?),
+                "image_variants" => options.image_variants = Some(input.parse()?),
+                "accept_variants" => options.accept_variants = Some(input.parse()?),
+                "templates" => options.templates = Some(input.parse()?),
+                "template_vars" => options.template_vars = Some(input.parse()?),
+                "trailing_slash" => options.trailing_slash = Some(input.parse()?),
+                "html_redirects" => options.html_redirects = Some(input.parse()?),
+                "index_files" => options.index_files = Some(input.parse()?),
+                "fn_name" => options.fn_name = Some(input.parse()?),
+                "vis" => options.vis = Some(input.parse()?),
+                _ => {
+                    return Err(syn::Error::new(key.span(), UNKNOWN_KEY));
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    /// Reject the options which can't be used together
+    fn check_combinations(&self) -> syn::Result<()> {
+        if let Some(html_redirects) = &self.html_redirects
+            && html_redirects.value
+            && !self
+                .strip_html_ext
+                .as_ref()
+                .is_some_and(|strip| strip.value)
+        {
+            return Err(syn::Error::new(
+                html_redirects.span,
+                "`html_redirects` requires `strip_html_ext` to be enabled",
+            ));
+        }
+
+        if let Some(inject_import_map) = &self.inject_import_map
+            && inject_import_map.value
+            && self.import_map.is_none()
+        {
+            return Err(syn::Error::new(
+                inject_import_map.span,
+                "`inject_import_map` requires the `import_map` route to be set",
+            ));
+        }
+
+        if let Some(bundle) = &self.bundle {
+            let is_set = |option: &Option<LitBool>, value| {
+                option.as_ref().is_some_and(|option| option.value == value)
+            };
+            let embedded_only = [
+                ("link_section", self.link_section.is_some()),
+                ("zstd_dictionary", is_set(&self.zstd_dictionary, true)),
+                ("identity = false", is_set(&self.identity, false)),
+                ("lazy_decompression", is_set(&self.lazy_decompression, true)),
+            ];
+            if let Some((option, _)) = embedded_only.iter().find(|(_, is_used)| *is_used) {
+                return Err(syn::Error::new(
+                    bundle.span(),
+                    format!(
+                        "`bundle` cannot be used with `{option}`, which applies to the bytes embedded in the binary"
+                    ),
+                ));
+            }
+        }
+
+        if let (Some(_), Some(spa_fallback_page)) = (&self.not_found, &self.spa_fallback) {
+            return Err(syn::Error::new(
+                spa_fallback_page.span(),
+                "`not_found` and `spa_fallback` cannot be used together",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The message of the error reporting an unknown key
+const UNKNOWN_KEY: &str = "Unknown key in embed_assets! macro. Expected `compress`, `ignore_paths`, `strip_html_ext`, `cache_busted_paths`, `cache_bust_query`, `immutable_aliases`, `allow_unknown_extensions`, `last_modified_only`, `tarball`, `route_definitions`, `report`, `import_map`, `inject_import_map`, `robots_txt`, `security_txt`, `not_found`, `spa_fallback`, `compression_groups`, `etag_salt`, `etag`, `expand_to`, `gzip_level`, `zstd_level`, `zstd_dictionary`, `identity`, `lazy_decompression`, `minify`, `fonts`, `image_variants`, `accept_variants`, `templates`, `template_vars`, `use_precompressed`, `debug_from_disk`, `fall_through_methods`, `rocket_routes`, `poem_routes`, `salvo_router`, `wrap`, `prefix`, `cache_control`, `cfg_gate`, `headers`, `protected`, `signed`, `source_maps`, `source_maps_header`, `source_maps_cookie`, `cors`, `encoding_preference`, `security_headers`, `referrer_policy`, `cross_origin_opener_policy`, `preload`, `max_age`, `mime_overrides`, `compress_min_size`, `compress_types`, `max_file_size`, `max_total_size`, `warn_over_budget`, `stream_min_size`, `stream_chunk_size`, `shard_size`, `link_section`, `bundle`, `unknown_mime`, `charset`, `include`, `exclude`, `skip_hidden`, `keep_hidden`, `route_map`, `fn_name`, `vis`, `manifest_relative`, `deny_conflicts`, `non_utf8_paths`, `auto_index`, `trailing_slash`, `html_redirects`, or `index_files`";
+
+impl Parse for EmbedAssets {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let manifest_relative = is_manifest_relative(input)?;
+        let assets_dirs = AssetsDirs::parse(input, manifest_relative)?;
+        let options = Options::parse(input, manifest_relative)?;
+        options.check_combinations()?;
+        Self::new(assets_dirs, options)
+    }
+}
+
+impl EmbedAssets {
+    /// The settings of the expansion, from the `options` validated
+    /// against the files of `assets_dirs`, or their defaults
+    #[expect(clippy::too_many_lines)]
+    fn new(assets_dirs: AssetsDirs, options: Options) -> syn::Result<Self> {
+        let dirs = &assets_dirs.0;
+        let validated_ignore_paths = validate_ignore_paths(
+            options.ignore_paths.unwrap_or(IgnorePathsWithSpan(vec![])),
+            dirs,
+        )?;
+        let cache_busted_paths = validate_cache_busted_paths(
+            options
+                .cache_busted_paths
+                .unwrap_or(CacheBustedPathsWithSpan(vec![])),
+            dirs,
+        )?;
+        let cache_controls = options.cache_control.unwrap_or(CacheControls(vec![]));
+        validate_cache_controls(&cache_controls, dirs)?;
+        let cfg_gates = options.cfg_gate.unwrap_or_default();
+        cfg_gates.validate(dirs)?;
+        let extra_headers = options.headers.unwrap_or_default();
+        validate_extra_headers(&extra_headers, dirs)?;
+        let protected = options.protected.unwrap_or_default();
+        protected.validate(dirs)?;
+        let signed = options.signed.unwrap_or_default();
+        validate_signed(&signed, dirs)?;
+        let preload = options.preload.unwrap_or_default();
+        validate_preload(&preload, dirs)?;
+        let route_map = options.route_map.unwrap_or_default();
+        validate_route_map(&route_map, dirs)?;
+        let fallback_page = match (&options.not_found, &options.spa_fallback) {
+            (Some(not_found_page), _) => Some((
+                validate_fallback_page(not_found_page, dirs, "not found page")?,
+                FallbackKind::NotFound,
+            )),
+            (None, Some(spa_fallback_page)) => Some((
+                validate_fallback_page(spa_fallback_page, dirs, "SPA fallback page")?,
+                FallbackKind::Spa,
+            )),
+            (None, None) => None,
+        };
+        let source_maps = options.source_maps.unwrap_or_default();
+
+        Ok(Self {
+            deny_conflicts: flag(options.deny_conflicts, false),
+            non_utf8_paths: options.non_utf8_paths.unwrap_or(NonUtf8Paths::Error),
+            auto_index: flag(options.auto_index, false),
+            zstd_dictionary: flag(options.zstd_dictionary, false),
+            identity: flag(options.identity, true),
+            lazy_decompression: flag(options.lazy_decompression, false),
+            minify: flag(options.minify, false),
+            fonts: flag(options.fonts, false),
+            image_variants: flag(options.image_variants, false),
+            accept_variants: options.accept_variants.unwrap_or_default(),
+            // `template_vars` enables the substitution unless `templates = false`
+            template_vars: match (
+                options.templates.map(|templates| templates.value),
+                options.template_vars,
+            ) {
+                (Some(false), _) | (None, None) => None,
+                (_, Some(template_vars)) => Some(template_vars),
+                (Some(true), None) => Some(TemplateVars::default()),
+            },
+            trailing_slash: options.trailing_slash,
+            index_files: options.index_files.unwrap_or_default(),
+            html_redirects: flag(options.html_redirects, false),
+            validated_ignore_paths,
+            path_filter: PathFilter {
+                include: options.include.unwrap_or_default(),
+                exclude: options.exclude.unwrap_or_default(),
+                skip_hidden: flag(options.skip_hidden, true).value,
+                keep_hidden: options.keep_hidden.unwrap_or_default(),
+                skip_source_maps: source_maps == SourceMaps::Skip,
+            },
+            should_compress: options
+                .compress
+                .unwrap_or(ShouldCompress(Algorithms::compress(false))),
+            should_strip_html_ext: ShouldStripHtmlExt(flag(options.strip_html_ext, false)),
+            cache_busted_paths,
+            cache_bust_query: flag(options.cache_bust_query, false),
+            immutable_aliases: flag(options.immutable_aliases, false),
+            unknown_content_type: unknown_content_type(
+                &flag(options.allow_unknown_extensions, false),
+                options.unknown_mime.as_ref(),
+            ),
+            last_modified_only: flag(options.last_modified_only, false),
+            tarball_route: options.tarball,
+            route_definitions: options.route_definitions,
+            report: options
+                .report
+                .map_or_else(Report::from_env, |report| report.0),
+            import_map_route: options.import_map,
+            inject_import_map: flag(options.inject_import_map, false),
+            robots_txt: options.robots_txt,
+            security_txt: options.security_txt,
+            fallback_page,
+            compression_groups: options
+                .compression_groups
+                .unwrap_or(CompressionGroups(vec![])),
+            etag_salt: options.etag_salt,
+            etag_algorithm: options.etag.unwrap_or_default(),
+            expand_to: options.expand_to,
+            gzip_level: options.gzip_level.unwrap_or_default(),
+            zstd_level: options.zstd_level.unwrap_or_default(),
+            use_precompressed: flag(options.use_precompressed, false),
+            debug_from_disk: flag(options.debug_from_disk, false),
+            fall_through_methods: flag(options.fall_through_methods, false),
+            rocket_routes: flag(options.rocket_routes, false),
+            poem_routes: flag(options.poem_routes, false),
+            salvo_router: flag(options.salvo_router, false),
+            wrap: options.wrap,
+            prefix: options.prefix.unwrap_or(RoutePrefix(String::new())),
+            cache_controls,
+            cfg_gates,
+            extra_headers,
+            protected,
+            signed,
+            source_map_gate: SourceMapGate::new(
+                source_maps,
+                options.source_maps_header.as_ref(),
+                options.source_maps_cookie.as_ref(),
+            )?,
+            preload,
+            route_map,
+            max_age: options.max_age,
+            mime_overrides: options.mime_overrides.unwrap_or_default(),
+            compress_policy: CompressPolicy {
+                min_size: options.compress_min_size.unwrap_or_default(),
+                types: options
+                    .compress_types
+                    .map(|compress_types| compress_types.0),
+            },
+            size_budgets: SizeBudgets {
+                max_file_size: options.max_file_size,
+                max_total_size: options.max_total_size,
+                warn: flag(options.warn_over_budget, false).value,
+            },
+            stream_bodies: StreamBodies::new(options.stream_min_size, options.stream_chunk_size)?,
+            shard_size: options.shard_size.unwrap_or(DEFAULT_SHARD_SIZE),
+            link_section: options.link_section,
+            bundle: options.bundle,
+            charset: options.charset.unwrap_or_default(),
+            cors: options.cors.unwrap_or_default(),
+            encoding_preference: options.encoding_preference.unwrap_or_default(),
+            security_headers: SecurityHeaders::new(
+                options.security_headers.as_ref(),
+                options.referrer_policy.as_ref(),
+                options.cross_origin_opener_policy.as_ref(),
+            )?,
+            fn_name: options.fn_name,
+            vis: options.vis.unwrap_or(parse_quote!(pub)),
+            assets_dirs,
+        })
+    }
+}
+
+pub(crate) struct AssetsDir(pub(crate) LitStr);
+
+/// Expand the `$VAR` and `${VAR}` environment variables of `path`, then with
+/// `manifest_relative = true`, resolve it against the directory of the manifest
+/// of the crate invoking the macro, instead of the current working directory
+/// of the compiler, which differs between `cargo`, rust-analyzer and workspaces
+pub(crate) fn resolve_path(path: &LitStr, manifest_relative: bool) -> syn::Result<LitStr> {
+    let value = env_vars::expand(&path.value(), path.span())?;
+    match env::var("CARGO_MANIFEST_DIR") {
+        Ok(manifest_dir) if manifest_relative && Path::new(&value).is_relative() => {
+            let resolved = Path::new(&manifest_dir).join(&value);
+            Ok(LitStr::new(&resolved.to_string_lossy(), path.span()))
+        }
+        _ => Ok(LitStr::new(&value, path.span())),
+    }
+}
+
+/// Whether the invocation has `manifest_relative = true`, looked up before
+/// parsing it since the paths preceding the key are resolved with it
+pub(crate) fn is_manifest_relative(input: ParseStream) -> syn::Result<bool> {
+    let tokens: Vec<TokenTree> = input.fork().parse::<TokenStream>()?.into_iter().collect();
+    Ok(tokens.windows(3).any(|window| {
+        matches!(
+            window,
+            [TokenTree::Ident(key), TokenTree::Punct(eq), TokenTree::Ident(value)]
+                if key == "manifest_relative" && eq.as_char() == '=' && value == "true"
+        )
+    }))
+}
+
+impl AssetsDir {
+    fn parse(input: ParseStream, manifest_relative: bool) -> syn::Result<Self> {
+        let input_span = input.span();
+        let assets_dir: LitStr = input.parse()?;
+        let literal = assets_dir.value();
+        let assets_dir = resolve_path(&assets_dir, manifest_relative)?;
+        let metadata = match fs::metadata(assets_dir.value()) {
+            Ok(meta) => meta,
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound) => {
+                return Err(syn::Error::new(
+                    input_span,
+                    "The specified assets directory does not exist",
+                ));
+            }
+            Err(e) => {
+                return Err(syn::Error::new(
+                    input_span,
+                    format!(
+                        "Error reading directory {literal}: {}",
+                        DisplayFullError(&e)
+                    ),
+                ));
+            }
+        };
+
+        if !metadata.is_dir() {
+            return Err(syn::Error::new(
+                input_span,
+                "The specified assets directory is not a directory",
+            ));
+        }
+
+        Ok(AssetsDir(assets_dir))
+    }
+}
+
+/// The assets directories, either a single one or a bracketed list whose
+/// later directories override the files of the earlier ones
+pub(crate) struct AssetsDirs(pub(crate) Vec<AssetsDir>);
+
+impl AssetsDirs {
+    fn parse(input: ParseStream, manifest_relative: bool) -> syn::Result<Self> {
+        if !input.peek(syn::token::Bracket) {
+            return Ok(AssetsDirs(vec![AssetsDir::parse(
+                input,
+                manifest_relative,
+            )?]));
+        }
+
+        let inner_content;
+        let brackets = bracketed!(inner_content in input);
+        let mut assets_dirs = Vec::new();
+        while !inner_content.is_empty() {
+            assets_dirs.push(AssetsDir::parse(&inner_content, manifest_relative)?);
+            if !inner_content.is_empty() {
+                inner_content.parse::<Token![,]>()?;
+            }
+        }
+        if assets_dirs.is_empty() {
+            return Err(syn::Error::new(
+                brackets.span.join(),
+                "Expected at least one assets directory",
+            ));
+        }
+
+        Ok(AssetsDirs(assets_dirs))
+    }
+
+    /// The span of the first directory, which the errors
+    /// of the expansion are reported at
+    pub(crate) fn span(&self) -> Span {
+        self.0[0].0.span()
+    }
+}
+
+/// The paths of `relative_path` in the `assets_dirs` where it exists,
+/// along with their metadata, in the order of the directories
+pub(crate) fn find_in_assets_dirs(
+    assets_dirs: &[AssetsDir],
+    relative_path: &Path,
+) -> io::Result<Vec<(PathBuf, fs::Metadata)>> {
+    let mut found = Vec::new();
+    for AssetsDir(assets_dir) in assets_dirs {
+        let full_path = PathBuf::from(assets_dir.value()).join(relative_path);
+        match fs::metadata(&full_path) {
+            Ok(meta) => found.push((full_path, meta)),
+            Err(e) if matches!(e.kind(), io::ErrorKind::NotFound) => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if found.is_empty() {
+        return Err(io::ErrorKind::NotFound.into());
+    }
+    Ok(found)
+}
+
+pub(crate) struct TarballRoute(pub(crate) LitStr);
+
+impl Parse for TarballRoute {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let route: LitStr = input.parse()?;
+        if !route.value().starts_with('/') {
+            return Err(syn::Error::new(
+                route.span(),
+                "The tarball route must start with `/`",
+            ));
+        }
+
+        Ok(TarballRoute(route))
+    }
+}
+
+/// The path all the routes are mounted under, without a trailing slash,
+/// so that it can be prepended to the routes
+pub(crate) struct RoutePrefix(pub(crate) String);
+
+impl Parse for RoutePrefix {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let prefix: LitStr = input.parse()?;
+        let value = prefix.value();
+        if !value.starts_with('/') {
+            return Err(syn::Error::new(
+                prefix.span(),
+                "The route prefix must start with `/`",
+            ));
+        }
+
+        Ok(RoutePrefix(value.trim_end_matches('/').to_owned()))
+    }
+}
+
+/// How the routes of the HTML pages stripped of their extension and of
+/// the directory indexes answer with or without a trailing slash
+#[derive(Clone, Copy)]
+pub(crate) enum TrailingSlash {
+    /// The other form redirects to the generated route
+    Redirect,
+    /// The directory indexes are served without their trailing slash
+    Strip,
+    /// Both forms are served
+    Both,
+}
+
+impl Parse for TrailingSlash {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let value: LitStr = input.parse()?;
+        match value.value().as_str() {
+            "redirect" => Ok(Self::Redirect),
+            "strip" => Ok(Self::Strip),
+            "both" => Ok(Self::Both),
+            _ => Err(syn::Error::new(
+                value.span(),
+                "Expected `\"redirect\"`, `\"strip\"` or `\"both\"`",
+            )),
+        }
+    }
+}
+
+/// The names of the files served at the routes of their directories,
+/// from `index_files`, in order of preference
+#[derive(Default)]
+pub(crate) struct IndexFiles(pub(crate) Vec<String>);
+
+impl Parse for IndexFiles {
+    /// Parse a list of file names such as `["index.html", "index.htm"]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+        let names = inner_content.parse_terminated(<LitStr as Parse>::parse, Token![,])?;
+        names
+            .into_iter()
+            .map(|name| {
+                let value = name.value();
+                if value.is_empty() || value.contains(['/', '\\']) {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        "Index files must be file names, without directories",
+                    ));
+                }
+                Ok(value)
+            })
+            .collect::<syn::Result<_>>()
+            .map(Self)
+    }
+}
+
+pub(crate) struct ImportMapRoute(pub(crate) LitStr);
+
+impl Parse for ImportMapRoute {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let route: LitStr = input.parse()?;
+        if !route.value().starts_with('/') {
+            return Err(syn::Error::new(
+                route.span(),
+                "The import map route must start with `/`",
+            ));
+        }
+
+        Ok(ImportMapRoute(route))
+    }
+}
+
+pub(crate) struct RouteDefinitions {
+    pub(crate) path: LitStr,
+    pub(crate) format: route_definitions::Format,
+}
+
+impl RouteDefinitions {
+    fn parse(input: ParseStream, manifest_relative: bool) -> syn::Result<Self> {
+        let path = resolve_path(&input.parse()?, manifest_relative)?;
+        let Some(format) = route_definitions::Format::from_path(&path.value()) else {
+            return Err(syn::Error::new(
+                path.span(),
+                "The route definitions file must end with `.json`, `.d.ts` or `.ts`",
+            ));
+        };
+
+        Ok(RouteDefinitions { path, format })
+    }
+}
+
+/// The page served for the unmatched routes
+#[derive(Clone, Copy)]
+pub(crate) enum FallbackKind {
+    /// `not_found`, with a `404 Not Found` status
+    NotFound,
+    /// `spa_fallback`, the entry point of a single-page application
+    /// served with a `200 OK` status
+    Spa,
+}
+
+/// Check the fallback page, described as `description`
+/// in the errors, is a file of the assets directories
+///
+/// The page of the last directory containing it wins.
+fn validate_fallback_page(
+    page: &LitStr,
+    assets_dirs: &[AssetsDir],
+    description: &str,
+) -> syn::Result<PathBuf> {
+    match find_in_assets_dirs(assets_dirs, Path::new(&page.value())).map(|mut found| found.pop()) {
+        Ok(Some((full_path, meta))) if meta.is_file() => Ok(full_path),
+        Ok(_) => Err(syn::Error::new(
+            page.span(),
+            format!("The specified {description} is not a file"),
+        )),
+        Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound) => Err(syn::Error::new(
+            page.span(),
+            format!("The specified {description} does not exist"),
+        )),
+        Err(e) => Err(syn::Error::new(
+            page.span(),
+            format!(
+                "Error reading {description} {}: {}",
+                page.value(),
+                DisplayFullError(&e)
+            ),
+        )),
+    }
+}
+
+/// The compression algorithms applied to an asset
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct Algorithms {
+    pub(crate) gzip: bool,
+    pub(crate) zstd: bool,
+    pub(crate) brotli: bool,
+}
+
+impl Algorithms {
+    /// Either gzip and zstd or nothing, as selected by `compress = true`
+    /// or `compress = false`
+    pub(crate) fn compress(enabled: bool) -> Self {
+        Self {
+            gzip: enabled,
+            zstd: enabled,
+            brotli: false,
+        }
+    }
+}
+
+impl Parse for Algorithms {
+    /// Parse a list of algorithms such as `["gzip", "zstd", "br"]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+
+        let mut algorithms = Algorithms::compress(false);
+        while !inner_content.is_empty() {
+            let algorithm = inner_content.parse::<LitStr>()?;
+            match algorithm.value().as_str() {
+                "gzip" => algorithms.gzip = true,
+                "zstd" => algorithms.zstd = true,
+                "br" => algorithms.brotli = true,
+                _ => {
+                    return Err(syn::Error::new(
+                        algorithm.span(),
+                        "Unknown compression algorithm. Expected `gzip`, `zstd`, or `br`",
+                    ));
+                }
+            }
+
+            if !inner_content.is_empty() {
+                inner_content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(algorithms)
+    }
+}
+
+/// Glob patterns, relative to the assets directory, with the compression
+/// algorithms of the matching files. The first matching pattern wins.
+pub(crate) struct CompressionGroups(Vec<(Pattern, Algorithms)>);
+
+impl CompressionGroups {
+    /// The algorithms of the file at `relative_path`, or `default`
+    /// if it doesn't belong to any group
+    pub(crate) fn algorithms(&self, relative_path: &str, default: Algorithms) -> Algorithms {
+        self.0
+            .iter()
+            .find(|(pattern, _)| pattern.matches(relative_path))
+            .map_or(default, |&(_, algorithms)| algorithms)
+    }
+}
+
+impl Parse for CompressionGroups {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        braced!(inner_content in input);
+
+        let mut groups = Vec::new();
+        while !inner_content.is_empty() {
+            let pattern_str = inner_content.parse::<LitStr>()?;
+            let pattern = Pattern::new(&pattern_str.value()).map_err(|e| {
+                syn::Error::new(
+                    pattern_str.span(),
+                    format!("Invalid glob pattern: {}", DisplayFullError(&e)),
+                )
+            })?;
+            inner_content.parse::<Token![=>]>()?;
+            let algorithms = inner_content.parse()?;
+            groups.push((pattern, algorithms));
+
+            if !inner_content.is_empty() {
+                inner_content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(CompressionGroups(groups))
+    }
+}
+
+/// The `include` and `exclude` glob patterns, relative to the assets directory,
+/// whether hidden files are skipped unless they match `keep_hidden`, and
+/// whether the source maps are skipped, with `source_maps = "skip"`
+pub(crate) struct PathFilter {
+    pub(crate) include: Vec<Pattern>,
+    pub(crate) exclude: Vec<Pattern>,
+    pub(crate) skip_hidden: bool,
+    pub(crate) keep_hidden: Vec<Pattern>,
+    pub(crate) skip_source_maps: bool,
+}
+
+impl PathFilter {
+    /// Whether the file at `relative_path` matches one of the `include`
+    /// patterns, if any, and none of the `exclude` ones, unless it's a
+    /// skipped hidden file or source map
+    pub(crate) fn allows(&self, relative_path: &str) -> bool {
+        if self.skip_source_maps && source_maps::is_source_map(relative_path) {
+            return false;
+        }
+        if self.skip_hidden
+            && is_hidden(relative_path)
+            && !self
+                .keep_hidden
+                .iter()
+                .any(|pattern| pattern.matches(relative_path))
+        {
+            return false;
+        }
+
+        (self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| pattern.matches(relative_path)))
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| pattern.matches(relative_path))
+    }
+}
+
+/// The file names of the platform junk skipped with the hidden files
+const JUNK_FILES: [&str; 2] = ["Thumbs.db", "desktop.ini"];
+
+/// Whether the file at `relative_path` is a dotfile, is in a hidden directory,
+/// or is platform junk such as `Thumbs.db` or an editor swap file
+fn is_hidden(relative_path: &str) -> bool {
+    let file_name = relative_path.rsplit('/').next().unwrap_or(relative_path);
+    relative_path
+        .split('/')
+        .any(|component| component.starts_with('.'))
+        || JUNK_FILES
+            .iter()
+            .any(|junk| file_name.eq_ignore_ascii_case(junk))
+        || Path::new(file_name)
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("swp"))
+        || file_name.ends_with('~')
+}
+
+/// Parse a bracketed list of glob patterns, such as `["**/*.js", "**/*.css"]`
+fn parse_patterns(input: ParseStream) -> syn::Result<Vec<Pattern>> {
+    let inner_content;
+    bracketed!(inner_content in input);
+
+    let mut patterns = Vec::new();
+    while !inner_content.is_empty() {
+        let pattern_str = inner_content.parse::<LitStr>()?;
+        let pattern = Pattern::new(&pattern_str.value()).map_err(|e| {
+            syn::Error::new(
+                pattern_str.span(),
+                format!("Invalid glob pattern: {}", DisplayFullError(&e)),
+            )
+        })?;
+        patterns.push(pattern);
+
+        if !inner_content.is_empty() {
+            inner_content.parse::<Token![,]>()?;
+        }
+    }
+
+    Ok(patterns)
+}
+
+pub(crate) struct IgnorePaths(pub(crate) Vec<PathBuf>);
+
+struct IgnorePathsWithSpan(Vec<(PathBuf, Span)>);
+
+impl Parse for IgnorePathsWithSpan {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let dirs = parse_dirs(input)?;
+
+        Ok(IgnorePathsWithSpan(dirs))
+    }
+}
+
+fn validate_ignore_paths(
+    ignore_paths: IgnorePathsWithSpan,
+    assets_dirs: &[AssetsDir],
+) -> syn::Result<IgnorePaths> {
+    let mut valid_ignore_paths = Vec::new();
+    for (dir, span) in ignore_paths.0 {
+        match find_in_assets_dirs(assets_dirs, &dir) {
+            Ok(found) => valid_ignore_paths.extend(found.into_iter().map(|(path, _)| path)),
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound) => {
+                return Err(syn::Error::new(
+                    span,
+                    "The specified ignored path does not exist",
+                ));
+            }
+            Err(e) => {
+                return Err(syn::Error::new(
+                    span,
+                    format!(
+                        "Error reading ignored path {}: {}",
+                        dir.to_string_lossy(),
+                        DisplayFullError(&e)
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(IgnorePaths(valid_ignore_paths))
+}
+
+/// Either `true`, `false`, or a list of algorithms
+pub(crate) struct ShouldCompress(pub(crate) Algorithms);
+
+impl Parse for ShouldCompress {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitBool) {
+            let lit: LitBool = input.parse()?;
+            Ok(ShouldCompress(Algorithms::compress(lit.value)))
+        } else {
+            input.parse().map(ShouldCompress)
+        }
+    }
+}
+
+/// The `max-age` of the `Cache-Control` header of cache-busted assets, in seconds
+pub(crate) struct MaxAge(pub(crate) u64);
+
+impl Parse for MaxAge {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lit: LitInt = input.parse()?;
+        Ok(MaxAge(lit.base10_parse()?))
+    }
+}
+
+/// The gzip compression level, from 0 to 9
+pub(crate) struct GzipLevel(pub(crate) u32);
+
+impl Default for GzipLevel {
+    fn default() -> Self {
+        Self(flate2::Compression::best().level())
+    }
+}
+
+impl Parse for GzipLevel {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lit: LitInt = input.parse()?;
+        let level = lit.base10_parse()?;
+        if level > 9 {
+            return Err(syn::Error::new(
+                lit.span(),
+                "The gzip compression level must be between 0 and 9",
+            ));
+        }
+        Ok(GzipLevel(level))
+    }
+}
+
+/// The zstd compression level, within `zstd::compression_level_range()`
+pub(crate) struct ZstdLevel(pub(crate) i32);
+
+impl Default for ZstdLevel {
+    fn default() -> Self {
+        Self(*zstd::compression_level_range().end())
+    }
+}
+
+impl Parse for ZstdLevel {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let lit: LitInt = input.parse()?;
+        let level = lit.base10_parse()?;
+        let range = zstd::compression_level_range();
+        if !range.contains(&level) {
+            return Err(syn::Error::new(
+                lit.span(),
+                format!(
+                    "The zstd compression level must be between {} and {}",
+                    range.start(),
+                    range.end()
+                ),
+            ));
+        }
+        Ok(ZstdLevel(level))
+    }
+}
+
+pub(crate) struct ShouldStripHtmlExt(pub(crate) LitBool);
+
+pub(crate) struct IsCacheBusted(pub(crate) LitBool);
+
+pub(crate) struct CacheBustedPaths {
+    pub(crate) dirs: Vec<PathBuf>,
+    pub(crate) files: Vec<PathBuf>,
+}
+
+struct CacheBustedPathsWithSpan(Vec<(PathBuf, Span)>);
+
+impl Parse for CacheBustedPathsWithSpan {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let dirs = parse_dirs(input)?;
+        Ok(CacheBustedPathsWithSpan(dirs))
+    }
+}
+
+fn validate_cache_busted_paths(
+    tuples: CacheBustedPathsWithSpan,
+    assets_dirs: &[AssetsDir],
+) -> syn::Result<CacheBustedPaths> {
+    let mut valid_dirs = Vec::new();
+    let mut valid_files = Vec::new();
+    for (dir, span) in tuples.0 {
+        match find_in_assets_dirs(assets_dirs, &dir) {
+            Ok(found) => {
+                for (full_path, meta) in found {
+                    if meta.is_dir() {
+                        valid_dirs.push(full_path);
+                    } else {
+                        valid_files.push(full_path);
+                    }
+                }
+            }
+            Err(e) if matches!(e.kind(), std::io::ErrorKind::NotFound) => {
+                return Err(syn::Error::new(
+                    span,
+                    "The specified directory for cache busting does not exist",
+                ));
+            }
+            Err(e) => {
+                return Err(syn::Error::new(
+                    span,
+                    format!(
+                        "Error reading path {}: {}",
+                        dir.to_string_lossy(),
+                        DisplayFullError(&e)
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(CacheBustedPaths {
+        dirs: valid_dirs,
+        files: valid_files,
+    })
+}
+
+/// Explicit `Cache-Control` values of the files under paths relative
+/// to the assets directory. The most specific path wins.
+pub(crate) struct CacheControls(Vec<(PathBuf, LitStr)>);
+
+impl CacheControls {
+    /// The `Cache-Control` value of the file at `relative_path`, if any
+    pub(crate) fn value(&self, relative_path: &Path) -> Option<String> {
+        self.0
+            .iter()
+            .filter(|(path, _)| relative_path.starts_with(path))
+            .max_by_key(|(path, _)| path.components().count())
+            .map(|(_, value)| value.value())
+    }
+}
+
+impl Parse for CacheControls {
+    /// Parse a list of tuples such as `[("images", "public, max-age=86400")]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+
+        let mut cache_controls = Vec::new();
+        while !inner_content.is_empty() {
+            let tuple_content;
+            parenthesized!(tuple_content in inner_content);
+            let path = tuple_content.parse::<LitStr>()?;
+            tuple_content.parse::<Token![,]>()?;
+            let value = tuple_content.parse::<LitStr>()?;
+            if !tuple_content.is_empty() {
+                tuple_content.parse::<Token![,]>()?;
+            }
+            cache_controls.push((PathBuf::from(path.value()), value));
+
+            if !inner_content.is_empty() {
+                inner_content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(CacheControls(cache_controls))
+    }
+}
+
+/// Check the paths of the `Cache-Control` values exist in the assets
+/// directories, and the values can be sent in a header
+fn validate_cache_controls(
+    cache_controls: &CacheControls,
+    assets_dirs: &[AssetsDir],
+) -> syn::Result<()> {
+    for (path, value) in &cache_controls.0 {
+        if find_in_assets_dirs(assets_dirs, path).is_err() {
+            return Err(syn::Error::new(
+                value.span(),
+                format!(
+                    "The path {} of the `Cache-Control` value does not exist",
+                    path.display()
+                ),
+            ));
+        }
+        if !is_header_value(&value.value()) {
+            return Err(syn::Error::new(
+                value.span(),
+                "The `Cache-Control` value must be made of visible ASCII characters",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Extra headers of all the assets, or of the files under paths relative to
+/// the assets directory, tuples of a lowercase header name and of its value
+#[derive(Default)]
+pub(crate) struct ExtraHeaders(Vec<(Option<PathBuf>, String, LitStr)>);
+
+impl ExtraHeaders {
+    /// The extra headers of the file at `relative_path`, or of
+    /// an asset generated at expansion time when `None`
+    pub(crate) fn headers(&self, relative_path: Option<&Path>) -> Vec<(String, String)> {
+        self.0
+            .iter()
+            .filter(|(path, _, _)| match (path, relative_path) {
+                (None, _) => true,
+                (Some(path), Some(relative_path)) => relative_path.starts_with(path),
+                (Some(_), None) => false,
+            })
+            .map(|(_, name, value)| (name.clone(), value.value()))
+            .collect()
+    }
+}
+
+impl Parse for ExtraHeaders {
+    /// Parse a list of tuples such as `[("X-Frame-Options", "DENY")]`, with
+    /// an optional leading path, e.g. `("admin", "X-Robots-Tag", "noindex")`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+
+        let mut extra_headers = Vec::new();
+        while !inner_content.is_empty() {
+            let tuple_content;
+            parenthesized!(tuple_content in inner_content);
+            let mut lit_strs = tuple_content
+                .parse_terminated(<LitStr as Parse>::parse, Token![,])?
+                .into_iter()
+                .collect::<Vec<_>>();
+            let (path, name, value) = match lit_strs.len() {
+                2 => {
+                    let value = lit_strs.pop().expect("the tuple has two elements");
+                    (
+                        None,
+                        lit_strs.pop().expect("the tuple has two elements"),
+                        value,
+                    )
+                }
+                3 => {
+                    let value = lit_strs.pop().expect("the tuple has three elements");
+                    let name = lit_strs.pop().expect("the tuple has three elements");
+                    let path = lit_strs.pop().expect("the tuple has three elements");
+                    (Some(PathBuf::from(path.value())), name, value)
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        tuple_content.span(),
+                        "Expected a tuple of a header name and value, with an optional leading path",
+                    ));
+                }
+            };
+            let lowercase_name = name.value().to_ascii_lowercase();
+            if lowercase_name.is_empty() || !lowercase_name.bytes().all(is_token_byte) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!("`{}` is not a valid header name", name.value()),
+                ));
+            }
+            extra_headers.push((path, lowercase_name, value));
+
+            if !inner_content.is_empty() {
+                inner_content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(ExtraHeaders(extra_headers))
+    }
+}
+
+/// Whether `byte` can be part of a token, such as a header name
+pub(crate) fn is_token_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+/// Check the paths of the extra headers exist in the assets
+/// directories, and the values can be sent in a header
+fn validate_extra_headers(
+    extra_headers: &ExtraHeaders,
+    assets_dirs: &[AssetsDir],
+) -> syn::Result<()> {
+    for (path, name, value) in &extra_headers.0 {
+        if let Some(path) = path
+            && find_in_assets_dirs(assets_dirs, path).is_err()
+        {
+            return Err(syn::Error::new(
+                value.span(),
+                format!(
+                    "The path {} of the `{name}` header does not exist",
+                    path.display()
+                ),
+            ));
+        }
+        if !is_header_value(&value.value()) {
+            return Err(syn::Error::new(
+                value.span(),
+                format!("The `{name}` value must be made of visible ASCII characters"),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The compressed variants sent to the clients accepting several encodings
+/// with the same quality, as names of `ContentEncoding` variants in order
+/// of preference, from `encoding_preference`
+#[derive(Default)]
+pub(crate) struct EncodingPreference(pub(crate) Vec<&'static str>);
+
+impl Parse for EncodingPreference {
+    /// Parse a list of encodings such as `["gzip", "zstd", "br"]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+        let encodings = inner_content.parse_terminated(<LitStr as Parse>::parse, Token![,])?;
+
+        let mut preference = Vec::new();
+        for encoding in encodings {
+            let variant = match encoding.value().as_str() {
+                "gzip" => "Gzip",
+                "zstd" => "Zstd",
+                "br" => "Brotli",
+                _ => {
+                    return Err(syn::Error::new(
+                        encoding.span(),
+                        "Expected `gzip`, `zstd` or `br`",
+                    ));
+                }
+            };
+            if preference.contains(&variant) {
+                return Err(syn::Error::new(
+                    encoding.span(),
+                    "The encoding is listed twice",
+                ));
+            }
+            preference.push(variant);
+        }
+        if preference.is_empty() {
+            return Err(syn::Error::new(
+                inner_content.span(),
+                "Expected at least one encoding",
+            ));
+        }
+        Ok(Self(preference))
+    }
+}
+
+/// The subtrees served with Basic authentication, tuples of a path
+/// relative to the assets directory and of the environment variable
+/// holding the accepted credentials
+#[derive(Default)]
+pub(crate) struct Protected(Vec<(LitStr, String)>);
+
+impl Protected {
+    /// The realm and the credentials variable of the file at
+    /// `relative_path`, from the innermost protected subtree holding it
+    pub(crate) fn basic_auth(&self, relative_path: &Path) -> Option<(String, String)> {
+        self.0
+            .iter()
+            .filter(|(path, _)| relative_path.starts_with(path.value()))
+            .max_by_key(|(path, _)| Path::new(&path.value()).components().count())
+            .map(|(path, credentials_var)| (path.value(), credentials_var.clone()))
+    }
+
+    /// Check the protected paths exist in the assets directories
+    fn validate(&self, assets_dirs: &[AssetsDir]) -> syn::Result<()> {
+        for (path, _) in &self.0 {
+            if find_in_assets_dirs(assets_dirs, Path::new(&path.value())).is_err() {
+                return Err(syn::Error::new(
+                    path.span(),
+                    format!("The protected path {} does not exist", path.value()),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Parse for Protected {
+    /// Parse a list of tuples such as `[("internal", "env:INTERNAL_DOCS_CREDS")]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+
+        let mut protected = Vec::new();
+        while !inner_content.is_empty() {
+            let tuple_content;
+            parenthesized!(tuple_content in inner_content);
+            let path: LitStr = tuple_content.parse()?;
+            tuple_content.parse::<Token![,]>()?;
+            let credentials: LitStr = tuple_content.parse()?;
+
+            // The path is the realm of the `WWW-Authenticate` header, a quoted string
+            let realm = path.value();
+            if !is_header_value(&realm) || realm.contains(['"', '\\']) {
+                return Err(syn::Error::new(
+                    path.span(),
+                    "The protected path must be made of visible ASCII characters, without quotes or backslashes",
+                ));
+            }
+            let credentials_var = credentials
+                .value()
+                .strip_prefix("env:")
+                .filter(|var| !var.is_empty() && !var.contains(['=', '\0']))
+                .map(str::to_owned)
+                .ok_or_else(|| {
+                    syn::Error::new(
+                        credentials.span(),
+                        "Expected the environment variable holding the credentials, e.g. `env:INTERNAL_DOCS_CREDS`",
+                    )
+                })?;
+            protected.push((path, credentials_var));
+
+            if !inner_content.is_empty() {
+                inner_content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(Protected(protected))
+    }
+}
+
+/// The subtrees of the assets directory only served
+/// to the URLs signed with the key of the `UrlSigner` extension
+#[derive(Default)]
+pub(crate) struct Signed(Vec<LitStr>);
+
+impl Signed {
+    /// Whether the file at `relative_path` is in a signed subtree
+    pub(crate) fn contains(&self, relative_path: &Path) -> bool {
+        self.0
+            .iter()
+            .any(|path| relative_path.starts_with(path.value()))
+    }
+}
+
+impl Parse for Signed {
+    /// Parse a list of paths such as `["downloads", "reports/annual.pdf"]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+        let paths = inner_content.parse_terminated(<LitStr as Parse>::parse, Token![,])?;
+        Ok(Signed(paths.into_iter().collect()))
+    }
+}
+
+/// Check the signed paths exist in the assets directories
+fn validate_signed(signed: &Signed, assets_dirs: &[AssetsDir]) -> syn::Result<()> {
+    for path in &signed.0 {
+        if find_in_assets_dirs(assets_dirs, Path::new(&path.value())).is_err() {
+            return Err(syn::Error::new(
+                path.span(),
+                format!("The signed path {} does not exist", path.value()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The files of the assets directory preloaded by the HTML pages
+#[derive(Default)]
+pub(crate) struct Preload(pub(crate) Vec<LitStr>);
+
+impl Parse for Preload {
+    /// Parse a list of files such as `["app.js", "styles.css"]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+        let paths = inner_content.parse_terminated(<LitStr as Parse>::parse, Token![,])?;
+        Ok(Preload(paths.into_iter().collect()))
+    }
+}
+
+/// Check the preloaded files exist in the assets directories
+fn validate_preload(preload: &Preload, assets_dirs: &[AssetsDir]) -> syn::Result<()> {
+    for path in &preload.0 {
+        let found = find_in_assets_dirs(assets_dirs, Path::new(&path.value()));
+        if !found.is_ok_and(|found| found.iter().any(|(_, meta)| meta.is_file())) {
+            return Err(syn::Error::new(
+                path.span(),
+                format!("The preloaded file {} does not exist", path.value()),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The files compressed at build time, from `compress_min_size`
+/// and `compress_types`
+#[derive(Default)]
+pub(crate) struct CompressPolicy {
+    /// The size under which files aren't compressed, in bytes
+    min_size: usize,
+    /// The patterns of the compressed media types, such as `text/*`,
+    /// all of them when `None`
+    types: Option<Vec<String>>,
+}
+
+impl CompressPolicy {
+    /// Whether to compress a file of `size` bytes served as `content_type`
+    pub(crate) fn applies_to(&self, content_type: &str, size: usize) -> bool {
+        let media_type = media_type(content_type).trim();
+        size >= self.min_size
+            && self.types.as_ref().is_none_or(|types| {
+                types.iter().any(|pattern| match pattern.strip_suffix('*') {
+                    Some(prefix) => media_type
+                        .get(..prefix.len())
+                        .is_some_and(|start| start.eq_ignore_ascii_case(prefix)),
+                    None => media_type.eq_ignore_ascii_case(pattern),
+                })
+            })
+    }
+}
+
+/// The large bodies sent in chunks, from `stream_min_size`
+/// and `stream_chunk_size`
+#[derive(Clone, Copy)]
+pub(crate) struct StreamBodies {
+    /// The size from which bodies are streamed, in bytes
+    pub(crate) min_size: u64,
+    pub(crate) chunk_size: NonZeroUsize,
+}
+
+impl StreamBodies {
+    /// The chunks sent by default, small enough to be written
+    /// as the connection drains them
+    const DEFAULT_CHUNK_SIZE: NonZeroUsize = NonZeroUsize::new(64 * 1024).unwrap();
+
+    fn new(
+        min_size: Option<ByteSize>,
+        chunk_size: Option<(ByteSize, Span)>,
+    ) -> syn::Result<Option<Self>> {
+        let chunk_size = chunk_size
+            .map(|(chunk_size, span)| {
+                usize::try_from(chunk_size.bytes())
+                    .ok()
+                    .and_then(NonZeroUsize::new)
+                    .map(|chunk_size| (chunk_size, span))
+                    .ok_or_else(|| {
+                        syn::Error::new(span, "`stream_chunk_size` must be a positive size")
+                    })
+            })
+            .transpose()?;
+        match (min_size, chunk_size) {
+            (Some(min_size), chunk_size) => Ok(Some(Self {
+                min_size: min_size.bytes(),
+                chunk_size: chunk_size
+                    .map_or(Self::DEFAULT_CHUNK_SIZE, |(chunk_size, _)| chunk_size),
+            })),
+            (None, Some((_, span))) => Err(syn::Error::new(
+                span,
+                "`stream_chunk_size` requires `stream_min_size` to be set",
+            )),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// The patterns of `compress_types`
+struct CompressTypes(Vec<String>);
+
+impl Parse for CompressTypes {
+    /// Parse a list of media types such as `["text/*", "image/svg+xml"]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+        let patterns = inner_content.parse_terminated(<LitStr as Parse>::parse, Token![,])?;
+        patterns
+            .into_iter()
+            .map(|pattern| {
+                let value = pattern.value();
+                let is_valid = value.split_once('/').is_some_and(|(kind, subtype)| {
+                    !kind.is_empty()
+                        && !kind.contains('*')
+                        && (subtype == "*" || !subtype.is_empty() && !subtype.contains('*'))
+                });
+                if is_valid {
+                    Ok(value)
+                } else {
+                    Err(syn::Error::new(
+                        pattern.span(),
+                        "Expected a media type such as `image/svg+xml`, or a wildcard such as `text/*`",
+                    ))
+                }
+            })
+            .collect::<syn::Result<_>>()
+            .map(CompressTypes)
+    }
+}
+
+/// Custom routes of some files of the assets directory, replacing
+/// the ones derived from their paths
+#[derive(Default)]
+pub(crate) struct RouteMap(Vec<(String, LitStr)>);
+
+impl RouteMap {
+    /// The custom route of the file at `relative_path`, if any
+    pub(crate) fn route(&self, relative_path: &str) -> Option<String> {
+        let relative_path = &normalize_web_path(relative_path)[1..];
+        self.0
+            .iter()
+            .find(|(path, _)| path == relative_path)
+            .map(|(_, route)| route.value())
+    }
+}
+
+impl Parse for RouteMap {
+    /// Parse a list of tuples such as `[("favicon/icon-32.png", "/favicon.ico")]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+
+        let mut route_map = Vec::new();
+        while !inner_content.is_empty() {
+            let tuple_content;
+            parenthesized!(tuple_content in inner_content);
+            let path = tuple_content.parse::<LitStr>()?;
+            tuple_content.parse::<Token![,]>()?;
+            let route = tuple_content.parse::<LitStr>()?;
+            if !tuple_content.is_empty() {
+                tuple_content.parse::<Token![,]>()?;
+            }
+
+            if !route.value().starts_with('/') {
+                return Err(syn::Error::new(
+                    route.span(),
+                    "The custom route must start with `/`",
+                ));
+            }
+            route_map.push((normalize_web_path(&path.value())[1..].to_owned(), route));
+
+            if !inner_content.is_empty() {
+                inner_content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(RouteMap(route_map))
+    }
+}
+
+/// Check the files given custom routes exist in the assets directories
+fn validate_route_map(route_map: &RouteMap, assets_dirs: &[AssetsDir]) -> syn::Result<()> {
+    for (path, route) in &route_map.0 {
+        let found = find_in_assets_dirs(assets_dirs, Path::new(path));
+        if !found.is_ok_and(|found| found.iter().any(|(_, meta)| meta.is_file())) {
+            return Err(syn::Error::new(
+                route.span(),
+                format!("The file {path} of the custom route does not exist"),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The charset appended to the textual content types, unless
+/// they have parameters already. Disabled with `charset = false`.
+pub(crate) struct Charset(pub(crate) Option<String>);
+
+impl Default for Charset {
+    fn default() -> Self {
+        Self(Some("utf-8".to_owned()))
+    }
+}
+
+impl Parse for Charset {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitBool) {
+            let enabled: LitBool = input.parse()?;
+            return Ok(if enabled.value {
+                Charset::default()
+            } else {
+                Charset(None)
+            });
+        }
+
+        let charset: LitStr = input.parse()?;
+        let value = charset.value();
+        if value.is_empty()
+            || !value
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+        {
+            return Err(syn::Error::new(
+                charset.span(),
+                "The charset must be a token such as `utf-8`",
+            ));
+        }
+        Ok(Charset(Some(value)))
+    }
+}
+
+/// The values of the `Referrer-Policy` header
+const REFERRER_POLICIES: &[&str] = &[
+    "no-referrer",
+    "no-referrer-when-downgrade",
+    "origin",
+    "origin-when-cross-origin",
+    "same-origin",
+    "strict-origin",
+    "strict-origin-when-cross-origin",
+    "unsafe-url",
+];
+
+/// The values of the `Cross-Origin-Opener-Policy` header
+const CROSS_ORIGIN_OPENER_POLICIES: &[&str] = &[
+    "unsafe-none",
+    "same-origin-allow-popups",
+    "same-origin",
+    "noopener-allow-popups",
+];
+
+/// The security headers sent with every asset, from `security_headers`,
+/// `referrer_policy` and `cross_origin_opener_policy`
+pub(crate) struct SecurityHeaders(Vec<(&'static str, String)>);
+
+impl SecurityHeaders {
+    /// `X-Content-Type-Options: nosniff` if `nosniff` is true, along
+    /// with the policies given, checked against the ones browsers know
+    pub(crate) fn new(
+        nosniff: Option<&LitBool>,
+        referrer_policy: Option<&LitStr>,
+        cross_origin_opener_policy: Option<&LitStr>,
+    ) -> syn::Result<Self> {
+        let mut headers = Vec::new();
+        if nosniff.is_some_and(|nosniff| nosniff.value) {
+            headers.push(("x-content-type-options", "nosniff".to_owned()));
+        }
+        for (name, policy, policies) in [
+            ("referrer-policy", referrer_policy, REFERRER_POLICIES),
+            (
+                "cross-origin-opener-policy",
+                cross_origin_opener_policy,
+                CROSS_ORIGIN_OPENER_POLICIES,
+            ),
+        ] {
+            let Some(policy) = policy else {
+                continue;
+            };
+            let value = policy.value();
+            if !policies.contains(&value.as_str()) {
+                return Err(syn::Error::new(
+                    policy.span(),
+                    format!(
+                        "`{value}` is not a valid `{name}`, expected one of {}",
+                        policies.join(", ")
+                    ),
+                ));
+            }
+            headers.push((name, value));
+        }
+        Ok(Self(headers))
+    }
+
+    /// Add the security headers to the `headers` of an asset,
+    /// unless they're already set with `headers`
+    pub(crate) fn add_to(&self, headers: &mut Vec<(String, String)>) {
+        for (name, value) in &self.0 {
+            if !headers.iter().any(|(existing, _)| existing == name) {
+                headers.push(((*name).to_owned(), value.clone()));
+            }
+        }
+    }
+}
+
+/// The origins allowed to read the assets with CORS requests,
+/// `*` allowing all of them. Disabled when empty.
+#[derive(Default)]
+pub(crate) struct Cors(pub(crate) Vec<String>);
+
+impl Parse for Cors {
+    /// Parse either `"*"` or a list of origins such as `["https://example.com"]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let origins = if input.peek(LitStr) {
+            vec![input.parse::<LitStr>()?]
+        } else {
+            let inner_content;
+            bracketed!(inner_content in input);
+            inner_content
+                .parse_terminated(<LitStr as Parse>::parse, Token![,])?
+                .into_iter()
+                .collect()
+        };
+
+        let is_wildcard = origins.iter().any(|origin| origin.value() == "*");
+        for origin in &origins {
+            let value = origin.value();
+            if is_wildcard && origins.len() > 1 {
+                return Err(syn::Error::new(
+                    origin.span(),
+                    "`*` cannot be combined with other origins",
+                ));
+            }
+            if value != "*"
+                && (!value.contains("://") || value.ends_with('/') || !is_header_value(&value))
+            {
+                return Err(syn::Error::new(
+                    origin.span(),
+                    format!(
+                        "`{value}` is not an origin such as `https://example.com`, without a path"
+                    ),
+                ));
+            }
+        }
+        Ok(Cors(origins.iter().map(LitStr::value).collect()))
+    }
+}
+
+/// The content type of the files with unknown or missing extensions
+pub(crate) struct UnknownMime(LitStr);
+
+impl Parse for UnknownMime {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let content_type = input.parse::<LitStr>()?;
+        if !is_header_value(&content_type.value()) {
+            return Err(syn::Error::new(
+                content_type.span(),
+                "The content type must be made of visible ASCII characters",
+            ));
+        }
+        Ok(UnknownMime(content_type))
+    }
+}
+
+/// The content type of the files with unknown extensions: `unknown_mime`,
+/// or `application/octet-stream` with `allow_unknown_extensions`
+pub(crate) fn unknown_content_type(
+    allow_unknown_extensions: &LitBool,
+    unknown_mime: Option<&UnknownMime>,
+) -> Option<String> {
+    unknown_mime
+        .map(|UnknownMime(content_type)| content_type.value())
+        .or_else(|| {
+            allow_unknown_extensions
+                .value
+                .then(|| OCTET_STREAM.to_owned())
+        })
+}
+
+/// Whether `value` is non-empty and made of the visible
+/// ASCII characters and tabs allowed in header values
+pub(crate) fn is_header_value(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|byte| byte == b'\t' || (b' '..=b'~').contains(&byte))
+}
+
+/// Content types of the file extensions which `mime_guess`
+/// doesn't know, or guesses wrongly
+#[derive(Default)]
+pub(crate) struct MimeOverrides(Vec<(String, String)>);
+
+impl MimeOverrides {
+    /// The content type of the files with the extension `ext`, if overridden
+    pub(crate) fn content_type(&self, ext: &str) -> Option<String> {
+        self.0
+            .iter()
+            .find(|(overridden, _)| overridden.eq_ignore_ascii_case(ext))
+            .map(|(_, content_type)| content_type.clone())
+    }
+}
+
+impl Parse for MimeOverrides {
+    /// Parse a list of tuples such as `[("wasm", "application/wasm")]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+
+        let mut mime_overrides = Vec::new();
+        while !inner_content.is_empty() {
+            let tuple_content;
+            parenthesized!(tuple_content in inner_content);
+            let ext = tuple_content.parse::<LitStr>()?;
+            tuple_content.parse::<Token![,]>()?;
+            let content_type = tuple_content.parse::<LitStr>()?;
+            if !tuple_content.is_empty() {
+                tuple_content.parse::<Token![,]>()?;
+            }
+
+            if ext.value().is_empty() || ext.value().starts_with('.') {
+                return Err(syn::Error::new(
+                    ext.span(),
+                    "The extension must be given without its leading dot",
+                ));
+            }
+            if !is_header_value(&content_type.value()) {
+                return Err(syn::Error::new(
+                    content_type.span(),
+                    "The content type must be made of visible ASCII characters",
+                ));
+            }
+            mime_overrides.push((ext.value(), content_type.value()));
+
+            if !inner_content.is_empty() {
+                inner_content.parse::<Token![,]>()?;
+            }
+        }
+
+        Ok(MimeOverrides(mime_overrides))
+    }
+}
+
+/// Helper function for turning an array of strs representing paths into
+/// a `Vec` containing tuples of each `PathBuf` and its `Span` in the `ParseStream`
+fn parse_dirs(input: ParseStream) -> syn::Result<Vec<(PathBuf, Span)>> {
+    let inner_content;
+    bracketed!(inner_content in input);
+
+    let mut dirs = Vec::new();
+    while !inner_content.is_empty() {
+        let directory_span = inner_content.span();
+        let directory_str = inner_content.parse::<LitStr>()?;
+        let path = PathBuf::from(directory_str.value());
+        dirs.push((path, directory_span));
+
+        if !inner_content.is_empty() {
+            inner_content.parse::<Token![,]>()?;
+        }
+    }
+    Ok(dirs)
+}
+
+/// How `embed_assets!` handles the files whose paths aren't valid UTF-8,
+/// such as legacy-encoded names on some filesystems
+#[derive(Clone, Copy)]
+pub(crate) enum NonUtf8Paths {
+    /// Fail the compilation, naming the file
+    Error,
+    /// Leave the file out, with a warning listing the skipped files
+    Skip,
+    /// Embed the file, percent-encoding the invalid bytes of its route
+    Encode,
+}
+
+impl Parse for NonUtf8Paths {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let value: LitStr = input.parse()?;
+        match value.value().as_str() {
+            "error" => Ok(Self::Error),
+            "skip" => Ok(Self::Skip),
+            "encode" => Ok(Self::Encode),
+            _ => Err(syn::Error::new(
+                value.span(),
+                "Expected `\"error\"`, `\"skip\"` or `\"encode\"`",
+            )),
+        }
+    }
+}
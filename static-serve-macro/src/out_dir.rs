@@ -0,0 +1,185 @@
+//! Storage of the embedded bytes in files under `OUT_DIR`, referenced
+//! with `include_bytes!` rather than spelled out in the token stream,
+//! and in the linker section of `link_section`, or out of the binary
+//! in the file of `bundle`
+//!
+//! Cargo only sets `OUT_DIR` for the crates with a build script,
+//! so the bytes of the other crates stay byte string literals.
+
+use std::{
+    env,
+    fmt::Write as _,
+    fs,
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use proc_macro2::TokenStream;
+use quote::{ToTokens, quote};
+use sha2::{Digest as _, Sha256};
+use syn::LitByteStr;
+
+use crate::{OptionBytesSlice, bundle::Bundle, error::Error};
+
+/// The size under which the bytes stay literals,
+/// which are then smaller than a file and its path
+const MIN_SIZE: usize = 1024;
+
+/// The number of files written by this process, naming their temporary files
+static WRITTEN: AtomicUsize = AtomicUsize::new(0);
+
+/// The directory the embedded bytes are written to
+pub(crate) struct BytesDir {
+    dir: Option<PathBuf>,
+    /// The linker section of the statics holding the bytes, if any
+    link_section: Option<String>,
+    /// The bundle file the bytes are written to instead, if any
+    bundle: Option<Bundle>,
+}
+
+impl BytesDir {
+    /// The `static-serve` directory under the `OUT_DIR` of the crate
+    /// expanding the macro, which is only set when it has a build script
+    pub(crate) fn from_env() -> Self {
+        Self {
+            dir: env::var_os("OUT_DIR").map(|out_dir| PathBuf::from(out_dir).join("static-serve")),
+            link_section: None,
+            bundle: None,
+        }
+    }
+
+    /// Place the embedded bytes in statics of the linker section `link_section`
+    pub(crate) fn with_link_section(mut self, link_section: Option<String>) -> Self {
+        self.link_section = link_section;
+        self
+    }
+
+    /// Write the embedded bytes to the bundle at `path` rather than to the binary
+    pub(crate) fn with_bundle(mut self, path: Option<PathBuf>) -> Self {
+        self.bundle = path.map(Bundle::new);
+        self
+    }
+
+    /// Whether the bytes are read from a bundle at runtime,
+    /// in which case they can't be used in constants
+    pub(crate) fn is_bundled(&self) -> bool {
+        self.bundle.is_some()
+    }
+
+    /// Write the bundle, if any, and generate the static mapping it
+    pub(crate) fn write_bundle(&self) -> Result<Option<TokenStream>, Error> {
+        self.bundle.as_ref().map(Bundle::write).transpose()
+    }
+
+    /// The `&'static [u8]` expression embedding `bytes`
+    pub(crate) fn bytes(&self, bytes: &LitByteStr) -> Result<TokenStream, Error> {
+        if let Some(bundle) = &self.bundle {
+            return Ok(bundle.bytes(&bytes.value()));
+        }
+        let array = self.array(bytes)?;
+        let Some(link_section) = &self.link_section else {
+            return Ok(quote! { #array.as_slice() });
+        };
+        let len = bytes.value().len();
+        Ok(quote! {{
+            #[unsafe(link_section = #link_section)]
+            static BYTES: [u8; #len] = *#array;
+            BYTES.as_slice()
+        }})
+    }
+
+    /// The `&'static [u8; N]` expression of `bytes`: a literal,
+    /// or a file included from the directory when they're large
+    fn array(&self, bytes: &LitByteStr) -> Result<TokenStream, Error> {
+        let contents = bytes.value();
+        let Some(dir) = self.dir.as_ref().filter(|_| contents.len() >= MIN_SIZE) else {
+            return Ok(bytes.to_token_stream());
+        };
+
+        // Named after their hash, so that identical bytes are written once
+        let mut file_name = String::with_capacity(64);
+        for byte in Sha256::digest(&contents) {
+            write!(file_name, "{byte:02x}").expect("writing to a String cannot fail");
+        }
+        let path = dir.join(&file_name);
+        // The expansions of parallel compilations may write the same file,
+        // so it's renamed into place once complete rather than written there
+        let temp_path = dir.join(format!(
+            "{file_name}.{}.{}.tmp",
+            process::id(),
+            WRITTEN.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(dir).map_err(Error::CannotWriteOutDir)?;
+        fs::write(&temp_path, &contents).map_err(Error::CannotWriteOutDir)?;
+        fs::rename(&temp_path, &path).map_err(Error::CannotWriteOutDir)?;
+        let path = path.to_str().ok_or(Error::FilePathIsNotUtf8)?;
+        Ok(quote! { ::core::include_bytes!(#path) })
+    }
+
+    /// The `Option<&'static [u8]>` expression embedding `bytes`, if any
+    pub(crate) fn option_bytes(&self, bytes: &OptionBytesSlice) -> Result<TokenStream, Error> {
+        Ok(if let Some(bytes) = &bytes.0 {
+            let bytes = self.bytes(bytes)?;
+            quote! { ::std::option::Option::Some(#bytes) }
+        } else {
+            quote! { ::std::option::Option::None }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use proc_macro2::Span;
+    use syn::LitByteStr;
+
+    use super::BytesDir;
+
+    #[test]
+    fn writes_large_bytes_to_files() {
+        let dir = std::env::temp_dir().join("static-serve-out-dir-test");
+        let bytes_dir = BytesDir {
+            dir: Some(dir.clone()),
+            link_section: None,
+            bundle: None,
+        };
+
+        let small = LitByteStr::new(b"small", Span::call_site());
+        let tokens = bytes_dir.bytes(&small).unwrap().to_string();
+        assert!(!tokens.contains("include_bytes"));
+
+        let contents = vec![b'a'; 4096];
+        let large = LitByteStr::new(&contents, Span::call_site());
+        let tokens = bytes_dir.bytes(&large).unwrap().to_string();
+        assert!(tokens.starts_with(":: core :: include_bytes !"));
+        let path = tokens.split('"').nth(1).unwrap();
+        assert!(path.starts_with(dir.to_str().unwrap()));
+        assert_eq!(fs::read(path).unwrap(), contents);
+
+        // Writing the same bytes again replaces the file, leaving no temporary one
+        bytes_dir.bytes(&large).unwrap();
+        assert_eq!(fs::read(path).unwrap(), contents);
+        assert!(fs::read_dir(&dir).unwrap().all(|entry| {
+            entry
+                .unwrap()
+                .path()
+                .extension()
+                .is_none_or(|extension| extension != "tmp")
+        }));
+    }
+
+    #[test]
+    fn places_bytes_in_link_section() {
+        let bytes_dir = BytesDir {
+            dir: None,
+            link_section: Some(".static_serve".to_owned()),
+            bundle: None,
+        };
+        let small = LitByteStr::new(b"small", Span::call_site());
+        let tokens = bytes_dir.bytes(&small).unwrap().to_string();
+        assert!(tokens.contains("link_section = \".static_serve\""));
+        assert!(tokens.contains("static BYTES : [u8 ; 5usize]"));
+    }
+}
@@ -0,0 +1,119 @@
+//! Generation of the `paths` module, with a constant holding the route
+//! of each embedded file, so that renaming a file fails the compilation
+//! of the code linking to it instead of making it 404, and of the
+//! `asset_url` function, looking up the URL linking to a route
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::{Ident, Visibility};
+
+use crate::{AssetEntry, EmbeddedFileInfo, error::Error};
+
+/// Generate the `paths` module of the `assets`,
+/// the first of which are embedded from `entries`
+pub(crate) fn paths_module(
+    entries: &[AssetEntry],
+    assets: &[EmbeddedFileInfo],
+    vis: &Visibility,
+) -> Result<TokenStream, Error> {
+    let mut constants: Vec<(String, &str, String, Option<&TokenStream>)> = Vec::new();
+    for (entry, asset) in entries.iter().zip(assets) {
+        let Some(route) = asset.url() else {
+            continue;
+        };
+        let name = constant_name(&entry.relative_path);
+        if let Some((_, first, ..)) = constants.iter().find(|(other, ..)| *other == name) {
+            return Err(Error::DuplicateConstant {
+                name,
+                first: (*first).to_owned(),
+                second: entry.relative_path.clone(),
+            });
+        }
+        constants.push((name, &entry.relative_path, route, asset.cfg_gate.as_ref()));
+    }
+
+    let constants = constants
+        .iter()
+        .map(|(name, relative_path, route, predicate)| {
+            let name = Ident::new(name, Span::call_site());
+            let doc = format!("The route of `{relative_path}`");
+            // Linking to an excluded asset fails to compile too
+            let cfg = predicate.map(|predicate| quote! { #[cfg(#predicate)] });
+            quote! {
+                #[doc = #doc]
+                #cfg
+                pub const #name: &str = #route;
+            }
+        });
+    Ok(quote! {
+        #[allow(dead_code)]
+        #vis mod paths {
+            #(#constants)*
+        }
+    })
+}
+
+/// Generate the `asset_url` function, returning the URL linking to the
+/// route of one of the `assets`, with the hash of its contents in the
+/// query string with `cache_bust_query`
+pub(crate) fn asset_url_fn(assets: &[EmbeddedFileInfo], vis: &Visibility) -> TokenStream {
+    let mut urls: Vec<(&str, String, usize)> = assets
+        .iter()
+        .enumerate()
+        .filter_map(|(index, asset)| Some((asset.entry_path.as_deref()?, asset.url()?, index)))
+        .collect();
+    urls.sort_unstable();
+    let urls_len = urls.len();
+    let urls = urls
+        .iter()
+        .map(|(route, url, index)| quote! { (#route, #url, #index) });
+    quote! {
+        #[allow(dead_code)]
+        #vis fn asset_url(web_path: &str) -> ::std::option::Option<&'static str> {
+            static URLS: [(&str, &str, usize); #urls_len] = [#(#urls),*];
+            // Decoded or encoded, in the spelling of the routes
+            let web_path = ::static_serve::canonical_path(web_path);
+            let index = URLS
+                .binary_search_by(|(route, ..)| (*route).cmp(&*web_path))
+                .ok()?;
+            let (_, url, asset_index) = URLS[index];
+            __STATIC_SERVE_INCLUDED[asset_index].then_some(url)
+        }
+    }
+}
+
+/// The name of the constant of the file at `relative_path`,
+/// e.g. `IMMUTABLE_APP_JS` for `immutable/app.js`
+fn constant_name(relative_path: &str) -> String {
+    let mut name: String = relative_path
+        .trim_start_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+#[cfg(test)]
+mod test {
+    use super::constant_name;
+
+    #[test]
+    fn names_constants_after_paths() {
+        assert_eq!(constant_name("app.js"), "APP_JS");
+        assert_eq!(
+            constant_name("immutable/app.min.js"),
+            "IMMUTABLE_APP_MIN_JS"
+        );
+        assert_eq!(constant_name("404.html"), "_404_HTML");
+        assert_eq!(constant_name("café.css"), "CAF__CSS");
+    }
+}
@@ -0,0 +1,172 @@
+//! Pre-compressed siblings of the embedded files, picked up by
+//! `embed_assets!` with `use_precompressed = true` instead of
+//! compressing the files at expansion time
+
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use flate2::read::MultiGzDecoder;
+
+use crate::{AssetEntry, BROTLI_BUFFER_SIZE, ZSTD_WINDOW_LOG, error::Error};
+
+/// The extensions of the siblings of `app.js`, e.g. `app.js.gz`
+const EXTENSIONS: [&str; 3] = ["gz", "zst", "br"];
+
+/// The pre-compressed variants of a file
+#[derive(Debug, Default)]
+pub(crate) struct Precompressed {
+    pub(crate) gzip: Option<Vec<u8>>,
+    pub(crate) zstd: Option<Vec<u8>>,
+    pub(crate) brotli: Option<Vec<u8>>,
+}
+
+impl Precompressed {
+    /// Whether the file has no pre-compressed sibling
+    pub(crate) fn is_empty(&self) -> bool {
+        self.gzip.is_none() && self.zstd.is_none() && self.brotli.is_none()
+    }
+}
+
+/// Remove the siblings of the other `entries`, which are
+/// embedded as their variants rather than as files of their own
+pub(crate) fn remove_siblings(entries: &mut Vec<AssetEntry>) {
+    let paths: Vec<PathBuf> = entries.iter().map(|entry| entry.path.clone()).collect();
+    entries.retain(|entry| {
+        let is_sibling = entry
+            .path
+            .extension()
+            .is_some_and(|extension| EXTENSIONS.iter().any(|ext| extension == *ext))
+            && paths.contains(&entry.path.with_extension(""));
+        !is_sibling
+    });
+}
+
+/// Read the siblings of the file at `path`, checking they decompress to its `contents`
+pub(crate) fn read(path: &Path, contents: &[u8]) -> Result<Precompressed, Error> {
+    let gzip = read_sibling(path, "gz")?;
+    let zstd = read_sibling(path, "zst")?;
+    let brotli = read_sibling(path, "br")?;
+
+    if let Some(gzip) = &gzip
+        && !gzip_decompresses_to(gzip, contents)
+    {
+        return Err(Error::PrecompressedMismatch(sibling_path(path, "gz")));
+    }
+    if let Some(zstd) = &zstd
+        && !zstd_decompresses_to(zstd, contents)
+    {
+        return Err(Error::PrecompressedMismatch(sibling_path(path, "zst")));
+    }
+    if let Some(brotli) = &brotli
+        && !brotli_decompresses_to(brotli, contents)
+    {
+        return Err(Error::PrecompressedMismatch(sibling_path(path, "br")));
+    }
+
+    Ok(Precompressed { gzip, zstd, brotli })
+}
+
+/// `path` with `extension` appended, e.g. `app.js.gz`
+fn sibling_path(path: &Path, extension: &str) -> PathBuf {
+    let mut sibling = OsString::from(path);
+    sibling.push(".");
+    sibling.push(extension);
+    PathBuf::from(sibling)
+}
+
+fn read_sibling(path: &Path, extension: &str) -> Result<Option<Vec<u8>>, Error> {
+    match fs::read(sibling_path(path, extension)) {
+        Ok(sibling) => Ok(Some(sibling)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(Error::CannotReadEntryContents(err)),
+    }
+}
+
+/// Whether the gzip-compressed `gzip` decompresses to `contents`
+pub(crate) fn gzip_decompresses_to(gzip: &[u8], contents: &[u8]) -> bool {
+    decompresses_to(MultiGzDecoder::new(gzip), contents)
+}
+
+/// Whether the zstd-compressed `zstd` decompresses to `contents` within
+/// the 8 MiB window the browsers are required to support (RFC 8878,
+/// section 3), the ones requiring more being rejected by some of them
+pub(crate) fn zstd_decompresses_to(zstd: &[u8], contents: &[u8]) -> bool {
+    zstd::Decoder::new(zstd).is_ok_and(|mut decoder| {
+        decoder.window_log_max(ZSTD_WINDOW_LOG).is_ok() && decompresses_to(decoder, contents)
+    })
+}
+
+/// Whether the Brotli-compressed `brotli` decompresses to `contents`
+pub(crate) fn brotli_decompresses_to(brotli: &[u8], contents: &[u8]) -> bool {
+    decompresses_to(
+        brotli::Decompressor::new(brotli, BROTLI_BUFFER_SIZE),
+        contents,
+    )
+}
+
+fn decompresses_to(mut decoder: impl Read, contents: &[u8]) -> bool {
+    let mut decompressed = Vec::new();
+    decoder.read_to_end(&mut decompressed).is_ok() && decompressed == contents
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use flate2::{Compression, read::MultiGzDecoder, write::GzEncoder};
+
+    use super::{brotli_decompresses_to, decompresses_to, zstd_decompresses_to};
+    use crate::brotli_compress;
+
+    #[test]
+    fn checks_decompressed_contents() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"console.log(1)").unwrap();
+        let gzip = encoder.finish().unwrap();
+
+        assert!(decompresses_to(
+            MultiGzDecoder::new(gzip.as_slice()),
+            b"console.log(1)"
+        ));
+        assert!(!decompresses_to(
+            MultiGzDecoder::new(gzip.as_slice()),
+            b"console.log(2)"
+        ));
+        assert!(!decompresses_to(
+            MultiGzDecoder::new(&b"not gzip"[..]),
+            b"not gzip"
+        ));
+    }
+
+    #[test]
+    fn checks_the_zstd_window() {
+        let contents = b"console.log(1)".repeat(16);
+        let compress = |window_log| {
+            let mut encoder = zstd::Encoder::new(Vec::new(), 3).unwrap();
+            encoder.window_log(window_log).unwrap();
+            encoder.include_contentsize(false).unwrap();
+            encoder.write_all(&contents).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        assert!(zstd_decompresses_to(&compress(23), &contents));
+        assert!(!zstd_decompresses_to(&compress(27), &contents));
+        assert!(!zstd_decompresses_to(&compress(23), b"console.log(2)"));
+    }
+
+    #[test]
+    fn round_trips_brotli() {
+        let contents = b"console.log(1)".repeat(64);
+        let brotli = brotli_compress(&contents).unwrap().unwrap();
+
+        assert!(brotli.len() < contents.len() / 4);
+        assert!(brotli_decompresses_to(&brotli, &contents));
+        assert!(!brotli_decompresses_to(&brotli, b"console.log(2)"));
+        assert!(!brotli_decompresses_to(b"not brotli", b"not brotli"));
+        assert_eq!(brotli_compress(b"tiny").unwrap(), None);
+    }
+}
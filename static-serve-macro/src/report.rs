@@ -0,0 +1,300 @@
+//! Generation of a JSON or CSV report of the sizes of the embedded assets,
+//! for CI to track the size of the bundle over time
+
+use std::{
+    env,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use syn::{Lit, LitStr, parse::ParseStream};
+
+use crate::{
+    EmbeddedFileInfo, env_vars, error::Error, options::resolve_path,
+    route_definitions::string_literal,
+};
+
+/// The file name of the report written to `OUT_DIR` by `report = true`
+/// or the `STATIC_SERVE_REPORT` environment variable
+const DEFAULT_FILE_NAME: &str = "static-serve-report.json";
+
+/// The format of the report, chosen from its extension
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// `.json`: an array with an object for every asset
+    Json,
+    /// `.csv`: a header line and a line for every asset
+    Csv,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_ascii_lowercase();
+        if extension == "json" {
+            Some(Self::Json)
+        } else if extension == "csv" {
+            Some(Self::Csv)
+        } else {
+            None
+        }
+    }
+}
+
+/// Where and how the report is written
+pub(crate) struct Report {
+    path: PathBuf,
+    format: Format,
+}
+
+impl Report {
+    /// The report requested with the `STATIC_SERVE_REPORT` environment
+    /// variable, written to `OUT_DIR` when the crate has a build script
+    pub(crate) fn from_env() -> Option<Self> {
+        // Tracked so that setting it expands the invocations again
+        env_vars::var_os("STATIC_SERVE_REPORT")
+            .filter(|value| !value.is_empty() && value != "0")?;
+        let out_dir = env::var_os("OUT_DIR")?;
+        Some(Self {
+            path: PathBuf::from(out_dir).join(DEFAULT_FILE_NAME),
+            format: Format::Json,
+        })
+    }
+
+    /// Write the report of `assets`
+    ///
+    /// Like the route definitions, the file is left untouched
+    /// when its contents are already up to date.
+    pub(crate) fn write(&self, assets: &[EmbeddedFileInfo]) -> Result<(), Error> {
+        let rows = assets.iter().map(Row::new);
+        let contents = match self.format {
+            Format::Json => json(rows),
+            Format::Csv => csv(rows),
+        };
+
+        if fs::read_to_string(&self.path).is_ok_and(|existing| existing == contents) {
+            return Ok(());
+        }
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).map_err(Error::CannotWriteReport)?;
+        }
+        fs::write(&self.path, contents).map_err(Error::CannotWriteReport)
+    }
+}
+
+/// `report = true`, `report = false` or `report = "path.json"`
+pub(crate) struct ReportOption(pub(crate) Option<Report>);
+
+impl ReportOption {
+    pub(crate) fn parse(input: ParseStream, manifest_relative: bool) -> syn::Result<Self> {
+        match input.parse()? {
+            Lit::Bool(enabled) if !enabled.value => Ok(Self(None)),
+            Lit::Bool(enabled) => {
+                let Some(out_dir) = env::var_os("OUT_DIR") else {
+                    return Err(syn::Error::new(
+                        enabled.span,
+                        "`report = true` writes the report to `OUT_DIR`, which is only set for crates with a build script. Add a `build.rs` or give the path of the report",
+                    ));
+                };
+                Ok(Self(Some(Report {
+                    path: PathBuf::from(out_dir).join(DEFAULT_FILE_NAME),
+                    format: Format::Json,
+                })))
+            }
+            Lit::Str(path) => {
+                let path: LitStr = resolve_path(&path, manifest_relative)?;
+                let value = PathBuf::from(path.value());
+                let Some(format) = Format::from_path(&value) else {
+                    return Err(syn::Error::new(
+                        path.span(),
+                        "The report file must end with `.json` or `.csv`",
+                    ));
+                };
+                Ok(Self(Some(Report {
+                    path: value,
+                    format,
+                })))
+            }
+            other => Err(syn::Error::new(
+                other.span(),
+                "Expected `true`, `false` or the path of the report file",
+            )),
+        }
+    }
+}
+
+/// The sizes and encodings of an asset
+#[derive(Debug, PartialEq, Eq)]
+struct Row<'a> {
+    route: &'a str,
+    content_type: &'a str,
+    size: usize,
+    gzip_size: Option<usize>,
+    zstd_size: Option<usize>,
+    brotli_size: Option<usize>,
+}
+
+impl<'a> Row<'a> {
+    fn new(asset: &'a EmbeddedFileInfo) -> Self {
+        Self {
+            route: asset.entry_path.as_deref().unwrap_or_default(),
+            content_type: &asset.content_type,
+            size: asset.lit_byte_str_contents.value().len(),
+            gzip_size: asset.maybe_gzip.0.as_ref().map(|gzip| gzip.value().len()),
+            zstd_size: asset.maybe_zstd.0.as_ref().map(|zstd| zstd.value().len()),
+            brotli_size: asset
+                .maybe_brotli
+                .0
+                .as_ref()
+                .map(|brotli| brotli.value().len()),
+        }
+    }
+
+    /// The compressed encodings kept for the asset, which are the ones
+    /// smaller than the original with its compression settings
+    fn encodings(&self) -> impl Iterator<Item = &'static str> {
+        [
+            ("gzip", self.gzip_size),
+            ("zstd", self.zstd_size),
+            ("br", self.brotli_size),
+        ]
+        .into_iter()
+        .filter_map(|(encoding, size)| size.map(|_| encoding))
+    }
+}
+
+fn json<'a>(rows: impl Iterator<Item = Row<'a>>) -> String {
+    fn size(size: Option<usize>) -> String {
+        size.map_or_else(|| "null".to_owned(), |size| size.to_string())
+    }
+
+    let mut report = String::from("[");
+    for (i, row) in rows.enumerate() {
+        let separator = if i == 0 { "" } else { "," };
+        let encodings = row
+            .encodings()
+            .map(string_literal)
+            .collect::<Vec<_>>()
+            .join(", ");
+        write!(
+            report,
+            "{separator}\n  {{\n    \"route\": {},\n    \"contentType\": {},\n    \"size\": {},\n    \"gzipSize\": {},\n    \"zstdSize\": {},\n    \"brotliSize\": {},\n    \"encodings\": [{encodings}]\n  }}",
+            string_literal(row.route),
+            string_literal(row.content_type),
+            row.size,
+            size(row.gzip_size),
+            size(row.zstd_size),
+            size(row.brotli_size),
+        )
+        .expect("writing to a String cannot fail");
+    }
+    report.push_str("\n]\n");
+    report
+}
+
+fn csv<'a>(rows: impl Iterator<Item = Row<'a>>) -> String {
+    fn size(size: Option<usize>) -> String {
+        size.map(|size| size.to_string()).unwrap_or_default()
+    }
+
+    let mut report =
+        String::from("route,content_type,size,gzip_size,zstd_size,brotli_size,encodings\n");
+    for row in rows {
+        writeln!(
+            report,
+            "{},{},{},{},{},{},{}",
+            csv_field(row.route),
+            csv_field(row.content_type),
+            row.size,
+            size(row.gzip_size),
+            size(row.zstd_size),
+            size(row.brotli_size),
+            row.encodings().collect::<Vec<_>>().join(" "),
+        )
+        .expect("writing to a String cannot fail");
+    }
+    report
+}
+
+/// Quote `value` when it contains a separator, a quote or a line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Report, Row, csv, json};
+    use crate::env_vars;
+
+    #[test]
+    fn formats_json_and_csv_reports() {
+        let rows = || {
+            [
+                Row {
+                    route: "/app.js",
+                    content_type: "text/javascript",
+                    size: 1000,
+                    gzip_size: Some(400),
+                    zstd_size: Some(380),
+                    brotli_size: None,
+                },
+                Row {
+                    route: "/a,b.png",
+                    content_type: "image/png",
+                    size: 50,
+                    gzip_size: None,
+                    zstd_size: None,
+                    brotli_size: None,
+                },
+            ]
+            .into_iter()
+        };
+
+        assert_eq!(
+            json(rows()),
+            r#"[
+  {
+    "route": "/app.js",
+    "contentType": "text/javascript",
+    "size": 1000,
+    "gzipSize": 400,
+    "zstdSize": 380,
+    "brotliSize": null,
+    "encodings": ["gzip", "zstd"]
+  },
+  {
+    "route": "/a,b.png",
+    "contentType": "image/png",
+    "size": 50,
+    "gzipSize": null,
+    "zstdSize": null,
+    "brotliSize": null,
+    "encodings": []
+  }
+]
+"#
+        );
+        assert_eq!(
+            csv(rows()),
+            "route,content_type,size,gzip_size,zstd_size,brotli_size,encodings\n\
+             /app.js,text/javascript,1000,400,380,,gzip zstd\n\
+             \"/a,b.png\",image/png,50,,,,\n"
+        );
+    }
+
+    #[test]
+    fn tracks_the_report_variable() {
+        env_vars::reset();
+        Report::from_env();
+        assert!(
+            env_vars::tracked()
+                .to_string()
+                .contains("\"STATIC_SERVE_REPORT\"")
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! Generation of a JSON or TypeScript file describing the embedded routes,
+//! for frontend builds to reference the assets served by the binary
+
+use std::{fmt::Write as _, fs, path::Path};
+
+use crate::{EmbeddedFileInfo, error::Error};
+
+/// The format of the route definitions file, chosen from its extension
+#[derive(Clone, Copy)]
+pub(crate) enum Format {
+    /// `.json`: an object mapping every route to its metadata
+    Json,
+    /// `.ts`: the same object exported as a constant, along with its types
+    TypeScript,
+    /// `.d.ts`: only the types
+    TypeScriptDeclarations,
+}
+
+impl Format {
+    pub(crate) fn from_path(path: &str) -> Option<Self> {
+        let path = path.to_ascii_lowercase();
+        let extension = Path::new(&path).extension()?;
+        if extension == "json" {
+            Some(Self::Json)
+        } else if extension == "ts" && path.ends_with(".d.ts") {
+            Some(Self::TypeScriptDeclarations)
+        } else if extension == "ts" {
+            Some(Self::TypeScript)
+        } else {
+            None
+        }
+    }
+}
+
+const HEADER: &str = "// Generated by static-serve, do not edit.\n";
+
+const ROUTE_INFO_TYPE: &str = "export interface StaticRouteInfo {
+  readonly contentType: string;
+  readonly hash: string | null;
+  readonly cacheBusted: boolean;
+  readonly integrity?: string;
+}
+";
+
+/// Write the definitions of the routes of `assets` to `path`
+///
+/// The file is left untouched when its contents are already up to date,
+/// so frontend tooling watching it isn't triggered on every expansion.
+pub(crate) fn write(path: &Path, format: Format, assets: &[EmbeddedFileInfo]) -> Result<(), Error> {
+    let contents = match format {
+        Format::Json => {
+            let mut contents = routes_object(assets);
+            contents.push('\n');
+            contents
+        }
+        Format::TypeScript => format!(
+            "{HEADER}\n{ROUTE_INFO_TYPE}\nexport const staticRoutes = {} as const satisfies Record<string, StaticRouteInfo>;\n\nexport type StaticRoute = keyof typeof staticRoutes;\n",
+            routes_object(assets)
+        ),
+        Format::TypeScriptDeclarations => format!(
+            "{HEADER}\n{ROUTE_INFO_TYPE}\nexport type StaticRoute ={};\n",
+            route_union(assets)
+        ),
+    };
+
+    if fs::read_to_string(path).is_ok_and(|existing| existing == contents) {
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(Error::CannotWriteRouteDefinitions)?;
+    }
+    fs::write(path, contents).map_err(Error::CannotWriteRouteDefinitions)
+}
+
+/// A JSON object (and TypeScript object literal) mapping every route
+/// to its content type, hash, whether it's cache-busted and
+/// its Subresource Integrity hash, when there is one
+fn routes_object(assets: &[EmbeddedFileInfo]) -> String {
+    let mut object = String::from("{");
+    for (i, asset) in assets.iter().enumerate() {
+        let separator = if i == 0 { "" } else { "," };
+        let route = asset.entry_path.as_deref().unwrap_or_default();
+        // The ETag without its quotes, when there is one
+        let hash = asset.etag_str.0.as_deref().map_or_else(
+            || "null".to_owned(),
+            |etag| string_literal(etag.trim_matches('"')),
+        );
+        let integrity = asset
+            .integrity
+            .0
+            .as_deref()
+            .map_or_else(String::new, |integrity| {
+                format!(",\n    \"integrity\": {}", string_literal(integrity))
+            });
+        write!(
+            object,
+            "{separator}\n  {}: {{\n    \"contentType\": {},\n    \"hash\": {hash},\n    \"cacheBusted\": {}{integrity}\n  }}",
+            string_literal(route),
+            string_literal(&asset.content_type),
+            asset.cache_busted,
+        )
+        .expect("writing to a String cannot fail");
+    }
+    object.push_str("\n}");
+    object
+}
+
+/// A TypeScript union of the string literal types of every route
+fn route_union(assets: &[EmbeddedFileInfo]) -> String {
+    if assets.is_empty() {
+        return " never".to_owned();
+    }
+
+    let mut union = String::new();
+    for asset in assets {
+        let route = asset.entry_path.as_deref().unwrap_or_default();
+        write!(union, "\n  | {}", string_literal(route)).expect("writing to a String cannot fail");
+    }
+    union
+}
+
+/// Quote and escape `value` as a JSON string, which is also
+/// a valid TypeScript string literal
+pub(crate) fn string_literal(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            // U+2028 and U+2029 are line terminators in older JavaScript engines
+            c if c.is_control() || c == '\u{2028}' || c == '\u{2029}' => {
+                write!(literal, "\\u{:04x}", u32::from(c))
+                    .expect("writing to a String cannot fail");
+            }
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
@@ -0,0 +1,136 @@
+//! The `source_maps` policy of the files with the `.map` extension:
+//! embedded and served like the other files, left out, or embedded
+//! but only served to the requests with a header or a cookie
+
+use std::path::Path;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    LitStr,
+    parse::{Parse, ParseStream},
+};
+
+use crate::options::is_token_byte;
+
+/// The header revealing the hidden source maps when
+/// neither `source_maps_header` nor `source_maps_cookie` is set
+const DEFAULT_HEADER: &str = "x-source-maps";
+
+/// How `embed_assets!` handles the source maps
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum SourceMaps {
+    /// Embed and serve them like the other files
+    #[default]
+    Embed,
+    /// Leave them out, like the files of `ignore_paths`
+    Skip,
+    /// Embed them, but answer the requests without the header or the
+    /// cookie of the `SourceMapGate` with `404 Not Found`
+    HeaderOnly,
+}
+
+impl Parse for SourceMaps {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let value: LitStr = input.parse()?;
+        match value.value().as_str() {
+            "embed" => Ok(Self::Embed),
+            "skip" => Ok(Self::Skip),
+            "header-only" => Ok(Self::HeaderOnly),
+            _ => Err(syn::Error::new(
+                value.span(),
+                "Expected `\"embed\"`, `\"skip\"` or `\"header-only\"`",
+            )),
+        }
+    }
+}
+
+/// The header or the cookie the source maps are only served with,
+/// for `source_maps = "header-only"`
+#[derive(Clone)]
+pub(crate) struct SourceMapGate {
+    /// The lowercase name of the header, from `source_maps_header`
+    header: Option<String>,
+    /// The name of the cookie, from `source_maps_cookie`
+    cookie: Option<String>,
+}
+
+impl SourceMapGate {
+    /// The gate of the source maps with the `source_maps` policy, if hidden
+    ///
+    /// The `x-source-maps` header reveals them when neither `header`
+    /// nor `cookie` is set, and either one does when both are.
+    pub(crate) fn new(
+        source_maps: SourceMaps,
+        header: Option<&LitStr>,
+        cookie: Option<&LitStr>,
+    ) -> syn::Result<Option<Self>> {
+        if source_maps != SourceMaps::HeaderOnly {
+            if let Some((option, value)) = [
+                ("source_maps_header", header),
+                ("source_maps_cookie", cookie),
+            ]
+            .into_iter()
+            .find_map(|(option, value)| Some((option, value?)))
+            {
+                return Err(syn::Error::new(
+                    value.span(),
+                    format!("`{option}` requires `source_maps = \"header-only\"`"),
+                ));
+            }
+            return Ok(None);
+        }
+
+        let header = header.map(|header| token(header, "header")).transpose()?;
+        let cookie = cookie.map(|cookie| token(cookie, "cookie")).transpose()?;
+        Ok(Some(match (header, cookie) {
+            (None, None) => Self {
+                header: Some(DEFAULT_HEADER.to_owned()),
+                cookie: None,
+            },
+            (header, cookie) => Self {
+                header: header.map(|header| header.to_ascii_lowercase()),
+                cookie,
+            },
+        }))
+    }
+
+    /// The `Option<SourceMapGate>` of the `StaticAsset` gated by `gate`
+    pub(crate) fn tokens(gate: Option<&Self>) -> TokenStream {
+        let Some(Self { header, cookie }) = gate else {
+            return quote! { ::std::option::Option::None };
+        };
+        let option = |name: Option<&str>| {
+            name.map_or_else(
+                || quote! { ::std::option::Option::None },
+                |name| quote! { ::std::option::Option::Some(#name) },
+            )
+        };
+        let (header, cookie) = (option(header.as_deref()), option(cookie.as_deref()));
+        quote! {
+            ::std::option::Option::Some(::static_serve::SourceMapGate {
+                header: #header,
+                cookie: #cookie,
+            })
+        }
+    }
+}
+
+/// The name of the header or the cookie `name`, which must be a token
+fn token(name: &LitStr, kind: &str) -> syn::Result<String> {
+    let value = name.value();
+    if value.is_empty() || !value.bytes().all(is_token_byte) {
+        return Err(syn::Error::new(
+            name.span(),
+            format!("The source maps {kind} must be a valid {kind} name"),
+        ));
+    }
+    Ok(value)
+}
+
+/// Whether the file at `relative_path` is a source map
+pub(crate) fn is_source_map(relative_path: &str) -> bool {
+    Path::new(relative_path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("map"))
+}
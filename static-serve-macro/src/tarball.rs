@@ -0,0 +1,139 @@
+//! Generation of a `.tar.gz` archive of the whole embedded asset tree
+
+use std::{fs, io::Write};
+
+use flate2::write::GzEncoder;
+use proc_macro2::Span;
+use syn::LitByteStr;
+
+use crate::{
+    AssetEntry, EmbeddedFileInfo, ImmutableAliases, OptionBytesSlice, OptionStr, StoredBody,
+    error::{Error, GzipType},
+    etag::EtagScheme,
+    normalize_web_path, validators,
+};
+
+const BLOCK_SIZE: usize = 512;
+
+/// Build the asset served at `route`: a gzipped tarball
+/// containing every file in `entries`, stored relative to their assets directory
+pub(crate) fn tarball_asset(
+    entries: &[&AssetEntry],
+    route: String,
+    last_modified_only: bool,
+    etag_scheme: EtagScheme<'_>,
+    gzip_level: u32,
+) -> Result<EmbeddedFileInfo, Error> {
+    let mut tar = Vec::new();
+    for AssetEntry {
+        path,
+        relative_path,
+        ..
+    } in entries.iter().copied()
+    {
+        let contents = fs::read(path).map_err(Error::CannotReadEntryContents)?;
+        append_file(&mut tar, &normalize_web_path(relative_path)[1..], &contents)?;
+    }
+    // The archive ends with two zero-filled blocks
+    tar.resize(tar.len() + 2 * BLOCK_SIZE, 0);
+
+    let mut compressor = GzEncoder::new(Vec::new(), flate2::Compression::new(gzip_level));
+    compressor
+        .write_all(&tar)
+        .map_err(|e| Error::Gzip(GzipType::CompressorWrite(e)))?;
+    let tarball = compressor
+        .finish()
+        .map_err(|e| Error::Gzip(GzipType::EncoderFinish(e)))?;
+
+    let (etag_str, last_modified) = validators(
+        &tarball,
+        entries.iter().map(|entry| entry.path.as_path()),
+        last_modified_only,
+        etag_scheme,
+    )?;
+
+    Ok(EmbeddedFileInfo {
+        entry_path: Some(route),
+        content_type: "application/gzip".to_owned(),
+        etag_str: OptionStr(etag_str),
+        last_modified: OptionStr(last_modified),
+        lit_byte_str_contents: LitByteStr::new(&tarball, Span::call_site()),
+        maybe_gzip: OptionBytesSlice(None),
+        maybe_zstd: OptionBytesSlice(None),
+        maybe_brotli: OptionBytesSlice(None),
+        cache_busted: false,
+        immutable_aliases: ImmutableAliases::default(),
+        cache_control: OptionStr(None),
+        headers: Vec::new(),
+        stored_body: StoredBody::Identity,
+        image_variants: Vec::new(),
+        media_variants: Vec::new(),
+        integrity: OptionStr(None),
+        cors: Vec::new(),
+        encoding_preference: Vec::new(),
+        file_path: None,
+        stream_chunk_size: None,
+        basic_auth: None,
+        signed: false,
+        source_map_gate: None,
+        cfg_gate: None,
+    })
+}
+
+/// Append a regular file to a ustar archive
+///
+/// Modification times, owners and permissions are fixed
+/// so the archive is reproducible across builds.
+fn append_file(tar: &mut Vec<u8>, path: &str, contents: &[u8]) -> Result<(), Error> {
+    let (prefix, name) = split_path(path)?;
+
+    let mut header = [0_u8; BLOCK_SIZE];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    write_octal(&mut header[100..108], 0o644);
+    write_octal(&mut header[108..116], 0);
+    write_octal(&mut header[116..124], 0);
+    write_octal(
+        &mut header[124..136],
+        contents
+            .len()
+            .try_into()
+            .expect("contents size should fit into u64"),
+    );
+    write_octal(&mut header[136..148], 0);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+    // The checksum is computed with the checksum field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum = header.iter().map(|&byte| u64::from(byte)).sum();
+    write_octal(&mut header[148..155], checksum);
+
+    tar.extend_from_slice(&header);
+    tar.extend_from_slice(contents);
+    tar.resize(tar.len().next_multiple_of(BLOCK_SIZE), 0);
+
+    Ok(())
+}
+
+/// Split a path into the ustar `prefix` (up to 155 bytes)
+/// and `name` (up to 100 bytes) fields
+fn split_path(path: &str) -> Result<(&str, &str), Error> {
+    if path.len() <= 100 {
+        return Ok(("", path));
+    }
+
+    path.match_indices('/')
+        .map(|(index, _)| (&path[..index], &path[index + 1..]))
+        .find(|(prefix, name)| prefix.len() <= 155 && name.len() <= 100 && !name.is_empty())
+        .ok_or_else(|| Error::TarballPathTooLong(path.to_owned()))
+}
+
+/// Write `value` as a zero-padded, NUL-terminated octal number filling `field`
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    let octal = format!("{value:0digits$o}");
+    field[..digits].copy_from_slice(octal.as_bytes());
+    field[digits] = 0;
+}
@@ -0,0 +1,195 @@
+//! Substitution of the `{{NAME}}` placeholders of the textual assets at
+//! expansion time, for `templates = true` and `template_vars`
+
+use std::{
+    collections::BTreeSet,
+    env,
+    path::Path,
+    sync::{Mutex, PoisonError},
+};
+
+use syn::{
+    LitStr, Token, bracketed, parenthesized,
+    parse::{Parse, ParseStream},
+};
+
+use crate::{env_vars, error::Error};
+
+/// The values of the placeholders given with `template_vars`,
+/// which take precedence over the environment variables
+#[derive(Default)]
+pub(crate) struct TemplateVars {
+    vars: Vec<(String, String)>,
+    /// The names of the environment variables read, whose
+    /// changes must trigger a new expansion
+    env_reads: Mutex<BTreeSet<String>>,
+}
+
+impl Parse for TemplateVars {
+    /// Parse a list of tuples such as `[("API_URL", "https://example.com")]`
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let inner_content;
+        bracketed!(inner_content in input);
+
+        let mut vars = Vec::new();
+        while !inner_content.is_empty() {
+            let tuple_content;
+            parenthesized!(tuple_content in inner_content);
+            let name = tuple_content.parse::<LitStr>()?;
+            tuple_content.parse::<Token![,]>()?;
+            let value = tuple_content.parse::<LitStr>()?;
+            if !tuple_content.is_empty() {
+                tuple_content.parse::<Token![,]>()?;
+            }
+
+            if placeholder_len(name.value().as_bytes()) != Some(name.value().len()) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    "The name must be made of uppercase ASCII letters, digits and underscores, and not start with a digit",
+                ));
+            }
+            vars.push((name.value(), value.value()));
+
+            if !inner_content.is_empty() {
+                inner_content.parse::<Token![,]>()?;
+            }
+        }
+        Ok(Self {
+            vars,
+            env_reads: Mutex::default(),
+        })
+    }
+}
+
+impl TemplateVars {
+    /// The value of the placeholder `name`, from `template_vars`
+    /// or else from the environment of the compilation
+    fn value(&self, name: &str) -> Option<String> {
+        self.vars
+            .iter()
+            .find(|(var, _)| var == name)
+            .map(|(_, value)| value.clone())
+            .or_else(|| {
+                self.env_reads
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .insert(name.to_owned());
+                env::var(name).ok()
+            })
+    }
+
+    /// Track the environment variables read, which the files are
+    /// substituted with on other threads than the invocation's
+    pub(crate) fn track_env_reads(&self) {
+        self.env_reads
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .for_each(|name| env_vars::track(name));
+    }
+}
+
+/// The length of the placeholder name at the start of `bytes`, if any
+fn placeholder_len(bytes: &[u8]) -> Option<usize> {
+    let len = bytes
+        .iter()
+        .take_while(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit() || **byte == b'_')
+        .count();
+    (len > 0 && !bytes[0].is_ascii_digit()).then_some(len)
+}
+
+/// Replace the `{{NAME}}` placeholders of the file at `path` with
+/// their values, failing on the ones which have none
+///
+/// Only uppercase names are placeholders, so that the `{{name}}`
+/// of client-side templates are left as they are.
+pub(crate) fn substitute(
+    contents: &[u8],
+    vars: &TemplateVars,
+    path: &Path,
+) -> Result<Vec<u8>, Error> {
+    let mut substituted = Vec::with_capacity(contents.len());
+    let mut rest = contents;
+    while let Some(start) = rest.windows(2).position(|window| window == b"{{") {
+        let after_braces = &rest[start + 2..];
+        let Some(len) =
+            placeholder_len(after_braces).filter(|&len| after_braces[len..].starts_with(b"}}"))
+        else {
+            substituted.extend_from_slice(&rest[..=start]);
+            rest = &rest[start + 1..];
+            continue;
+        };
+
+        let name = str::from_utf8(&after_braces[..len]).expect("the name is ASCII");
+        let value = vars
+            .value(name)
+            .ok_or_else(|| Error::UnresolvedPlaceholder {
+                name: name.to_owned(),
+                path: path.to_path_buf(),
+            })?;
+        substituted.extend_from_slice(&rest[..start]);
+        substituted.extend_from_slice(value.as_bytes());
+        rest = &after_braces[len + 2..];
+    }
+    substituted.extend_from_slice(rest);
+    Ok(substituted)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{TemplateVars, substitute};
+    use crate::env_vars;
+
+    #[test]
+    fn substitutes_uppercase_placeholders() {
+        let vars = TemplateVars {
+            vars: vec![("API_URL".to_owned(), "https://api".to_owned())],
+            ..TemplateVars::default()
+        };
+        let substituted = substitute(
+            b"fetch('{{API_URL}}'); {{ API_URL }} {{name}} {{1X}} {{{API_URL}}}",
+            &vars,
+            Path::new("config.js"),
+        )
+        .unwrap();
+        assert_eq!(
+            substituted,
+            b"fetch('https://api'); {{ API_URL }} {{name}} {{1X}} {https://api}"
+        );
+    }
+
+    #[test]
+    fn fails_on_unresolved_placeholders() {
+        let error = substitute(
+            b"{{STATIC_SERVE_SURELY_UNSET}}",
+            &TemplateVars::default(),
+            Path::new("config.js"),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "The placeholder {{STATIC_SERVE_SURELY_UNSET}} of config.js has no value in `template_vars` nor in the environment"
+        );
+    }
+
+    #[test]
+    fn tracks_the_environment_variables_read() {
+        let vars = TemplateVars {
+            vars: vec![("API_URL".to_owned(), "https://api".to_owned())],
+            ..TemplateVars::default()
+        };
+        env_vars::reset();
+        substitute(
+            b"{{API_URL}} {{CARGO_PKG_NAME}}",
+            &vars,
+            Path::new("config.js"),
+        )
+        .unwrap();
+        vars.track_env_reads();
+        let tracked = env_vars::tracked().to_string();
+        assert!(tracked.contains("\"CARGO_PKG_NAME\""));
+        assert!(!tracked.contains("API_URL"));
+    }
+}
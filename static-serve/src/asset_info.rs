@@ -0,0 +1,83 @@
+//! Introspection of the assets embedded by `embed_assets!`
+
+use crate::{LazyBody, StaticAsset};
+
+/// The description of an asset embedded by `embed_assets!`,
+/// as listed by the generated `asset_manifest` function
+///
+/// Useful to build debugging pages, sitemaps or preload headers
+/// from what was actually embedded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AssetInfo {
+    /// The route of the asset
+    pub path: &'static str,
+    /// The value of the `Content-Type` header
+    pub content_type: &'static str,
+    /// The size of the uncompressed contents, in bytes
+    pub size: usize,
+    /// The size of the gzip-compressed contents, if embedded
+    /// rather than derived on first access
+    pub gzip_size: Option<usize>,
+    /// The size of the zstd-compressed contents, if embedded
+    pub zstd_size: Option<usize>,
+    /// The size of the Brotli-compressed contents, if embedded
+    pub br_size: Option<usize>,
+    /// The value of the `ETag` header, if any
+    pub etag: Option<&'static str>,
+    /// The Subresource Integrity hash of the contents, for the `integrity`
+    /// attribute of `<script>` and `<link>` elements, with a full `etag` algorithm
+    pub integrity: Option<&'static str>,
+    /// Whether the asset is served with an immutable `Cache-Control`
+    pub cache_busted: bool,
+}
+
+impl AssetInfo {
+    fn new(path: &'static str, asset: &StaticAsset) -> Self {
+        Self {
+            path,
+            content_type: asset.content_type,
+            size: asset.lazy_body.map_or(asset.body.len(), LazyBody::size),
+            gzip_size: asset.body_gz.map(<[u8]>::len),
+            zstd_size: asset.body_zst.map(<[u8]>::len),
+            br_size: asset.body_br.map(<[u8]>::len),
+            etag: asset.etag,
+            integrity: asset.integrity,
+            cache_busted: asset.cache_busted,
+        }
+    }
+}
+
+/// The Content Security Policy hashes of the scripts and styles embedded
+/// by `embed_assets!`, as returned by the generated `csp_hashes` function
+///
+/// The sources allow the embedded files under a strict policy
+/// without `'unsafe-inline'`, e.g. once inlined in a page:
+///
+/// ```rust,ignore
+/// let hashes = csp_hashes();
+/// let policy = format!(
+///     "script-src 'self' {}; style-src 'self' {}",
+///     hashes.script_src.join(" "),
+///     hashes.style_src.join(" "),
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CspHashes {
+    /// The `'sha256-...'` sources of the JavaScript files, for `script-src`
+    pub script_src: &'static [&'static str],
+    /// The `'sha256-...'` sources of the CSS files, for `style-src`
+    pub style_src: &'static [&'static str],
+}
+
+#[doc(hidden)]
+/// Describe the table of embedded `assets`
+///
+/// Used by the `asset_manifest` function generated
+/// by `embed_assets!`, so it needs to be `pub`.
+#[must_use]
+pub fn asset_manifest(assets: &[(&'static str, StaticAsset)]) -> Vec<AssetInfo> {
+    assets
+        .iter()
+        .map(|(path, asset)| AssetInfo::new(path, asset))
+        .collect()
+}
@@ -0,0 +1,113 @@
+//! The assets embedded by `embed_asset_set!`, as a value
+
+use std::slice;
+
+use crate::{Fallback, Redirect, Route, StaticAsset, StaticAssetBackend, percent::canonical_path};
+
+/// The routes, redirects and fallback of embedded assets, as a value which
+/// can be inspected, merged with other sets and turned into a router
+///
+/// `embed_asset_set!` takes the same options as `embed_assets!`, and
+/// evaluates to the set of the embedded assets instead of generating
+/// functions, which `embed_assets!` also provides as `static_asset_set()`:
+///
+/// ```rust,ignore
+/// let site = embed_asset_set!("site", compress = true);
+/// let docs = embed_asset_set!("docs", prefix = "/docs");
+/// let assets = site.merge(docs);
+/// assert!(assets.get("/docs/index.html").is_some());
+/// let router: Router = assets.into_axum_router();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StaticAssetSet {
+    routes: Vec<Route>,
+    redirects: Vec<Redirect>,
+    fallback: Option<Fallback>,
+}
+
+impl StaticAssetSet {
+    #[doc(hidden)]
+    /// The set of the embedded `routes`, `redirects` and `fallback`
+    ///
+    /// Used by the `static_asset_set` function generated
+    /// by `embed_assets!`, so it needs to be `pub`.
+    #[must_use]
+    pub fn new(routes: &[Route], redirects: &[Redirect], fallback: Option<Fallback>) -> Self {
+        Self {
+            routes: routes.to_vec(),
+            redirects: redirects.to_vec(),
+            fallback,
+        }
+    }
+
+    /// The asset served at `web_path`, decoded or percent-encoded,
+    /// including the aliases of the assets
+    #[must_use]
+    pub fn get(&self, web_path: &str) -> Option<&'static StaticAsset> {
+        let web_path = canonical_path(web_path);
+        self.routes
+            .iter()
+            .find(|route| route.web_path == web_path)
+            .map(|route| route.asset)
+    }
+
+    /// The routes of the assets, and of their aliases
+    pub fn iter(&self) -> slice::Iter<'_, Route> {
+        self.routes.iter()
+    }
+
+    /// The routes answered with a redirect to another one
+    #[must_use]
+    pub fn redirects(&self) -> &[Redirect] {
+        &self.redirects
+    }
+
+    /// The asset served for the unmatched routes, if any
+    #[must_use]
+    pub const fn fallback(&self) -> Option<Fallback> {
+        self.fallback
+    }
+
+    /// The assets of this set and of `other`
+    ///
+    /// The routes and the redirects of `other` replace the ones of this set
+    /// at the same paths, and its fallback is used if this set has none.
+    #[must_use]
+    pub fn merge(mut self, other: Self) -> Self {
+        let is_replaced = |path: &str| {
+            other.routes.iter().any(|route| route.web_path == path)
+                || other.redirects.iter().any(|redirect| redirect.from == path)
+        };
+        self.routes.retain(|route| !is_replaced(route.web_path));
+        self.redirects
+            .retain(|redirect| !is_replaced(redirect.from));
+        self.routes.extend(other.routes);
+        self.redirects.extend(other.redirects);
+        self.fallback = self.fallback.or(other.fallback);
+        self
+    }
+
+    /// Serve the assets with the framework `B`
+    #[must_use]
+    pub fn into_router<B: StaticAssetBackend>(self) -> B::Router {
+        B::router(&self.routes, &self.redirects, self.fallback)
+    }
+
+    /// Serve the assets with an axum router, like `static_router()`
+    #[cfg(feature = "axum")]
+    pub fn into_axum_router<S>(self) -> axum::Router<S>
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        self.into_router::<crate::Axum<S>>()
+    }
+}
+
+impl<'a> IntoIterator for &'a StaticAssetSet {
+    type Item = &'a Route;
+    type IntoIter = slice::Iter<'a, Route>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,127 @@
+//! The Basic authentication of the assets of the subtrees
+//! protected with the `protected` option of `embed_assets!`
+
+use std::{
+    env,
+    fmt::{self, Debug, Formatter},
+    sync::Arc,
+};
+
+use base64::{Engine as _, prelude::BASE64_STANDARD};
+use http::HeaderValue;
+
+use crate::StaticAsset;
+
+/// The Basic authentication required to read an asset, from `protected`
+///
+/// The accepted credentials, `user:password`, are read from the
+/// environment variable `credentials_var` when the router is built, so
+/// that they aren't embedded in the binary. The requests are rejected
+/// when the variable isn't set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BasicAuth {
+    /// The protection space sent in the `WWW-Authenticate` header
+    pub realm: &'static str,
+    /// The environment variable holding the accepted credentials
+    pub credentials_var: &'static str,
+}
+
+impl BasicAuth {
+    /// The credentials held by `credentials_var`, or `None` when
+    /// the variable isn't set and every request is rejected
+    ///
+    /// The generated routers read them once, when they're built.
+    #[must_use]
+    pub fn credentials(&self) -> Option<Credentials> {
+        let credentials = env::var_os(self.credentials_var)?;
+        Some(Credentials {
+            token: BASE64_STANDARD
+                .encode(credentials.as_encoded_bytes())
+                .into(),
+        })
+    }
+
+    /// The value of the `WWW-Authenticate` header of
+    /// the `401 Unauthorized` responses
+    ///
+    /// # Panics
+    ///
+    /// If the realm isn't a valid header value, which the macro guarantees.
+    #[must_use]
+    pub fn challenge(&self) -> HeaderValue {
+        HeaderValue::from_str(&format!(
+            "Basic realm=\"{}\", charset=\"UTF-8\"",
+            self.realm
+        ))
+        .expect("the realm is made of visible ASCII characters")
+    }
+}
+
+/// The credentials accepted by a [`BasicAuth`], read from its
+/// variable by [`BasicAuth::credentials`]
+#[derive(Clone)]
+pub struct Credentials {
+    /// The base64 of `user:password`, as sent by the clients
+    token: Arc<str>,
+}
+
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials").finish_non_exhaustive()
+    }
+}
+
+impl Credentials {
+    /// The credentials of `asset`, if it's `protected` and they're set
+    pub(crate) fn of(asset: &StaticAsset) -> Option<Self> {
+        asset.basic_auth.as_ref().and_then(BasicAuth::credentials)
+    }
+
+    /// Whether the `Authorization` header of a request holds these credentials
+    #[must_use]
+    pub fn is_authorized(&self, authorization: Option<&HeaderValue>) -> bool {
+        let Some((scheme, token)) = authorization
+            .and_then(|authorization| authorization.to_str().ok())
+            .and_then(|authorization| authorization.trim().split_once(' '))
+        else {
+            return false;
+        };
+        scheme.eq_ignore_ascii_case("basic")
+            && constant_time_eq(token.trim().as_bytes(), self.token.as_bytes())
+    }
+}
+
+/// Compare `a` and `b` in a time independent of their contents
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use http::HeaderValue;
+
+    use super::Credentials;
+
+    #[test]
+    fn checks_the_authorization_header() {
+        let credentials = Credentials {
+            token: "QWxhZGRpbjpvcGVuIHNlc2FtZQ==".into(),
+        };
+        for authorization in [
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==",
+            " basic  QWxhZGRpbjpvcGVuIHNlc2FtZQ== ",
+        ] {
+            let authorization = HeaderValue::from_static(authorization);
+            assert!(credentials.is_authorized(Some(&authorization)));
+        }
+        for authorization in [
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZR==",
+            "Bearer QWxhZGRpbjpvcGVuIHNlc2FtZQ==",
+            "QWxhZGRpbjpvcGVuIHNlc2FtZQ==",
+        ] {
+            let authorization = HeaderValue::from_static(authorization);
+            assert!(!credentials.is_authorized(Some(&authorization)));
+        }
+        assert!(!credentials.is_authorized(None));
+    }
+}
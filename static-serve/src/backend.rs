@@ -0,0 +1,587 @@
+//! The web frameworks serving the assets embedded by `embed_assets!`
+
+#[cfg(feature = "axum")]
+use std::marker::PhantomData;
+
+#[cfg(feature = "axum")]
+use axum::{Extension, routing::MethodRouter};
+
+use crate::StaticAsset;
+#[cfg(feature = "axum")]
+use crate::{
+    ConfigError, StaticServeConfig, disk::disk_method_router, not_found::fallback_router,
+    routing::route_method_router,
+};
+
+/// A web framework serving the assets embedded by `embed_assets!`
+///
+/// The generated `static_router_for::<B>()` function hands the embedded
+/// routes to `B`, so that frameworks other than axum can be supported
+/// without changes to the macro:
+///
+/// ```rust,ignore
+/// struct MyFramework;
+///
+/// impl StaticAssetBackend for MyFramework {
+///     type Router = my_framework::Router;
+///
+///     fn router(
+///         assets: &[Route],
+///         redirects: &[Redirect],
+///         fallback: Option<Fallback>,
+///     ) -> Self::Router {
+///         // ...
+///     }
+/// }
+///
+/// embed_assets!("assets", compress = true);
+/// let router = static_router_for::<MyFramework>();
+/// ```
+pub trait StaticAssetBackend {
+    /// The type serving the assets, usually a router of the framework
+    type Router;
+
+    /// Serve the embedded `assets` and the `redirects`,
+    /// and `fallback` for the unmatched routes
+    fn router(assets: &[Route], redirects: &[Redirect], fallback: Option<Fallback>)
+    -> Self::Router;
+}
+
+/// An embedded asset and its route
+#[derive(Debug, Clone, Copy)]
+pub struct Route {
+    /// The route of the asset
+    pub web_path: &'static str,
+    /// The embedded asset
+    pub asset: &'static StaticAsset,
+    /// The file to read at request time instead of the embedded
+    /// contents, set in debug builds by `debug_from_disk = true`
+    pub file_path: Option<&'static str>,
+    /// Whether the requests with other methods than `GET` and `HEAD` are
+    /// left to the other routes of the path, from `fall_through_methods`,
+    /// rather than answered with the allowed methods for `OPTIONS`
+    /// and with a `405 Method Not Allowed` otherwise
+    pub fall_through_methods: bool,
+}
+
+/// A route answered with a `308 Permanent Redirect` to another one,
+/// from `trailing_slash = "redirect"` or `html_redirects = true`
+#[derive(Debug, Clone, Copy)]
+pub struct Redirect {
+    /// The redirected route
+    pub from: &'static str,
+    /// The route sent in the `Location` header
+    pub to: &'static str,
+}
+
+#[doc(hidden)]
+/// Add the `aliases` of `routes`, tuples of a route and of the index
+/// of the route serving the same asset
+///
+/// Used by the functions generated by `embed_assets!`, so it needs to be `pub`.
+pub fn add_aliases(routes: &mut Vec<Route>, aliases: &[(&'static str, usize)]) {
+    for &(web_path, index) in aliases {
+        routes.push(Route {
+            web_path,
+            ..routes[index]
+        });
+    }
+}
+
+#[doc(hidden)]
+/// Remove the routes of the assets excluded by `cfg_gate`, given the
+/// `included` flags of the assets, from `routes`, the routes of the
+/// assets followed by their `aliases`
+///
+/// Used by the functions generated by `embed_assets!`, so it needs to be `pub`.
+pub fn remove_excluded(
+    routes: &mut Vec<Route>,
+    aliases: &[(&'static str, usize)],
+    included: &[bool],
+) {
+    let mut route_included = included
+        .iter()
+        .copied()
+        .chain(aliases.iter().map(|&(_, index)| included[index]));
+    routes.retain(|_| route_included.next().unwrap_or(true));
+}
+
+/// The asset served for the unmatched routes
+#[derive(Debug, Clone, Copy)]
+pub enum Fallback {
+    /// A page served with a `404 Not Found` status, from `not_found`
+    NotFound(&'static StaticAsset),
+    /// The entry point of a single-page application, from `spa_fallback`
+    Spa(&'static StaticAsset),
+}
+
+/// The axum [`StaticAssetBackend`], building a `Router<S>`
+///
+/// `static_router()` is a shorthand for `static_router_for::<Axum<S>>()`.
+#[cfg(feature = "axum")]
+#[derive(Debug)]
+pub struct Axum<S>(PhantomData<S>);
+
+#[cfg(feature = "axum")]
+impl<S> StaticAssetBackend for Axum<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    type Router = axum::Router<S>;
+
+    fn router(
+        assets: &[Route],
+        redirects: &[Redirect],
+        fallback: Option<Fallback>,
+    ) -> Self::Router {
+        Self::wrapped_router(assets, redirects, fallback, |_, method_router| {
+            method_router
+        })
+    }
+}
+
+#[cfg(feature = "axum")]
+impl<S> Axum<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    #[doc(hidden)]
+    /// The router of the `assets`, the `redirects` and the `fallback`,
+    /// with the method router of every asset passed to `wrap` along with
+    /// its route, e.g. to add a layer to some of them
+    ///
+    /// Used by the functions generated by `embed_assets!` with `wrap`,
+    /// so it needs to be `pub`.
+    pub fn wrapped_router(
+        assets: &[Route],
+        redirects: &[Redirect],
+        fallback: Option<Fallback>,
+        wrap: impl Fn(&Route, MethodRouter<S>) -> MethodRouter<S>,
+    ) -> axum::Router<S> {
+        let router = Self::routes(assets, redirects, wrap);
+        match fallback {
+            Some(fallback) => fallback_router(router, fallback, None),
+            None => router,
+        }
+    }
+
+    #[doc(hidden)]
+    /// The router of `wrapped_router` with the settings of `config`
+    ///
+    /// Used by the `static_router_with_config` function generated
+    /// by `embed_assets!`, so it needs to be `pub`.
+    ///
+    /// # Errors
+    ///
+    /// If the `not_found` page of `config` isn't embedded.
+    pub fn configured_router(
+        assets: &[Route],
+        redirects: &[Redirect],
+        fallback: Option<Fallback>,
+        config: &StaticServeConfig,
+        wrap: impl Fn(&Route, MethodRouter<S>) -> MethodRouter<S>,
+    ) -> Result<axum::Router<S>, ConfigError> {
+        let fallback = config.fallback(assets, fallback)?;
+        let router = Self::routes(assets, redirects, |route, method_router| {
+            let method_router = match config.settings(route.web_path) {
+                Some(settings) => method_router.layer(Extension(settings)),
+                None => method_router,
+            };
+            wrap(route, method_router)
+        });
+        Ok(match fallback {
+            Some((fallback, settings)) => fallback_router(router, fallback, settings),
+            None => router,
+        })
+    }
+
+    /// The router of the `assets` and the `redirects`, without a fallback
+    fn routes(
+        assets: &[Route],
+        redirects: &[Redirect],
+        wrap: impl Fn(&Route, MethodRouter<S>) -> MethodRouter<S>,
+    ) -> axum::Router<S> {
+        let mut router = axum::Router::new();
+        for route in assets {
+            let method_router = match route.file_path {
+                Some(file_path) => {
+                    disk_method_router(route.asset, file_path, route.fall_through_methods)
+                }
+                None => route_method_router(route.asset, route.fall_through_methods),
+            };
+            router = router.route(route.web_path, wrap(route, method_router));
+        }
+        for &Redirect { from, to } in redirects {
+            router = crate::routing::redirect_route(router, from, to);
+        }
+        router
+    }
+}
+
+#[cfg(feature = "rocket")]
+#[doc(hidden)]
+/// The parts of the request to the assets mounted under `base`, from the
+/// `method`, the `path`, the `query` and the `headers` of a Rocket request,
+/// or `None` when they don't make a valid `http` request
+///
+/// Used by the routes of `__rocket_routes!`, so it needs to be `pub`.
+pub fn mounted_request_parts<'a>(
+    method: &str,
+    base: &str,
+    path: &str,
+    query: Option<&str>,
+    headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Option<http::request::Parts> {
+    let with_query = |path: &str| {
+        let mut uri = if path.starts_with('/') {
+            path.to_owned()
+        } else {
+            format!("/{path}")
+        };
+        if let Some(query) = query {
+            uri.push('?');
+            uri.push_str(query);
+        }
+        uri
+    };
+    // The signed URLs are signed for the path the client requested
+    let requested = http::Uri::try_from(with_query(path)).ok()?;
+    // The routes of the assets are relative to where they're mounted
+    let mounted = path
+        .strip_prefix(base.trim_end_matches('/'))
+        .unwrap_or(path);
+    let mut builder = http::Request::builder()
+        .method(method)
+        .uri(with_query(mounted))
+        .extension(crate::RequestedUri(requested));
+    for (name, value) in headers {
+        builder = builder.header(name, value);
+    }
+    builder.body(()).ok().map(|request| request.into_parts().0)
+}
+
+#[cfg(feature = "salvo")]
+#[doc(hidden)]
+/// The URI of the request to the assets nested in a salvo router, from
+/// the `rest` of its path matched by the router, which salvo decodes,
+/// and its `query`, or `None` when they don't make a valid URI
+///
+/// Used by the handler of `__salvo_routes!`, so it needs to be `pub`.
+#[must_use]
+pub fn nested_uri(rest: &str, query: Option<&str>) -> Option<http::Uri> {
+    let mut uri = crate::percent::encode_route(format!("/{rest}").as_bytes());
+    if let Some(query) = query {
+        uri.push('?');
+        uri.push_str(query);
+    }
+    uri.parse().ok()
+}
+
+#[cfg(feature = "axum")]
+#[doc(hidden)]
+#[macro_export]
+/// Expand to the items generated by `embed_assets!` which depend on axum
+macro_rules! __axum_items {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "axum"))]
+#[doc(hidden)]
+#[macro_export]
+/// Drop the items generated by `embed_assets!` which depend on axum
+macro_rules! __axum_items {
+    ($($item:item)*) => {};
+}
+
+#[cfg(feature = "test-overrides")]
+#[doc(hidden)]
+#[macro_export]
+/// Expand to the items generated by `embed_assets!` for the tests
+macro_rules! __overrides_items {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "test-overrides"))]
+#[doc(hidden)]
+#[macro_export]
+/// Drop the items generated by `embed_assets!` for the tests
+macro_rules! __overrides_items {
+    ($($item:item)*) => {};
+}
+
+#[cfg(feature = "rocket")]
+#[doc(hidden)]
+#[macro_export]
+/// Expand to the items generated by `embed_assets!` with `rocket_routes = true`
+macro_rules! __rocket_items {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "rocket"))]
+#[doc(hidden)]
+#[macro_export]
+/// Refuse `rocket_routes = true` without the `rocket` feature
+macro_rules! __rocket_items {
+    ($($item:item)*) => {
+        ::core::compile_error!(
+            "`rocket_routes = true` requires the `rocket` feature of static-serve"
+        );
+    };
+}
+
+#[cfg(feature = "rocket")]
+#[doc(hidden)]
+#[macro_export]
+/// Expand to the Rocket routes answering the requests with `$service`, a
+/// `StaticService`, and forwarding the unmatched ones to the other routes
+/// when it has no fallback
+///
+/// The routes are expanded in the crate using Rocket, so that this one
+/// doesn't depend on it, and the `$rocket` crate is named by the caller:
+/// the paths starting with `::rocket` in the body of an exported macro
+/// would load it in every crate using this one, bringing the `PartialEq`
+/// implementations of `ubyte` which break the type inference of some
+/// comparisons of byte slices. Their rank is 100, higher than the default ones,
+/// so that the other routes of the application win. The bodies are read
+/// from the embedded bytes, in the chunks of `stream_chunk_size` if any.
+/// `HEAD` requests are answered by Rocket with the `GET` route.
+macro_rules! __rocket_routes {
+    ($rocket:ident, $service:expr) => {{
+        #[derive(Clone)]
+        struct StaticHandler($crate::StaticService);
+
+        #[::$rocket::async_trait]
+        impl ::$rocket::route::Handler for StaticHandler {
+            async fn handle<'r>(
+                &self,
+                request: &'r ::$rocket::Request<'_>,
+                data: ::$rocket::Data<'r>,
+            ) -> ::$rocket::route::Outcome<'r> {
+                use ::$rocket::{http::Status, route::Outcome};
+
+                let query = request.uri().query();
+                let headers = request.headers().iter().collect::<::std::vec::Vec<_>>();
+                let Some(mut parts) = $crate::mounted_request_parts(
+                    request.method().as_str(),
+                    request.route().map_or("", |route| route.uri.base()),
+                    request.uri().path().as_str(),
+                    query.as_ref().map(|query| query.as_str()),
+                    headers
+                        .iter()
+                        .map(|header| (header.name().as_str(), header.value())),
+                ) else {
+                    return Outcome::error(Status::BadRequest);
+                };
+                if let Some(url_signer) = request.rocket().state::<$crate::UrlSigner>() {
+                    parts.extensions.insert(url_signer.clone());
+                }
+                let Some(response) = self.0.respond(&parts) else {
+                    return Outcome::forward(data, Status::NotFound);
+                };
+
+                let (parts, body) = response.into_parts();
+                let mut builder = ::$rocket::Response::build();
+                builder.status(Status::new(parts.status.as_u16()));
+                for (name, value) in &parts.headers {
+                    // Rocket's header values are strings: the ones of the assets
+                    // are built from Rust strings, so they're kept byte for byte,
+                    // even the non-ASCII ones which `HeaderValue::to_str` refuses
+                    let value = ::std::string::String::from_utf8_lossy(value.as_bytes());
+                    builder.raw_header_adjoin(name.as_str().to_owned(), value.into_owned());
+                }
+                // Rocket sends the length of the sized bodies, while the bodiless
+                // `304 Not Modified` responses have the length of the full body
+                if !parts.headers.contains_key("content-length")
+                    && let Some((bytes, chunk_size)) = body.into_data()
+                {
+                    builder.sized_body(bytes.len(), ::std::io::Cursor::new(bytes));
+                    if let Some(chunk_size) = chunk_size {
+                        builder.max_chunk_size(chunk_size.get());
+                    }
+                }
+                Outcome::Success(builder.finalize())
+            }
+        }
+
+        let handler = StaticHandler($service);
+        [
+            ::$rocket::http::Method::Get,
+            ::$rocket::http::Method::Put,
+            ::$rocket::http::Method::Post,
+            ::$rocket::http::Method::Delete,
+            ::$rocket::http::Method::Options,
+            ::$rocket::http::Method::Trace,
+            ::$rocket::http::Method::Connect,
+            ::$rocket::http::Method::Patch,
+        ]
+        .into_iter()
+        .map(|method| ::$rocket::Route::ranked(100, method, "/<path..>", handler.clone()))
+        .collect::<::std::vec::Vec<_>>()
+    }};
+}
+
+#[cfg(feature = "poem")]
+#[doc(hidden)]
+#[macro_export]
+/// Expand to the items generated by `embed_assets!` with `poem_routes = true`
+macro_rules! __poem_items {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "poem"))]
+#[doc(hidden)]
+#[macro_export]
+/// Refuse `poem_routes = true` without the `poem` feature
+macro_rules! __poem_items {
+    ($($item:item)*) => {
+        ::core::compile_error!("`poem_routes = true` requires the `poem` feature of static-serve");
+    };
+}
+
+#[cfg(feature = "poem")]
+#[doc(hidden)]
+#[macro_export]
+/// Expand to the `poem::Route` answering the requests with `$service`,
+/// a `StaticService`, and the unmatched ones with a `404 Not Found` error
+/// when it has no fallback
+///
+/// Like the routes of `__rocket_routes!`, the endpoint is expanded in the
+/// crate using poem, named by the caller as `$poem`, since poem depends on
+/// `serde_json`, whose `PartialEq` implementations break the same
+/// comparisons. The bodies are sent from the embedded bytes, in the chunks
+/// of `stream_chunk_size` if any, and the `UrlSigner` of the `signed`
+/// assets is taken from the extensions of the requests, e.g. from `data`.
+macro_rules! __poem_routes {
+    ($poem:ident, $service:expr) => {{
+        struct StaticEndpoint($crate::StaticService);
+
+        impl ::$poem::Endpoint for StaticEndpoint {
+            type Output = ::$poem::Response;
+
+            async fn call(&self, request: ::$poem::Request) -> ::$poem::Result<Self::Output> {
+                // The requests built without a server, e.g. in tests, have
+                // no original URI, which nesting can't have made `/` anyway
+                let original = request.original_uri();
+                let requested = $crate::RequestedUri(
+                    if *original == ::$poem::http::Uri::default() {
+                        request.uri()
+                    } else {
+                        original
+                    }
+                    .clone(),
+                );
+                let (request, _body) = request.into_parts();
+                let (mut parts, ()) = ::$poem::http::Request::new(()).into_parts();
+                parts.method = request.method;
+                parts.uri = request.uri;
+                parts.version = request.version;
+                parts.headers = request.headers;
+                parts.extensions = request.extensions;
+                parts.extensions.insert(requested);
+                let Some(response) = self.0.respond(&parts) else {
+                    return ::core::result::Result::Err(::$poem::error::NotFoundError.into());
+                };
+                ::core::result::Result::Ok(::$poem::Response::from(
+                    response.map($crate::StaticBody::into_fallible::<::std::io::Error>),
+                ))
+            }
+        }
+
+        ::$poem::Route::new().nest("/", StaticEndpoint($service))
+    }};
+}
+
+#[cfg(feature = "salvo")]
+#[doc(hidden)]
+#[macro_export]
+/// Expand to the items generated by `embed_assets!` with `salvo_router = true`
+macro_rules! __salvo_items {
+    ($($item:item)*) => {
+        $($item)*
+    };
+}
+
+#[cfg(not(feature = "salvo"))]
+#[doc(hidden)]
+#[macro_export]
+/// Refuse `salvo_router = true` without the `salvo` feature
+macro_rules! __salvo_items {
+    ($($item:item)*) => {
+        ::core::compile_error!(
+            "`salvo_router = true` requires the `salvo` feature of static-serve"
+        );
+    };
+}
+
+#[cfg(feature = "salvo")]
+#[doc(hidden)]
+#[macro_export]
+/// Expand to the salvo `Router` answering the requests with `$service`,
+/// a `StaticService`, and the unmatched ones with a `404 Not Found` status
+/// when it has no fallback
+///
+/// Like the routes of `__rocket_routes!`, the handler is expanded in the
+/// crate using salvo, named by the caller as `$salvo`, since salvo depends
+/// on `serde_json`. The router matches the whole rest of the path, to be
+/// pushed under the path of the assets. The bodies are sent from the
+/// embedded bytes, in the chunks of `stream_chunk_size` if any, and the
+/// `UrlSigner` of the `signed` assets is taken from the depot, e.g.
+/// injected by `affix_state::inject`, or else from the extensions of the
+/// requests.
+macro_rules! __salvo_routes {
+    ($salvo:ident, $service:expr) => {{
+        struct StaticHandler($crate::StaticService);
+
+        #[::$salvo::async_trait]
+        impl ::$salvo::Handler for StaticHandler {
+            async fn handle(
+                &self,
+                request: &mut ::$salvo::Request,
+                depot: &mut ::$salvo::Depot,
+                response: &mut ::$salvo::Response,
+                _ctrl: &mut ::$salvo::FlowCtrl,
+            ) {
+                let Some(uri) = $crate::nested_uri(
+                    request.params().tail().unwrap_or_default(),
+                    request.uri().query(),
+                ) else {
+                    response.status_code(::$salvo::http::StatusCode::BAD_REQUEST);
+                    return;
+                };
+                let (mut parts, ()) = ::$salvo::hyper::Request::new(()).into_parts();
+                parts.method = request.method().clone();
+                parts.uri = uri;
+                parts.version = request.version();
+                parts.headers = request.headers().clone();
+                parts.extensions = request.extensions().clone();
+                parts
+                    .extensions
+                    .insert($crate::RequestedUri(request.uri().clone()));
+                if let Ok(url_signer) = depot.obtain::<$crate::UrlSigner>() {
+                    parts.extensions.insert(url_signer.clone());
+                }
+                let Some(static_response) = self.0.respond(&parts) else {
+                    response.status_code(::$salvo::http::StatusCode::NOT_FOUND);
+                    return;
+                };
+
+                let (parts, body) = static_response.into_parts();
+                response.status_code(parts.status);
+                response.headers_mut().extend(parts.headers);
+                response.body(::$salvo::http::ResBody::Boxed(::std::boxed::Box::pin(
+                    body.into_fallible::<::$salvo::BoxedError>(),
+                )));
+            }
+        }
+
+        ::$salvo::Router::with_path("{**rest}").goal(StaticHandler($service))
+    }};
+}
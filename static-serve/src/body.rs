@@ -0,0 +1,176 @@
+//! The bodies of the responses sending the embedded assets,
+//! for the servers which don't use the axum backend
+
+#[cfg(any(feature = "poem", feature = "salvo"))]
+use std::marker::PhantomData;
+use std::{
+    convert::Infallible,
+    num::NonZeroUsize,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http_body::{Body, Frame, SizeHint};
+
+use crate::ChunkedBody;
+
+/// The body of a response of [`StaticService`](crate::StaticService)
+/// and of the other backends: a slice of an embedded asset, sent at once
+/// or in chunks, or no body at all
+///
+/// The body is never copied: the embedded bytes are sent as they are.
+#[derive(Debug, Clone)]
+pub struct StaticBody {
+    kind: Kind,
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    /// The whole body in one frame, taken when polled
+    Full(Option<Bytes>),
+    /// The body in chunks, from `stream_min_size`
+    Chunked(ChunkedBody),
+    /// No body, without an exact length so that none is filled in
+    /// for the `304 Not Modified` responses of unknown length
+    Unsized,
+}
+
+impl StaticBody {
+    /// A body sending `bytes` in one frame
+    #[must_use]
+    pub fn full(bytes: Bytes) -> Self {
+        Self {
+            kind: Kind::Full(Some(bytes).filter(|bytes| !bytes.is_empty())),
+        }
+    }
+
+    /// An empty body
+    #[must_use]
+    pub fn empty() -> Self {
+        Self {
+            kind: Kind::Full(None),
+        }
+    }
+
+    /// An empty body without an exact length, for the `304 Not Modified`
+    /// responses whose `Content-Length` isn't known
+    pub(crate) fn unsized_empty() -> Self {
+        Self {
+            kind: Kind::Unsized,
+        }
+    }
+
+    /// A body sending `body` in chunks
+    #[must_use]
+    pub fn chunked(body: ChunkedBody) -> Self {
+        Self {
+            kind: Kind::Chunked(body),
+        }
+    }
+
+    /// The bytes of the body and the size of its chunks, if sent in
+    /// chunks, or `None` for the empty bodies without an exact length
+    ///
+    /// This hands the embedded bytes, without copying them, to the
+    /// frameworks whose bodies aren't [`Body`] implementations.
+    #[must_use]
+    pub fn into_data(self) -> Option<(Bytes, Option<NonZeroUsize>)> {
+        match self.kind {
+            Kind::Full(bytes) => Some((bytes.unwrap_or_default(), None)),
+            Kind::Chunked(chunked) => {
+                let (bytes, chunk_size) = chunked.into_parts();
+                Some((bytes, Some(chunk_size)))
+            }
+            Kind::Unsized => None,
+        }
+    }
+
+    #[cfg(any(feature = "poem", feature = "salvo"))]
+    #[doc(hidden)]
+    /// This body with `E` as its error type, for the frameworks which
+    /// require theirs of the bodies they send
+    ///
+    /// Used by the endpoints of `__poem_routes!` and `__salvo_routes!`,
+    /// so it needs to be `pub`.
+    #[must_use]
+    pub fn into_fallible<E>(self) -> FallibleBody<E> {
+        FallibleBody {
+            body: self,
+            error: PhantomData,
+        }
+    }
+}
+
+#[cfg(any(feature = "poem", feature = "salvo"))]
+#[doc(hidden)]
+/// A [`StaticBody`] failing with `E`, which it never does,
+/// returned by [`StaticBody::into_fallible`]
+#[derive(Debug)]
+pub struct FallibleBody<E> {
+    body: StaticBody,
+    error: PhantomData<fn() -> E>,
+}
+
+#[cfg(any(feature = "poem", feature = "salvo"))]
+impl<E> Body for FallibleBody<E> {
+    type Data = Bytes;
+    type Error = E;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        Pin::new(&mut self.body)
+            .poll_frame(cx)
+            .map_err(|never| match never {})
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.body.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.body.size_hint()
+    }
+}
+
+impl Default for StaticBody {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl Body for StaticBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match &mut self.kind {
+            Kind::Full(bytes) => Poll::Ready(bytes.take().map(|bytes| Ok(Frame::data(bytes)))),
+            Kind::Chunked(chunked) => Pin::new(chunked).poll_frame(cx),
+            Kind::Unsized => Poll::Ready(None),
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        match &self.kind {
+            Kind::Full(bytes) => bytes.is_none(),
+            Kind::Chunked(chunked) => chunked.is_end_stream(),
+            Kind::Unsized => true,
+        }
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        match &self.kind {
+            Kind::Full(bytes) => {
+                SizeHint::with_exact(bytes.as_ref().map_or(0, |bytes| bytes.len() as u64))
+            }
+            Kind::Chunked(chunked) => chunked.size_hint(),
+            Kind::Unsized => SizeHint::new(),
+        }
+    }
+}
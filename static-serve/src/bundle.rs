@@ -0,0 +1,89 @@
+//! The packed bundle file the bytes of the assets are written to by
+//! `embed_assets!` with `bundle`, memory-mapped on first access
+
+use std::{env, fs::File, path::PathBuf, sync::OnceLock};
+
+use memmap2::Mmap;
+
+/// The size of the header of the bundle: the SHA-256 hash of its contents
+const HEADER_SIZE: usize = 32;
+
+/// A bundle file holding the bytes of the assets, mapped into
+/// memory when the table of the assets is first built
+///
+/// The bundle is looked up next to the executable, where it's deployed
+/// with it, and else at the path it was written to at build time, so that
+/// `cargo run` and `cargo test` find it.
+///
+/// Its header holds the hash of its contents, checked against
+/// the one of the build, so that a stale bundle is never served.
+#[derive(Debug)]
+pub struct Bundle {
+    file_name: &'static str,
+    build_path: &'static str,
+    hash: [u8; HEADER_SIZE],
+    map: OnceLock<Mmap>,
+}
+
+impl Bundle {
+    #[doc(hidden)]
+    /// The bundle named `file_name`, written to `build_path`
+    /// by the macro, whose contents hash to `hash`
+    ///
+    /// Used by the code generated by `embed_assets!`, so it needs to be `pub`.
+    #[must_use]
+    pub const fn new(
+        file_name: &'static str,
+        build_path: &'static str,
+        hash: [u8; HEADER_SIZE],
+    ) -> Self {
+        Self {
+            file_name,
+            build_path,
+            hash,
+            map: OnceLock::new(),
+        }
+    }
+
+    /// The path the bundle is read from: next to the
+    /// executable if it's there, else its build path
+    #[must_use]
+    pub fn path(&self) -> PathBuf {
+        env::current_exe()
+            .ok()
+            .and_then(|exe| Some(exe.parent()?.join(self.file_name)))
+            .filter(|path| path.is_file())
+            .unwrap_or_else(|| PathBuf::from(self.build_path))
+    }
+
+    #[doc(hidden)]
+    /// The `len` bytes at `offset` in the bundle, mapping it on the first call
+    ///
+    /// Used by the code generated by `embed_assets!`, so it needs to be `pub`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the bundle can't be mapped, or if it isn't
+    /// the one written by the build of the executable.
+    #[must_use]
+    pub fn slice(&'static self, offset: usize, len: usize) -> &'static [u8] {
+        &self.map.get_or_init(|| self.open())[offset..offset + len]
+    }
+
+    fn open(&self) -> Mmap {
+        let path = self.path();
+        let file = File::open(&path)
+            .unwrap_or_else(|err| panic!("cannot open the bundle {}: {err}", path.display()));
+        #[expect(unsafe_code)]
+        // SAFETY: the bundle is written once by the build and only read
+        // afterwards, modifying it while the executable runs isn't supported
+        let map = unsafe { Mmap::map(&file) }
+            .unwrap_or_else(|err| panic!("cannot map the bundle {}: {err}", path.display()));
+        assert!(
+            map.get(..HEADER_SIZE) == Some(self.hash.as_slice()),
+            "the bundle {} wasn't written by the build of this executable",
+            path.display()
+        );
+        map
+    }
+}
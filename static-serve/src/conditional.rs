@@ -0,0 +1,302 @@
+//! The evaluation of the preconditions of the requests
+//! ([RFC 9110 Section 13](https://www.rfc-editor.org/rfc/rfc9110#section-13))
+
+use http::{
+    HeaderMap,
+    header::{HeaderValue, IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_UNMODIFIED_SINCE},
+};
+
+use crate::http_date;
+
+/// The conditional headers of a `GET` or `HEAD` request
+#[derive(Debug)]
+pub(crate) struct Preconditions {
+    /// `If-Match`
+    matching: Option<HeaderValue>,
+    /// `If-Unmodified-Since`
+    unmodified_since: Option<HeaderValue>,
+    /// `If-None-Match`
+    none_matching: Option<HeaderValue>,
+    /// `If-Modified-Since`
+    modified_since: Option<HeaderValue>,
+}
+
+/// How a request is answered once its preconditions are evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    /// Answered like an unconditional request
+    Proceed,
+    /// Answered with `304 Not Modified`, the client's copy being current
+    NotModified,
+    /// Answered with `412 Precondition Failed`, the client's
+    /// copy being outdated, e.g. when resuming a download
+    PreconditionFailed,
+}
+
+impl Preconditions {
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Self {
+        Self {
+            matching: headers.get(IF_MATCH).cloned(),
+            unmodified_since: headers.get(IF_UNMODIFIED_SINCE).cloned(),
+            none_matching: headers.get(IF_NONE_MATCH).cloned(),
+            modified_since: headers.get(IF_MODIFIED_SINCE).cloned(),
+        }
+    }
+
+    /// Evaluate the preconditions against the validators of the selected
+    /// representation, in the order of RFC 9110 (section 13.2.2):
+    ///
+    /// 1. `If-Match`, or `If-Unmodified-Since` when it's absent
+    /// 2. `If-None-Match`, or `If-Modified-Since` when it's absent
+    ///
+    /// A representation without an `ETag` is revalidated with
+    /// `If-Modified-Since` only, so that the entity tags sent by
+    /// proxies mangling the `ETag` header can't produce false positives.
+    /// Unparseable dates are ignored, as required by RFC 9110.
+    pub(crate) fn evaluate(&self, etag: Option<&str>, last_modified: Option<&str>) -> Outcome {
+        let last_modified = last_modified.and_then(http_date::parse);
+
+        let is_unchanged = match &self.matching {
+            Some(if_match) => header_str(if_match).is_some_and(|if_match| {
+                if_match.trim() == "*" || etag.is_some_and(|etag| strong_match(if_match, etag))
+            }),
+            None => parse_date(self.unmodified_since.as_ref())
+                .zip(last_modified)
+                .is_none_or(|(if_unmodified_since, last_modified)| {
+                    last_modified <= if_unmodified_since
+                }),
+        };
+        if !is_unchanged {
+            return Outcome::PreconditionFailed;
+        }
+
+        let is_not_modified = match (&self.none_matching, etag) {
+            (Some(if_none_match), Some(etag)) => {
+                header_str(if_none_match).is_some_and(|if_none_match| {
+                    if_none_match.trim() == "*" || weak_match(if_none_match, etag)
+                })
+            }
+            _ => parse_date(self.modified_since.as_ref())
+                .zip(last_modified)
+                .is_some_and(|(if_modified_since, last_modified)| {
+                    last_modified <= if_modified_since
+                }),
+        };
+        if is_not_modified {
+            Outcome::NotModified
+        } else {
+            Outcome::Proceed
+        }
+    }
+}
+
+fn header_str(value: &HeaderValue) -> Option<&str> {
+    value.to_str().ok()
+}
+
+fn parse_date(value: Option<&HeaderValue>) -> Option<u64> {
+    value.and_then(header_str).and_then(http_date::parse)
+}
+
+/// Whether the comma-separated list of entity tags holds `etag`, with the
+/// strong comparison of RFC 9110 (section 8.8.3.2): weak tags never match
+fn strong_match(entity_tags: &str, etag: &str) -> bool {
+    !is_weak(etag)
+        && entity_tags
+            .split(',')
+            .map(str::trim)
+            .any(|entity_tag| !is_weak(entity_tag) && entity_tag == etag)
+}
+
+/// Whether the comma-separated list of entity tags holds `etag`, with the
+/// weak comparison of RFC 9110 (section 8.8.3.2) since `W/` markers may be
+/// added by intermediaries
+fn weak_match(entity_tags: &str, etag: &str) -> bool {
+    let etag = opaque_tag(etag);
+    entity_tags
+        .split(',')
+        .any(|entity_tag| opaque_tag(entity_tag.trim()) == etag)
+}
+
+fn is_weak(entity_tag: &str) -> bool {
+    entity_tag.starts_with("W/")
+}
+
+/// The entity tag without its weakness indicator
+fn opaque_tag(entity_tag: &str) -> &str {
+    entity_tag.strip_prefix("W/").unwrap_or(entity_tag)
+}
+
+#[cfg(test)]
+mod test {
+    use http::{
+        HeaderMap, HeaderName, HeaderValue,
+        header::{IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_UNMODIFIED_SINCE},
+    };
+
+    use super::{Outcome, Preconditions, strong_match, weak_match};
+
+    const ETAG: &str = "\"xyzzy\"";
+    const LAST_MODIFIED: &str = "Sat, 29 Oct 1994 19:43:31 GMT";
+    const EARLIER: &str = "Fri, 28 Oct 1994 19:43:31 GMT";
+    const LATER: &str = "Sun, 30 Oct 1994 19:43:31 GMT";
+
+    fn evaluate(headers: &[(HeaderName, &'static str)], etag: Option<&str>) -> Outcome {
+        let headers: HeaderMap = headers
+            .iter()
+            .map(|(name, value)| (name.clone(), HeaderValue::from_static(value)))
+            .collect();
+        Preconditions::from_headers(&headers).evaluate(etag, Some(LAST_MODIFIED))
+    }
+
+    #[test]
+    fn compares_entity_tags() {
+        // The examples of RFC 9110, section 8.8.3.2
+        for (first, second, strong, weak) in [
+            ("W/\"1\"", "W/\"1\"", false, true),
+            ("W/\"1\"", "W/\"2\"", false, false),
+            ("W/\"1\"", "\"1\"", false, true),
+            ("\"1\"", "\"1\"", true, true),
+        ] {
+            assert_eq!(strong_match(first, second), strong, "{first} {second}");
+            assert_eq!(strong_match(second, first), strong, "{second} {first}");
+            assert_eq!(weak_match(first, second), weak, "{first} {second}");
+            assert_eq!(weak_match(second, first), weak, "{second} {first}");
+        }
+    }
+
+    #[test]
+    fn evaluates_if_match() {
+        // The examples of RFC 9110, section 13.1.1
+        for if_match in ["\"xyzzy\"", "\"xyzzy\", \"r2d2xxxx\", \"c3piozzzz\"", "*"] {
+            assert_eq!(
+                evaluate(&[(IF_MATCH, if_match)], Some(ETAG)),
+                Outcome::Proceed,
+                "If-Match: {if_match}"
+            );
+        }
+        for if_match in ["\"r2d2xxxx\"", "W/\"xyzzy\""] {
+            assert_eq!(
+                evaluate(&[(IF_MATCH, if_match)], Some(ETAG)),
+                Outcome::PreconditionFailed,
+                "If-Match: {if_match}"
+            );
+        }
+        // Without an `ETag`, only `*` matches
+        assert_eq!(evaluate(&[(IF_MATCH, "*")], None), Outcome::Proceed);
+        assert_eq!(
+            evaluate(&[(IF_MATCH, ETAG)], None),
+            Outcome::PreconditionFailed
+        );
+    }
+
+    #[test]
+    fn evaluates_if_unmodified_since() {
+        // The example of RFC 9110, section 13.1.4
+        for (if_unmodified_since, outcome) in [
+            (LAST_MODIFIED, Outcome::Proceed),
+            (LATER, Outcome::Proceed),
+            (EARLIER, Outcome::PreconditionFailed),
+            ("not a date", Outcome::Proceed),
+        ] {
+            assert_eq!(
+                evaluate(&[(IF_UNMODIFIED_SINCE, if_unmodified_since)], Some(ETAG)),
+                outcome,
+                "If-Unmodified-Since: {if_unmodified_since}"
+            );
+        }
+        // `If-Match` takes precedence
+        assert_eq!(
+            evaluate(
+                &[(IF_MATCH, ETAG), (IF_UNMODIFIED_SINCE, EARLIER)],
+                Some(ETAG)
+            ),
+            Outcome::Proceed
+        );
+    }
+
+    #[test]
+    fn evaluates_if_none_match() {
+        // The examples of RFC 9110, section 13.1.2
+        for if_none_match in [
+            "\"xyzzy\"",
+            "W/\"xyzzy\"",
+            "\"xyzzy\", \"r2d2xxxx\", \"c3piozzzz\"",
+            "W/\"xyzzy\", W/\"r2d2xxxx\", W/\"c3piozzzz\"",
+            "*",
+        ] {
+            assert_eq!(
+                evaluate(&[(IF_NONE_MATCH, if_none_match)], Some(ETAG)),
+                Outcome::NotModified,
+                "If-None-Match: {if_none_match}"
+            );
+        }
+        assert_eq!(
+            evaluate(&[(IF_NONE_MATCH, "\"r2d2xxxx\"")], Some(ETAG)),
+            Outcome::Proceed
+        );
+    }
+
+    #[test]
+    fn evaluates_if_modified_since() {
+        // The example of RFC 9110, section 13.1.3
+        for (if_modified_since, outcome) in [
+            (LAST_MODIFIED, Outcome::NotModified),
+            (LATER, Outcome::NotModified),
+            (EARLIER, Outcome::Proceed),
+            ("not a date", Outcome::Proceed),
+        ] {
+            assert_eq!(
+                evaluate(&[(IF_MODIFIED_SINCE, if_modified_since)], Some(ETAG)),
+                outcome,
+                "If-Modified-Since: {if_modified_since}"
+            );
+        }
+    }
+
+    #[test]
+    fn evaluates_combined_preconditions() {
+        // `If-None-Match` takes precedence over `If-Modified-Since`
+        assert_eq!(
+            evaluate(
+                &[(IF_NONE_MATCH, "\"r2d2xxxx\""), (IF_MODIFIED_SINCE, LATER)],
+                Some(ETAG)
+            ),
+            Outcome::Proceed
+        );
+        assert_eq!(
+            evaluate(
+                &[(IF_NONE_MATCH, ETAG), (IF_MODIFIED_SINCE, EARLIER)],
+                Some(ETAG)
+            ),
+            Outcome::NotModified
+        );
+        // ...unless there's no `ETag` to compare it with
+        assert_eq!(
+            evaluate(
+                &[(IF_NONE_MATCH, "\"r2d2xxxx\""), (IF_MODIFIED_SINCE, LATER)],
+                None
+            ),
+            Outcome::NotModified
+        );
+        // A failed `If-Match` is answered before a matching `If-None-Match`
+        assert_eq!(
+            evaluate(
+                &[(IF_MATCH, "\"r2d2xxxx\""), (IF_NONE_MATCH, ETAG)],
+                Some(ETAG)
+            ),
+            Outcome::PreconditionFailed
+        );
+        assert_eq!(
+            evaluate(
+                &[(IF_UNMODIFIED_SINCE, EARLIER), (IF_MODIFIED_SINCE, LATER)],
+                Some(ETAG)
+            ),
+            Outcome::PreconditionFailed
+        );
+        assert_eq!(
+            evaluate(&[(IF_MATCH, ETAG), (IF_NONE_MATCH, ETAG)], Some(ETAG)),
+            Outcome::NotModified
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//! Tuning of the generated routers at runtime, without re-embedding the assets
+
+#[cfg(feature = "axum")]
+use std::collections::HashMap;
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    sync::Arc,
+};
+
+use http::{
+    HeaderName, HeaderValue,
+    header::{InvalidHeaderName, InvalidHeaderValue},
+};
+
+use crate::headers::ContentEncoding;
+#[cfg(feature = "axum")]
+use crate::{Fallback, Route};
+
+/// Serving settings resolved at runtime by the generated
+/// `static_router_with_config` function, so that operators can
+/// tune them from the configuration of an application:
+///
+/// ```rust,ignore
+/// let config = StaticServeConfig::new()
+///     .cache_control("/images/", "public, max-age=86400")?
+///     .header("/", "x-frame-options", "DENY")?
+///     .encodings(&[ContentEncoding::Gzip])
+///     .not_found("/404.html");
+/// let router: Router<()> = static_router_with_config(&config)?;
+/// ```
+///
+/// The settings apply to the assets whose route starts with a given
+/// prefix, the longest one winning. They're resolved once, when building
+/// the router, into the settings of every route they change, which its
+/// handler applies to the embedded asset when responding.
+#[derive(Debug, Default, Clone)]
+pub struct StaticServeConfig {
+    cache_controls: Vec<(String, HeaderValue)>,
+    headers: Vec<(String, HeaderName, HeaderValue)>,
+    encodings: Option<Arc<[ContentEncoding]>>,
+    not_found: Option<String>,
+}
+
+/// An invalid setting of a [`StaticServeConfig`]
+#[derive(Debug)]
+pub enum ConfigError {
+    /// A header name isn't valid
+    HeaderName {
+        /// The invalid name
+        name: String,
+        /// The error reported by `http`
+        source: InvalidHeaderName,
+    },
+    /// A header value, or a `Cache-Control`, isn't valid
+    HeaderValue {
+        /// The invalid value
+        value: String,
+        /// The error reported by `http`
+        source: InvalidHeaderValue,
+    },
+    /// No asset is embedded at the route of the `not_found` page
+    NotFound {
+        /// The route of the page
+        web_path: String,
+    },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::HeaderName { name, .. } => write!(f, "Invalid header name {name:?}"),
+            Self::HeaderValue { value, .. } => write!(f, "Invalid header value {value:?}"),
+            Self::NotFound { web_path } => write!(
+                f,
+                "Cannot serve {web_path} as the 404 page: no asset is embedded at this route"
+            ),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::HeaderName { source, .. } => Some(source),
+            Self::HeaderValue { source, .. } => Some(source),
+            Self::NotFound { .. } => None,
+        }
+    }
+}
+
+/// The settings of a route resolved from a [`StaticServeConfig`],
+/// found by its handler in the extensions of the requests
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RouteSettings {
+    /// The `Cache-Control` replacing the embedded one
+    pub(crate) cache_control: Option<HeaderValue>,
+    /// The headers replacing the embedded ones of the same names
+    pub(crate) headers: Arc<[(HeaderName, HeaderValue)]>,
+    /// The encodings replacing the `encoding_preference` of the asset
+    pub(crate) encodings: Option<Arc<[ContentEncoding]>>,
+}
+
+impl RouteSettings {
+    /// The configured encodings of the optional `settings`
+    pub(crate) fn encodings(settings: Option<&Self>) -> Option<&[ContentEncoding]> {
+        settings.and_then(|settings| settings.encodings.as_deref())
+    }
+}
+
+/// `value` as a header value
+fn header_value(value: &str) -> Result<HeaderValue, ConfigError> {
+    HeaderValue::from_str(value).map_err(|source| ConfigError::HeaderValue {
+        value: value.to_owned(),
+        source,
+    })
+}
+
+impl StaticServeConfig {
+    /// Serve the assets as embedded
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send `value` as the `Cache-Control` header of the assets whose
+    /// route starts with `route_prefix`, replacing the embedded one
+    ///
+    /// # Errors
+    ///
+    /// If `value` isn't a valid header value.
+    pub fn cache_control(
+        mut self,
+        route_prefix: impl Into<String>,
+        value: &str,
+    ) -> Result<Self, ConfigError> {
+        self.cache_controls
+            .push((route_prefix.into(), header_value(value)?));
+        Ok(self)
+    }
+
+    /// Send the header `name` with `value` with the assets whose route
+    /// starts with `route_prefix`, replacing the embedded header of the
+    /// same name, if any
+    ///
+    /// # Errors
+    ///
+    /// If `name` isn't a valid header name or `value` a valid header value.
+    pub fn header(
+        mut self,
+        route_prefix: impl Into<String>,
+        name: &str,
+        value: &str,
+    ) -> Result<Self, ConfigError> {
+        let header_name = HeaderName::try_from(name).map_err(|source| ConfigError::HeaderName {
+            name: name.to_owned(),
+            source,
+        })?;
+        self.headers
+            .push((route_prefix.into(), header_name, header_value(value)?));
+        Ok(self)
+    }
+
+    /// Only send the compressed variants in `encodings`, preferring the
+    /// first ones among the encodings a client accepts with the same
+    /// quality, the clients accepting none of them being sent the
+    /// uncompressed bodies (defaults to the `encoding_preference`
+    /// of `embed_assets!`, which an empty list also keeps)
+    #[must_use]
+    pub fn encodings(mut self, encodings: &[ContentEncoding]) -> Self {
+        self.encodings = Some(encodings)
+            .filter(|encodings| !encodings.is_empty())
+            .map(Arc::from);
+        self
+    }
+
+    /// Answer the unmatched routes with `404 Not Found` and the asset
+    /// served at `web_path`, instead of the `not_found` or `spa_fallback`
+    /// page of `embed_assets!`
+    #[must_use]
+    pub fn not_found(mut self, web_path: impl Into<String>) -> Self {
+        self.not_found = Some(web_path.into());
+        self
+    }
+
+    /// The `Cache-Control` of the asset at `web_path`, if configured
+    #[cfg(feature = "axum")]
+    fn cache_control_of(&self, web_path: &str) -> Option<&HeaderValue> {
+        self.cache_controls
+            .iter()
+            .filter(|(prefix, _)| web_path.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, value)| value)
+    }
+
+    /// The configured headers of the asset at `web_path`, the
+    /// longest prefix winning for the headers of the same name
+    #[cfg(feature = "axum")]
+    fn headers_of(&self, web_path: &str) -> Vec<(HeaderName, HeaderValue)> {
+        let mut headers: HashMap<&HeaderName, (usize, &HeaderValue)> = HashMap::new();
+        for (prefix, name, value) in &self.headers {
+            if !web_path.starts_with(prefix.as_str()) {
+                continue;
+            }
+            let longest = headers
+                .get(name)
+                .is_none_or(|&(longest, _)| prefix.len() >= longest);
+            if longest {
+                headers.insert(name, (prefix.len(), value));
+            }
+        }
+        let mut headers: Vec<_> = headers
+            .into_iter()
+            .map(|(name, (_, value))| (name.clone(), value.clone()))
+            .collect();
+        headers.sort_unstable_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+        headers
+    }
+
+    /// The settings of the route `web_path`, or `None` if none of them changes it
+    #[cfg(feature = "axum")]
+    pub(crate) fn settings(&self, web_path: &str) -> Option<RouteSettings> {
+        let cache_control = self.cache_control_of(web_path).cloned();
+        let headers = self.headers_of(web_path);
+        if cache_control.is_none() && headers.is_empty() && self.encodings.is_none() {
+            return None;
+        }
+        Some(RouteSettings {
+            cache_control,
+            headers: headers.into(),
+            encodings: self.encodings.clone(),
+        })
+    }
+
+    /// The `fallback` of the embedded `routes`, or the `not_found` page
+    /// of this configuration, with the settings of its own route
+    ///
+    /// # Errors
+    ///
+    /// If the `not_found` page isn't embedded, so that a typo in the
+    /// configuration doesn't go unnoticed.
+    #[cfg(feature = "axum")]
+    pub(crate) fn fallback(
+        &self,
+        routes: &[Route],
+        fallback: Option<Fallback>,
+    ) -> Result<Option<(Fallback, Option<RouteSettings>)>, ConfigError> {
+        let fallback = match &self.not_found {
+            Some(web_path) => {
+                let route = routes
+                    .iter()
+                    .find(|route| route.web_path == *web_path)
+                    .ok_or_else(|| ConfigError::NotFound {
+                        web_path: web_path.clone(),
+                    })?;
+                Fallback::NotFound(route.asset)
+            }
+            None => match fallback {
+                Some(fallback) => fallback,
+                None => return Ok(None),
+            },
+        };
+        let (Fallback::NotFound(asset) | Fallback::Spa(asset)) = fallback;
+        // The fallback page is configured like at its own route
+        let settings = routes
+            .iter()
+            .find(|route| std::ptr::eq(route.asset, asset))
+            .and_then(|route| self.settings(route.web_path));
+        Ok(Some((fallback, settings)))
+    }
+}
@@ -0,0 +1,169 @@
+//! Decompression of the bodies embedded compressed only, at startup with
+//! `zstd_dictionary = true` or `identity = false`, or on first access
+//! with `lazy_decompression = true`
+//!
+//! The zstd bodies require the `zstd` feature, whose C library doesn't
+//! build for every target, e.g. `wasm32-unknown-unknown`, or the pure-Rust
+//! decoder of the `ruzstd` feature.
+
+use std::{
+    io::{Read, Write},
+    sync::OnceLock,
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+
+#[cfg(any(feature = "zstd", feature = "ruzstd"))]
+use crate::zstd_decoder;
+
+/// The body of an asset embedded only as its zstd variant
+/// by `lazy_decompression = true`, from which the identity
+/// and gzip bodies are derived on first access
+#[derive(Debug)]
+pub struct LazyBody {
+    compressed: &'static [u8],
+    size: usize,
+    gzip: bool,
+    body: OnceLock<Vec<u8>>,
+    body_gz: OnceLock<Vec<u8>>,
+}
+
+impl LazyBody {
+    #[doc(hidden)]
+    /// The body of `size` bytes compressed with zstd as `compressed`,
+    /// also sent as gzip when `gzip` is true
+    ///
+    /// Used by the code generated by `embed_assets!`, so it needs to be `pub`.
+    #[cfg(any(feature = "zstd", feature = "ruzstd"))]
+    #[must_use]
+    pub const fn new(compressed: &'static [u8], size: usize, gzip: bool) -> Self {
+        Self {
+            compressed,
+            size,
+            gzip,
+            body: OnceLock::new(),
+            body_gz: OnceLock::new(),
+        }
+    }
+
+    /// The size of the uncompressed body, in bytes
+    #[must_use]
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Whether a gzip variant is derived from the body
+    #[must_use]
+    pub const fn has_gzip(&self) -> bool {
+        self.gzip
+    }
+
+    /// The length of the gzip variant of the body,
+    /// if it was already compressed
+    #[must_use]
+    pub fn gzip_len(&self) -> Option<usize> {
+        self.body_gz.get().map(Vec::len)
+    }
+
+    /// The uncompressed body, decompressed on the first call
+    ///
+    /// # Panics
+    ///
+    /// Panics if the zstd variant isn't valid, which the macro guarantees.
+    #[must_use]
+    pub fn body(&'static self) -> &'static [u8] {
+        self.body
+            .get_or_init(|| zstd_decompress(self.compressed, self.size))
+    }
+
+    /// The gzip variant of the body, if any, compressed on the first call
+    ///
+    /// # Panics
+    ///
+    /// Panics if the zstd variant isn't valid, which the macro guarantees.
+    #[must_use]
+    pub fn body_gz(&'static self) -> Option<&'static [u8]> {
+        self.gzip.then(|| {
+            self.body_gz
+                .get_or_init(|| {
+                    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                    encoder
+                        .write_all(self.body())
+                        .and_then(|()| encoder.finish())
+                        .expect("writing to a Vec cannot fail")
+                })
+                .as_slice()
+        })
+    }
+}
+
+/// Decompress the zstd variant of a body of `size` bytes
+#[cfg(any(feature = "zstd", feature = "ruzstd"))]
+fn zstd_decompress(compressed: &[u8], size: usize) -> Vec<u8> {
+    zstd_decoder::decode(compressed, None, size).expect("the zstd variant is valid")
+}
+
+/// Without the `zstd` and `ruzstd` features, `LazyBody::new`
+/// doesn't exist, so there is no zstd variant to decompress
+#[cfg(not(any(feature = "zstd", feature = "ruzstd")))]
+fn zstd_decompress(_compressed: &[u8], _size: usize) -> Vec<u8> {
+    unreachable!("the bodies embedded as zstd only require the `zstd` or `ruzstd` feature")
+}
+
+#[doc(hidden)]
+/// Decompress `compressed`, a body of `size` bytes compressed with the
+/// shared zstd `dictionary`, into memory which lives until the process exits
+///
+/// Used once per asset by the functions generated by `embed_assets!`
+/// with `zstd_dictionary = true`, so it needs to be `pub`.
+///
+/// # Panics
+///
+/// Panics if the body wasn't compressed with `dictionary`,
+/// which the macro guarantees.
+#[cfg(any(feature = "zstd", feature = "ruzstd"))]
+#[must_use]
+pub fn decompress_with_dictionary(
+    compressed: &[u8],
+    dictionary: &[u8],
+    size: usize,
+) -> &'static [u8] {
+    let body = zstd_decoder::decode(compressed, Some(dictionary), size)
+        .expect("the body was compressed with the dictionary");
+    body.leak()
+}
+
+#[doc(hidden)]
+/// Decompress the zstd variant of a body of `size` bytes
+/// into memory which lives until the process exits
+///
+/// Used once per asset by the code generated with `identity = false`,
+/// so it needs to be `pub`.
+///
+/// # Panics
+///
+/// Panics if the variant isn't valid zstd, which the macro guarantees.
+#[cfg(any(feature = "zstd", feature = "ruzstd"))]
+#[must_use]
+pub fn decompress_zstd(compressed: &[u8], size: usize) -> &'static [u8] {
+    zstd_decompress(compressed, size).leak()
+}
+
+#[doc(hidden)]
+/// Decompress the gzip variant of a body of `size` bytes
+/// into memory which lives until the process exits
+///
+/// Used once per asset by the code generated with `identity = false`,
+/// so it needs to be `pub`.
+///
+/// # Panics
+///
+/// Panics if the variant isn't valid gzip, which the macro guarantees.
+#[must_use]
+pub fn decompress_gzip(compressed: &[u8], size: usize) -> &'static [u8] {
+    let mut body = Vec::with_capacity(size);
+    GzDecoder::new(compressed)
+        .read_to_end(&mut body)
+        .expect("the gzip variant is valid");
+    body.leak()
+}
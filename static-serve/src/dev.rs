@@ -0,0 +1,286 @@
+//! Serving a directory from the filesystem during development,
+//! with the response semantics of the embedded assets
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write as _,
+    num::NonZeroUsize,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    time::UNIX_EPOCH,
+};
+
+use axum::{
+    Router,
+    body::Body,
+    extract::Request,
+    http::{HeaderValue, Method, StatusCode},
+    response::{IntoResponse, Response},
+};
+use bytes::Bytes;
+use flate2::{Compression, write::GzEncoder};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use crate::{
+    AcceptEncoding, DEFAULT_ENCODING_PREFERENCE, NotAcceptable,
+    etag::content_etag,
+    headers::{ContentEncoding, ResponseHeadersBuilder},
+    http_date,
+    percent::decode_path,
+    record::RequestRecord,
+    respond::{Representation, RepresentationRequest, send_representation},
+};
+
+/// A router serving the files of the directory at `path`, read from disk
+///
+/// The files are served with the same headers, content negotiation,
+/// conditional and range request handling as the router generated by
+/// `embed_assets!`, compressing them with gzip and zstd on the fly, so
+/// that apps can switch between both with one `cfg`:
+///
+/// ```rust,ignore
+/// #[cfg(debug_assertions)]
+/// let assets = static_serve::dev::watch_router("assets");
+/// #[cfg(not(debug_assertions))]
+/// let assets = static_router();
+/// ```
+///
+/// The files are read and compressed once, and read again after the
+/// filesystem notifies a change in the directory, so that edits are picked
+/// up on reload. When the directory can't be watched, they're read on
+/// every request. Directories serve their `index.html`. The textual files
+/// are sent with a UTF-8 charset, and the responses have a
+/// `Cache-Control: no-cache` header, so that browsers revalidate them with
+/// their `ETag` or their `Last-Modified` date rather than using stale copies.
+pub fn watch_router<S>(path: impl Into<PathBuf>) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let root = path.into();
+    let files = Arc::new(Mutex::default());
+    let watcher = watch(&root, Arc::clone(&files));
+    let dir = Arc::new(WatchedDir {
+        root,
+        files,
+        watcher,
+    });
+    Router::new().fallback(move |request: Request| {
+        let dir = Arc::clone(&dir);
+        async move { dir.respond(request) }
+    })
+}
+
+/// The files of a directory served by `watch_router`
+struct WatchedDir {
+    root: PathBuf,
+    /// The files read since the last change in the directory
+    files: Arc<Mutex<ReadFiles>>,
+    /// The watcher of the changes, kept alive with the router,
+    /// or `None` when the files are read on every request
+    watcher: Option<RecommendedWatcher>,
+}
+
+/// The files read since the last change in a watched directory
+#[derive(Default)]
+struct ReadFiles {
+    /// The number of changes notified, so that a file read
+    /// before a change isn't kept after it
+    generation: u64,
+    by_path: HashMap<PathBuf, Arc<DevFile>>,
+}
+
+/// Watch the directory at `root`, forgetting the `files` read from it on
+/// every change, or `None` when the filesystem can't notify the changes
+fn watch(root: &Path, files: Arc<Mutex<ReadFiles>>) -> Option<RecommendedWatcher> {
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        // Reading the files doesn't change them
+        if event.is_ok_and(|event| matches!(event.kind, EventKind::Access(_))) {
+            return;
+        }
+        let mut files = files.lock().unwrap_or_else(PoisonError::into_inner);
+        files.generation += 1;
+        files.by_path.clear();
+    })
+    .ok()?;
+    watcher.watch(root, RecursiveMode::Recursive).ok()?;
+    Some(watcher)
+}
+
+impl WatchedDir {
+    fn respond(&self, request: Request) -> Response {
+        let (parts, _body) = request.into_parts();
+        if parts.method != Method::GET && parts.method != Method::HEAD {
+            return StatusCode::METHOD_NOT_ALLOWED.into_response();
+        }
+        let Some(file) = self
+            .file_path(parts.uri.path())
+            .and_then(|file_path| self.file(&file_path))
+        else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+
+        let mut headers_builder = ResponseHeadersBuilder::new(file.content_type.clone())
+            .etag(HeaderValue::from_str(&file.etag).expect("the ETag is made of hex digits"))
+            .cache_control(HeaderValue::from_static("no-cache"))
+            .vary_accept_encoding(file.body_gz.is_some() || file.body_zst.is_some());
+        if let Some(last_modified) = &file.last_modified {
+            headers_builder = headers_builder.last_modified(
+                HeaderValue::from_str(last_modified).expect("HTTP-dates are visible ASCII"),
+            );
+        }
+        send_representation(
+            &*file,
+            headers_builder,
+            RepresentationRequest::from_parts(&parts),
+            None,
+            &RequestRecord::new(parts.uri.clone()),
+        )
+        .map(Body::new)
+    }
+
+    /// The file served at the route `path`, the `index.html` of directories,
+    /// rejecting the paths leaving the directory
+    fn file_path(&self, path: &str) -> Option<PathBuf> {
+        let path = decode_path(path)?;
+        let mut file_path = self.root.clone();
+        for component in Path::new(path.trim_start_matches('/')).components() {
+            match component {
+                Component::Normal(name) => file_path.push(name),
+                Component::CurDir => {}
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => return None,
+            }
+        }
+        if file_path.is_dir() {
+            file_path.push("index.html");
+        }
+        file_path.is_file().then_some(file_path)
+    }
+
+    /// The file at `file_path`, read again when the directory
+    /// changed since it was last read
+    fn file(&self, file_path: &Path) -> Option<Arc<DevFile>> {
+        let generation = {
+            let files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+            if let Some(file) = files.by_path.get(file_path) {
+                return Some(Arc::clone(file));
+            }
+            files.generation
+        };
+
+        let file = Arc::new(DevFile::read(file_path)?);
+        let mut files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        if self.watcher.is_some() && files.generation == generation {
+            files
+                .by_path
+                .insert(file_path.to_owned(), Arc::clone(&file));
+        }
+        Some(file)
+    }
+}
+
+/// A file read by `watch_router`, with the compressed
+/// variants which are smaller than its contents
+struct DevFile {
+    content_type: HeaderValue,
+    etag: String,
+    last_modified: Option<String>,
+    body: Bytes,
+    body_gz: Option<Bytes>,
+    body_zst: Option<Bytes>,
+}
+
+impl DevFile {
+    /// Read the file at `file_path`, or `None` when it can't be read
+    fn read(file_path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(file_path).ok()?;
+        let body = fs::read(file_path).ok()?;
+        let smaller =
+            |compressed: Vec<u8>| (compressed.len() < body.len()).then(|| Bytes::from(compressed));
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        let body_gz = encoder
+            .write_all(&body)
+            .and_then(|()| encoder.finish())
+            .ok()
+            .and_then(smaller);
+        let body_zst = zstd::bulk::compress(&body, zstd::DEFAULT_COMPRESSION_LEVEL)
+            .ok()
+            .and_then(smaller);
+
+        Some(Self {
+            content_type: content_type(file_path),
+            etag: content_etag(&body),
+            last_modified: metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|modified| http_date::format(modified.as_secs())),
+            body: Bytes::from(body),
+            body_gz,
+            body_zst,
+        })
+    }
+
+    /// The compressed variant of the body with `content_encoding`, if any
+    fn variant(&self, content_encoding: ContentEncoding) -> Option<&Bytes> {
+        match content_encoding {
+            ContentEncoding::Gzip => self.body_gz.as_ref(),
+            ContentEncoding::Zstd => self.body_zst.as_ref(),
+            ContentEncoding::Brotli => None,
+        }
+    }
+}
+
+impl Representation for DevFile {
+    fn etag(&self) -> Option<&str> {
+        Some(&self.etag)
+    }
+
+    fn last_modified(&self) -> Option<&str> {
+        self.last_modified.as_deref()
+    }
+
+    fn negotiate_encoding(
+        &self,
+        accept_encoding: AcceptEncoding,
+        configured: Option<&[ContentEncoding]>,
+    ) -> Result<Option<ContentEncoding>, NotAcceptable> {
+        let preference = configured.unwrap_or(DEFAULT_ENCODING_PREFERENCE.as_slice());
+        accept_encoding.negotiate(preference, |content_encoding| {
+            self.variant(content_encoding).is_some()
+        })
+    }
+
+    fn encoded_len(&self, content_encoding: Option<ContentEncoding>) -> Option<usize> {
+        Some(self.encoded_body(content_encoding).len())
+    }
+
+    fn encoded_body(&self, content_encoding: Option<ContentEncoding>) -> Bytes {
+        content_encoding
+            .and_then(|content_encoding| self.variant(content_encoding))
+            .unwrap_or(&self.body)
+            .clone()
+    }
+
+    fn stream_chunk_size(&self) -> Option<NonZeroUsize> {
+        None
+    }
+}
+
+/// The `Content-Type` of the file at `file_path`, with the UTF-8 charset
+/// of the textual types, as `embed_assets!` sends them by default
+fn content_type(file_path: &Path) -> HeaderValue {
+    let content_type = mime_guess::from_path(file_path)
+        .first_raw()
+        .unwrap_or("application/octet-stream");
+    let textual = content_type.starts_with("text/")
+        || content_type == "application/javascript"
+        || content_type == "application/json";
+    if textual {
+        HeaderValue::from_str(&format!("{content_type}; charset=utf-8"))
+            .expect("the media types are visible ASCII")
+    } else {
+        HeaderValue::from_static(content_type)
+    }
+}
@@ -0,0 +1,135 @@
+//! Serving the files from the filesystem in debug builds,
+//! for `embed_assets!` with `debug_from_disk = true`
+
+use std::fs;
+
+use axum::{
+    Extension, Router,
+    extract::OriginalUri,
+    http::{HeaderMap, HeaderValue, StatusCode, header::ORIGIN},
+    response::{IntoResponse, Response},
+    routing::MethodRouter,
+};
+use bytes::Bytes;
+use range_requests::{
+    headers::{if_range::IfRange, range::HttpRange},
+    serve_file_with_http_range,
+};
+
+use crate::{
+    Credentials, StaticAsset, UrlSigner,
+    conditional::{Outcome, Preconditions},
+    etag::content_etag,
+    headers::ResponseHeadersBuilder,
+    not_modified::not_modified,
+    respond::{is_authorized, is_revealed, is_signed_url, unauthorized},
+    routing::with_methods,
+};
+
+#[doc(hidden)]
+/// Add a route serving the file at `file_path`, read at request time,
+/// with the content type of the embedded `asset`
+///
+/// The other methods are handled like by `static_route`.
+///
+/// Like `static_route`, it's `pub` for the routers built
+/// from the routes of `embed_assets!` outside of its functions.
+pub fn disk_route<S>(
+    router: Router<S>,
+    web_path: &'static str,
+    asset: &'static StaticAsset,
+    file_path: &'static str,
+    fall_through_methods: bool,
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.route(
+        web_path,
+        disk_method_router(asset, file_path, fall_through_methods),
+    )
+}
+
+/// The method router of `disk_route`
+pub(crate) fn disk_method_router<S>(
+    asset: &'static StaticAsset,
+    file_path: &'static str,
+    fall_through_methods: bool,
+) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let credentials = Credentials::of(asset);
+    let method_router = MethodRouter::get(
+        MethodRouter::new(),
+        move |preconditions: Preconditions,
+              http_range: Option<HttpRange>,
+              if_range: Option<IfRange>,
+              url_signer: Option<Extension<UrlSigner>>,
+              OriginalUri(uri): OriginalUri,
+              headers: HeaderMap| async move {
+            if let Some(basic_auth) = &asset.basic_auth
+                && !is_authorized(asset, credentials.as_ref(), &headers)
+            {
+                return unauthorized(basic_auth).into_response();
+            }
+            let url_signer = url_signer.as_ref().map(|Extension(url_signer)| url_signer);
+            if !is_signed_url(asset, url_signer, &uri) {
+                return StatusCode::FORBIDDEN.into_response();
+            }
+            if !is_revealed(asset, &headers) {
+                return StatusCode::NOT_FOUND.into_response();
+            }
+            let origin = headers.get(ORIGIN).cloned();
+            disk_inner(
+                asset,
+                file_path,
+                &preconditions,
+                origin,
+                http_range,
+                if_range,
+            )
+        },
+    );
+    with_methods(method_router, asset.cors, fall_through_methods)
+}
+
+/// Serve the current contents of the file, uncompressed and without
+/// a `Cache-Control` header so that edits are picked up on reload
+fn disk_inner(
+    asset: &StaticAsset,
+    file_path: &str,
+    preconditions: &Preconditions,
+    origin: Option<HeaderValue>,
+    http_range: Option<HttpRange>,
+    if_range: Option<IfRange>,
+) -> Response {
+    let Ok(body) = fs::read(file_path) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let etag = content_etag(&body);
+    let etag_value = HeaderValue::from_str(&etag).expect("the ETag is made of hex digits");
+    let headers_builder = ResponseHeadersBuilder::new(HeaderValue::from_static(asset.content_type))
+        .etag(etag_value.clone())
+        .extra_headers(asset.headers)
+        .cors(asset.cors, origin);
+
+    match preconditions.evaluate(Some(&etag), None) {
+        Outcome::Proceed => {}
+        Outcome::NotModified => {
+            return not_modified(headers_builder.build_not_modified(), Some(body.len()))
+                .into_response();
+        }
+        Outcome::PreconditionFailed => return StatusCode::PRECONDITION_FAILED.into_response(),
+    }
+
+    let http_range = match (http_range, if_range) {
+        (Some(range), Some(if_range)) => if_range.evaluate(range, None, Some(&etag_value)),
+        (range, _) => range,
+    };
+    match serve_file_with_http_range(Bytes::from(body), http_range) {
+        Ok(body_range) => (headers_builder.build(), body_range).into_response(),
+        Err(unsatisfiable) => (headers_builder.build(), unsatisfiable).into_response(),
+    }
+}
@@ -0,0 +1,16 @@
+//! The `ETag`s of the contents read at runtime, computed like
+//! the default ones of the embedded assets
+
+use sha2::{Digest, Sha256};
+
+/// The quoted `ETag` of `body`: its SHA-256 folded into 64 bits, as
+/// with the default `etag` of `embed_assets!`, so that the same contents
+/// get the same `ETag` whether they're embedded or not
+pub(crate) fn content_etag(body: &[u8]) -> String {
+    let sha256 = Sha256::digest(body);
+    let hash = sha256
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().expect("the chunks are 8 bytes long")))
+        .fold(0, |hash, chunk| hash ^ chunk);
+    format!("\"{hash:016x}\"")
+}
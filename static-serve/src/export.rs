@@ -0,0 +1,154 @@
+//! Writing of the assets embedded by `embed_assets!` to a directory,
+//! e.g. to upload them to a CDN during deployment
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{StaticAsset, etag::content_etag, percent::decode_path};
+
+/// How the generated `write_assets_to_with` function writes the assets
+///
+/// ```rust,ignore
+/// let written = write_assets_to_with(
+///     Path::new("dist"),
+///     WriteOptions::new().fingerprinted(true).precompressed(true),
+/// )?;
+/// for (route, file) in written {
+///     println!("{route} -> {}", file.display());
+/// }
+/// ```
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    fingerprinted: bool,
+    precompressed: bool,
+}
+
+impl WriteOptions {
+    /// Write every asset under its route, without compressed siblings
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            fingerprinted: false,
+            precompressed: false,
+        }
+    }
+
+    /// Insert the hash of the contents before the extension of the files,
+    /// e.g. `app.0123456789abcdef.js`, unless they're cache-busted already
+    #[must_use]
+    pub const fn fingerprinted(mut self, fingerprinted: bool) -> Self {
+        self.fingerprinted = fingerprinted;
+        self
+    }
+
+    /// Write the embedded gzip, zstd and Brotli variants next to
+    /// the files, with the `.gz`, `.zst` and `.br` extensions
+    #[must_use]
+    pub const fn precompressed(mut self, precompressed: bool) -> Self {
+        self.precompressed = precompressed;
+        self
+    }
+}
+
+#[doc(hidden)]
+/// Write the `assets` whose `included` flag is set to `dir`, with `options`,
+/// returning the routes and the paths of the files written for them
+///
+/// Used by the `write_assets_to_with` function generated
+/// by `embed_assets!`, so it needs to be `pub`.
+///
+/// # Errors
+///
+/// If a file can't be written, or if a route isn't a relative path.
+pub fn write_assets(
+    assets: &'static [(&'static str, StaticAsset)],
+    included: &[bool],
+    dir: &Path,
+    options: WriteOptions,
+) -> io::Result<Vec<(&'static str, PathBuf)>> {
+    let mut written = Vec::new();
+    for ((route, asset), &included) in assets.iter().zip(included) {
+        // Writing the protected, signed and hidden assets would publish them
+        if !included
+            || asset.basic_auth.is_some()
+            || asset.signed
+            || asset.source_map_gate.is_some()
+        {
+            continue;
+        }
+        let mut path = dir.join(relative_path(route, asset)?);
+        if options.fingerprinted && !asset.cache_busted {
+            path = fingerprinted(&path, asset);
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, asset.uncompressed_body())?;
+        if options.precompressed {
+            let variants = [
+                ("gz", asset.body_gz.or_else(|| asset.lazy_body?.body_gz())),
+                ("zst", asset.body_zst),
+                ("br", asset.body_br),
+            ];
+            for (extension, body) in variants {
+                if let Some(body) = body {
+                    fs::write(with_appended_extension(&path, extension), body)?;
+                }
+            }
+        }
+        written.push((*route, path));
+    }
+    Ok(written)
+}
+
+/// The path of the file of the asset served at `route`, an `index.html`
+/// for the routes of directories, and with the `.html` extension stripped
+/// by `strip_html_ext` restored, so that static hosts serve it as HTML
+fn relative_path(route: &str, asset: &StaticAsset) -> io::Result<PathBuf> {
+    let decoded = decode_path(route).unwrap_or_else(|| route.to_owned());
+    let mut path = PathBuf::new();
+    for segment in decoded.split('/').filter(|segment| !segment.is_empty()) {
+        if segment == "." || segment == ".." {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("the route {route} isn't a relative path"),
+            ));
+        }
+        path.push(segment);
+    }
+    if decoded.ends_with('/') {
+        path.push("index.html");
+    } else if asset.content_type.starts_with("text/html") && path.extension().is_none() {
+        path.set_extension("html");
+    }
+    Ok(path)
+}
+
+/// `path` with the hash of the contents of `asset` before its extension
+fn fingerprinted(path: &Path, asset: &StaticAsset) -> PathBuf {
+    let etag = asset
+        .etag
+        .map_or_else(|| content_etag(asset.uncompressed_body()), str::to_owned);
+    let hash: String = etag
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .skip(usize::from(etag.starts_with("W/")))
+        .take(16)
+        .collect();
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{stem}.{hash}.{}", extension.to_string_lossy()),
+        None => format!("{stem}.{hash}"),
+    };
+    path.with_file_name(file_name)
+}
+
+/// `path` with `extension` appended to its own, e.g. `app.js.gz`
+fn with_appended_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
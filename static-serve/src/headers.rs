@@ -0,0 +1,293 @@
+//! The response headers of the embedded assets, for handlers serving
+//! their own payloads consistently with the generated routes
+
+use std::sync::Arc;
+
+use http::{
+    HeaderMap,
+    header::{
+        ACCEPT_RANGES, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+        ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_HEADERS,
+        CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, ETAG, HeaderName, HeaderValue,
+        LAST_MODIFIED, ORIGIN, VARY,
+    },
+};
+
+/// The value of the `Cache-Control` header of cache-busted assets
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+/// The methods the assets are served with, listed in the `Allow` header
+/// and in the `Access-Control-Allow-Methods` header of CORS preflights
+pub(crate) const ALLOWED_METHODS: &str = "GET, HEAD";
+
+/// A compressed encoding of the body
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ContentEncoding {
+    /// `Content-Encoding: gzip`
+    Gzip,
+    /// `Content-Encoding: zstd`
+    Zstd,
+    /// `Content-Encoding: br`
+    Brotli,
+}
+
+impl ContentEncoding {
+    pub(crate) const fn as_str(self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Zstd => "zstd",
+            Self::Brotli => "br",
+        }
+    }
+
+    pub(crate) fn header_value(self) -> HeaderValue {
+        HeaderValue::from_static(self.as_str())
+    }
+}
+
+/// Builds the exact set of headers sent by the routes
+/// generated by `embed_assets!` and `embed_asset!`
+///
+/// ```rust
+/// use http::HeaderValue;
+/// use static_serve::headers::{ContentEncoding, ResponseHeadersBuilder};
+///
+/// let headers = ResponseHeadersBuilder::new(HeaderValue::from_static("text/javascript"))
+///     .etag(HeaderValue::from_static("\"1234567890abcdef\""))
+///     .cache_busted(true)
+///     .content_encoding(ContentEncoding::Zstd)
+///     .build();
+/// assert_eq!(headers["content-encoding"], "zstd");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResponseHeadersBuilder {
+    content_type: HeaderValue,
+    etag: Option<HeaderValue>,
+    last_modified: Option<HeaderValue>,
+    cache_busted: bool,
+    cache_control: Option<HeaderValue>,
+    content_encoding: Option<ContentEncoding>,
+    extra_headers: &'static [(&'static str, &'static str)],
+    cors: &'static [&'static str],
+    origin: Option<HeaderValue>,
+    vary_accept_encoding: bool,
+    vary_accept: bool,
+    configured_headers: Option<Arc<[(HeaderName, HeaderValue)]>>,
+}
+
+impl ResponseHeadersBuilder {
+    /// Start from the `Content-Type` of the body
+    #[must_use]
+    pub fn new(content_type: HeaderValue) -> Self {
+        Self {
+            content_type,
+            etag: None,
+            last_modified: None,
+            cache_busted: false,
+            cache_control: None,
+            content_encoding: None,
+            extra_headers: &[],
+            cors: &[],
+            origin: None,
+            vary_accept_encoding: true,
+            vary_accept: false,
+            configured_headers: None,
+        }
+    }
+
+    /// Set the `ETag` header
+    #[must_use]
+    pub fn etag(mut self, etag: HeaderValue) -> Self {
+        self.etag = Some(etag);
+        self
+    }
+
+    /// Set the `Last-Modified` header
+    #[must_use]
+    pub fn last_modified(mut self, last_modified: HeaderValue) -> Self {
+        self.last_modified = Some(last_modified);
+        self
+    }
+
+    /// Add an immutable `Cache-Control` header if `cache_busted` is true
+    #[must_use]
+    pub fn cache_busted(mut self, cache_busted: bool) -> Self {
+        self.cache_busted = cache_busted;
+        self
+    }
+
+    /// Set an explicit `Cache-Control` header, replacing the immutable one
+    /// of cache-busted assets
+    #[must_use]
+    pub fn cache_control(mut self, cache_control: HeaderValue) -> Self {
+        self.cache_control = Some(cache_control);
+        self
+    }
+
+    /// Add the `extra_headers` declared with `headers`, tuples
+    /// of a lowercase header name and of its value
+    ///
+    /// # Panics
+    ///
+    /// When building the headers, if a name isn't lowercase
+    /// or a value isn't a valid header value.
+    #[must_use]
+    pub fn extra_headers(mut self, extra_headers: &'static [(&'static str, &'static str)]) -> Self {
+        self.extra_headers = extra_headers;
+        self
+    }
+
+    /// Allow the `allowed_origins` to read the body with CORS requests, `*`
+    /// allowing all of them, given the `Origin` header of the request
+    #[must_use]
+    pub fn cors(
+        mut self,
+        allowed_origins: &'static [&'static str],
+        origin: Option<HeaderValue>,
+    ) -> Self {
+        self.cors = allowed_origins;
+        self.origin = origin;
+        self
+    }
+
+    /// Make the response vary with the `Accept-Encoding` header, which is
+    /// only needed when the body has compressed variants (defaults to true)
+    #[must_use]
+    pub fn vary_accept_encoding(mut self, vary_accept_encoding: bool) -> Self {
+        self.vary_accept_encoding = vary_accept_encoding;
+        self
+    }
+
+    /// Make the response vary with the `Accept` header, for the
+    /// images negotiated with their AVIF and WebP variants
+    #[must_use]
+    pub fn vary_accept(mut self, vary_accept: bool) -> Self {
+        self.vary_accept = vary_accept;
+        self
+    }
+
+    /// Add the headers of a `StaticServeConfig`, replacing the extra
+    /// headers of the same names
+    #[must_use]
+    pub(crate) fn configured_headers(
+        mut self,
+        configured_headers: Arc<[(HeaderName, HeaderValue)]>,
+    ) -> Self {
+        self.configured_headers = Some(configured_headers);
+        self
+    }
+
+    /// Set the `Content-Encoding` of the body, which is sent uncompressed by default
+    #[must_use]
+    pub fn content_encoding(
+        mut self,
+        content_encoding: impl Into<Option<ContentEncoding>>,
+    ) -> Self {
+        self.content_encoding = content_encoding.into();
+        self
+    }
+
+    /// The headers of a response with a body, full or partial
+    #[must_use]
+    pub fn build(self) -> HeaderMap {
+        let content_type = self.content_type.clone();
+        let content_encoding = self.content_encoding;
+        let mut headers = self.build_not_modified();
+        headers.insert(CONTENT_TYPE, content_type);
+        headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        if let Some(content_encoding) = content_encoding {
+            headers.insert(CONTENT_ENCODING, content_encoding.header_value());
+        }
+        headers
+    }
+
+    /// The headers of a `304 Not Modified` response: the validators, the
+    /// `Cache-Control` and the `Vary` the `200 OK` response would have sent
+    /// (RFC 9110, section 15.4.5), along with the CORS and extra headers,
+    /// without the `Content-Type` and `Content-Encoding` of a body
+    #[must_use]
+    pub fn build_not_modified(self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if self.vary_accept_encoding {
+            headers.insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+        }
+        if self.vary_accept {
+            headers.append(VARY, HeaderValue::from_static("Accept"));
+        }
+        if let Some(etag) = self.etag {
+            headers.insert(ETAG, etag);
+        }
+        if let Some(last_modified) = self.last_modified {
+            headers.insert(LAST_MODIFIED, last_modified);
+        }
+        if let Some(cache_control) = self.cache_control {
+            headers.insert(CACHE_CONTROL, cache_control);
+        } else if self.cache_busted {
+            headers.insert(CACHE_CONTROL, HeaderValue::from_static(IMMUTABLE));
+        }
+        insert_allow_origin(&mut headers, self.cors, self.origin.as_ref());
+        for &(name, value) in self.extra_headers {
+            headers.append(
+                HeaderName::from_static(name),
+                HeaderValue::from_static(value),
+            );
+        }
+        for (name, value) in self
+            .configured_headers
+            .iter()
+            .flat_map(|headers| &**headers)
+        {
+            headers.insert(name, value.clone());
+        }
+        headers
+    }
+}
+
+/// The headers of the `204 No Content` response to a CORS preflight
+/// request, described by its `request_headers`, from one of the
+/// `allowed_origins`
+#[must_use]
+pub fn preflight_headers(
+    allowed_origins: &'static [&'static str],
+    request_headers: &HeaderMap,
+) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    insert_allow_origin(&mut headers, allowed_origins, request_headers.get(ORIGIN));
+    if !headers.contains_key(ACCESS_CONTROL_ALLOW_ORIGIN) {
+        return headers;
+    }
+    headers.insert(
+        ACCESS_CONTROL_ALLOW_METHODS,
+        HeaderValue::from_static(ALLOWED_METHODS),
+    );
+    if let Some(request_headers) = request_headers.get(ACCESS_CONTROL_REQUEST_HEADERS) {
+        headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, request_headers.clone());
+    }
+    headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static("86400"));
+    headers
+}
+
+/// Add the `Access-Control-Allow-Origin` header if `origin` is one of the
+/// `allowed_origins`, which makes the response vary with the `Origin`
+/// unless all of them are allowed
+fn insert_allow_origin(
+    headers: &mut HeaderMap,
+    allowed_origins: &[&str],
+    origin: Option<&HeaderValue>,
+) {
+    if allowed_origins == ["*"] {
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+        return;
+    }
+    if allowed_origins.is_empty() {
+        return;
+    }
+    headers.append(VARY, HeaderValue::from_static("Origin"));
+    if let Some(origin) = origin
+        && origin
+            .to_str()
+            .is_ok_and(|origin| allowed_origins.contains(&origin))
+    {
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+    }
+}
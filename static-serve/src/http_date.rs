@@ -0,0 +1,120 @@
+//! Parsing and formatting of HTTP-dates ([RFC 9110 Section 5.6.7](https://www.rfc-editor.org/rfc/rfc9110#section-5.6.7))
+//!
+//! Shared with `static-serve-macro`, which formats the `Last-Modified`
+//! dates at compile time, parsed at runtime by the conditional requests.
+
+const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Parse an IMF-fixdate (e.g. `Sun, 06 Nov 1994 08:49:37 GMT`)
+/// into seconds since the UNIX epoch
+///
+/// The obsolete RFC 850 and asctime formats are not supported:
+/// recipients are allowed to ignore dates they cannot parse.
+pub(crate) fn parse(value: &str) -> Option<u64> {
+    let (_weekday, date) = value.split_once(", ")?;
+    let mut parts = date.split(' ');
+    let day: u64 = parts.next()?.parse().ok()?;
+    let month_name = parts.next()?;
+    let month = MONTHS.iter().position(|month| *month == month_name)?;
+    let month = u64::try_from(month).ok()? + 1;
+    let year: u64 = parts.next()?.parse().ok()?;
+
+    let mut time = parts.next()?.split(':');
+    let hours: u64 = time.next()?.parse().ok()?;
+    let minutes: u64 = time.next()?.parse().ok()?;
+    let seconds: u64 = time.next()?.parse().ok()?;
+
+    if parts.next()? != "GMT" || parts.next().is_some() || time.next().is_some() {
+        return None;
+    }
+    if year < 1970 || !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+
+    let days = days_from_civil(year, month, day);
+    Some(days * 86_400 + hours * 3600 + minutes * 60 + seconds)
+}
+
+/// Format seconds since the UNIX epoch as an IMF-fixdate,
+/// e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+pub(crate) fn format(secs_since_epoch: u64) -> String {
+    let days = secs_since_epoch / 86_400;
+    let secs_of_day = secs_since_epoch % 86_400;
+    let (year, month, day) = civil_from_days(days);
+
+    // 1970-01-01 was a Thursday, which is the first entry of `WEEKDAYS`
+    let weekday = WEEKDAYS[usize::try_from(days % 7).expect("weekday index fits into usize")];
+    let month = MONTHS[usize::try_from(month - 1).expect("month index fits into usize")];
+
+    format!(
+        "{weekday}, {day:02} {month} {year:04} {:02}:{:02}:{:02} GMT",
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+/// Convert days since the UNIX epoch into a `(year, month, day)` triple
+/// of the proleptic Gregorian calendar
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+
+    (year, month, day)
+}
+
+/// Convert a `(year, month, day)` triple of the proleptic Gregorian
+/// calendar into days since the UNIX epoch
+///
+/// See <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+
+    #[test]
+    fn parses_imf_fixdate() {
+        assert_eq!(parse("Sun, 06 Nov 1994 08:49:37 GMT"), Some(784_111_777));
+        assert_eq!(parse("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(parse("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+        assert_eq!(parse("Sun Nov  6 08:49:37 1994"), None);
+    }
+
+    #[test]
+    fn formats_what_it_parses() {
+        for date in [
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Thu, 01 Jan 1970 00:00:00 GMT",
+            "Tue, 29 Feb 2000 23:59:59 GMT",
+        ] {
+            assert_eq!(super::format(parse(date).unwrap()), date);
+        }
+    }
+}
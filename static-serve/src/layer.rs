@@ -0,0 +1,126 @@
+//! A tower `Layer` serving the embedded assets as a fallback of any service
+
+use std::{
+    borrow::Cow,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use axum::{
+    BoxError, Router,
+    body::{Body, HttpBody},
+    http::{Method, Request, Response, StatusCode, Uri, uri::PathAndQuery},
+};
+use bytes::Bytes;
+use tower::{Layer, Service};
+
+use crate::percent::canonical_path;
+
+/// A [`Layer`] serving the embedded assets whenever the wrapped
+/// service responds with `404 Not Found`
+///
+/// This bolts the assets onto existing tower stacks
+/// without restructuring their routing:
+///
+/// ```rust,ignore
+/// embed_assets!("assets", compress = true);
+/// let service = ServiceBuilder::new()
+///     .layer(StaticServeLayer::new(static_router()))
+///     .service(api_service);
+/// ```
+///
+/// Only `GET` and `HEAD` requests fall back to the assets. The response
+/// of the wrapped service is kept when no asset matches either.
+#[derive(Debug, Clone)]
+pub struct StaticServeLayer {
+    router: Router,
+}
+
+impl StaticServeLayer {
+    /// Serve the routes of `router`, usually the one returned
+    /// by the `static_router()` function generated by `embed_assets!`
+    #[must_use]
+    pub fn new(router: Router) -> Self {
+        Self { router }
+    }
+}
+
+impl<S> Layer<S> for StaticServeLayer {
+    type Service = StaticServe<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        StaticServe {
+            inner,
+            router: self.router.clone(),
+        }
+    }
+}
+
+/// The service created by [`StaticServeLayer`]
+#[derive(Debug, Clone)]
+pub struct StaticServe<S> {
+    inner: S,
+    router: Router,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for StaticServe<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+    S::Future: Send + 'static,
+    ResBody: HttpBody<Data = Bytes> + Send + 'static,
+    ResBody::Error: Into<BoxError>,
+{
+    type Response = Response<Body>;
+    type Error = S::Error;
+    type Future =
+        Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send + 'static>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<ReqBody>) -> Self::Future {
+        // The request is consumed by the inner service, so keep what's
+        // needed to replay it: asset requests have no body anyway
+        let fallback_request = matches!(*request.method(), Method::GET | Method::HEAD).then(|| {
+            let mut fallback_request = Request::new(Body::empty());
+            *fallback_request.method_mut() = request.method().clone();
+            // Matched in the spelling of the routes, whichever the client used
+            *fallback_request.uri_mut() = canonical_uri(request.uri());
+            *fallback_request.version_mut() = request.version();
+            *fallback_request.headers_mut() = request.headers().clone();
+            fallback_request
+        });
+        let mut router = self.router.clone();
+        let response = self.inner.call(request);
+
+        Box::pin(async move {
+            let response = response.await?;
+            if response.status() == StatusCode::NOT_FOUND
+                && let Some(fallback_request) = fallback_request
+            {
+                let Ok(static_response) = router.call(fallback_request).await;
+                if static_response.status() != StatusCode::NOT_FOUND {
+                    return Ok(static_response);
+                }
+            }
+
+            Ok(response.map(Body::new))
+        })
+    }
+}
+
+/// `uri` with its path in the spelling of the routes
+fn canonical_uri(uri: &Uri) -> Uri {
+    let Cow::Owned(path) = canonical_path(uri.path()) else {
+        return uri.clone();
+    };
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path,
+    };
+    let mut parts = uri.clone().into_parts();
+    // The canonical path only has characters valid in a URI
+    parts.path_and_query = PathAndQuery::try_from(path_and_query).ok();
+    Uri::from_parts(parts).unwrap_or_else(|_| uri.clone())
+}
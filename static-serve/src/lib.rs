@@ -1,250 +1,739 @@
 #![doc = include_str!("../README.md")]
 
-use std::{convert::Infallible, future};
-
-use axum::{
-    Router,
-    extract::FromRequestParts,
-    http::{
-        StatusCode,
-        header::{
-            ACCEPT_ENCODING, ACCEPT_RANGES, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_TYPE, ETAG,
-            HeaderValue, IF_NONE_MATCH, VARY,
-        },
-        request::Parts,
-    },
-    response::IntoResponse,
-    routing::{MethodRouter, get},
+use std::{borrow::Cow, num::NonZeroUsize};
+
+use http::{
+    HeaderMap,
+    header::{ACCEPT, ACCEPT_ENCODING, HeaderValue},
 };
-use bytes::Bytes;
-use range_requests::{
-    headers::{if_range::IfRange, range::HttpRange},
-    serve_file_with_http_range,
+
+pub use static_serve_macro::{embed_asset, embed_asset_set, embed_assets};
+
+use crate::headers::ContentEncoding;
+
+mod asset_info;
+mod asset_set;
+mod auth;
+mod backend;
+mod body;
+#[cfg(feature = "bundle")]
+mod bundle;
+mod conditional;
+mod config;
+mod decompress;
+#[cfg(feature = "dev")]
+pub mod dev;
+#[cfg(feature = "axum")]
+mod disk;
+mod etag;
+mod export;
+pub mod headers;
+// Only the `dev` router formats the dates at runtime
+#[cfg_attr(not(any(test, feature = "dev")), expect(dead_code))]
+mod http_date;
+#[cfg(feature = "axum")]
+mod layer;
+mod lookup;
+mod metrics;
+mod not_found;
+mod not_modified;
+#[cfg(feature = "test-overrides")]
+mod overrides;
+// The routes of the files are only encoded by the macros
+#[cfg_attr(not(test), expect(dead_code))]
+mod percent;
+mod record;
+mod respond;
+#[cfg(feature = "axum")]
+mod routing;
+mod service;
+mod signed;
+mod source_maps;
+mod stream;
+mod verify;
+#[cfg(any(feature = "zstd", feature = "ruzstd"))]
+mod zstd_decoder;
+
+#[doc(hidden)]
+pub use asset_info::asset_manifest;
+pub use asset_info::{AssetInfo, CspHashes};
+pub use asset_set::StaticAssetSet;
+pub use auth::{BasicAuth, Credentials};
+#[cfg(feature = "axum")]
+pub use backend::Axum;
+#[cfg(feature = "rocket")]
+#[doc(hidden)]
+pub use backend::mounted_request_parts;
+#[cfg(feature = "salvo")]
+#[doc(hidden)]
+pub use backend::nested_uri;
+pub use backend::{Fallback, Redirect, Route, StaticAssetBackend};
+#[doc(hidden)]
+pub use backend::{add_aliases, remove_excluded};
+#[cfg(any(feature = "poem", feature = "salvo"))]
+#[doc(hidden)]
+pub use body::FallibleBody;
+pub use body::StaticBody;
+#[cfg(feature = "bundle")]
+pub use bundle::Bundle;
+pub use config::{ConfigError, StaticServeConfig};
+#[doc(hidden)]
+pub use decompress::LazyBody;
+#[doc(hidden)]
+pub use decompress::decompress_gzip;
+#[cfg(any(feature = "zstd", feature = "ruzstd"))]
+#[doc(hidden)]
+pub use decompress::{decompress_with_dictionary, decompress_zstd};
+#[cfg(feature = "axum")]
+#[doc(hidden)]
+pub use disk::disk_route;
+pub use export::WriteOptions;
+#[doc(hidden)]
+pub use export::write_assets;
+#[cfg(feature = "axum")]
+pub use layer::{StaticServe, StaticServeLayer};
+#[doc(hidden)]
+pub use lookup::perfect_hash_index;
+pub use metrics::{
+    AssetCounters, AssetMetrics, ServedAsset, SetAssetMetricsError, set_asset_metrics,
 };
+#[cfg(feature = "axum")]
+#[doc(hidden)]
+pub use not_found::{spa_fallback, static_fallback};
+#[cfg(feature = "test-overrides")]
+pub use overrides::AssetOverrides;
+#[cfg(feature = "test-overrides")]
+#[doc(hidden)]
+pub use overrides::apply_overrides;
+#[cfg(feature = "axum")]
+pub use routing::AssetResponse;
+#[cfg(feature = "axum")]
+#[doc(hidden)]
+pub use routing::{static_method_router, static_route};
+pub use service::StaticService;
+#[doc(hidden)]
+pub use signed::RequestedUri;
+pub use signed::UrlSigner;
+pub use source_maps::SourceMapGate;
+pub use stream::ChunkedBody;
+pub use verify::VerifyError;
+#[doc(hidden)]
+pub use verify::verify_assets;
+
+#[doc(hidden)]
+/// The spelling of `path` used by the routes of `embed_assets!`, with only
+/// the bytes which can't appear in a URL path percent-encoded, in uppercase
+///
+/// Used by the `get_asset` function generated by `embed_assets!`,
+/// so it needs to be `pub`.
+#[must_use]
+pub fn canonical_path(path: &str) -> Cow<'_, str> {
+    percent::canonical_path(path)
+}
 
-pub use static_serve_macro::{embed_asset, embed_assets};
+/// The order of preference of the compressed variants
+/// of the assets without an `encoding_preference`
+const DEFAULT_ENCODING_PREFERENCE: [ContentEncoding; 3] = [
+    ContentEncoding::Zstd,
+    ContentEncoding::Brotli,
+    ContentEncoding::Gzip,
+];
 
-/// The accept/reject status for gzip and zstd encoding
+/// The quality values of gzip, zstd and Brotli encoding in the
+/// `Accept-Encoding` header, 0 meaning the encoding isn't accepted
 #[derive(Debug, Copy, Clone)]
 struct AcceptEncoding {
-    /// Is gzip accepted?
-    pub gzip: bool,
-    /// Is zstd accepted?
-    pub zstd: bool,
+    /// The quality of gzip
+    pub gzip: f32,
+    /// The quality of zstd
+    pub zstd: f32,
+    /// The quality of Brotli
+    pub br: f32,
+    /// The quality of the uncompressed body, acceptable
+    /// unless excluded by `identity;q=0` or `*;q=0`
+    pub identity: f32,
+    /// The quality of the uncompressed body when listed with `identity` or
+    /// `*`, the compressed variants with a lower one not being sent
+    pub listed_identity: Option<f32>,
 }
 
-impl<S> FromRequestParts<S> for AcceptEncoding
-where
-    S: Send + Sync,
-{
-    type Rejection = Infallible;
-
-    fn from_request_parts(
-        parts: &mut Parts,
-        _state: &S,
-    ) -> impl Future<Output = Result<Self, Self::Rejection>> {
-        let accept_encoding = parts.headers.get(ACCEPT_ENCODING);
+impl AcceptEncoding {
+    /// The quality value of `content_encoding`
+    const fn quality(&self, content_encoding: ContentEncoding) -> f32 {
+        match content_encoding {
+            ContentEncoding::Gzip => self.gzip,
+            ContentEncoding::Zstd => self.zstd,
+            ContentEncoding::Brotli => self.br,
+        }
+    }
+
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let accept_encoding = headers.get(ACCEPT_ENCODING);
         let accept_encoding = accept_encoding
             .and_then(|accept_encoding| accept_encoding.to_str().ok())
             .unwrap_or_default();
 
-        future::ready(Ok(Self {
-            gzip: accept_encoding.contains("gzip"),
-            zstd: accept_encoding.contains("zstd"),
-        }))
-    }
-}
-
-/// Check if the  `IfNoneMatch` header is present
-#[derive(Debug)]
-struct IfNoneMatch(Option<HeaderValue>);
+        let mut gzip = None;
+        let mut zstd = None;
+        let mut br = None;
+        let mut identity = None;
+        let mut wildcard = None;
+        for (name, quality) in weighted_elements(accept_encoding) {
+            let slot = if name.eq_ignore_ascii_case("gzip") || name.eq_ignore_ascii_case("x-gzip") {
+                &mut gzip
+            } else if name.eq_ignore_ascii_case("zstd") {
+                &mut zstd
+            } else if name.eq_ignore_ascii_case("br") {
+                &mut br
+            } else if name.eq_ignore_ascii_case("identity") {
+                &mut identity
+            } else if name == "*" {
+                &mut wildcard
+            } else {
+                continue;
+            };
+            *slot = Some(slot.map_or(quality, |previous: f32| previous.max(quality)));
+        }
 
-impl IfNoneMatch {
-    /// required function for checking if `IfNoneMatch` is present
-    fn matches(&self, etag: &str) -> bool {
-        self.0
-            .as_ref()
-            .is_some_and(|if_none_match| if_none_match.as_bytes() == etag.as_bytes())
+        // The wildcard matches the encodings which aren't listed
+        Self {
+            gzip: gzip.or(wildcard).unwrap_or_default(),
+            zstd: zstd.or(wildcard).unwrap_or_default(),
+            br: br.or(wildcard).unwrap_or_default(),
+            identity: identity.or(wildcard).unwrap_or(1.0),
+            listed_identity: identity.or(wildcard),
+        }
     }
-}
 
-impl<S> FromRequestParts<S> for IfNoneMatch
-where
-    S: Send + Sync,
-{
-    type Rejection = Infallible;
-
-    fn from_request_parts(
-        parts: &mut Parts,
-        _state: &S,
-    ) -> impl Future<Output = Result<Self, Self::Rejection>> {
-        let if_none_match = parts.headers.get(IF_NONE_MATCH).cloned();
-        future::ready(Ok(Self(if_none_match)))
+    /// The encoding of a body whose variants are the encodings for which
+    /// `has_variant` is true: the variant with the highest quality value,
+    /// preferring the first ones of `preference` among equal ones, or
+    /// `None` for the uncompressed body
+    ///
+    /// `NotAcceptable` when the client accepts neither the uncompressed
+    /// body, with `identity;q=0`, nor any of the variants.
+    fn negotiate(
+        self,
+        preference: &[ContentEncoding],
+        has_variant: impl Fn(ContentEncoding) -> bool,
+    ) -> Result<Option<ContentEncoding>, NotAcceptable> {
+        let mut negotiated = None;
+        let mut best_quality = 0.0;
+        for &content_encoding in preference {
+            let quality = self.quality(content_encoding);
+            if quality > best_quality && has_variant(content_encoding) {
+                negotiated = Some(content_encoding);
+                best_quality = quality;
+            }
+        }
+        // The uncompressed body is sent when the client prefers it, the
+        // variants winning ties and the implicitly acceptable `identity`
+        if self
+            .listed_identity
+            .is_some_and(|identity| identity > best_quality)
+        {
+            return Ok(None);
+        }
+        match negotiated {
+            Some(content_encoding) => Ok(Some(content_encoding)),
+            None if self.identity > 0.0 => Ok(None),
+            None => Err(NotAcceptable),
+        }
     }
 }
 
-#[doc(hidden)]
-#[expect(clippy::too_many_arguments)]
-/// The router for adding routes for static assets
-pub fn static_route<S>(
-    router: Router<S>,
-    web_path: &'static str,
+/// A client accepting none of the encodings of an asset, answered with `406 Not Acceptable`
+#[derive(Debug)]
+struct NotAcceptable;
+
+/// An asset embedded in the binary by `embed_assets!` or `embed_asset!`,
+/// found with `get_asset` or `StaticAssetSet::get`
+#[derive(Debug)]
+pub struct StaticAsset {
+    /// The value of the `Content-Type` header
     content_type: &'static str,
-    etag: &'static str,
+    /// The value of the `ETag` header, omitted when
+    /// conditional requests rely on `Last-Modified` only
+    etag: Option<&'static str>,
+    /// The value of the `Last-Modified` header
+    last_modified: Option<&'static str>,
+    /// The uncompressed contents
     body: &'static [u8],
+    /// The gzip-compressed contents, if compression was enabled and beneficial
     body_gz: Option<&'static [u8]>,
+    /// The zstd-compressed contents, if compression was enabled and beneficial
     body_zst: Option<&'static [u8]>,
+    /// The Brotli-compressed contents, if compression was enabled and beneficial
+    body_br: Option<&'static [u8]>,
+    /// Whether the asset is served with an immutable `Cache-Control`
     cache_busted: bool,
-) -> Router<S>
-where
-    S: Clone + Send + Sync + 'static,
-{
-    router.route(
-        web_path,
-        get(
-            move |accept_encoding: AcceptEncoding,
-                  if_none_match: IfNoneMatch,
-                  http_range: Option<HttpRange>,
-                  if_range: Option<IfRange>| async move {
-                static_inner(StaticInnerData {
-                    content_type,
-                    etag,
-                    body,
-                    body_gz,
-                    body_zst,
-                    cache_busted,
-                    accept_encoding,
-                    if_none_match,
-                    http_range,
-                    if_range,
-                })
-            },
-        ),
-    )
+    /// The requests the asset is also served with an immutable
+    /// `Cache-Control` at, from `cache_bust_query` and `immutable_aliases`
+    immutable_aliases: ImmutableAliases,
+    /// An explicit value of the `Cache-Control` header,
+    /// taking precedence over `cache_busted`
+    cache_control: Option<&'static str>,
+    /// Extra headers sent with the asset, tuples of
+    /// a lowercase header name and of its value
+    headers: &'static [(&'static str, &'static str)],
+    /// The origins allowed to read the asset with CORS requests,
+    /// `*` allowing all of them. Disabled when empty.
+    cors: &'static [&'static str],
+    /// The body embedded only as `body_zst` and decompressed on first access,
+    /// in which case `body` is empty and `body_gz` is derived from it
+    lazy_body: Option<&'static LazyBody>,
+    /// The AVIF and WebP encodings of an image, in order of preference,
+    /// sent instead of it to the clients listing their type in `Accept`
+    image_variants: &'static [StaticAsset],
+    /// The other representations of a data endpoint, e.g. XML next to JSON,
+    /// in order of preference, negotiated with this asset on `Accept`
+    media_variants: &'static [StaticAsset],
+    /// The Subresource Integrity hash of the uncompressed contents,
+    /// e.g. `sha256-...`, with `etag = "sha256"`, `"sha384"` or `"sha512"`
+    integrity: Option<&'static str>,
+    /// The size of the chunks the body is sent in, for the
+    /// large assets streamed with `stream_min_size`
+    stream_chunk_size: Option<NonZeroUsize>,
+    /// The Basic authentication required to read the asset,
+    /// for the files of the subtrees listed in `protected`
+    basic_auth: Option<BasicAuth>,
+    /// Whether the asset is only served to the URLs signed with the key
+    /// of the [`UrlSigner`] extension, for the files listed in `signed`
+    signed: bool,
+    /// The compressed variants sent to the clients accepting several
+    /// encodings with the same quality, in order of preference, from
+    /// `encoding_preference`, the other ones not being sent. When empty,
+    /// zstd is preferred, then Brotli, then gzip.
+    encoding_preference: &'static [ContentEncoding],
+    /// The header or cookie the asset is only served with, for
+    /// the source maps embedded with `source_maps = "header-only"`
+    source_map_gate: Option<SourceMapGate>,
 }
 
-#[doc(hidden)]
-/// Creates a route for a single static asset.
+/// The fields of a [`StaticAsset`], documented on its own private fields
 ///
-/// Used by the `embed_asset!` macro, so it needs to be `pub`.
-pub fn static_method_router<S>(
-    content_type: &'static str,
-    etag: &'static str,
-    body: &'static [u8],
-    body_gz: Option<&'static [u8]>,
-    body_zst: Option<&'static [u8]>,
-    cache_busted: bool,
-) -> MethodRouter<S>
-where
-    S: Clone + Send + Sync + 'static,
-{
-    MethodRouter::get(
-        MethodRouter::new(),
-        move |accept_encoding: AcceptEncoding,
-              if_none_match: IfNoneMatch,
-              http_range: Option<HttpRange>,
-              if_range: Option<IfRange>| async move {
-            static_inner(StaticInnerData {
-                content_type,
-                etag,
-                body,
-                body_gz,
-                body_zst,
-                cache_busted,
-                accept_encoding,
-                if_none_match,
-                http_range,
-                if_range,
-            })
-        },
-    )
+/// Used by the macros to build the assets in a `static` with
+/// [`StaticAsset::from_fields`], so it needs to be `pub`.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy)]
+pub struct StaticAssetFields {
+    pub content_type: &'static str,
+    pub etag: Option<&'static str>,
+    pub last_modified: Option<&'static str>,
+    pub body: &'static [u8],
+    pub body_gz: Option<&'static [u8]>,
+    pub body_zst: Option<&'static [u8]>,
+    pub body_br: Option<&'static [u8]>,
+    pub cache_busted: bool,
+    pub immutable_aliases: ImmutableAliases,
+    pub cache_control: Option<&'static str>,
+    pub headers: &'static [(&'static str, &'static str)],
+    pub cors: &'static [&'static str],
+    pub lazy_body: Option<&'static LazyBody>,
+    pub image_variants: &'static [StaticAsset],
+    pub media_variants: &'static [StaticAsset],
+    pub integrity: Option<&'static str>,
+    pub stream_chunk_size: Option<NonZeroUsize>,
+    pub basic_auth: Option<BasicAuth>,
+    pub signed: bool,
+    pub encoding_preference: &'static [ContentEncoding],
+    pub source_map_gate: Option<SourceMapGate>,
 }
 
-/// Struct of parameters for `static_inner` (to avoid `clippy::too_many_arguments`)
-///
-/// This differs from `StaticRouteData` because it
-/// includes the `AcceptEncoding` and `IfNoneMatch` fields
-/// and excludes the `web_path`
-struct StaticInnerData {
-    content_type: &'static str,
-    etag: &'static str,
-    body: &'static [u8],
-    body_gz: Option<&'static [u8]>,
-    body_zst: Option<&'static [u8]>,
-    cache_busted: bool,
-    accept_encoding: AcceptEncoding,
-    if_none_match: IfNoneMatch,
-    http_range: Option<HttpRange>,
-    if_range: Option<IfRange>,
+/// The requests an asset is served with an immutable `Cache-Control` at,
+/// besides its own route when `cache_busted`, their URL holding the hash
+/// of its contents
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImmutableAliases {
+    /// With a `v` query parameter, from `cache_bust_query`
+    pub query: bool,
+    /// At `/_immutable/<hash>/<route>`, from `immutable_aliases`
+    pub route: bool,
 }
 
-fn static_inner(static_inner_data: StaticInnerData) -> impl IntoResponse {
-    let StaticInnerData {
-        content_type,
-        etag,
-        body,
-        body_gz,
-        body_zst,
-        cache_busted,
-        accept_encoding,
-        if_none_match,
-        http_range,
-        if_range,
-    } = static_inner_data;
-
-    let optional_cache_control = if cache_busted {
-        Some([(
-            CACHE_CONTROL,
-            HeaderValue::from_static("public, max-age=31536000, immutable"),
-        )])
-    } else {
-        None
+impl ImmutableAliases {
+    /// No alias, the asset being immutable at its `cache_busted` route only
+    pub const NONE: Self = Self {
+        query: false,
+        route: false,
     };
 
-    let resp_base = (
-        [
-            (CONTENT_TYPE, HeaderValue::from_static(content_type)),
-            (ETAG, HeaderValue::from_static(etag)),
-            (VARY, HeaderValue::from_static("Accept-Encoding")),
-        ],
-        optional_cache_control,
-    );
-
-    if if_none_match.matches(etag) {
-        return (resp_base, StatusCode::NOT_MODIFIED).into_response();
-    }
-
-    let resp_base = (
-        [(ACCEPT_RANGES, HeaderValue::from_static("bytes"))],
-        resp_base,
-    );
-
-    let http_range = match (http_range, if_range) {
-        (Some(range), Some(if_range)) => {
-            let etag_value = HeaderValue::from_static(etag);
-            if_range.evaluate(range, None, Some(&etag_value))
+    /// Whether a request through the aliases of `requested` reaches one of these
+    pub(crate) const fn matches(self, requested: Self) -> bool {
+        (self.query && requested.query) || (self.route && requested.route)
+    }
+}
+
+impl StaticAsset {
+    #[doc(hidden)]
+    /// The asset made of `fields`
+    ///
+    /// Used by the code generated by the macros, so it needs to be `pub`.
+    #[must_use]
+    pub const fn from_fields(fields: StaticAssetFields) -> Self {
+        let StaticAssetFields {
+            content_type,
+            etag,
+            last_modified,
+            body,
+            body_gz,
+            body_zst,
+            body_br,
+            cache_busted,
+            immutable_aliases,
+            cache_control,
+            headers,
+            cors,
+            lazy_body,
+            image_variants,
+            media_variants,
+            integrity,
+            stream_chunk_size,
+            basic_auth,
+            signed,
+            encoding_preference,
+            source_map_gate,
+        } = fields;
+        Self {
+            content_type,
+            etag,
+            last_modified,
+            body,
+            body_gz,
+            body_zst,
+            body_br,
+            cache_busted,
+            immutable_aliases,
+            cache_control,
+            headers,
+            cors,
+            lazy_body,
+            image_variants,
+            media_variants,
+            integrity,
+            stream_chunk_size,
+            basic_auth,
+            signed,
+            encoding_preference,
+            source_map_gate,
         }
-        (range, _) => range,
-    };
+    }
 
-    let (selected_body, optional_content_encoding) = match (
-        (accept_encoding.gzip, body_gz),
-        (accept_encoding.zstd, body_zst),
-        &http_range,
-    ) {
-        (_, (true, Some(body_zst)), None) => (
-            Bytes::from_static(body_zst),
-            Some([(CONTENT_ENCODING, HeaderValue::from_static("zstd"))]),
-        ),
-        ((true, Some(body_gz)), _, None) => (
-            Bytes::from_static(body_gz),
-            Some([(CONTENT_ENCODING, HeaderValue::from_static("gzip"))]),
-        ),
-        _ => (Bytes::from_static(body), None),
+    #[doc(hidden)]
+    /// The placeholder of the assets excluded by the `cfg` predicates of
+    /// `cfg_gate`, keeping the indices of the table of the assets stable
+    ///
+    /// Used by the functions generated by `embed_assets!`, so it needs to be `pub`.
+    pub const EXCLUDED: Self = Self {
+        content_type: "",
+        etag: None,
+        last_modified: None,
+        body: &[],
+        body_gz: None,
+        body_zst: None,
+        body_br: None,
+        cache_busted: false,
+        immutable_aliases: ImmutableAliases::NONE,
+        cache_control: None,
+        headers: &[],
+        cors: &[],
+        lazy_body: None,
+        image_variants: &[],
+        media_variants: &[],
+        integrity: None,
+        stream_chunk_size: None,
+        basic_auth: None,
+        signed: false,
+        encoding_preference: &[],
+        source_map_gate: None,
     };
 
-    match serve_file_with_http_range(selected_body, http_range) {
-        Ok(body_range) => (resp_base, optional_content_encoding, body_range).into_response(),
-        Err(unsatisfiable) => (resp_base, unsatisfiable).into_response(),
+    /// The uncompressed contents, decompressed on the first call
+    /// when the asset has a `lazy_body`
+    #[must_use]
+    pub fn uncompressed_body(&self) -> &'static [u8] {
+        self.lazy_body.map_or(self.body, LazyBody::body)
+    }
+
+    /// The value of the `Content-Type` header
+    #[must_use]
+    pub const fn content_type(&self) -> &'static str {
+        self.content_type
+    }
+
+    /// The value of the `ETag` header, `None` when conditional
+    /// requests rely on `Last-Modified` only
+    #[must_use]
+    pub const fn etag(&self) -> Option<&'static str> {
+        self.etag
+    }
+
+    /// The value of the `Last-Modified` header, if any
+    #[must_use]
+    pub const fn last_modified(&self) -> Option<&'static str> {
+        self.last_modified
+    }
+
+    /// Whether the asset is served with an immutable `Cache-Control`
+    /// at its own route, e.g. from `cache_busted_paths`
+    #[must_use]
+    pub const fn cache_busted(&self) -> bool {
+        self.cache_busted
+    }
+
+    /// The contents compressed with `content_encoding`, if embedded,
+    /// compressing the lazy body with gzip on the first call
+    #[must_use]
+    pub fn compressed_body(&self, content_encoding: ContentEncoding) -> Option<&'static [u8]> {
+        self.variant(content_encoding)
+    }
+
+    /// The Subresource Integrity hash of the uncompressed contents,
+    /// e.g. `sha256-...`, with `etag = "sha256"`, `"sha384"` or `"sha512"`
+    #[must_use]
+    pub const fn integrity(&self) -> Option<&'static str> {
+        self.integrity
+    }
+
+    /// The AVIF and WebP encodings of an image, in order of preference
+    #[must_use]
+    pub const fn image_variants(&self) -> &'static [Self] {
+        self.image_variants
+    }
+
+    /// The other representations of a data endpoint, in order of preference
+    #[must_use]
+    pub const fn media_variants(&self) -> &'static [Self] {
+        self.media_variants
+    }
+
+    /// Whether the asset can be sent with `content_encoding`
+    fn has_variant(&self, content_encoding: ContentEncoding) -> bool {
+        match content_encoding {
+            ContentEncoding::Zstd => self.body_zst.is_some(),
+            ContentEncoding::Brotli => self.body_br.is_some(),
+            ContentEncoding::Gzip => {
+                self.body_gz.is_some() || self.lazy_body.is_some_and(LazyBody::has_gzip)
+            }
+        }
+    }
+
+    /// The contents compressed with `content_encoding`, if available
+    fn variant(&self, content_encoding: ContentEncoding) -> Option<&'static [u8]> {
+        match content_encoding {
+            ContentEncoding::Zstd => self.body_zst,
+            ContentEncoding::Brotli => self.body_br,
+            ContentEncoding::Gzip => self
+                .body_gz
+                .or_else(|| self.lazy_body.and_then(LazyBody::body_gz)),
+        }
+    }
+
+    /// The image variant whose type has the highest quality value in the
+    /// `Accept` header, preferring the first ones among equal values, or
+    /// this asset when none is listed with at least its own quality value
+    ///
+    /// Wildcards such as `image/*` are ignored for the variants, since
+    /// browsers send them without supporting every image format, while
+    /// this asset gets the quality of the most specific range matching it.
+    fn negotiate_image(&'static self, accept: Option<&HeaderValue>) -> &'static Self {
+        let Some(accept) = accept.and_then(|accept| accept.to_str().ok()) else {
+            return self;
+        };
+
+        let mut negotiated = self;
+        let mut best_quality = 0.0;
+        let original_quality = media_quality(accept, self.content_type);
+        for variant in self.image_variants {
+            let media_type = variant
+                .content_type
+                .split(';')
+                .next()
+                .unwrap_or_default()
+                .trim();
+            let quality = weighted_elements(accept)
+                .filter(|(name, _)| name.eq_ignore_ascii_case(media_type))
+                .map(|(_, quality)| quality)
+                .fold(0.0, f32::max);
+            if quality > best_quality && quality >= original_quality {
+                negotiated = variant;
+                best_quality = quality;
+            }
+        }
+        negotiated
+    }
+
+    /// The representation among this asset and its media variants whose
+    /// type has the highest quality value in the `Accept` header,
+    /// preferring this asset, then the first variants, among equal values
+    ///
+    /// Each type gets the quality of the most specific media range matching
+    /// it, e.g. `application/json`, then `application/*`, then `*/*`. This
+    /// asset is sent when the header is missing or accepts none of them.
+    fn negotiate_media(&'static self, accept: Option<&HeaderValue>) -> &'static Self {
+        let Some(accept) = accept.and_then(|accept| accept.to_str().ok()) else {
+            return self;
+        };
+
+        let mut negotiated = self;
+        let mut best_quality = media_quality(accept, self.content_type);
+        for variant in self.media_variants {
+            let quality = media_quality(accept, variant.content_type);
+            if quality > best_quality {
+                negotiated = variant;
+                best_quality = quality;
+            }
+        }
+        negotiated
+    }
+
+    /// Whether the asset has a compressed variant, in which case
+    /// the response varies with the `Accept-Encoding` of the request
+    fn has_compressed_variant(&self) -> bool {
+        [
+            ContentEncoding::Zstd,
+            ContentEncoding::Brotli,
+            ContentEncoding::Gzip,
+        ]
+        .into_iter()
+        .any(|content_encoding| self.has_variant(content_encoding))
+    }
+
+    /// The encoding of the body to send to a client accepting
+    /// `accept_encoding`: the embedded variant with the highest quality
+    /// value, preferring the first ones of `encoding_preference` among
+    /// equal ones, or `None` for the uncompressed body
+    ///
+    /// `NotAcceptable` when the client accepts neither the uncompressed
+    /// body, with `identity;q=0`, nor any of the variants. No lazy
+    /// body is decompressed or compressed to negotiate it.
+    ///
+    /// The `configured` encodings, from `StaticServeConfig::encodings`,
+    /// replace the `encoding_preference`.
+    fn negotiate_encoding(
+        &self,
+        accept_encoding: AcceptEncoding,
+        configured: Option<&[ContentEncoding]>,
+    ) -> Result<Option<ContentEncoding>, NotAcceptable> {
+        let preference = match configured {
+            Some(configured) => configured,
+            None if self.encoding_preference.is_empty() => DEFAULT_ENCODING_PREFERENCE.as_slice(),
+            None => self.encoding_preference,
+        };
+        accept_encoding.negotiate(preference, |content_encoding| {
+            self.has_variant(content_encoding)
+        })
+    }
+
+    /// The body sent with `content_encoding`, decompressing or
+    /// compressing the lazy body on the first call
+    fn encoded_body(&self, content_encoding: Option<ContentEncoding>) -> &'static [u8] {
+        content_encoding
+            .and_then(|content_encoding| self.variant(content_encoding))
+            .unwrap_or_else(|| self.uncompressed_body())
+    }
+
+    /// The length of the body sent with `content_encoding`, if known
+    /// without decompressing or compressing the lazy body
+    fn encoded_len(&self, content_encoding: Option<ContentEncoding>) -> Option<usize> {
+        match content_encoding {
+            None => Some(self.lazy_body.map_or(self.body.len(), LazyBody::size)),
+            Some(ContentEncoding::Gzip) if self.body_gz.is_none() => {
+                self.lazy_body.and_then(LazyBody::gzip_len)
+            }
+            Some(content_encoding) => self.variant(content_encoding).map(<[u8]>::len),
+        }
+    }
+
+    /// The body to send to a client accepting `accept_encoding`,
+    /// with its encoding, see `negotiate_encoding`
+    fn negotiate_body(
+        &self,
+        accept_encoding: AcceptEncoding,
+        configured: Option<&[ContentEncoding]>,
+    ) -> Option<(&'static [u8], Option<ContentEncoding>)> {
+        self.negotiate_encoding(accept_encoding, configured)
+            .ok()
+            .map(|content_encoding| (self.encoded_body(content_encoding), content_encoding))
+    }
+
+    /// Negotiate the representation of this asset sent in response
+    /// to a request with `headers`, for the servers which don't use the
+    /// axum backend, e.g. in workers or WASI HTTP servers on `wasm32`
+    ///
+    /// `None` when the client accepts none of the available encodings,
+    /// which is answered with `406 Not Acceptable`. The headers of the
+    /// response are built with [`headers::ResponseHeadersBuilder`].
+    ///
+    /// The `basic_auth` of the protected assets is checked by the caller,
+    /// with [`Credentials::is_authorized`] on the credentials read once by
+    /// [`BasicAuth::credentials`], as is the URL of the signed
+    /// assets, with [`UrlSigner::verify`], and the header or cookie of the
+    /// hidden source maps, with [`SourceMapGate::reveals`].
+    #[must_use]
+    pub fn negotiate(&'static self, headers: &HeaderMap) -> Option<Negotiated> {
+        let accept = headers.get(ACCEPT);
+        let asset = self.negotiate_media(accept).negotiate_image(accept);
+        let (body, content_encoding) =
+            asset.negotiate_body(AcceptEncoding::from_headers(headers), None)?;
+        Some(Negotiated {
+            asset,
+            body,
+            content_encoding,
+        })
+    }
+}
+
+/// The representation of an asset negotiated by [`StaticAsset::negotiate`]
+#[derive(Debug, Clone, Copy)]
+pub struct Negotiated {
+    /// The asset sent: the negotiated one, or its image
+    /// or media variant preferred by the `Accept` header
+    pub asset: &'static StaticAsset,
+    /// The body sent, compressed with `content_encoding`
+    pub body: &'static [u8],
+    /// The `Content-Encoding` of the body, `None` when uncompressed
+    pub content_encoding: Option<ContentEncoding>,
+}
+
+/// The elements of a header listing weighted values, such as `Accept` or
+/// `Accept-Encoding`, as tuples of their name and of their quality value
+/// (`q=`, defaulting to 1), the ones with an invalid quality being ignored
+fn weighted_elements(header: &str) -> impl Iterator<Item = (&str, f32)> {
+    header.split(',').filter_map(|element| {
+        let mut params = element.split(';');
+        let name = params.next().unwrap_or_default().trim();
+        let quality = params
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .map_or(Some(1.0), |quality| quality.trim().parse::<f32>().ok())
+            .filter(|quality| (0.0..=1.0).contains(quality))?;
+        Some((name, quality))
+    })
+}
+
+/// The quality value given to `content_type` by the most specific
+/// media range of an `Accept` header matching it, 0 when none does
+fn media_quality(accept: &str, content_type: &str) -> f32 {
+    let media_type = content_type.split(';').next().unwrap_or_default().trim();
+    let main_type = media_type.split('/').next().unwrap_or_default();
+
+    let mut best = None;
+    for (name, quality) in weighted_elements(accept) {
+        let specificity = if name.eq_ignore_ascii_case(media_type) {
+            2
+        } else if name
+            .strip_suffix("/*")
+            .is_some_and(|range_type| range_type.eq_ignore_ascii_case(main_type))
+        {
+            1
+        } else if name == "*/*" {
+            0
+        } else {
+            continue;
+        };
+        if best.is_none_or(|(best_specificity, _)| specificity > best_specificity) {
+            best = Some((specificity, quality));
+        }
     }
+    best.map_or(0.0, |(_, quality)| quality)
 }
@@ -0,0 +1,48 @@
+//! Lookup of the assets by route in the perfect hash table
+//! generated by `embed_assets!`, so that `get_asset` doesn't
+//! compare the route with every embedded one
+
+/// The hash of `route` with `seed`
+///
+/// Must stay in sync with the one of `static-serve-macro`,
+/// which builds the table at compile time.
+const fn route_hash(route: &str, seed: u64) -> u64 {
+    let bytes = route.as_bytes();
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ seed;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+        i += 1;
+    }
+    mix(hash)
+}
+
+/// The splitmix64 finalizer, spreading the bits of `hash`
+const fn mix(mut hash: u64) -> u64 {
+    hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
+#[doc(hidden)]
+/// The index of the asset which may be served at `route`, found in the
+/// `slots` with the `displacements` of their buckets, which the caller
+/// still needs to compare with `route`
+///
+/// Used by the code generated by `embed_assets!`, so it needs to be `pub`.
+#[must_use]
+#[expect(clippy::cast_possible_truncation)]
+pub const fn perfect_hash_index(
+    route: &str,
+    seed: u64,
+    displacements: &[u32],
+    slots: &[usize],
+) -> Option<usize> {
+    if slots.is_empty() {
+        return None;
+    }
+    let hash = route_hash(route, seed);
+    let displacement = displacements[(hash % displacements.len() as u64) as usize];
+    Some(slots[(mix(hash ^ displacement as u64) % slots.len() as u64) as usize])
+}
@@ -0,0 +1,160 @@
+//! Metrics of the requests answered with the embedded assets,
+//! reported to the `AssetMetrics` installed by the application
+
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::{self, Display, Formatter, Write as _},
+    sync::{Mutex, OnceLock},
+};
+
+use http::StatusCode;
+
+use crate::headers::ContentEncoding;
+
+static ASSET_METRICS: OnceLock<&'static dyn AssetMetrics> = OnceLock::new();
+
+/// A request answered with an embedded asset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServedAsset<'a> {
+    /// The path of the request
+    pub path: &'a str,
+    /// The status of the response, e.g. `304 Not Modified`
+    pub status: StatusCode,
+    /// The `Content-Encoding` of the body, `None` for the identity
+    pub encoding: Option<ContentEncoding>,
+    /// The size of the body sent, in bytes
+    pub size: usize,
+}
+
+/// A receiver of the metrics of the requests answered with the embedded
+/// assets, installed with [`set_asset_metrics`]
+///
+/// [`AssetCounters`] counts them in memory, and exports them to
+/// Prometheus. Other implementations can feed any metrics library.
+pub trait AssetMetrics: Send + Sync {
+    /// Record that a request was answered with an embedded asset
+    fn record(&self, served: &ServedAsset<'_>);
+}
+
+/// The error returned by [`set_asset_metrics`] when
+/// the metrics were installed already
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetAssetMetricsError;
+
+impl Display for SetAssetMetricsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("The asset metrics are already set")
+    }
+}
+
+impl Error for SetAssetMetricsError {}
+
+/// Install the `metrics` recording every request answered with an
+/// embedded asset, by the generated routes or by `StaticAsset::respond_to`
+///
+/// ```rust
+/// use static_serve::{AssetCounters, set_asset_metrics};
+///
+/// static ASSET_COUNTERS: AssetCounters = AssetCounters::new();
+///
+/// set_asset_metrics(&ASSET_COUNTERS).unwrap();
+/// // Served from a `/metrics` route
+/// let exposition = ASSET_COUNTERS.to_prometheus();
+/// ```
+///
+/// # Errors
+///
+/// The metrics can only be installed once.
+pub fn set_asset_metrics(metrics: &'static dyn AssetMetrics) -> Result<(), SetAssetMetricsError> {
+    ASSET_METRICS.set(metrics).map_err(|_| SetAssetMetricsError)
+}
+
+/// Report `served` to the installed metrics, if any
+pub(crate) fn record(served: &ServedAsset<'_>) {
+    if let Some(metrics) = ASSET_METRICS.get() {
+        metrics.record(served);
+    }
+}
+
+/// The counts of the requests with the same path, status and encoding
+#[derive(Debug, Default, Clone, Copy)]
+struct Count {
+    requests: u64,
+    bytes: u64,
+}
+
+/// [`AssetMetrics`] counting the requests and the bytes sent
+/// by path, status and encoding, in memory
+#[derive(Debug, Default)]
+pub struct AssetCounters {
+    counts: Mutex<BTreeMap<(String, u16, &'static str), Count>>,
+}
+
+impl AssetCounters {
+    /// Counters without any request yet, usable in a `static`
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            counts: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Render the counters in the Prometheus text exposition format,
+    /// as the `static_serve_requests_total` and `static_serve_sent_bytes_total`
+    /// counters labelled with the `path`, `status` and `encoding` of the requests
+    ///
+    /// # Panics
+    ///
+    /// Panics if a thread panicked while recording a request.
+    #[must_use]
+    pub fn to_prometheus(&self) -> String {
+        let counts = self.counts.lock().unwrap();
+        let mut exposition = String::new();
+        for (name, help, value) in [
+            (
+                "static_serve_requests_total",
+                "Requests answered with an embedded asset",
+                (|count: &Count| count.requests) as fn(&Count) -> u64,
+            ),
+            (
+                "static_serve_sent_bytes_total",
+                "Bytes of the bodies of the embedded assets sent",
+                |count: &Count| count.bytes,
+            ),
+        ] {
+            writeln!(exposition, "# HELP {name} {help}\n# TYPE {name} counter")
+                .expect("writing to a String cannot fail");
+            for ((path, status, encoding), count) in counts.iter() {
+                writeln!(
+                    exposition,
+                    "{name}{{path=\"{}\",status=\"{status}\",encoding=\"{encoding}\"}} {}",
+                    escape_label(path),
+                    value(count),
+                )
+                .expect("writing to a String cannot fail");
+            }
+        }
+        exposition
+    }
+}
+
+impl AssetMetrics for AssetCounters {
+    fn record(&self, served: &ServedAsset<'_>) {
+        let encoding = served.encoding.map_or("identity", ContentEncoding::as_str);
+        let mut counts = self.counts.lock().unwrap();
+        let count = counts
+            .entry((served.path.to_owned(), served.status.as_u16(), encoding))
+            .or_default();
+        count.requests += 1;
+        count.bytes += served.size as u64;
+    }
+}
+
+/// Escape `value` as the value of a Prometheus label
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
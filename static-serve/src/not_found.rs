@@ -0,0 +1,223 @@
+//! Fallbacks of the unmatched routes: content-negotiated `404 Not Found`
+//! responses, or the entry point of a single-page application
+
+#[cfg(feature = "axum")]
+use std::{convert::Infallible, future};
+
+#[cfg(feature = "axum")]
+use axum::{Router, body::Body, extract::FromRequestParts};
+use bytes::Bytes;
+use http::{
+    HeaderMap, Method, Response, StatusCode,
+    header::{ACCEPT, CONTENT_ENCODING, CONTENT_TYPE, HeaderValue, VARY},
+    request::Parts,
+};
+
+#[cfg(feature = "axum")]
+use crate::Fallback;
+use crate::{
+    AcceptEncoding, Credentials, StaticAsset, StaticBody, UrlSigner,
+    config::RouteSettings,
+    respond::{empty_response, respond, without_body},
+};
+
+/// A problem details object ([RFC 9457](https://www.rfc-editor.org/rfc/rfc9457))
+/// describing the missing resource
+const PROBLEM_DETAILS: &str = r#"{"type":"about:blank","title":"Not Found","status":404}"#;
+
+/// Whether the client prefers an HTML page over a JSON body,
+/// according to the `Accept` header
+#[cfg(feature = "axum")]
+#[derive(Debug)]
+struct AcceptsHtml(bool);
+
+#[cfg(feature = "axum")]
+impl<S> FromRequestParts<S> for AcceptsHtml
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> {
+        future::ready(Ok(Self(accepts_html(&parts.headers))))
+    }
+}
+
+/// Whether the client sending `headers` prefers an HTML page
+/// over a JSON body, according to the `Accept` header
+pub(crate) fn accepts_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(prefers_html)
+}
+
+/// Compare the quality values of `text/html` and of the JSON media types
+///
+/// Wildcards are ignored: browsers list `text/html` explicitly,
+/// while API clients commonly send `*/*`.
+fn prefers_html(accept: &str) -> bool {
+    let mut html_quality = 0.0_f32;
+    let mut json_quality = 0.0_f32;
+    for media_range in accept.split(',') {
+        let mut params = media_range.split(';');
+        let media_type = params.next().unwrap_or_default().trim();
+        let quality = params
+            .find_map(|param| param.trim().strip_prefix("q="))
+            .and_then(|quality| quality.parse().ok())
+            .unwrap_or(1.0);
+
+        if media_type.eq_ignore_ascii_case("text/html") {
+            html_quality = html_quality.max(quality);
+        } else if media_type.eq_ignore_ascii_case("application/json")
+            || media_type.eq_ignore_ascii_case("application/problem+json")
+        {
+            json_quality = json_quality.max(quality);
+        }
+    }
+
+    html_quality > 0.0 && html_quality >= json_quality
+}
+
+#[doc(hidden)]
+/// Set the fallback of `router`, responding with `404 Not Found`
+/// and the `not_found` page to browsers, or a problem details
+/// JSON object to the other clients.
+///
+/// Used by the `embed_assets!` macro, so it needs to be `pub`.
+#[cfg(feature = "axum")]
+pub fn static_fallback<S>(router: Router<S>, not_found: &'static StaticAsset) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fallback_router(router, Fallback::NotFound(not_found), None)
+}
+
+#[doc(hidden)]
+/// Set the fallback of `router`, responding to `GET` and `HEAD`
+/// requests with the `index` page of a single-page application,
+/// whose client-side router handles the path
+///
+/// The page is served like from its own route, with conditional
+/// and range requests. Other methods get a `404 Not Found`.
+///
+/// Used by the `embed_assets!` macro, so it needs to be `pub`.
+#[cfg(feature = "axum")]
+pub fn spa_fallback<S>(router: Router<S>, index: &'static StaticAsset) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    fallback_router(router, Fallback::Spa(index), None)
+}
+
+/// Set `fallback` as the fallback of `router`, served with the `settings`
+/// of its route from `static_router_with_config`, if any
+#[cfg(feature = "axum")]
+pub(crate) fn fallback_router<S>(
+    router: Router<S>,
+    fallback: Fallback,
+    settings: Option<RouteSettings>,
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    match fallback {
+        Fallback::NotFound(not_found) => router.fallback(
+            move |accepts_html: AcceptsHtml, accept_encoding: AcceptEncoding| async move {
+                not_found_inner(
+                    not_found,
+                    accepts_html.0,
+                    accept_encoding,
+                    settings.as_ref(),
+                )
+                .map(Body::new)
+            },
+        ),
+        Fallback::Spa(index) => {
+            let credentials = Credentials::of(index);
+            router.fallback(move |mut parts: Parts| async move {
+                if let Some(settings) = settings {
+                    parts.extensions.insert(settings);
+                }
+                spa_inner(index, &parts, parts.extensions.get(), credentials.as_ref())
+                    .map(Body::new)
+            })
+        }
+    }
+}
+
+/// The response to the request described by `parts` with the `index`
+/// page of a single-page application for `GET` and `HEAD`, its URL
+/// being signed with the key of `url_signer` and its readers holding
+/// the `credentials` read for it, if any, or with an empty
+/// `404 Not Found` for the other methods
+pub(crate) fn spa_inner(
+    index: &'static StaticAsset,
+    parts: &Parts,
+    url_signer: Option<&UrlSigner>,
+    credentials: Option<&Credentials>,
+) -> Response<StaticBody> {
+    match parts.method {
+        Method::GET => respond(index, parts, url_signer, credentials),
+        Method::HEAD => without_body(respond(index, parts, url_signer, credentials)),
+        _ => empty_response(StatusCode::NOT_FOUND, HeaderMap::new()),
+    }
+}
+
+/// The `404 Not Found` response with the `not_found` page to the clients
+/// which `accepts_html`, or with a problem details JSON object otherwise,
+/// compressed with the encodings of its `settings`, if any
+pub(crate) fn not_found_inner(
+    not_found: &'static StaticAsset,
+    accepts_html: bool,
+    accept_encoding: AcceptEncoding,
+    settings: Option<&RouteSettings>,
+) -> Response<StaticBody> {
+    let mut headers = HeaderMap::new();
+    headers.insert(VARY, HeaderValue::from_static("Accept, Accept-Encoding"));
+
+    let body = if accepts_html {
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static(not_found.content_type),
+        );
+        // The page is sent even to the clients refusing every encoding of it
+        let (selected_body, content_encoding) = not_found
+            .negotiate_body(accept_encoding, RouteSettings::encodings(settings))
+            .unwrap_or((not_found.uncompressed_body(), None));
+        if let Some(content_encoding) = content_encoding {
+            headers.insert(CONTENT_ENCODING, content_encoding.header_value());
+        }
+        Bytes::from_static(selected_body)
+    } else {
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        Bytes::from_static(PROBLEM_DETAILS.as_bytes())
+    };
+
+    let mut response = Response::new(StaticBody::full(body));
+    *response.status_mut() = StatusCode::NOT_FOUND;
+    *response.headers_mut() = headers;
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::prefers_html;
+
+    #[test]
+    fn negotiates_html_or_json() {
+        assert!(prefers_html(
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"
+        ));
+        assert!(!prefers_html("*/*"));
+        assert!(!prefers_html("application/json"));
+        assert!(!prefers_html("text/html;q=0.5, application/json"));
+        assert!(!prefers_html("text/html;q=0"));
+    }
+}
@@ -0,0 +1,68 @@
+//! The `304 Not Modified` responses of the assets
+//!
+//! A `304` has no body, but its `Content-Length`, if any, is the one the
+//! `200 OK` response would have had (RFC 9110, section 8.6). Left unset,
+//! axum would fill it from the empty body with `0`, so it's set explicitly
+//! to the length of the representation selected for the request, when
+//! known without decompressing or compressing its lazy body, and the body
+//! has no exact length otherwise.
+
+use http::{
+    HeaderMap, HeaderValue, Response, StatusCode,
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+};
+
+use crate::{StaticBody, respond::empty_response};
+
+/// The `304 Not Modified` response with the `headers` built by
+/// `ResponseHeadersBuilder::build_not_modified`, for a representation
+/// of `content_length` bytes, if known
+pub(crate) fn not_modified(
+    mut headers: HeaderMap,
+    content_length: Option<usize>,
+) -> Response<StaticBody> {
+    debug_assert!(!headers.contains_key(CONTENT_TYPE));
+    let Some(content_length) = content_length else {
+        let mut response = Response::new(StaticBody::unsized_empty());
+        *response.status_mut() = StatusCode::NOT_MODIFIED;
+        *response.headers_mut() = headers;
+        return response;
+    };
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(content_length));
+    empty_response(StatusCode::NOT_MODIFIED, headers)
+}
+
+#[cfg(test)]
+mod test {
+    use http::{
+        HeaderValue, StatusCode,
+        header::{CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, ETAG, VARY},
+    };
+    use http_body::Body as _;
+
+    use super::not_modified;
+    use crate::headers::{ContentEncoding, ResponseHeadersBuilder};
+
+    #[test]
+    fn sends_validators_without_body_headers() {
+        let headers = ResponseHeadersBuilder::new(HeaderValue::from_static("text/javascript"))
+            .etag(HeaderValue::from_static("\"1234567890abcdef\""))
+            .cache_busted(true)
+            .content_encoding(ContentEncoding::Zstd)
+            .build_not_modified();
+        let response = not_modified(headers, Some(1234));
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        let headers = response.headers();
+        assert_eq!(headers[ETAG], "\"1234567890abcdef\"");
+        assert_eq!(
+            headers[CACHE_CONTROL],
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(headers[VARY], "Accept-Encoding");
+        assert_eq!(headers[CONTENT_LENGTH], "1234");
+        assert!(!headers.contains_key(CONTENT_TYPE));
+        assert!(!headers.contains_key(CONTENT_ENCODING));
+        assert_eq!(response.body().size_hint().exact(), Some(0));
+    }
+}
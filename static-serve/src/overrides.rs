@@ -0,0 +1,102 @@
+//! Replacement of embedded assets with fixtures in tests
+
+use std::collections::HashMap;
+
+use crate::{ImmutableAliases, StaticAsset, etag::content_etag};
+
+/// Replacement contents for some of the assets embedded by `embed_assets!`
+///
+/// Pass it to the `static_router_with_overrides` function generated with
+/// the `test-overrides` feature to exercise the real router in tests while
+/// controlling the payloads, e.g. with tiny stand-ins for large media files:
+///
+/// ```rust,ignore
+/// let overrides = AssetOverrides::new().replace("/video.mp4", b"not really a video".to_vec());
+/// let router: Router<()> = static_router_with_overrides(&overrides);
+/// ```
+///
+/// The replaced assets keep their content type and caching headers,
+/// but are served uncompressed with an `ETag` computed from the new contents.
+/// Their contents are leaked, which is why the feature is only meant to be
+/// enabled in the `dev-dependencies`.
+#[derive(Debug, Default, Clone)]
+pub struct AssetOverrides {
+    bodies: HashMap<String, Vec<u8>>,
+}
+
+impl AssetOverrides {
+    /// Create an empty set of overrides
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `body` instead of the embedded contents of the asset at `web_path`
+    #[must_use]
+    pub fn replace(mut self, web_path: impl Into<String>, body: impl Into<Vec<u8>>) -> Self {
+        self.bodies.insert(web_path.into(), body.into());
+        self
+    }
+}
+
+#[doc(hidden)]
+/// Substitute the overridden assets in the table of embedded `assets`
+///
+/// Used by the `static_router_with_overrides` function generated
+/// by `embed_assets!`, so it needs to be `pub`.
+///
+/// # Panics
+///
+/// Panics if an override targets a route which isn't embedded,
+/// so that tests don't silently exercise the original contents.
+#[must_use]
+pub fn apply_overrides(
+    assets: &'static [(&'static str, StaticAsset)],
+    overrides: &AssetOverrides,
+) -> Vec<(&'static str, &'static StaticAsset)> {
+    if let Some(unknown) = overrides
+        .bodies
+        .keys()
+        .find(|web_path| !assets.iter().any(|(path, _)| path == web_path))
+    {
+        panic!("Cannot override {unknown}: no asset is embedded at this route");
+    }
+
+    assets
+        .iter()
+        .map(|(web_path, asset)| {
+            let Some(body) = overrides.bodies.get(*web_path) else {
+                return (*web_path, asset);
+            };
+
+            let overridden: &'static StaticAsset = Box::leak(Box::new(StaticAsset {
+                content_type: asset.content_type,
+                etag: Some(content_etag(body).leak()),
+                last_modified: None,
+                body: body.clone().leak(),
+                body_gz: None,
+                body_zst: None,
+                body_br: None,
+                cache_busted: asset.cache_busted,
+                // The `/_immutable/` alias holds the hash of the embedded contents
+                immutable_aliases: ImmutableAliases {
+                    route: false,
+                    ..asset.immutable_aliases
+                },
+                cache_control: asset.cache_control,
+                headers: asset.headers,
+                cors: asset.cors,
+                lazy_body: None,
+                image_variants: &[],
+                media_variants: &[],
+                integrity: None,
+                stream_chunk_size: asset.stream_chunk_size,
+                basic_auth: asset.basic_auth,
+                signed: asset.signed,
+                encoding_preference: asset.encoding_preference,
+                source_map_gate: asset.source_map_gate,
+            }));
+            (*web_path, overridden)
+        })
+        .collect()
+}
@@ -0,0 +1,134 @@
+//! Percent-encoding of the routes generated by `embed_assets!`, so that
+//! files with spaces, `#`, `%` or non-ASCII characters in their names
+//! get routes matching the paths sent by clients, and matching of the
+//! request paths with them
+//!
+//! Shared with `static-serve-macro`, which percent-encodes the routes
+//! at compile time, so that both crates spell them the same way.
+
+use std::{
+    borrow::Cow,
+    fmt::Write as _,
+    path::{Component, Path},
+};
+
+/// Whether `byte` is left as is in a route: the unreserved characters,
+/// the sub-delimiters, `:`, `@` and the `/` separating the segments
+fn is_route_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/".contains(&byte)
+}
+
+/// The value of the two hexadecimal digits at the start of `hex`
+fn hex_byte(hex: &[u8]) -> Option<u8> {
+    let digit = |digit: u8| char::from(digit).to_digit(16);
+    match hex {
+        [high, low, ..] => Some(u8::try_from(digit(*high)? << 4 | digit(*low)?).ok()?),
+        _ => None,
+    }
+}
+
+/// Percent-encode the bytes of `route` which can't appear in the path of
+/// a URL, in uppercase, its `%` signs included
+pub(crate) fn encode_route(route: &[u8]) -> String {
+    let mut encoded = String::with_capacity(route.len());
+    for &byte in route {
+        if is_route_byte(byte) {
+            encoded.push(char::from(byte));
+        } else {
+            write!(encoded, "%{byte:02X}").expect("writing to a String cannot fail");
+        }
+    }
+    encoded
+}
+
+/// The bytes of `path` with its percent-encoded bytes decoded,
+/// the `%` signs not followed by two hexadecimal digits being kept
+fn decode(path: &str) -> Vec<u8> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let (byte, len) = match bytes[i] {
+            b'%' => hex_byte(&bytes[i + 1..]).map_or((b'%', 1), |byte| (byte, 3)),
+            byte => (byte, 1),
+        };
+        decoded.push(byte);
+        i += len;
+    }
+    decoded
+}
+
+/// The percent-encoded route of the file at `relative_path`,
+/// whose name doesn't need to be valid UTF-8
+pub(crate) fn web_route(relative_path: &Path) -> String {
+    let mut route = Vec::new();
+    for component in relative_path.components() {
+        if let Component::Normal(segment) = component {
+            route.push(b'/');
+            route.extend_from_slice(segment.as_encoded_bytes());
+        }
+    }
+    if route.is_empty() {
+        route.push(b'/');
+    }
+    encode_route(&route)
+}
+
+/// The spelling of `path` used by the routes, with only the bytes which
+/// can't appear in a URL path percent-encoded, in uppercase
+///
+/// The routes are matched against this spelling, so that `/caf%c3%a9`,
+/// `/caf%C3%A9` and the decoded `/café` all match the same route.
+pub(crate) fn canonical_path(path: &str) -> Cow<'_, str> {
+    if path.bytes().all(is_route_byte) {
+        return Cow::Borrowed(path);
+    }
+    Cow::Owned(encode_route(&decode(path)))
+}
+
+/// `path` with its percent-encoded bytes decoded,
+/// if they form valid UTF-8
+pub(crate) fn decode_path(path: &str) -> Option<String> {
+    String::from_utf8(decode(path)).ok()
+}
+
+/// Decode the percent-encoded bytes of `route`, for display
+pub(crate) fn decode_route(route: &str) -> Cow<'_, str> {
+    if !route.contains('%') {
+        return Cow::Borrowed(route);
+    }
+    Cow::Owned(String::from_utf8_lossy(&decode(route)).into_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use super::{canonical_path, decode_path, decode_route, web_route};
+
+    #[test]
+    fn encodes_special_characters_in_routes() {
+        assert_eq!(web_route(Path::new("sub/app.js")), "/sub/app.js");
+        assert_eq!(
+            web_route(Path::new("my file #1 (100%).txt")),
+            "/my%20file%20%231%20(100%25).txt"
+        );
+        assert_eq!(
+            web_route(Path::new("café/naïve?.html")),
+            "/caf%C3%A9/na%C3%AFve%3F.html"
+        );
+        assert_eq!(decode_route("/caf%C3%A9/100%25%2"), "/café/100%%2");
+        assert_eq!(decode_path("/caf%C3%A9"), Some("/café".to_owned()));
+        assert_eq!(decode_path("/caf%E9"), None);
+    }
+
+    #[test]
+    fn canonicalizes_percent_encoding() {
+        assert_eq!(canonical_path("/sub/app.js"), "/sub/app.js");
+        assert_eq!(canonical_path("/my file #1.txt"), "/my%20file%20%231.txt");
+        assert_eq!(canonical_path("/my%20file%2"), "/my%20file%252");
+        assert_eq!(canonical_path("/caf%c3%a9"), "/caf%C3%A9");
+        assert_eq!(canonical_path("/café"), "/caf%C3%A9");
+        assert_eq!(canonical_path("/%61pp.js"), "/app.js");
+    }
+}
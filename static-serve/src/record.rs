@@ -0,0 +1,59 @@
+//! Recording of the requests answered with the embedded assets: in a
+//! `tracing` span with the `tracing` feature, and to the `AssetMetrics`
+
+use http::{StatusCode, Uri};
+
+use crate::{
+    headers::ContentEncoding,
+    metrics::{self, ServedAsset},
+};
+
+/// The record of a request to an embedded asset, with the path of the
+/// request, and once answered, the status, encoding and size of the body
+pub(crate) struct RequestRecord {
+    uri: Uri,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl RequestRecord {
+    /// Start the record of a request to `uri`, in the current span
+    pub(crate) fn new(uri: Uri) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "static_serve",
+                path = uri.path(),
+                status = tracing::field::Empty,
+                encoding = tracing::field::Empty,
+                size = tracing::field::Empty,
+            ),
+            uri,
+        }
+    }
+
+    /// Record the response with the `status`, the `content_encoding`
+    /// of the body and its `size` in bytes, empty for `304 Not Modified`
+    pub(crate) fn finish(
+        &self,
+        status: StatusCode,
+        content_encoding: Option<ContentEncoding>,
+        size: usize,
+    ) {
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("status", status.as_u16());
+            self.span.record(
+                "encoding",
+                content_encoding.map_or("identity", ContentEncoding::as_str),
+            );
+            self.span.record("size", size);
+        }
+        metrics::record(&ServedAsset {
+            path: self.uri.path(),
+            status,
+            encoding: content_encoding,
+            size,
+        });
+    }
+}
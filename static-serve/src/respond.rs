@@ -0,0 +1,515 @@
+//! The responses of the embedded assets, built with the `http` types so
+//! that every backend, axum or not, answers the requests the same way
+
+use std::{num::NonZeroUsize, sync::Arc};
+
+use bytes::Bytes;
+use http::{
+    HeaderMap, Method, Response, StatusCode, Uri,
+    header::{
+        ACCEPT, ALLOW, AUTHORIZATION, CONTENT_LENGTH, CONTENT_RANGE, HeaderValue, IF_RANGE, ORIGIN,
+        RANGE, VARY, WWW_AUTHENTICATE,
+    },
+    request::Parts,
+};
+use http_body::Body as _;
+use range_requests::{
+    headers::{if_range::IfRange, range::HttpRange},
+    serve_file_with_http_range,
+};
+
+use crate::{
+    AcceptEncoding, BasicAuth, ChunkedBody, Credentials, ImmutableAliases, NotAcceptable,
+    StaticAsset, StaticBody, UrlSigner,
+    conditional::{Outcome, Preconditions},
+    config::RouteSettings,
+    headers::{ALLOWED_METHODS, ContentEncoding, ResponseHeadersBuilder, preflight_headers},
+    not_modified::not_modified,
+    record::RequestRecord,
+    signed::requested_uri,
+};
+
+/// The `Cache-Control` of the protected and signed assets without an explicit one
+const PRIVATE: &str = "private, no-cache";
+
+/// The start of the aliases of the assets generated by `immutable_aliases`
+const IMMUTABLE_ALIAS_PREFIX: &str = "/_immutable/";
+
+/// A response with `status`, `headers` and no body
+pub(crate) fn empty_response(status: StatusCode, headers: HeaderMap) -> Response<StaticBody> {
+    let mut response = Response::new(StaticBody::empty());
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+    response
+}
+
+/// Answer the request described by `parts` for `asset`: `GET` and
+/// `HEAD` with the asset, `OPTIONS` with the allowed methods, and the
+/// other methods with a `405 Method Not Allowed` listing them
+///
+/// `HEAD` requests get the headers of the `GET` response,
+/// including the length of its body, and no body.
+pub(crate) fn respond_to_method(
+    asset: &'static StaticAsset,
+    parts: &Parts,
+    url_signer: Option<&UrlSigner>,
+    credentials: Option<&Credentials>,
+) -> Response<StaticBody> {
+    match parts.method {
+        Method::GET => respond(asset, parts, url_signer, credentials),
+        Method::HEAD => without_body(respond(asset, parts, url_signer, credentials)),
+        Method::OPTIONS => empty_response(
+            StatusCode::NO_CONTENT,
+            options_headers(asset.cors, &parts.headers),
+        ),
+        _ => method_not_allowed(),
+    }
+}
+
+/// `response` without its body, keeping the length it would have had
+pub(crate) fn without_body(mut response: Response<StaticBody>) -> Response<StaticBody> {
+    if !response.headers().contains_key(CONTENT_LENGTH)
+        && let Some(length) = response.body().size_hint().exact()
+    {
+        response
+            .headers_mut()
+            .insert(CONTENT_LENGTH, HeaderValue::from(length));
+    }
+    response.map(|_| StaticBody::empty())
+}
+
+/// Respond with `asset` to the `GET` or `HEAD` request described by
+/// `parts`, its URL being signed with the key of `url_signer`, if any,
+/// and its `protected` readers holding the `credentials` read for it
+pub(crate) fn respond(
+    asset: &'static StaticAsset,
+    parts: &Parts,
+    url_signer: Option<&UrlSigner>,
+    credentials: Option<&Credentials>,
+) -> Response<StaticBody> {
+    let headers = &parts.headers;
+    static_inner(StaticInnerData {
+        asset,
+        request: RepresentationRequest::from_parts(parts),
+        origin: headers.get(ORIGIN).cloned(),
+        accept: headers.get(ACCEPT).cloned(),
+        authorized: is_authorized(asset, credentials, headers),
+        signed_url: is_signed_url(asset, url_signer, requested_uri(parts)),
+        revealed: is_revealed(asset, headers),
+        requested_aliases: requested_aliases(&parts.uri),
+        record: RequestRecord::new(parts.uri.clone()),
+        settings: parts.extensions.get::<RouteSettings>().cloned(),
+    })
+}
+
+/// The headers of the `204 No Content` response to `OPTIONS` requests:
+/// the allowed methods, and the headers of CORS preflights
+pub(crate) fn options_headers(
+    allowed_origins: &'static [&'static str],
+    request_headers: &HeaderMap,
+) -> HeaderMap {
+    let mut headers = preflight_headers(allowed_origins, request_headers);
+    headers.insert(ALLOW, HeaderValue::from_static(ALLOWED_METHODS));
+    headers
+}
+
+/// The response to the methods other than `GET`, `HEAD` and `OPTIONS`
+pub(crate) fn method_not_allowed() -> Response<StaticBody> {
+    let mut headers = HeaderMap::new();
+    headers.insert(ALLOW, HeaderValue::from_static(ALLOWED_METHODS));
+    empty_response(StatusCode::METHOD_NOT_ALLOWED, headers)
+}
+
+/// The `401 Unauthorized` response to the requests of an asset
+/// protected with `basic_auth` without its credentials
+pub(crate) fn unauthorized(basic_auth: &BasicAuth) -> Response<StaticBody> {
+    let mut headers = HeaderMap::new();
+    headers.insert(WWW_AUTHENTICATE, basic_auth.challenge());
+    empty_response(StatusCode::UNAUTHORIZED, headers)
+}
+
+/// The headers of a request selecting the representation sent,
+/// its encoding and its range
+pub(crate) struct RepresentationRequest {
+    pub(crate) accept_encoding: AcceptEncoding,
+    pub(crate) preconditions: Preconditions,
+    pub(crate) http_range: Option<HttpRange>,
+    pub(crate) if_range: Option<IfRange>,
+}
+
+impl RepresentationRequest {
+    /// The headers of the request described by `parts`
+    pub(crate) fn from_parts(parts: &Parts) -> Self {
+        let headers = &parts.headers;
+        Self {
+            accept_encoding: AcceptEncoding::from_headers(headers),
+            preconditions: Preconditions::from_headers(headers),
+            // Like the `HttpRange` extractor, ignore ranges of non-GET requests
+            http_range: if parts.method == Method::GET {
+                headers
+                    .get(RANGE)
+                    .and_then(|range| HttpRange::try_from(range).ok())
+            } else {
+                None
+            },
+            if_range: headers
+                .get(IF_RANGE)
+                .and_then(|if_range| IfRange::try_from(if_range).ok()),
+        }
+    }
+}
+
+/// The bodies of a representation sent with the content negotiation,
+/// conditional and range request handling of the assets: an embedded
+/// asset, or a file read from disk by `dev::watch_router`
+pub(crate) trait Representation {
+    /// The `ETag`, if any
+    fn etag(&self) -> Option<&str>;
+    /// The `Last-Modified` date, if any
+    fn last_modified(&self) -> Option<&str>;
+    /// The encoding of the body sent to a client accepting `accept_encoding`,
+    /// among the `configured` ones if any, see `StaticAsset::negotiate_encoding`
+    fn negotiate_encoding(
+        &self,
+        accept_encoding: AcceptEncoding,
+        configured: Option<&[ContentEncoding]>,
+    ) -> Result<Option<ContentEncoding>, NotAcceptable>;
+    /// The length of the body sent with `content_encoding`, if known
+    fn encoded_len(&self, content_encoding: Option<ContentEncoding>) -> Option<usize>;
+    /// The body sent with `content_encoding`
+    fn encoded_body(&self, content_encoding: Option<ContentEncoding>) -> Bytes;
+    /// The size of the chunks the body is sent in, if any
+    fn stream_chunk_size(&self) -> Option<NonZeroUsize>;
+}
+
+impl Representation for StaticAsset {
+    fn etag(&self) -> Option<&str> {
+        self.etag
+    }
+
+    fn last_modified(&self) -> Option<&str> {
+        self.last_modified
+    }
+
+    fn negotiate_encoding(
+        &self,
+        accept_encoding: AcceptEncoding,
+        configured: Option<&[ContentEncoding]>,
+    ) -> Result<Option<ContentEncoding>, NotAcceptable> {
+        self.negotiate_encoding(accept_encoding, configured)
+    }
+
+    fn encoded_len(&self, content_encoding: Option<ContentEncoding>) -> Option<usize> {
+        self.encoded_len(content_encoding)
+    }
+
+    fn encoded_body(&self, content_encoding: Option<ContentEncoding>) -> Bytes {
+        Bytes::from_static(self.encoded_body(content_encoding))
+    }
+
+    fn stream_chunk_size(&self) -> Option<NonZeroUsize> {
+        self.stream_chunk_size
+    }
+}
+
+/// Struct of parameters for `static_inner` (to avoid `clippy::too_many_arguments`)
+///
+/// This bundles the embedded `StaticAsset` with the
+/// `RepresentationRequest` extracted from the request
+pub(crate) struct StaticInnerData {
+    pub(crate) asset: &'static StaticAsset,
+    pub(crate) request: RepresentationRequest,
+    /// The `Origin` of CORS requests
+    pub(crate) origin: Option<HeaderValue>,
+    /// The media types accepted by the client, to negotiate the image variants
+    pub(crate) accept: Option<HeaderValue>,
+    /// Whether the request holds the credentials of the `protected`
+    /// assets, always true for the other ones
+    pub(crate) authorized: bool,
+    /// Whether the URL is signed with the key of the `UrlSigner`
+    /// extension, always true for the assets which aren't `signed`
+    pub(crate) signed_url: bool,
+    /// Whether the request has the header or the cookie of the
+    /// `SourceMapGate`, always true for the assets without one
+    pub(crate) revealed: bool,
+    /// The immutable aliases the URL may be, with the `v` query parameter
+    /// of `cache_bust_query` or the prefix of `immutable_aliases`
+    pub(crate) requested_aliases: ImmutableAliases,
+    pub(crate) record: RequestRecord,
+    /// The settings of the route from `static_router_with_config`, if any
+    pub(crate) settings: Option<RouteSettings>,
+}
+
+/// Whether `uri` is signed with the key of `url_signer` for the
+/// `signed` assets, or `asset` isn't signed
+pub(crate) fn is_signed_url(
+    asset: &StaticAsset,
+    url_signer: Option<&UrlSigner>,
+    uri: &Uri,
+) -> bool {
+    !asset.signed || url_signer.is_some_and(|url_signer| url_signer.verify(uri.path(), uri.query()))
+}
+
+/// Whether a request with `headers` holds the `credentials` of the
+/// `protected` assets, or `asset` isn't protected
+pub(crate) fn is_authorized(
+    asset: &StaticAsset,
+    credentials: Option<&Credentials>,
+    headers: &HeaderMap,
+) -> bool {
+    asset.basic_auth.is_none()
+        || credentials
+            .is_some_and(|credentials| credentials.is_authorized(headers.get(AUTHORIZATION)))
+}
+
+/// Whether a request with `headers` has the header or the cookie
+/// revealing `asset`, or `asset` isn't a hidden source map
+pub(crate) fn is_revealed(asset: &StaticAsset, headers: &HeaderMap) -> bool {
+    asset
+        .source_map_gate
+        .is_none_or(|source_map_gate| source_map_gate.reveals(headers))
+}
+
+/// The immutable aliases `uri` may be, whether the asset has them or not
+pub(crate) fn requested_aliases(uri: &Uri) -> ImmutableAliases {
+    ImmutableAliases {
+        query: has_version_query(uri),
+        route: is_immutable_alias(uri),
+    }
+}
+
+/// Whether the path of `uri` is an `/_immutable/<hash>/<route>`
+/// alias, as generated with `immutable_aliases`
+fn is_immutable_alias(uri: &Uri) -> bool {
+    uri.path().starts_with(IMMUTABLE_ALIAS_PREFIX)
+}
+
+/// Whether the query of `uri` has a `v` parameter, with any value,
+/// as in the URLs generated with `cache_bust_query`
+fn has_version_query(uri: &Uri) -> bool {
+    uri.query().is_some_and(|query| {
+        query
+            .split('&')
+            .any(|param| param == "v" || param.starts_with("v="))
+    })
+}
+
+/// The status and the response refusing a request for `asset` without
+/// its credentials, its signature or the header revealing it, if any
+fn refusal(
+    asset: &StaticAsset,
+    authorized: bool,
+    signed_url: bool,
+    revealed: bool,
+) -> Option<(StatusCode, Response<StaticBody>)> {
+    if let Some(basic_auth) = &asset.basic_auth
+        && !authorized
+    {
+        return Some((StatusCode::UNAUTHORIZED, unauthorized(basic_auth)));
+    }
+    if !signed_url {
+        return Some((
+            StatusCode::FORBIDDEN,
+            empty_response(StatusCode::FORBIDDEN, HeaderMap::new()),
+        ));
+    }
+    if !revealed {
+        return Some((
+            StatusCode::NOT_FOUND,
+            empty_response(StatusCode::NOT_FOUND, HeaderMap::new()),
+        ));
+    }
+    None
+}
+
+/// The headers of the responses sending the negotiated `asset`
+/// to a request from `origin` through `requested_aliases`
+fn response_headers(
+    asset: &StaticAsset,
+    requested_aliases: ImmutableAliases,
+    origin: Option<HeaderValue>,
+    vary_accept: bool,
+    settings: Option<&RouteSettings>,
+) -> ResponseHeadersBuilder {
+    let &StaticAsset {
+        content_type,
+        etag,
+        last_modified,
+        body: _,
+        body_gz: _,
+        body_zst: _,
+        body_br: _,
+        cache_busted,
+        immutable_aliases,
+        cache_control,
+        headers,
+        cors,
+        lazy_body: _,
+        image_variants: _,
+        media_variants: _,
+        integrity: _,
+        stream_chunk_size: _,
+        basic_auth,
+        signed,
+        encoding_preference: _,
+        source_map_gate,
+    } = asset;
+
+    let mut headers_builder = ResponseHeadersBuilder::new(HeaderValue::from_static(content_type))
+        .cache_busted(cache_busted || immutable_aliases.matches(requested_aliases))
+        .extra_headers(headers)
+        .cors(cors, origin)
+        .vary_accept_encoding(asset.has_compressed_variant())
+        .vary_accept(vary_accept);
+    if let Some(etag) = etag {
+        headers_builder = headers_builder.etag(HeaderValue::from_static(etag));
+    }
+    if let Some(last_modified) = last_modified {
+        headers_builder = headers_builder.last_modified(HeaderValue::from_static(last_modified));
+    }
+    if let Some(settings) = settings {
+        headers_builder = headers_builder.configured_headers(Arc::clone(&settings.headers));
+    }
+    if let Some(cache_control) = settings.and_then(|settings| settings.cache_control.clone()) {
+        headers_builder = headers_builder.cache_control(cache_control);
+    } else if let Some(cache_control) = cache_control {
+        headers_builder = headers_builder.cache_control(HeaderValue::from_static(cache_control));
+    } else if basic_auth.is_some() || signed || source_map_gate.is_some() {
+        // Shared caches would send the protected assets and the hidden
+        // source maps to anyone, and the signed ones after their URL expired
+        headers_builder = headers_builder.cache_control(HeaderValue::from_static(PRIVATE));
+    }
+    headers_builder
+}
+
+pub(crate) fn static_inner(static_inner_data: StaticInnerData) -> Response<StaticBody> {
+    let StaticInnerData {
+        asset,
+        request,
+        origin,
+        accept,
+        authorized,
+        signed_url,
+        revealed,
+        requested_aliases,
+        record,
+        settings,
+    } = static_inner_data;
+    if let Some((status, response)) = refusal(asset, authorized, signed_url, revealed) {
+        record.finish(status, None, 0);
+        return response;
+    }
+    let vary_accept = !asset.image_variants.is_empty() || !asset.media_variants.is_empty();
+    let asset = asset
+        .negotiate_media(accept.as_ref())
+        .negotiate_image(accept.as_ref());
+    let headers_builder = response_headers(
+        asset,
+        requested_aliases,
+        origin,
+        vary_accept,
+        settings.as_ref(),
+    );
+    send_representation(
+        asset,
+        headers_builder,
+        request,
+        RouteSettings::encodings(settings.as_ref()),
+        &record,
+    )
+}
+
+/// Send `representation` with the headers of `headers_builder`, in
+/// response to `request`, among the `configured` encodings if any
+pub(crate) fn send_representation(
+    representation: &impl Representation,
+    headers_builder: ResponseHeadersBuilder,
+    request: RepresentationRequest,
+    configured: Option<&[ContentEncoding]>,
+    record: &RequestRecord,
+) -> Response<StaticBody> {
+    let RepresentationRequest {
+        accept_encoding,
+        preconditions,
+        http_range,
+        if_range,
+    } = request;
+    let (etag, last_modified) = (representation.etag(), representation.last_modified());
+
+    let outcome = preconditions.evaluate(etag, last_modified);
+
+    let http_range = match (http_range, if_range) {
+        (Some(range), Some(if_range)) => {
+            let last_modified = last_modified.and_then(|value| HeaderValue::from_str(value).ok());
+            let etag = etag.and_then(|value| HeaderValue::from_str(value).ok());
+            if_range.evaluate(range, last_modified.as_ref(), etag.as_ref())
+        }
+        (range, _) => range,
+    };
+
+    // Ranges are requested from the identity body, unless the client refuses
+    // it with `identity;q=0`, while a `304 Not Modified` has the length of
+    // the whole body the client would have been sent
+    let http_range =
+        http_range.filter(|_| accept_encoding.identity > 0.0 && outcome == Outcome::Proceed);
+    let negotiated = if http_range.is_none() {
+        representation.negotiate_encoding(accept_encoding, configured)
+    } else {
+        Ok(None)
+    };
+    // The conditions are ignored when the response without them wouldn't
+    // be a success (RFC 9110, section 13.2.1), so the encoding is negotiated
+    // first, while the lazy bodies are only materialized for the bodies sent
+    let Ok(content_encoding) = negotiated else {
+        record.finish(StatusCode::NOT_ACCEPTABLE, None, 0);
+        let mut headers = HeaderMap::new();
+        headers.insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+        return empty_response(StatusCode::NOT_ACCEPTABLE, headers);
+    };
+    match outcome {
+        Outcome::Proceed => {}
+        Outcome::NotModified => {
+            record.finish(StatusCode::NOT_MODIFIED, None, 0);
+            return not_modified(
+                headers_builder.build_not_modified(),
+                representation.encoded_len(content_encoding),
+            );
+        }
+        Outcome::PreconditionFailed => {
+            record.finish(StatusCode::PRECONDITION_FAILED, None, 0);
+            return empty_response(StatusCode::PRECONDITION_FAILED, HeaderMap::new());
+        }
+    }
+    let selected_body = representation.encoded_body(content_encoding);
+
+    match serve_file_with_http_range(selected_body, http_range) {
+        Ok(body_range) => {
+            let content_range = body_range.header();
+            let status = if content_range.is_some() {
+                StatusCode::PARTIAL_CONTENT
+            } else {
+                StatusCode::OK
+            };
+            record.finish(status, content_encoding, body_range.body().len());
+            let mut headers = headers_builder.content_encoding(content_encoding).build();
+            if let Some(content_range) = content_range {
+                headers.insert(CONTENT_RANGE, HeaderValue::from(&content_range));
+            }
+            let sent_body = body_range.into_body();
+            let body = match representation.stream_chunk_size() {
+                Some(chunk_size) => StaticBody::chunked(ChunkedBody::new(sent_body, chunk_size)),
+                None => StaticBody::full(sent_body),
+            };
+            let mut response = Response::new(body);
+            *response.status_mut() = status;
+            *response.headers_mut() = headers;
+            response
+        }
+        Err(unsatisfiable) => {
+            record.finish(StatusCode::RANGE_NOT_SATISFIABLE, None, 0);
+            let mut headers = headers_builder.build();
+            headers.insert(CONTENT_RANGE, HeaderValue::from(&unsatisfiable.header()));
+            empty_response(StatusCode::RANGE_NOT_SATISFIABLE, headers)
+        }
+    }
+}
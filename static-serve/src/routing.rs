@@ -0,0 +1,194 @@
+//! The axum routes of the embedded assets
+
+use std::{convert::Infallible, future};
+
+use axum::{
+    Router,
+    body::Body,
+    extract::FromRequestParts,
+    http::{HeaderMap, StatusCode, request::Parts},
+    response::{IntoResponse, Redirect, Response},
+    routing::{MethodRouter, get},
+};
+
+use crate::{
+    AcceptEncoding, Credentials, StaticAsset,
+    conditional::Preconditions,
+    headers::preflight_headers,
+    respond::{method_not_allowed, options_headers, respond},
+};
+
+impl<S> FromRequestParts<S> for AcceptEncoding
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> {
+        future::ready(Ok(Self::from_headers(&parts.headers)))
+    }
+}
+
+impl<S> FromRequestParts<S> for Preconditions
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> {
+        future::ready(Ok(Self::from_headers(&parts.headers)))
+    }
+}
+
+impl StaticAsset {
+    /// Respond with this asset to the request described by `parts`
+    ///
+    /// The returned value implements [`IntoResponse`], performing
+    /// the same content negotiation, conditional and range request
+    /// handling as the generated routes, so that handlers can serve
+    /// embedded assets from their own routes:
+    ///
+    /// ```rust,ignore
+    /// async fn index(parts: Parts) -> Response {
+    ///     // ...check the authorization of the request...
+    ///     get_asset("/index.html").unwrap().respond_to(&parts).into_response()
+    /// }
+    /// ```
+    #[must_use]
+    pub fn respond_to<'a>(&'static self, parts: &'a Parts) -> AssetResponse<'a> {
+        AssetResponse { asset: self, parts }
+    }
+}
+
+/// A [`StaticAsset`] bound to the request it responds to,
+/// created by [`StaticAsset::respond_to`]
+#[derive(Debug)]
+pub struct AssetResponse<'a> {
+    asset: &'static StaticAsset,
+    parts: &'a Parts,
+}
+
+impl IntoResponse for AssetResponse<'_> {
+    fn into_response(self) -> Response {
+        let Self { asset, parts } = self;
+        respond(
+            asset,
+            parts,
+            parts.extensions.get(),
+            Credentials::of(asset).as_ref(),
+        )
+        .map(Body::new)
+    }
+}
+
+#[doc(hidden)]
+/// The router for adding routes for static assets
+///
+/// With `fall_through_methods`, the methods other than `GET` and `HEAD`
+/// are left to the other routes of the path, see [`Route`](crate::Route).
+pub fn static_route<S>(
+    router: Router<S>,
+    web_path: &'static str,
+    asset: &'static StaticAsset,
+    fall_through_methods: bool,
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.route(web_path, route_method_router(asset, fall_through_methods))
+}
+
+/// The method router of `static_route`
+pub(crate) fn route_method_router<S>(
+    asset: &'static StaticAsset,
+    fall_through_methods: bool,
+) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    with_methods(asset_method_router(asset), asset.cors, fall_through_methods)
+}
+
+/// Answer the `GET` and `HEAD` requests to `from` with
+/// a `308 Permanent Redirect` to `to`
+pub(crate) fn redirect_route<S>(
+    router: Router<S>,
+    from: &'static str,
+    to: &'static str,
+) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router.route(from, get(move || future::ready(Redirect::permanent(to))))
+}
+
+#[doc(hidden)]
+/// Creates a route for a single static asset.
+///
+/// Like every `GET` route of axum, it also answers `HEAD` requests,
+/// with the same headers and an empty body.
+///
+/// `OPTIONS` requests are answered with the allowed methods in the
+/// `Allow` header, and the requests with other methods with a
+/// `405 Method Not Allowed` listing them.
+///
+/// Used by the `embed_asset!` macro, so it needs to be `pub`.
+pub fn static_method_router<S>(asset: &'static StaticAsset) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    with_methods(asset_method_router(asset), asset.cors, false)
+}
+
+/// The method router answering the `GET` and `HEAD` requests with `asset`
+fn asset_method_router<S>(asset: &'static StaticAsset) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let credentials = Credentials::of(asset);
+    MethodRouter::get(MethodRouter::new(), move |parts: Parts| async move {
+        respond(asset, &parts, parts.extensions.get(), credentials.as_ref()).map(Body::new)
+    })
+}
+
+/// Answer the `OPTIONS` requests with the allowed methods, and the CORS
+/// preflight requests of the `allowed_origins`, if any, and the other
+/// methods with a `405 Method Not Allowed` listing them
+///
+/// With `fall_through_methods`, only the CORS preflight requests are
+/// answered, and the other methods are left to the other routes of the
+/// path, so that the method router can be merged with them.
+pub(crate) fn with_methods<S>(
+    method_router: MethodRouter<S>,
+    allowed_origins: &'static [&'static str],
+    fall_through_methods: bool,
+) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    if !fall_through_methods {
+        return method_router
+            .options(move |headers: HeaderMap| async move {
+                (
+                    StatusCode::NO_CONTENT,
+                    options_headers(allowed_origins, &headers),
+                )
+            })
+            .fallback(|| future::ready(method_not_allowed().map(Body::new)));
+    }
+    if allowed_origins.is_empty() {
+        return method_router;
+    }
+    method_router.options(move |headers: HeaderMap| async move {
+        (
+            StatusCode::NO_CONTENT,
+            preflight_headers(allowed_origins, &headers),
+        )
+    })
+}
@@ -0,0 +1,188 @@
+//! A plain tower `Service` serving the embedded assets outside of axum routers
+
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    future::{Ready, ready},
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use http::{
+    HeaderMap, Method, Request, Response, StatusCode,
+    header::{HeaderValue, LOCATION},
+    request::Parts,
+};
+use tower_service::Service;
+
+use crate::{
+    AcceptEncoding, Credentials, Fallback, Redirect, Route, StaticAsset, StaticAssetBackend,
+    StaticBody, UrlSigner,
+    not_found::{accepts_html, not_found_inner, spa_inner},
+    percent::canonical_path,
+    respond::{empty_response, method_not_allowed, respond_to_method, without_body},
+};
+
+/// A [`Service`] serving the assets embedded by `embed_assets!`,
+/// returned by the generated `static_service()` function
+///
+/// It only depends on the `http` and `tower-service` crates, and accepts
+/// requests with any body type, so the assets can be mounted in hyper,
+/// warp or any other tower stack, with or without the `axum` feature:
+///
+/// ```rust,ignore
+/// embed_assets!("assets", compress = true);
+/// let service = ServiceBuilder::new()
+///     .layer(TraceLayer::new_for_http())
+///     .service(static_service());
+/// ```
+///
+/// The assets get the same content negotiation, conditional and range
+/// request handling as with `static_router()`, and the unmatched routes
+/// the `not_found` or `spa_fallback` page, or an empty `404 Not Found`
+/// without them. The [`UrlSigner`] of the `signed` assets is taken from
+/// the extensions of the requests. `OPTIONS` requests get the allowed
+/// methods in the `Allow` header, and the CORS preflight headers of the
+/// assets embedded with `cors`, and the other methods get an empty
+/// `405 Method Not Allowed` listing them, whatever `fall_through_methods`,
+/// since the service has no other routes to fall through to. The files of
+/// `debug_from_disk` are served from their embedded contents.
+///
+/// Cloning the service is cheap: the routes are shared.
+#[derive(Debug, Clone)]
+pub struct StaticService {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    /// The embedded assets, by route
+    routes: HashMap<&'static str, Route>,
+    /// The `Location` of the redirected routes, by route
+    redirects: HashMap<&'static str, &'static str>,
+    fallback: Option<Fallback>,
+    /// The credentials of the `protected` assets, by variable,
+    /// read when the service is built
+    credentials: HashMap<&'static str, Credentials>,
+}
+
+impl Inner {
+    /// The credentials read for `asset`, if it's `protected` and they're set
+    fn credentials(&self, asset: &StaticAsset) -> Option<&Credentials> {
+        asset
+            .basic_auth
+            .and_then(|basic_auth| self.credentials.get(basic_auth.credentials_var))
+    }
+}
+
+impl StaticAssetBackend for StaticService {
+    type Router = Self;
+
+    fn router(
+        assets: &[Route],
+        redirects: &[Redirect],
+        fallback: Option<Fallback>,
+    ) -> Self::Router {
+        let fallback_page =
+            fallback.map(|(Fallback::NotFound(asset) | Fallback::Spa(asset))| asset);
+        let credentials = assets
+            .iter()
+            .map(|route| route.asset)
+            .chain(fallback_page)
+            .filter_map(|asset| Some((asset.basic_auth?.credentials_var, Credentials::of(asset)?)))
+            .collect();
+        Self {
+            inner: Arc::new(Inner {
+                routes: assets
+                    .iter()
+                    .map(|route| (route.web_path, *route))
+                    .collect(),
+                redirects: redirects
+                    .iter()
+                    .map(|&Redirect { from, to }| (from, to))
+                    .collect(),
+                fallback,
+                credentials,
+            }),
+        }
+    }
+}
+
+impl StaticService {
+    /// The response to the request described by `parts`, or `None` when
+    /// its route isn't embedded and there's no fallback
+    ///
+    /// This is the response of the service, for the frameworks which don't
+    /// use tower services, except that the unmatched requests can be left
+    /// to their other routes rather than answered with `404 Not Found`.
+    #[must_use]
+    pub fn respond(&self, parts: &Parts) -> Option<Response<StaticBody>> {
+        let inner = &*self.inner;
+        let Inner {
+            routes,
+            redirects,
+            fallback,
+            credentials: _,
+        } = inner;
+        let url_signer = parts.extensions.get::<UrlSigner>();
+        let path = canonical_path(parts.uri.path());
+        if let Some(&location) = redirects.get(&*path) {
+            return Some(match parts.method {
+                Method::GET | Method::HEAD => {
+                    let mut headers = HeaderMap::new();
+                    headers.insert(LOCATION, HeaderValue::from_static(location));
+                    empty_response(StatusCode::PERMANENT_REDIRECT, headers)
+                }
+                _ => method_not_allowed(),
+            });
+        }
+        if let Some(route) = routes.get(&*path) {
+            return Some(respond_to_method(
+                route.asset,
+                parts,
+                url_signer,
+                inner.credentials(route.asset),
+            ));
+        }
+
+        match (*fallback)? {
+            Fallback::NotFound(not_found) => {
+                let response = not_found_inner(
+                    not_found,
+                    accepts_html(&parts.headers),
+                    AcceptEncoding::from_headers(&parts.headers),
+                    None,
+                );
+                Some(if parts.method == Method::HEAD {
+                    without_body(response)
+                } else {
+                    response
+                })
+            }
+            Fallback::Spa(index) => Some(spa_inner(
+                index,
+                parts,
+                url_signer,
+                inner.credentials(index),
+            )),
+        }
+    }
+}
+
+impl<B> Service<Request<B>> for StaticService {
+    type Response = Response<StaticBody>;
+    type Error = Infallible;
+    type Future = Ready<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let (parts, _body) = request.into_parts();
+        let response = self
+            .respond(&parts)
+            .unwrap_or_else(|| empty_response(StatusCode::NOT_FOUND, HeaderMap::new()));
+        ready(Ok(response))
+    }
+}
@@ -0,0 +1,180 @@
+//! The signed URLs of the assets of the subtrees
+//! listed in the `signed` option of `embed_assets!`
+
+use std::{
+    fmt::{self, Write},
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, KeyInit, Mac};
+use http::{Uri, request::Parts};
+use sha2::Sha256;
+
+use crate::percent::canonical_path;
+
+/// The key signing the URLs of the assets listed in `signed`
+///
+/// The routes read it from the extensions of the requests, like the
+/// state of a router, so that the generated routers stay generic over
+/// theirs:
+///
+/// ```rust,ignore
+/// let signer = UrlSigner::new(env::var("DOWNLOADS_KEY")?);
+/// let router = static_router().layer(Extension(signer.clone()));
+///
+/// // In the handler of a purchase
+/// let url = signer.sign("/downloads/album.zip", SystemTime::now() + Duration::from_secs(3600));
+/// ```
+///
+/// The signed URLs are only valid for the route they were signed for,
+/// and until they expire. The route is the path the clients request,
+/// including the prefix of the routers nesting the assets, spelled with
+/// or without percent-encoding. The requests of the signed assets are
+/// answered with `403 Forbidden` when the extension is missing.
+#[derive(Clone)]
+pub struct UrlSigner {
+    key: Arc<[u8]>,
+}
+
+impl fmt::Debug for UrlSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UrlSigner").finish_non_exhaustive()
+    }
+}
+
+impl UrlSigner {
+    /// The signer of the URLs with the HMAC-SHA256 `key`
+    #[must_use]
+    pub fn new(key: impl AsRef<[u8]>) -> Self {
+        Self {
+            key: key.as_ref().into(),
+        }
+    }
+
+    /// The URL of the route `path`, valid until `expires`, with the
+    /// `expires` and `signature` query parameters, e.g.
+    /// `/downloads/album.zip?expires=1767225600&signature=...`
+    #[must_use]
+    pub fn sign(&self, path: &str, expires: SystemTime) -> String {
+        let expires = unix_seconds(expires);
+        let signature = self.mac(path, expires).finalize().into_bytes();
+        format!("{path}?expires={expires}&signature={}", hex(&signature))
+    }
+
+    /// Whether `query` holds a signature of the route `path`
+    /// made with this key, which hasn't expired
+    #[must_use]
+    pub fn verify(&self, path: &str, query: Option<&str>) -> bool {
+        let mut expires = None;
+        let mut signature = None;
+        for param in query.unwrap_or_default().split('&') {
+            match param.split_once('=') {
+                Some(("expires", value)) => expires = value.parse::<u64>().ok(),
+                Some(("signature", value)) => signature = decode_hex(value),
+                _ => {}
+            }
+        }
+        let (Some(expires), Some(signature)) = (expires, signature) else {
+            return false;
+        };
+        expires > unix_seconds(SystemTime::now())
+            && self.mac(path, expires).verify_slice(&signature).is_ok()
+    }
+
+    /// The HMAC of `path` and of its expiry, `path` being spelled like
+    /// the routes so that a URL is verified whatever its percent-encoding
+    fn mac(&self, path: &str, expires: u64) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC takes keys of any length");
+        mac.update(format!("{expires}\n{}", canonical_path(path)).as_bytes());
+        mac
+    }
+}
+
+#[doc(hidden)]
+/// The URI of a request as the client sent it, before the routers nesting
+/// the assets stripped their prefix, which the signed URLs are signed for
+///
+/// Inserted in the extensions of the requests by the routes generated for
+/// Rocket, poem and salvo, so it needs to be `pub`. The axum routers
+/// insert their `OriginalUri` instead.
+#[derive(Debug, Clone)]
+pub struct RequestedUri(pub Uri);
+
+/// The URI of the request described by `parts`, as the client sent it
+pub(crate) fn requested_uri(parts: &Parts) -> &Uri {
+    #[cfg(feature = "axum")]
+    if let Some(axum::extract::OriginalUri(uri)) = parts.extensions.get() {
+        return uri;
+    }
+    parts
+        .extensions
+        .get::<RequestedUri>()
+        .map_or(&parts.uri, |RequestedUri(uri)| uri)
+}
+
+/// The seconds from the Unix epoch to `time`, 0 before it
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+/// Encode `bytes` as lowercase hex digits
+fn hex(bytes: &[u8]) -> String {
+    let mut hex = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        write!(hex, "{byte:02x}").expect("writing to a String cannot fail");
+    }
+    hex
+}
+
+/// Decode the hex digits of `hex`, if they're valid
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, SystemTime};
+
+    use super::{UrlSigner, decode_hex, hex};
+
+    #[test]
+    fn encodes_and_decodes_hex() {
+        assert_eq!(hex(&[0x5b, 0xdc, 0x0f]), "5bdc0f");
+        assert_eq!(decode_hex("5bDC0f"), Some(vec![0x5b, 0xdc, 0x0f]));
+        assert_eq!(decode_hex("5bd"), None);
+        assert_eq!(decode_hex("5g"), None);
+        assert_eq!(decode_hex("+5"), None);
+    }
+
+    #[test]
+    fn verifies_the_path_and_the_expiry() {
+        let signer = UrlSigner::new("key");
+        let in_an_hour = SystemTime::now() + Duration::from_secs(3600);
+        let url = signer.sign("/downloads/a.zip", in_an_hour);
+        let (path, query) = url.split_once('?').unwrap();
+        assert!(signer.verify(path, Some(query)));
+        assert!(!signer.verify("/downloads/b.zip", Some(query)));
+        assert!(!UrlSigner::new("other key").verify(path, Some(query)));
+        assert!(!signer.verify(path, None));
+        // The path is verified whatever its percent-encoding
+        let url = signer.sign("/downloads/caf\u{e9}.zip", in_an_hour);
+        let (_, query) = url.split_once('?').unwrap();
+        assert!(signer.verify("/downloads/caf%C3%A9.zip", Some(query)));
+
+        let expired = signer.sign(
+            "/downloads/a.zip",
+            SystemTime::now() - Duration::from_secs(1),
+        );
+        let (path, query) = expired.split_once('?').unwrap();
+        assert!(!signer.verify(path, Some(query)));
+    }
+}
@@ -0,0 +1,46 @@
+//! The source maps embedded with `source_maps = "header-only"`
+//! in `embed_assets!`, hidden from the other requests
+
+use http::{HeaderMap, header::COOKIE};
+
+/// The request header or cookie a source map is only served with,
+/// from `source_maps_header` and `source_maps_cookie`
+///
+/// The other requests are answered with `404 Not Found`, so that the
+/// source maps are only loaded by the developer tools of the browsers
+/// sending it, e.g. with an extension setting the header. They're hidden
+/// rather than protected: the `protected` option requires credentials.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceMapGate {
+    /// The lowercase name of the header revealing the source map, if any
+    pub header: Option<&'static str>,
+    /// The name of the cookie revealing the source map, if any
+    pub cookie: Option<&'static str>,
+}
+
+impl SourceMapGate {
+    /// Whether a request with `headers` has the header or the
+    /// cookie revealing the source map, whatever their value
+    #[must_use]
+    pub fn reveals(&self, headers: &HeaderMap) -> bool {
+        self.header
+            .is_some_and(|header| headers.contains_key(header))
+            || self
+                .cookie
+                .is_some_and(|cookie| has_cookie(headers, cookie))
+    }
+}
+
+/// Whether the `Cookie` headers hold a cookie named `name`
+fn has_cookie(headers: &HeaderMap, name: &str) -> bool {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|cookies| cookies.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .any(|cookie| {
+            cookie
+                .split_once('=')
+                .is_some_and(|(cookie_name, _)| cookie_name.trim() == name)
+        })
+}
@@ -0,0 +1,75 @@
+//! The chunked bodies of the large assets, from `stream_min_size`
+
+use std::{
+    convert::Infallible,
+    num::NonZeroUsize,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use http_body::{Body, Frame, SizeHint};
+
+/// A body sending a slice of an embedded asset in chunks of at most
+/// `chunk_size` bytes
+///
+/// Each chunk is only produced when the connection polls for the next one,
+/// so servers write large downloads, e.g. installers, at the pace the client
+/// reads them, and layers can throttle them chunk by chunk. The exact size
+/// of the body is known, so it's still sent with a `Content-Length`.
+///
+/// ```rust
+/// use std::num::NonZeroUsize;
+///
+/// use http_body::Body;
+/// use static_serve::ChunkedBody;
+///
+/// let body = ChunkedBody::new(vec![0; 100], NonZeroUsize::new(64).unwrap());
+/// assert_eq!(body.size_hint().exact(), Some(100));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChunkedBody {
+    remaining: Bytes,
+    chunk_size: NonZeroUsize,
+}
+
+impl ChunkedBody {
+    /// Send `body` in chunks of at most `chunk_size` bytes
+    #[must_use]
+    pub fn new(body: impl Into<Bytes>, chunk_size: NonZeroUsize) -> Self {
+        Self {
+            remaining: body.into(),
+            chunk_size,
+        }
+    }
+
+    /// The remaining bytes of the body and the size of its chunks
+    pub(crate) fn into_parts(self) -> (Bytes, NonZeroUsize) {
+        (self.remaining, self.chunk_size)
+    }
+}
+
+impl Body for ChunkedBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        if self.remaining.is_empty() {
+            return Poll::Ready(None);
+        }
+        let len = self.chunk_size.get().min(self.remaining.len());
+        let chunk = self.remaining.split_to(len);
+        Poll::Ready(Some(Ok(Frame::data(chunk))))
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.remaining.is_empty()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        SizeHint::with_exact(self.remaining.len() as u64)
+    }
+}
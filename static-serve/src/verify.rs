@@ -0,0 +1,126 @@
+//! Self-check of the compressed variants embedded by `embed_assets!`
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{self, Read},
+};
+
+use flate2::read::GzDecoder;
+
+use crate::StaticAsset;
+#[cfg(any(feature = "zstd", feature = "ruzstd"))]
+use crate::zstd_decoder;
+
+/// An embedded compressed variant that doesn't round-trip to the identity body
+#[derive(Debug)]
+pub enum VerifyError {
+    /// The compressed variant could not be decompressed
+    Decompress {
+        /// The route of the asset
+        web_path: &'static str,
+        /// The `Content-Encoding` of the variant
+        encoding: &'static str,
+        /// The error reported by the decoder
+        source: io::Error,
+    },
+    /// The compressed variant decompressed to different bytes than the identity body
+    Mismatch {
+        /// The route of the asset
+        web_path: &'static str,
+        /// The `Content-Encoding` of the variant
+        encoding: &'static str,
+    },
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decompress {
+                web_path, encoding, ..
+            } => write!(f, "Cannot decompress the {encoding} variant of {web_path}"),
+            Self::Mismatch { web_path, encoding } => write!(
+                f,
+                "The {encoding} variant of {web_path} does not match the identity body"
+            ),
+        }
+    }
+}
+
+impl Error for VerifyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Decompress { source, .. } => Some(source),
+            Self::Mismatch { .. } => None,
+        }
+    }
+}
+
+#[doc(hidden)]
+/// Decompress every embedded compressed variant and check
+/// it matches the identity body of the asset.
+///
+/// zstd variants aren't checked without the `zstd` or `ruzstd` feature.
+///
+/// Used by the `debug_verify` function generated by `embed_assets!`,
+/// so it needs to be `pub`.
+///
+/// # Errors
+///
+/// Returns the first variant which cannot be decompressed
+/// or which doesn't match the identity body.
+pub fn verify_assets(assets: &[(&'static str, StaticAsset)]) -> Result<(), VerifyError> {
+    for (web_path, asset) in assets {
+        if let Some(body_gz) = asset.body_gz {
+            let mut decompressed = Vec::new();
+            GzDecoder::new(body_gz)
+                .read_to_end(&mut decompressed)
+                .map_err(|source| VerifyError::Decompress {
+                    web_path,
+                    encoding: "gzip",
+                    source,
+                })?;
+            check_matches(web_path, "gzip", &decompressed, asset.uncompressed_body())?;
+        }
+
+        #[cfg(any(feature = "zstd", feature = "ruzstd"))]
+        if let Some(body_zst) = asset.body_zst {
+            let decompressed =
+                zstd_decoder::decode(body_zst, None, asset.uncompressed_body().len()).map_err(
+                    |source| VerifyError::Decompress {
+                        web_path,
+                        encoding: "zstd",
+                        source,
+                    },
+                )?;
+            check_matches(web_path, "zstd", &decompressed, asset.uncompressed_body())?;
+        }
+
+        if let Some(body_br) = asset.body_br {
+            let mut decompressed = Vec::new();
+            brotli_decompressor::Decompressor::new(body_br, 4096)
+                .read_to_end(&mut decompressed)
+                .map_err(|source| VerifyError::Decompress {
+                    web_path,
+                    encoding: "br",
+                    source,
+                })?;
+            check_matches(web_path, "br", &decompressed, asset.uncompressed_body())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_matches(
+    web_path: &'static str,
+    encoding: &'static str,
+    decompressed: &[u8],
+    body: &[u8],
+) -> Result<(), VerifyError> {
+    if decompressed == body {
+        Ok(())
+    } else {
+        Err(VerifyError::Mismatch { web_path, encoding })
+    }
+}
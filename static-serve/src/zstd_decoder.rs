@@ -0,0 +1,45 @@
+//! Decoding of the zstd variants, with the C library of the `zstd` feature,
+//! or else with the pure-Rust decoder of the `ruzstd` feature, for the
+//! targets without a C toolchain
+
+use std::io::{self, Read};
+
+#[cfg(all(feature = "ruzstd", not(feature = "zstd")))]
+use ruzstd::decoding::{Dictionary, FrameDecoder, StreamingDecoder};
+
+/// Decode `compressed`, a body of about `size` bytes compressed with
+/// the shared `dictionary` of `zstd_dictionary = true`, if any
+#[cfg(feature = "zstd")]
+pub(crate) fn decode(
+    compressed: &[u8],
+    dictionary: Option<&[u8]>,
+    size: usize,
+) -> io::Result<Vec<u8>> {
+    let mut body = Vec::with_capacity(size);
+    match dictionary {
+        Some(dictionary) => zstd::stream::Decoder::with_dictionary(compressed, dictionary)?
+            .read_to_end(&mut body)?,
+        None => zstd::stream::Decoder::with_buffer(compressed)?.read_to_end(&mut body)?,
+    };
+    Ok(body)
+}
+
+/// Decode `compressed`, a body of about `size` bytes compressed with
+/// the shared `dictionary` of `zstd_dictionary = true`, if any
+#[cfg(all(feature = "ruzstd", not(feature = "zstd")))]
+pub(crate) fn decode(
+    compressed: &[u8],
+    dictionary: Option<&[u8]>,
+    size: usize,
+) -> io::Result<Vec<u8>> {
+    let mut decoder = FrameDecoder::new();
+    if let Some(dictionary) = dictionary {
+        let dictionary = Dictionary::decode_dict(dictionary).map_err(io::Error::other)?;
+        decoder.add_dict(dictionary).map_err(io::Error::other)?;
+    }
+    let mut body = Vec::with_capacity(size);
+    StreamingDecoder::new_with_decoder(compressed, decoder)
+        .map_err(io::Error::other)?
+        .read_to_end(&mut body)?;
+    Ok(body)
+}
@@ -0,0 +1,115 @@
+//! Integration tests of the router generated with the `test-overrides`
+//! feature, apart from the other ones which don't need it
+#![cfg(feature = "test-overrides")]
+
+use axum::{
+    Router,
+    body::Body,
+    http::{
+        HeaderValue, Request, Response, StatusCode,
+        header::{ACCEPT_ENCODING, CONTENT_ENCODING, ETAG, IF_NONE_MATCH},
+    },
+    routing::MethodRouter,
+};
+use http_body_util::BodyExt;
+use tower::ServiceExt;
+
+use static_serve::{AssetOverrides, Route, embed_assets};
+
+async fn get_response(router: Router<()>, request: Request<Body>) -> Response<Body> {
+    router
+        .into_service()
+        .oneshot(request)
+        .await
+        .expect("sending request")
+}
+
+fn create_request(route: &str, accept_encoding: &'static str) -> Request<Body> {
+    Request::builder()
+        .uri(route)
+        .header(ACCEPT_ENCODING, accept_encoding)
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn serves_overridden_assets() {
+    embed_assets!("../static-serve/test_assets/big", compress = true);
+    let overrides = AssetOverrides::new().replace("/app.js", b"console.log(1)".to_vec());
+    let router: Router<()> = static_router_with_overrides(&overrides);
+
+    let request = create_request("/app.js", "zstd, gzip");
+    let response = get_response(router.clone(), request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::OK);
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/javascript; charset=utf-8"
+    );
+    assert!(parts.headers.get(CONTENT_ENCODING).is_none());
+    let etag = parts.headers.get(ETAG).unwrap().clone();
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    assert_eq!(collected_body_bytes, "console.log(1)");
+
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(IF_NONE_MATCH, etag)
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // The other assets are untouched
+    let request = create_request("/styles.css", "zstd");
+    let response = get_response(router, request).await;
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "zstd");
+}
+
+#[tokio::test]
+async fn computes_the_etags_of_the_overrides_like_the_embedded_ones() {
+    embed_assets!("../static-serve/test_assets/big");
+    let overrides = AssetOverrides::new().replace(
+        "/app.js",
+        include_bytes!("../../test_assets/big/app.js").to_vec(),
+    );
+    let overridden: Router<()> = static_router_with_overrides(&overrides);
+
+    let request = create_request("/app.js", "identity");
+    let response = get_response(overridden, request).await;
+    assert_eq!(
+        response.headers()[ETAG],
+        get_asset("/app.js").unwrap().etag().unwrap()
+    );
+}
+
+#[test]
+#[should_panic(expected = "no asset is embedded at this route")]
+fn rejects_overrides_of_missing_assets() {
+    embed_assets!("../static-serve/test_assets/big");
+    let overrides = AssetOverrides::new().replace("/missing.js", Vec::new());
+    let _router: Router<()> = static_router_with_overrides(&overrides);
+}
+
+/// Add `X-Wrapped: true` to the responses
+fn wrap_all<S>(_route: &Route, method_router: MethodRouter<S>) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    method_router.layer(axum::middleware::map_response(
+        |mut response: axum::response::Response| async move {
+            response
+                .headers_mut()
+                .insert("x-wrapped", HeaderValue::from_static("true"));
+            response
+        },
+    ))
+}
+
+#[tokio::test]
+async fn wraps_the_overridden_routes() {
+    embed_assets!("../static-serve/test_assets/small", wrap = wrap_all);
+    let router: Router<()> = static_router_with_overrides(&AssetOverrides::new());
+    let request = create_request("/app.js", "identity");
+    let response = get_response(router, request).await;
+    assert_eq!(response.headers()["x-wrapped"], "true");
+}
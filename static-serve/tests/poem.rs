@@ -0,0 +1,128 @@
+//! Integration tests of the route generated with `poem_routes = true`,
+//! apart from the other ones so that they don't link poem
+#![cfg(feature = "poem")]
+
+use http_body_util::{BodyExt, combinators::BoxBody};
+use poem::{
+    Endpoint, EndpointExt, Request,
+    http::{
+        StatusCode,
+        header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_RANGE, ETAG, IF_NONE_MATCH, RANGE},
+    },
+};
+use std::time::{Duration, SystemTime};
+
+use static_serve::{UrlSigner, embed_assets, headers::ContentEncoding};
+
+#[tokio::test]
+async fn serves_assets_with_poem() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        poem_routes = true
+    );
+    let route = poem::Route::new().nest("/static", static_routes());
+
+    let request = Request::builder()
+        .uri_str("/static/app.js")
+        .header(ACCEPT_ENCODING, "zstd")
+        .finish();
+    let response = route.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_ENCODING], "zstd");
+    let etag = response.headers()[ETAG].clone();
+    assert_eq!(
+        response.into_body().into_bytes().await.unwrap(),
+        get_asset("/app.js")
+            .unwrap()
+            .compressed_body(ContentEncoding::Zstd)
+            .unwrap()
+    );
+
+    let request = Request::builder()
+        .uri_str("/static/app.js")
+        .header(IF_NONE_MATCH, etag)
+        .finish();
+    let response = route.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let request = Request::builder().uri_str("/static/app.js").finish();
+    let response = route.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.into_body().into_bytes().await.unwrap(),
+        include_bytes!("../../test_assets/big/app.js").as_slice()
+    );
+
+    let request = Request::builder()
+        .uri_str("/static/app.js")
+        .header(RANGE, "bytes=0-9")
+        .finish();
+    let response = route.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let body_len = include_bytes!("../../test_assets/big/app.js").len();
+    assert_eq!(
+        response.headers()[CONTENT_RANGE],
+        format!("bytes 0-9/{body_len}")
+    );
+    assert_eq!(
+        response.into_body().into_bytes().await.unwrap(),
+        include_bytes!("../../test_assets/big/app.js")[..10]
+    );
+
+    let request = Request::builder().uri_str("/static/missing.js").finish();
+    let response = route.call(request).await;
+    assert_eq!(response.unwrap_err().status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn streams_assets_with_poem() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        stream_min_size = 1,
+        stream_chunk_size = 64,
+        poem_routes = true
+    );
+    let route = poem::Route::new().nest("/", static_routes());
+
+    let request = Request::builder().uri_str("/app.js").finish();
+    let response = route.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let mut body = BoxBody::from(response.into_body());
+    let mut received = Vec::new();
+    while let Some(frame) = body.frame().await {
+        let chunk = frame.unwrap().into_data().unwrap();
+        assert!(chunk.len() <= 64);
+        received.extend_from_slice(&chunk);
+    }
+    assert_eq!(received, include_bytes!("../../test_assets/big/app.js"));
+}
+
+#[tokio::test]
+async fn takes_the_url_signer_from_the_poem_data() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        signed = ["immutable"],
+        poem_routes = true
+    );
+    let signer = UrlSigner::new("downloads key");
+    let url = signer.sign(
+        "/immutable/app.js",
+        SystemTime::now() + Duration::from_secs(3600),
+    );
+
+    let route = poem::Route::new().nest("/", static_routes()).data(signer);
+    let request = Request::builder().uri_str(&url).finish();
+    let response = route.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = Request::builder().uri_str("/immutable/app.js").finish();
+    let response = route.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Without the key, the signed files are never served
+    let route = poem::Route::new().nest("/", static_routes());
+    let request = Request::builder().uri_str(&url).finish();
+    let response = route.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
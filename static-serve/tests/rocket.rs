@@ -0,0 +1,105 @@
+//! Integration tests of the routes generated with `rocket_routes = true`,
+//! apart from the other ones so that they don't link Rocket
+#![cfg(feature = "rocket")]
+
+use rocket::{
+    http::{Header, Status},
+    local::asynchronous::Client,
+};
+use static_serve::{embed_assets, headers::ContentEncoding};
+
+#[tokio::test]
+async fn serves_assets_with_rocket() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        rocket_routes = true
+    );
+    let client = Client::untracked(rocket::build().mount("/static", rocket_routes()))
+        .await
+        .unwrap();
+
+    let response = client
+        .get("/static/app.js")
+        .header(Header::new("accept-encoding", "zstd"))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.headers().get_one("content-encoding"), Some("zstd"));
+    let etag = response.headers().get_one("etag").unwrap().to_owned();
+    assert_eq!(
+        response.into_bytes().await.unwrap(),
+        get_asset("/app.js")
+            .unwrap()
+            .compressed_body(ContentEncoding::Zstd)
+            .unwrap()
+    );
+
+    let response = client
+        .get("/static/app.js")
+        .header(Header::new("if-none-match", etag))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::NotModified);
+    let body_len = include_bytes!("../../test_assets/big/app.js").len();
+    assert_eq!(
+        response.headers().get("content-length").collect::<Vec<_>>(),
+        [body_len.to_string()]
+    );
+
+    let response = client.get("/static/app.js").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.into_bytes().await.unwrap(),
+        include_bytes!("../../test_assets/big/app.js")
+    );
+
+    let response = client
+        .get("/static/app.js")
+        .header(Header::new("range", "bytes=0-9"))
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::PartialContent);
+    assert_eq!(
+        response.headers().get_one("content-range"),
+        Some(format!("bytes 0-9/{body_len}").as_str())
+    );
+    assert_eq!(
+        response.into_bytes().await.unwrap(),
+        include_bytes!("../../test_assets/big/app.js")[..10]
+    );
+
+    // The unmatched routes are left to the other routes of the application
+    let response = client.get("/static/missing.js").dispatch().await;
+    assert_eq!(response.status(), Status::NotFound);
+}
+
+#[tokio::test]
+async fn streams_assets_with_rocket() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        stream_min_size = 1,
+        stream_chunk_size = 64,
+        rocket_routes = true
+    );
+    let client = Client::untracked(rocket::build().mount("/", rocket_routes()))
+        .await
+        .unwrap();
+
+    let response = client.get("/app.js").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.body().max_chunk_size(), 64);
+    assert_eq!(
+        response.into_bytes().await.unwrap(),
+        include_bytes!("../../test_assets/big/app.js")
+    );
+
+    // Rocket sends the length of the body stripped from the `GET` response
+    let response = client.head("/app.js").dispatch().await;
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(
+        response.body().preset_size(),
+        Some(include_bytes!("../../test_assets/big/app.js").len())
+    );
+    assert!(response.into_bytes().await.unwrap_or_default().is_empty());
+}
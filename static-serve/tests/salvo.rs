@@ -0,0 +1,140 @@
+//! Integration tests of the router generated with `salvo_router = true`,
+//! apart from the other ones so that they don't link salvo
+#![cfg(feature = "salvo")]
+
+use std::time::{Duration, SystemTime};
+
+use salvo::{
+    Depot, FlowCtrl, Handler, Request, Response, Router, Service,
+    http::{
+        StatusCode,
+        header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_RANGE, ETAG, IF_NONE_MATCH, RANGE},
+    },
+    test::{ResponseExt, TestClient},
+};
+use static_serve::{UrlSigner, embed_assets, headers::ContentEncoding};
+
+#[tokio::test]
+async fn serves_assets_with_salvo() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        salvo_router = true
+    );
+    let service = Service::new(Router::with_path("static").push(salvo_router()));
+
+    let mut response = TestClient::get("http://localhost/static/app.js")
+        .add_header(ACCEPT_ENCODING, "zstd", true)
+        .send(&service)
+        .await;
+    assert_eq!(response.status_code, Some(StatusCode::OK));
+    assert_eq!(response.headers()[CONTENT_ENCODING], "zstd");
+    let etag = response.headers()[ETAG].clone();
+    assert_eq!(
+        response.take_bytes(None).await.unwrap(),
+        get_asset("/app.js")
+            .unwrap()
+            .compressed_body(ContentEncoding::Zstd)
+            .unwrap()
+    );
+
+    let response = TestClient::get("http://localhost/static/app.js")
+        .add_header(IF_NONE_MATCH, etag, true)
+        .send(&service)
+        .await;
+    assert_eq!(response.status_code, Some(StatusCode::NOT_MODIFIED));
+
+    let mut response = TestClient::get("http://localhost/static/app.js")
+        .send(&service)
+        .await;
+    assert_eq!(response.status_code, Some(StatusCode::OK));
+    assert_eq!(
+        response.take_bytes(None).await.unwrap(),
+        include_bytes!("../../test_assets/big/app.js").as_slice()
+    );
+
+    let mut response = TestClient::get("http://localhost/static/app.js")
+        .add_header(RANGE, "bytes=0-9", true)
+        .send(&service)
+        .await;
+    assert_eq!(response.status_code, Some(StatusCode::PARTIAL_CONTENT));
+    let body_len = include_bytes!("../../test_assets/big/app.js").len();
+    assert_eq!(
+        response.headers()[CONTENT_RANGE],
+        format!("bytes 0-9/{body_len}")
+    );
+    assert_eq!(
+        response.take_bytes(None).await.unwrap(),
+        include_bytes!("../../test_assets/big/app.js")[..10]
+    );
+
+    let response = TestClient::get("http://localhost/static/missing.js")
+        .send(&service)
+        .await;
+    assert_eq!(response.status_code, Some(StatusCode::NOT_FOUND));
+}
+
+#[tokio::test]
+async fn serves_percent_encoded_routes_with_salvo() {
+    embed_assets!("../static-serve/test_special_chars", salvo_router = true);
+    let service = Service::new(Router::new().push(salvo_router()));
+
+    // Salvo decodes the paths, which are encoded again for the routes
+    for (route, body) in [
+        ("/my%20file.txt", "spaces\n"),
+        ("/100%25.txt", "percent\n"),
+        ("/caf%C3%A9.txt", "accent\n"),
+        ("/tag%231.txt", "hash\n"),
+    ] {
+        let mut response = TestClient::get(format!("http://localhost{route}"))
+            .send(&service)
+            .await;
+        assert_eq!(response.status_code, Some(StatusCode::OK), "{route}");
+        assert_eq!(response.take_string().await.unwrap(), body, "{route}");
+    }
+}
+
+/// Injects the `UrlSigner` of the signed assets into the depot
+struct InjectSigner(UrlSigner);
+
+#[salvo::async_trait]
+impl Handler for InjectSigner {
+    async fn handle(
+        &self,
+        _request: &mut Request,
+        depot: &mut Depot,
+        _response: &mut Response,
+        _ctrl: &mut FlowCtrl,
+    ) {
+        depot.inject(self.0.clone());
+    }
+}
+
+#[tokio::test]
+async fn takes_the_url_signer_from_the_salvo_depot() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        signed = ["immutable"],
+        salvo_router = true
+    );
+    let signer = UrlSigner::new("downloads key");
+    let url = signer.sign(
+        "/immutable/app.js",
+        SystemTime::now() + Duration::from_secs(3600),
+    );
+
+    let service = Service::new(
+        Router::new()
+            .hoop(InjectSigner(signer))
+            .push(salvo_router()),
+    );
+    let response = TestClient::get(format!("http://localhost{url}"))
+        .send(&service)
+        .await;
+    assert_eq!(response.status_code, Some(StatusCode::OK));
+
+    let response = TestClient::get("http://localhost/immutable/app.js")
+        .send(&service)
+        .await;
+    assert_eq!(response.status_code, Some(StatusCode::FORBIDDEN));
+}
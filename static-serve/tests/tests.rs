@@ -1,24 +1,42 @@
 //! Integration tests for static-serve and macro
-use std::io::Read;
+use std::{
+    convert::Infallible,
+    error::Error as _,
+    io::Read,
+    path::Path,
+    time::{Duration, SystemTime},
+};
 
 use axum::{
-    Router,
+    Extension, Router,
     body::Body,
     http::{
-        HeaderValue, Request, Response, StatusCode,
+        HeaderMap, HeaderValue, Request, Response, StatusCode,
         header::{
-            ACCEPT_ENCODING, ACCEPT_RANGES, CONTENT_ENCODING, IF_NONE_MATCH, IF_RANGE, RANGE,
+            ACCEPT_ENCODING, ACCEPT_RANGES, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH, ETAG,
+            IF_MATCH, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, IF_UNMODIFIED_SINCE,
+            LAST_MODIFIED, LOCATION, RANGE,
         },
     },
+    response::IntoResponse,
+    routing::MethodRouter,
 };
 use http_body_util::BodyExt;
-use tower::ServiceExt;
-
-use static_serve_macro::{embed_asset, embed_assets};
+use tower::{Layer, ServiceExt};
+
+use static_serve::{
+    AssetCounters, ConfigError, Fallback, ImmutableAliases, Redirect, Route, StaticAsset,
+    StaticAssetBackend, StaticAssetFields, StaticAssetSet, StaticServeConfig, StaticServeLayer,
+    UrlSigner, VerifyError, WriteOptions,
+    headers::{ContentEncoding, ResponseHeadersBuilder},
+    set_asset_metrics, verify_assets,
+};
+use static_serve_macro::{embed_asset, embed_asset_set, embed_assets};
 
 enum Compression {
     Zstd,
     Gzip,
+    Brotli,
     Both,
     None,
 }
@@ -39,6 +57,7 @@ fn create_request(route: &str, compression: &Compression) -> Request<axum::body:
         Compression::Both => Some(HeaderValue::from_static("zstd, gzip")),
         Compression::Zstd => Some(HeaderValue::from_static("zstd")),
         Compression::Gzip => Some(HeaderValue::from_static("gzip")),
+        Compression::Brotli => Some(HeaderValue::from_static("br")),
         Compression::None => None,
     };
     match accept_encoding_header {
@@ -67,6 +86,36 @@ fn decompress_gzip(compressed_body: &[u8]) -> Vec<u8> {
     decompressed_body
 }
 
+/// Read the paths and contents of the regular files in a ustar archive
+fn read_tar(tar: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let mut files = Vec::new();
+    let mut offset = 0;
+    while tar[offset..offset + 512].iter().any(|&byte| byte != 0) {
+        let header = &tar[offset..offset + 512];
+        let field = |range: std::ops::Range<usize>| {
+            let bytes = &header[range];
+            let end = bytes
+                .iter()
+                .position(|&byte| byte == 0)
+                .unwrap_or(bytes.len());
+            String::from_utf8(bytes[..end].to_vec()).expect("tar field is not utf-8")
+        };
+        let name = field(0..100);
+        let prefix = field(345..500);
+        let size = usize::from_str_radix(&field(124..136), 8).expect("invalid tar size");
+        let path = if prefix.is_empty() {
+            name
+        } else {
+            format!("{prefix}/{name}")
+        };
+
+        let start = offset + 512;
+        files.push((path, tar[start..start + size].to_vec()));
+        offset = start + size.next_multiple_of(512);
+    }
+    files
+}
+
 #[tokio::test]
 async fn router_created_with_lit_str() {
     embed_assets!("../static-serve/test_assets/small", compress = false);
@@ -78,7 +127,7 @@ async fn router_created_with_lit_str() {
     assert!(parts.status.is_success());
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
 
@@ -99,7 +148,7 @@ async fn router_created_uncompressed_because_not_worthwhile() {
     assert!(parts.status.is_success());
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
 
@@ -125,7 +174,7 @@ async fn router_created_compressed_zstd_only() {
     );
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
 
@@ -159,7 +208,7 @@ async fn router_created_compressed_gzip_only() {
     );
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
 
@@ -193,7 +242,7 @@ async fn router_created_compressed_zstd_or_gzip_accepted() {
     );
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
 
@@ -225,7 +274,7 @@ async fn router_created_ignore_paths_one() {
 
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
     assert_eq!(*collected_body_bytes, *expected_body_bytes);
@@ -267,7 +316,7 @@ async fn handles_conditional_requests_same_etag() {
     );
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     let etag = parts
         .headers
@@ -299,7 +348,7 @@ async fn handles_conditional_requests_same_etag() {
             .headers
             .get("content-length")
             .expect("no content-length header!"),
-        "0"
+        &expected_body_bytes.len().to_string()
     );
     let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
     assert!(collected_body_bytes.is_empty());
@@ -358,7 +407,10 @@ async fn strips_html_correctly() {
     let response = get_response(router.clone(), request).await;
     let (parts, body) = response.into_parts();
     assert!(parts.status.is_success());
-    assert_eq!(parts.headers.get("content-type").unwrap(), "text/html");
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
     assert!(parts.headers.contains_key("etag"));
 
     let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
@@ -370,7 +422,10 @@ async fn strips_html_correctly() {
     let response = get_response(router, request).await;
     let (parts, body) = response.into_parts();
     assert!(parts.status.is_success());
-    assert_eq!(parts.headers.get("content-type").unwrap(), "text/html");
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
     assert!(parts.headers.contains_key("etag"));
 
     let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
@@ -399,7 +454,10 @@ async fn doesnt_strip_html_when_strip_html_false() {
     let response = get_response(router, request).await;
     let (parts, body) = response.into_parts();
     assert!(parts.status.is_success());
-    assert_eq!(parts.headers.get("content-type").unwrap(), "text/html");
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
     assert!(parts.headers.contains_key("etag"));
 
     let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
@@ -424,7 +482,10 @@ async fn doesnt_strip_html_when_not_specified() {
     let response = get_response(router, request).await;
     let (parts, body) = response.into_parts();
     assert!(parts.status.is_success());
-    assert_eq!(parts.headers.get("content-type").unwrap(), "text/html");
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
     assert!(parts.headers.contains_key("etag"));
 
     let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
@@ -448,7 +509,10 @@ async fn handles_one_file_uncompressed() {
     let (parts, body) = response.into_parts();
 
     assert!(parts.status.is_success());
-    assert_eq!(parts.headers.get("content-type").unwrap(), "text/plain");
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/plain; charset=utf-8"
+    );
     assert!(parts.headers.contains_key("etag"));
 
     let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
@@ -472,7 +536,7 @@ async fn handles_one_file_compressed_zstd() {
     assert!(parts.status.is_success());
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
 
@@ -509,7 +573,7 @@ async fn handles_one_file_same_etag_with_cache_control() {
     assert!(parts.status.is_success());
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
     let etag = parts
@@ -544,7 +608,7 @@ async fn handles_one_file_same_etag_with_cache_control() {
             .headers
             .get("content-length")
             .expect("no content-length header!"),
-        "0"
+        &expected_body_bytes.len().to_string()
     );
     assert!(
         parts
@@ -579,7 +643,7 @@ async fn handles_one_file_different_etag_with_cache_control() {
     assert!(parts.status.is_success());
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
     assert!(
@@ -655,7 +719,7 @@ async fn handles_one_file_same_etag_no_cache_control() {
     assert!(parts.status.is_success());
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
     let etag = parts
@@ -691,7 +755,7 @@ async fn handles_one_file_same_etag_no_cache_control() {
             .headers
             .get("content-length")
             .expect("no content-length header!"),
-        "0"
+        &expected_body_bytes.len().to_string()
     );
     assert!(parts.headers.get("cache-control").is_none());
 
@@ -718,7 +782,7 @@ async fn handles_one_file_different_etag_no_cache_control() {
     assert!(parts.status.is_success());
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
     assert!(parts.headers.get("cache-control").is_none());
@@ -786,7 +850,7 @@ async fn using_both_macros_together_works() {
     );
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
 
@@ -810,7 +874,10 @@ async fn using_both_macros_together_works() {
     let (parts, body) = response.into_parts();
 
     assert!(parts.status.is_success());
-    assert_eq!(parts.headers.get("content-type").unwrap(), "text/plain");
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/plain; charset=utf-8"
+    );
     assert!(parts.headers.contains_key("etag"));
 
     let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
@@ -836,7 +903,10 @@ async fn handles_one_file_with_cache_control() {
     let (parts, body) = response.into_parts();
 
     assert!(parts.status.is_success());
-    assert_eq!(parts.headers.get("content-type").unwrap(), "text/plain");
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/plain; charset=utf-8"
+    );
     assert!(
         parts
             .headers
@@ -871,7 +941,10 @@ async fn handles_one_file_with_cache_control_denied() {
     let (parts, body) = response.into_parts();
 
     assert!(parts.status.is_success());
-    assert_eq!(parts.headers.get("content-type").unwrap(), "text/plain");
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/plain; charset=utf-8"
+    );
     assert!(parts.headers.get("cache-control").is_none());
     assert!(parts.headers.contains_key("etag"));
 
@@ -906,7 +979,7 @@ async fn using_both_macros_with_cache_busting_works() {
     );
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
     assert!(parts.headers.get("cache-control").is_none());
@@ -935,7 +1008,7 @@ async fn using_both_macros_with_cache_busting_works() {
     );
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.contains_key("etag"));
     assert!(
@@ -968,7 +1041,10 @@ async fn using_both_macros_with_cache_busting_works() {
     let (parts, body) = response.into_parts();
 
     assert!(parts.status.is_success());
-    assert_eq!(parts.headers.get("content-type").unwrap(), "text/plain");
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/plain; charset=utf-8"
+    );
     assert!(
         parts
             .headers
@@ -1003,7 +1079,10 @@ async fn handles_dir_with_cache_control_on_single_file() {
     let (parts, body) = response.into_parts();
 
     assert!(parts.status.is_success());
-    assert_eq!(parts.headers.get("content-type").unwrap(), "text/css");
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/css; charset=utf-8"
+    );
     assert!(
         parts
             .headers
@@ -1028,7 +1107,7 @@ async fn handles_dir_with_cache_control_on_single_file() {
     assert!(parts.status.is_success());
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(parts.headers.get("cache-control").is_none());
     assert!(parts.headers.contains_key("etag"));
@@ -1055,7 +1134,10 @@ async fn handles_dir_with_cache_control_on_filename_and_dir() {
     let (parts, body) = response.into_parts();
 
     assert!(parts.status.is_success());
-    assert_eq!(parts.headers.get("content-type").unwrap(), "text/css");
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/css; charset=utf-8"
+    );
     assert!(
         parts
             .headers
@@ -1080,7 +1162,7 @@ async fn handles_dir_with_cache_control_on_filename_and_dir() {
     assert!(parts.status.is_success());
     assert_eq!(
         parts.headers.get("content-type").unwrap(),
-        "text/javascript"
+        "text/javascript; charset=utf-8"
     );
     assert!(
         parts
@@ -1232,6 +1314,39 @@ async fn accept_ranges_absent_on_304() {
     assert!(parts.headers.get(ACCEPT_RANGES).is_none());
 }
 
+#[tokio::test]
+async fn not_modified_has_validators_and_length_of_the_body() {
+    embed_assets!("../static-serve/test_assets/big", compress = true);
+    let router: Router<()> = static_router();
+
+    let request = create_request("/app.js", &Compression::Zstd);
+    let response = get_response(router.clone(), request).await;
+    let ok_headers = response.headers().clone();
+
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(ACCEPT_ENCODING, "zstd")
+        .header(IF_NONE_MATCH, ok_headers[ETAG].clone())
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    let headers = response.headers();
+    for name in [
+        "etag",
+        "last-modified",
+        "cache-control",
+        "vary",
+        "content-length",
+    ] {
+        assert_eq!(headers.get(name), ok_headers.get(name), "{name}");
+    }
+    assert!(headers.get("content-type").is_none());
+    assert!(headers.get(CONTENT_ENCODING).is_none());
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(body.is_empty());
+}
+
 #[tokio::test]
 async fn range_request_disables_compression() {
     embed_assets!("../static-serve/test_assets/big", compress = true);
@@ -1283,6 +1398,44 @@ async fn if_range_matching_etag_honors_range() {
     assert_eq!(*collected_body_bytes, *expected);
 }
 
+#[tokio::test]
+async fn if_range_matching_last_modified_honors_range() {
+    let handler = embed_asset!(
+        "../static-serve/test_assets/small/app.js",
+        last_modified_only = true
+    );
+    let router: Router<()> = Router::new().route("/app.js", handler);
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    let last_modified = response.headers().get(LAST_MODIFIED).unwrap().clone();
+
+    // Range + If-Range with the `Last-Modified` date -> 206
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(RANGE, "bytes=5-")
+        .header(IF_RANGE, last_modified)
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(parts.headers.get("content-range").unwrap(), "bytes 5-29/30");
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    let expected = &include_bytes!("../../test_assets/small/app.js")[5..];
+    assert_eq!(*collected_body_bytes, *expected);
+
+    // Range + If-Range with an older date -> 200 with full body
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(RANGE, "bytes=5-")
+        .header(IF_RANGE, "Thu, 01 Jan 1970 00:00:00 GMT")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
 #[tokio::test]
 async fn if_range_mismatched_etag_returns_full_body() {
     embed_assets!("../static-serve/test_assets/small", compress = false);
@@ -1325,14 +1478,14 @@ async fn if_range_mismatched_allows_compression() {
     assert_eq!(parts.headers.get(CONTENT_ENCODING).unwrap(), "zstd");
 }
 
-/// The corresponding failing test is in static-serve-macro/src/lib.rs
-/// in the `embed_assets` docstring, because only doctests support
-/// the `compile_fail` attribute.
+/// The corresponding failing expansion is tested by
+/// `rejects_unknown_extensions` in static-serve-macro/src/lib.rs
 #[tokio::test]
 async fn serves_unknown_extensions() {
     embed_assets!(
         "../static-serve/test_unknown_extensions",
-        allow_unknown_extensions = true
+        allow_unknown_extensions = true,
+        skip_hidden = false
     );
     let router: Router<()> = static_router();
     assert!(router.has_routes());
@@ -1370,6 +1523,118 @@ async fn serves_unknown_extensions() {
     assert_eq!(&collected_body_bytes, &b"example.wtf");
 }
 
+#[tokio::test]
+async fn serves_unknown_extensions_with_configured_content_type() {
+    embed_assets!(
+        "../static-serve/test_unknown_extensions",
+        unknown_mime = "text/plain",
+        keep_hidden = [".DS_STORE"]
+    );
+    let router: Router<()> = static_router();
+    for route in ["/.DS_STORE", "/example.wtf"] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(
+            response.headers().get("content-type").unwrap(),
+            "text/plain; charset=utf-8",
+            "{route}"
+        );
+    }
+
+    let handler = embed_asset!(
+        "../static-serve/test_unknown_extensions/example.wtf",
+        unknown_mime = "application/x-wtf"
+    );
+    let router: Router<()> = Router::new().route("/example.wtf", handler);
+    let request = create_request("/example.wtf", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/x-wtf"
+    );
+}
+
+#[tokio::test]
+async fn appends_configured_charset_to_text_content_types() {
+    let router: Router<()> = {
+        embed_assets!("../static-serve/test_assets/small", charset = "iso-8859-1");
+        static_router()
+    };
+    let request = create_request("/styles.css", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/css; charset=iso-8859-1"
+    );
+
+    embed_assets!(
+        "../static-serve/test_unknown_extensions",
+        allow_unknown_extensions = true,
+        charset = false
+    );
+    let router: Router<()> = static_router();
+    let request = create_request("/example.wtf", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/octet-stream"
+    );
+
+    let handler = embed_asset!("../static-serve/test_assets/small/app.js", charset = false);
+    let router: Router<()> = Router::new().route("/app.js", handler);
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/javascript"
+    );
+}
+
+#[tokio::test]
+async fn overrides_content_types_of_extensions() {
+    let router: Router<()> = {
+        embed_assets!(
+            "../static-serve/test_unknown_extensions",
+            allow_unknown_extensions = true,
+            skip_hidden = false,
+            mime_overrides = [("WTF", "application/x-wtf")]
+        );
+        static_router()
+    };
+    let request = create_request("/example.wtf", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/x-wtf"
+    );
+    // Files without an extension still fall back to octet-stream
+    let request = create_request("/.DS_STORE", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/octet-stream"
+    );
+
+    // Overrides also apply to the extensions known to `mime_guess`
+    embed_assets!(
+        "../static-serve/test_assets/small",
+        mime_overrides = [("js", "application/javascript")]
+    );
+    let router: Router<()> = static_router();
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/javascript; charset=utf-8"
+    );
+    let request = create_request("/styles.css", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/css; charset=utf-8"
+    );
+}
+
 #[tokio::test]
 async fn allow_unknown_extensions_must_retain_content_type_for_known_extensions() {
     // Given a router embedding assets with `allow_unknown_extensions = true` and a file with known
@@ -1386,7 +1651,3751 @@ async fn allow_unknown_extensions_must_retain_content_type_for_known_extensions(
 
     // Then the content type must be `application/javascript`
     assert_eq!(
-        "text/javascript",
+        "text/javascript; charset=utf-8",
         response.headers().get("content-type").unwrap()
     );
 }
+
+#[tokio::test]
+async fn last_modified_only_omits_etag() {
+    embed_assets!(
+        "../static-serve/test_assets/small",
+        last_modified_only = true
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    let (parts, _body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::OK);
+    assert!(parts.headers.get(ETAG).is_none());
+    let last_modified = parts
+        .headers
+        .get(LAST_MODIFIED)
+        .expect("no last-modified header when there should be one!")
+        .clone();
+
+    // Revalidating with the same date is a hit
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(IF_MODIFIED_SINCE, last_modified)
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::NOT_MODIFIED);
+    assert!(parts.headers.get(ETAG).is_none());
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    assert!(collected_body_bytes.is_empty());
+
+    // Revalidating with an older date serves the full body
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(IF_MODIFIED_SINCE, "Thu, 01 Jan 1970 00:00:00 GMT")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::OK);
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    let expected_body_bytes = include_bytes!("../../test_assets/small/app.js");
+    assert_eq!(*collected_body_bytes, *expected_body_bytes);
+}
+
+#[tokio::test]
+async fn negotiates_encoding_with_quality_values() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = ["gzip", "zstd", "br"]
+    );
+    let router: Router<()> = static_router();
+
+    for (accept_encoding, content_encoding) in [
+        ("gzip;q=0", None),
+        ("gzip;q=0, zstd;q=0", None),
+        ("gzip, zstd;q=0.5", Some("gzip")),
+        ("gzip;q=0.8, br;q=0.9, zstd;q=0.1", Some("br")),
+        ("GZIP", Some("gzip")),
+        ("x-gzip", Some("gzip")),
+        ("*", Some("zstd")),
+        ("zstd;q=0, *;q=0.5", Some("br")),
+        ("*;q=0", None),
+        ("gzip;q=2", None),
+        ("identity", None),
+        ("gzip;q=0.1, identity", None),
+        ("br;q=0.9, identity;q=0.5", Some("br")),
+        ("gzip;q=0.5, identity;q=0.5", Some("gzip")),
+    ] {
+        let request = Request::builder()
+            .uri("/app.js")
+            .header(ACCEPT_ENCODING, accept_encoding)
+            .body(Body::empty())
+            .unwrap();
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(
+            response
+                .headers()
+                .get(CONTENT_ENCODING)
+                .map(|content_encoding| content_encoding.to_str().unwrap()),
+            content_encoding,
+            "Accept-Encoding: {accept_encoding}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_unacceptable_identity_bodies() {
+    embed_assets!("../static-serve/test_assets/big", compress = true);
+    let router: Router<()> = static_router();
+
+    for (route, accept_encoding, status, content_encoding) in [
+        (
+            "/app.js",
+            "identity;q=0, gzip",
+            StatusCode::OK,
+            Some("gzip"),
+        ),
+        ("/app.js", "*;q=0, identity", StatusCode::OK, None),
+        ("/app.js", "identity;q=0", StatusCode::NOT_ACCEPTABLE, None),
+        ("/app.js", "*;q=0", StatusCode::NOT_ACCEPTABLE, None),
+        (
+            "/app.js",
+            "br, identity;q=0",
+            StatusCode::NOT_ACCEPTABLE,
+            None,
+        ),
+    ] {
+        let request = Request::builder()
+            .uri(route)
+            .header(ACCEPT_ENCODING, accept_encoding)
+            .body(Body::empty())
+            .unwrap();
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(
+            response.status(),
+            status,
+            "Accept-Encoding: {accept_encoding}"
+        );
+        assert_eq!(
+            response
+                .headers()
+                .get(CONTENT_ENCODING)
+                .map(|content_encoding| content_encoding.to_str().unwrap()),
+            content_encoding,
+            "Accept-Encoding: {accept_encoding}"
+        );
+        assert_eq!(response.headers()["vary"], "Accept-Encoding");
+    }
+
+    // Ranges of the identity body are ignored when it's refused
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(ACCEPT_ENCODING, "identity;q=0, zstd")
+        .header(RANGE, "bytes=0-9")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_ENCODING], "zstd");
+}
+
+#[tokio::test]
+async fn varies_with_accept_encoding_only_with_compressed_variants() {
+    embed_assets!("../static-serve/test_assets/small", compress = false);
+    let router: Router<()> = static_router();
+
+    let request = create_request("/app.js", &Compression::Zstd);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("vary").is_none());
+
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(ACCEPT_ENCODING, "identity;q=0")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_ACCEPTABLE);
+    assert_eq!(response.headers()["vary"], "Accept-Encoding");
+}
+
+#[tokio::test]
+async fn handles_if_none_match_lists_and_weak_etags() {
+    embed_assets!("../static-serve/test_assets/small", compress = false);
+    let router: Router<()> = static_router();
+    let etag = get_asset("/app.js").unwrap().etag().unwrap();
+
+    for (if_none_match, status) in [
+        (format!("W/{etag}"), StatusCode::NOT_MODIFIED),
+        (
+            format!("\"0123456789abcdef\", {etag}"),
+            StatusCode::NOT_MODIFIED,
+        ),
+        (
+            format!("\"0123456789abcdef\",W/{etag}"),
+            StatusCode::NOT_MODIFIED,
+        ),
+        ("*".to_owned(), StatusCode::NOT_MODIFIED),
+        (
+            "\"0123456789abcdef\", W/\"fedcba9876543210\"".to_owned(),
+            StatusCode::OK,
+        ),
+        (etag.trim_matches('"').to_owned(), StatusCode::OK),
+    ] {
+        let request = Request::builder()
+            .uri("/app.js")
+            .header(IF_NONE_MATCH, &if_none_match)
+            .body(Body::empty())
+            .unwrap();
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), status, "If-None-Match: {if_none_match}");
+    }
+}
+
+#[tokio::test]
+async fn handles_if_modified_since_alongside_etags() {
+    embed_assets!("../static-serve/test_assets/small", compress = false);
+    let router: Router<()> = static_router();
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert!(response.headers().contains_key(ETAG));
+    let last_modified = response.headers().get(LAST_MODIFIED).unwrap().clone();
+
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(IF_MODIFIED_SINCE, last_modified.clone())
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // `If-None-Match` takes precedence
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(IF_NONE_MATCH, "\"0123456789abcdef\"")
+        .header(IF_MODIFIED_SINCE, last_modified)
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn answers_failed_preconditions() {
+    embed_assets!("../static-serve/test_assets/small", compress = false);
+    let router: Router<()> = static_router();
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    let etag = response.headers().get(ETAG).unwrap().clone();
+    let last_modified = response.headers().get(LAST_MODIFIED).unwrap().clone();
+    let earlier = HeaderValue::from_static("Thu, 01 Jan 1970 00:00:00 GMT");
+
+    let weak_etag = HeaderValue::from_str(&format!("W/{}", etag.to_str().unwrap())).unwrap();
+    for (headers, status) in [
+        (vec![(IF_MATCH, etag.clone())], StatusCode::OK),
+        (
+            vec![(IF_MATCH, HeaderValue::from_static("*"))],
+            StatusCode::OK,
+        ),
+        (
+            vec![(IF_MATCH, HeaderValue::from_static("\"0123456789abcdef\""))],
+            StatusCode::PRECONDITION_FAILED,
+        ),
+        // `If-Match` uses the strong comparison
+        (vec![(IF_MATCH, weak_etag)], StatusCode::PRECONDITION_FAILED),
+        (
+            vec![(IF_UNMODIFIED_SINCE, last_modified.clone())],
+            StatusCode::OK,
+        ),
+        (
+            vec![(IF_UNMODIFIED_SINCE, earlier.clone())],
+            StatusCode::PRECONDITION_FAILED,
+        ),
+        // `If-Match` takes precedence over `If-Unmodified-Since`
+        (
+            vec![
+                (IF_MATCH, etag.clone()),
+                (IF_UNMODIFIED_SINCE, earlier.clone()),
+            ],
+            StatusCode::OK,
+        ),
+        // ...and is evaluated before `If-None-Match`
+        (
+            vec![(IF_MATCH, etag.clone()), (IF_NONE_MATCH, etag.clone())],
+            StatusCode::NOT_MODIFIED,
+        ),
+        (
+            vec![
+                (IF_UNMODIFIED_SINCE, earlier.clone()),
+                (IF_NONE_MATCH, etag.clone()),
+            ],
+            StatusCode::PRECONDITION_FAILED,
+        ),
+        // A failed precondition isn't answered with a partial body
+        (
+            vec![
+                (IF_UNMODIFIED_SINCE, earlier),
+                (RANGE, HeaderValue::from_static("bytes=0-3")),
+            ],
+            StatusCode::PRECONDITION_FAILED,
+        ),
+    ] {
+        let mut request = Request::builder().uri("/app.js");
+        for (name, value) in &headers {
+            request = request.header(name, value);
+        }
+        let request = request.body(Body::empty()).unwrap();
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), status, "{headers:?}");
+        if status == StatusCode::PRECONDITION_FAILED {
+            let body = response.into_body().collect().await.unwrap().to_bytes();
+            assert!(body.is_empty());
+        }
+    }
+}
+
+#[tokio::test]
+async fn handles_one_file_last_modified_only() {
+    let router: Router<()> = Router::new();
+    let handler = embed_asset!(
+        "../static-serve/test_assets/small/app.js",
+        last_modified_only = true
+    );
+    let router = router.route("/app.js", handler);
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    let (parts, _body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::OK);
+    assert!(parts.headers.get(ETAG).is_none());
+    let last_modified = parts.headers.get(LAST_MODIFIED).unwrap().clone();
+
+    // An ETag sent by a mangling proxy can't produce a false positive
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(IF_NONE_MATCH, "\"n0t4r34l3t4g\"")
+        .header(IF_MODIFIED_SINCE, last_modified)
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn salts_etags() {
+    let unsalted = embed_asset!("../static-serve/test_assets/small/app.js");
+    let salted = embed_asset!(
+        "../static-serve/test_assets/small/app.js",
+        etag_salt = "2024-06-release"
+    );
+    let router: Router<()> = Router::new()
+        .route("/unsalted.js", unsalted)
+        .route("/salted.js", salted);
+
+    let request = create_request("/unsalted.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    let unsalted_etag = response.headers().get(ETAG).unwrap().clone();
+
+    let request = create_request("/salted.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    let (parts, body) = response.into_parts();
+    let salted_etag = parts.headers.get(ETAG).unwrap().clone();
+    assert_ne!(salted_etag, unsalted_etag);
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    let expected_body_bytes = include_bytes!("../../test_assets/small/app.js");
+    assert_eq!(*collected_body_bytes, *expected_body_bytes);
+
+    // Validators cached before the salt changed are stale
+    let request = Request::builder()
+        .uri("/salted.js")
+        .header(IF_NONE_MATCH, unsalted_etag)
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = Request::builder()
+        .uri("/salted.js")
+        .header(IF_NONE_MATCH, salted_etag)
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn builds_headers_of_embedded_routes() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        cache_busted_paths = ["app.js"]
+    );
+    let router: Router<()> = static_router();
+    let asset = get_asset("/app.js").unwrap();
+    let builder = ResponseHeadersBuilder::new(HeaderValue::from_static(asset.content_type()))
+        .etag(HeaderValue::from_static(asset.etag().unwrap()))
+        .last_modified(HeaderValue::from_static(asset.last_modified().unwrap()))
+        .cache_busted(asset.cache_busted());
+
+    let request = create_request("/app.js", &Compression::Zstd);
+    let response = get_response(router.clone(), request).await;
+    // `Content-Length` is set by the body
+    let mut headers = response.headers().clone();
+    headers.remove(CONTENT_LENGTH);
+    assert_eq!(
+        headers,
+        builder
+            .clone()
+            .content_encoding(ContentEncoding::Zstd)
+            .build()
+    );
+
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(IF_NONE_MATCH, asset.etag().unwrap())
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    let mut headers = response.headers().clone();
+    headers.remove(CONTENT_LENGTH);
+    assert_eq!(headers, builder.build_not_modified());
+}
+
+#[test]
+fn writes_assets_to_a_directory() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        cache_busted_paths = ["immutable"]
+    );
+    let dir = std::env::temp_dir().join("static-serve-write-assets-test");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    write_assets_to(&dir).unwrap();
+    assert_eq!(
+        std::fs::read(dir.join("app.js")).unwrap(),
+        include_bytes!("../../test_assets/big/app.js")
+    );
+    assert!(dir.join("immutable/styles.css").is_file());
+    assert!(!dir.join("app.js.zst").exists());
+
+    let cdn_dir = dir.join("cdn");
+    let written = write_assets_to_with(
+        &cdn_dir,
+        WriteOptions::new().fingerprinted(true).precompressed(true),
+    )
+    .unwrap();
+    let file = |route: &str| {
+        written
+            .iter()
+            .find(|(written_route, _)| *written_route == route)
+            .map(|(_, file)| file.clone())
+            .unwrap()
+    };
+    let app_js = file("/app.js");
+    let name = app_js.file_name().unwrap().to_str().unwrap();
+    assert!(name.starts_with("app."), "{name}");
+    assert!(
+        app_js
+            .extension()
+            .is_some_and(|extension| extension == "js"),
+        "{name}"
+    );
+    assert_eq!(name.len(), "app..js".len() + 16);
+    let mut zst = app_js.into_os_string();
+    zst.push(".zst");
+    assert_eq!(
+        decompress_zstd(&std::fs::read(zst).unwrap()),
+        include_bytes!("../../test_assets/big/app.js")
+    );
+    // The cache-busted files keep their names
+    assert_eq!(
+        file("/immutable/styles.css"),
+        cdn_dir.join("immutable/styles.css")
+    );
+}
+
+#[test]
+fn debug_verify_accepts_embedded_variants() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = ["gzip", "zstd", "br"]
+    );
+    debug_verify().expect("embedded variants should round-trip");
+}
+
+#[test]
+fn lists_embedded_assets_in_manifest() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        cache_busted_paths = ["immutable"]
+    );
+    let manifest = asset_manifest();
+    assert_eq!(manifest.len(), 4);
+    assert!(std::ptr::eq(manifest, asset_manifest()));
+
+    let app_js = manifest.iter().find(|info| info.path == "/app.js").unwrap();
+    let asset = get_asset("/app.js").unwrap();
+    assert_eq!(app_js.content_type, "text/javascript; charset=utf-8");
+    assert_eq!(app_js.size, asset.uncompressed_body().len());
+    assert_eq!(
+        app_js.gzip_size,
+        asset
+            .compressed_body(ContentEncoding::Gzip)
+            .map(<[u8]>::len)
+    );
+    assert_eq!(
+        app_js.zstd_size,
+        asset
+            .compressed_body(ContentEncoding::Zstd)
+            .map(<[u8]>::len)
+    );
+    assert!(app_js.gzip_size.is_some_and(|size| size < app_js.size));
+    assert_eq!(app_js.br_size, None);
+    assert_eq!(app_js.etag, asset.etag());
+    assert!(!app_js.cache_busted);
+
+    let immutable_app_js = manifest
+        .iter()
+        .find(|info| info.path == "/immutable/app.js")
+        .unwrap();
+    assert!(immutable_app_js.cache_busted);
+}
+
+#[tokio::test]
+async fn counts_requests_in_asset_metrics() {
+    static ASSET_COUNTERS: AssetCounters = AssetCounters::new();
+    set_asset_metrics(&ASSET_COUNTERS).unwrap();
+    assert!(set_asset_metrics(&ASSET_COUNTERS).is_err());
+
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        prefix = "/metrics/"
+    );
+    let router: Router<()> = static_router();
+
+    let mut etag = None;
+    for _ in 0..2 {
+        let request = create_request("/metrics/app.js", &Compression::Gzip);
+        let response = get_response(router.clone(), request).await;
+        etag = response.headers().get(ETAG).cloned();
+    }
+    let request = Request::builder()
+        .uri("/metrics/app.js")
+        .header(IF_NONE_MATCH, etag.unwrap())
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    let request = create_request("/metrics/styles.css", &Compression::None);
+    get_response(router, request).await;
+
+    let gzip_size = get_asset("/metrics/app.js")
+        .unwrap()
+        .compressed_body(ContentEncoding::Gzip)
+        .unwrap()
+        .len();
+    let styles_size = get_asset("/metrics/styles.css")
+        .unwrap()
+        .uncompressed_body()
+        .len();
+    let exposition = ASSET_COUNTERS.to_prometheus();
+    for line in [
+        "# TYPE static_serve_requests_total counter".to_owned(),
+        "static_serve_requests_total{path=\"/metrics/app.js\",status=\"200\",encoding=\"gzip\"} 2"
+            .to_owned(),
+        "static_serve_requests_total{path=\"/metrics/app.js\",status=\"304\",encoding=\"identity\"} 1"
+            .to_owned(),
+        format!(
+            "static_serve_sent_bytes_total{{path=\"/metrics/app.js\",status=\"200\",encoding=\"gzip\"}} {}",
+            2 * gzip_size
+        ),
+        format!(
+            "static_serve_sent_bytes_total{{path=\"/metrics/styles.css\",status=\"200\",encoding=\"identity\"}} {styles_size}"
+        ),
+    ] {
+        assert!(exposition.lines().any(|l| l == line), "{line}");
+    }
+}
+
+#[tokio::test]
+async fn serves_robots_txt_and_security_txt() {
+    embed_assets!(
+        "../static-serve/test_assets/small",
+        robots_txt = ["/admin", "/api"],
+        security_txt = "Contact: mailto:security@example.com\nExpires: 2030-01-01T00:00:00Z\n"
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/robots.txt", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers().get(CACHE_CONTROL).unwrap(),
+        "public, max-age=86400"
+    );
+    assert!(response.headers().contains_key(ETAG));
+    let body = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(body, "User-agent: *\nDisallow: /admin\nDisallow: /api\n");
+
+    let security_txt = get_asset("/.well-known/security.txt").unwrap();
+    assert_eq!(
+        security_txt.uncompressed_body(),
+        b"Contact: mailto:security@example.com\nExpires: 2030-01-01T00:00:00Z\n"
+    );
+    assert!(get_asset("/app.js").is_some());
+}
+
+#[test]
+fn looks_up_every_embedded_asset() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        cache_busted_paths = ["immutable"]
+    );
+    for info in asset_manifest() {
+        let asset = get_asset(info.path).unwrap();
+        assert_eq!(asset.etag(), info.etag);
+    }
+    let styles_css = get_asset("/styles.css").unwrap();
+    assert_eq!(
+        styles_css.uncompressed_body(),
+        include_bytes!("../../test_assets/big/styles.css")
+    );
+    assert_eq!(styles_css.content_type(), "text/css; charset=utf-8");
+    assert!(styles_css.compressed_body(ContentEncoding::Gzip).is_some());
+    for route in ["", "/", "/styles", "/styles.css/", "/immutable", "/APP.JS"] {
+        assert!(get_asset(route).is_none(), "{route}");
+    }
+}
+
+#[tokio::test]
+async fn percent_encodes_special_characters_in_routes() {
+    embed_assets!("../static-serve/test_special_chars");
+    let router: Router<()> = static_router();
+
+    for (route, body) in [
+        ("/my%20file.txt", "spaces\n"),
+        ("/100%25.txt", "percent\n"),
+        ("/caf%C3%A9.txt", "accent\n"),
+        ("/tag%231.txt", "hash\n"),
+    ] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK, "{route}");
+        let collected_body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(collected_body_bytes, body, "{route}");
+    }
+
+    // Looked up decoded or in any encoding
+    for route in [
+        "/café.txt",
+        "/caf%c3%a9.txt",
+        "/caf%C3%A9.txt",
+        "/my file.txt",
+    ] {
+        assert!(get_asset(route).is_some(), "{route}");
+    }
+    assert!(get_asset("/100%.txt").is_some());
+    assert!(get_asset("/caf%C3.txt").is_none());
+}
+
+#[test]
+fn generates_route_constants() {
+    embed_assets!("../static-serve/test_assets/big", prefix = "/static/");
+    assert_eq!(paths::APP_JS, "/static/app.js");
+    assert_eq!(paths::IMMUTABLE_STYLES_CSS, "/static/immutable/styles.css");
+    assert!(get_asset(paths::IMMUTABLE_APP_JS).is_some());
+
+    embed_assets!(
+        "../static-serve/test_assets/with_html",
+        strip_html_ext = true,
+        fn_name = html_assets
+    );
+    assert_eq!(html_assets::paths::INDEX_HTML, "/");
+    assert_eq!(html_assets::paths::INDEX2_HTM, "/index2");
+}
+
+#[tokio::test]
+async fn composes_asset_sets() {
+    let small = embed_asset_set!("../static-serve/test_assets/small");
+    let big = embed_asset_set!("../static-serve/test_assets/big", prefix = "/big/");
+    assert_eq!(small.iter().count(), 2);
+    assert!(small.get("/big/app.js").is_none());
+
+    let assets: StaticAssetSet = small.clone().merge(big);
+    assert_eq!(assets.iter().count(), 6);
+    assert_eq!(
+        assets.get("/app.js").unwrap().uncompressed_body(),
+        include_bytes!("../../test_assets/small/app.js")
+    );
+    assert_eq!(
+        assets
+            .get("/big/immutable/app.js")
+            .unwrap()
+            .uncompressed_body(),
+        include_bytes!("../../test_assets/big/immutable/app.js")
+    );
+
+    // The routes of the merged set replace the ones at the same paths
+    let replaced = embed_asset_set!("../static-serve/test_assets/big").merge(small);
+    assert_eq!(replaced.iter().count(), 4);
+    assert_eq!(
+        replaced.get("/app.js").unwrap().uncompressed_body(),
+        include_bytes!("../../test_assets/small/app.js")
+    );
+
+    let router: Router<()> = assets.into_axum_router();
+    for route in ["/styles.css", "/big/styles.css"] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK, "{route}");
+    }
+}
+
+/// The corresponding failing expansion is tested by
+/// `rejects_assets_over_budget` in static-serve-macro/src/lib.rs
+#[test]
+// `warn_over_budget` warns with a deprecated item
+#[expect(deprecated)]
+fn enforces_size_budgets() {
+    let within_budgets = {
+        embed_assets!(
+            "../static-serve/test_assets/big",
+            max_file_size = "1MB",
+            max_total_size = 8_000_000
+        );
+        asset_manifest()
+    };
+    assert_eq!(within_budgets.len(), 4);
+
+    let over_budgets = {
+        embed_assets!(
+            "../static-serve/test_assets/big",
+            max_file_size = "10B",
+            warn_over_budget = true
+        );
+        asset_manifest()
+    };
+    assert_eq!(over_budgets.len(), 4);
+}
+
+#[test]
+fn filters_files_with_include_and_exclude_patterns() {
+    let included = {
+        embed_assets!("../static-serve/test_assets/big", include = ["**/*.js"]);
+        asset_manifest()
+    };
+    let mut web_paths: Vec<_> = included.iter().map(|info| info.path).collect();
+    web_paths.sort_unstable();
+    assert_eq!(web_paths, ["/app.js", "/immutable/app.js"]);
+
+    let excluded = {
+        embed_assets!(
+            "../static-serve/test_assets/big",
+            exclude = ["immutable/*", "*.css"]
+        );
+        asset_manifest()
+    };
+    let web_paths: Vec<_> = excluded.iter().map(|info| info.path).collect();
+    assert_eq!(web_paths, ["/app.js"]);
+
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        include = ["**/*.css"],
+        exclude = ["immutable/**"]
+    );
+    let web_paths: Vec<_> = asset_manifest().iter().map(|info| info.path).collect();
+    assert_eq!(web_paths, ["/styles.css"]);
+}
+
+#[test]
+fn skips_hidden_files_by_default() {
+    let default = {
+        embed_assets!(
+            "../static-serve/test_unknown_extensions",
+            allow_unknown_extensions = true
+        );
+        asset_manifest()
+    };
+    let web_paths: Vec<_> = default.iter().map(|info| info.path).collect();
+    assert_eq!(web_paths, ["/example.wtf"]);
+
+    let kept = {
+        embed_assets!(
+            "../static-serve/test_unknown_extensions",
+            allow_unknown_extensions = true,
+            keep_hidden = ["*.DS_STORE"]
+        );
+        asset_manifest()
+    };
+    assert_eq!(kept.len(), 2);
+
+    embed_assets!(
+        "../static-serve/test_unknown_extensions",
+        allow_unknown_extensions = true,
+        skip_hidden = false
+    );
+    assert_eq!(asset_manifest().len(), 2);
+}
+
+#[test]
+fn debug_verify_detects_corrupted_variants() {
+    static CORRUPTED_GZIP: [(&str, StaticAsset); 1] = [(
+        "/app.js",
+        StaticAsset::from_fields(StaticAssetFields {
+            content_type: "text/javascript",
+            etag: None,
+            last_modified: None,
+            body: include_bytes!("../../test_assets/big/app.js"),
+            body_gz: Some(b"definitely not gzip"),
+            body_zst: None,
+            body_br: None,
+            cache_busted: false,
+            immutable_aliases: ImmutableAliases::NONE,
+            cache_control: None,
+            headers: &[],
+            cors: &[],
+            lazy_body: None,
+            image_variants: &[],
+            media_variants: &[],
+            integrity: None,
+            stream_chunk_size: None,
+            basic_auth: None,
+            signed: false,
+            encoding_preference: &[],
+            source_map_gate: None,
+        }),
+    )];
+    static MISMATCHED_ZSTD: [(&str, StaticAsset); 1] = [(
+        "/styles.css",
+        StaticAsset::from_fields(StaticAssetFields {
+            content_type: "text/css",
+            etag: None,
+            last_modified: None,
+            body: include_bytes!("../../test_assets/big/styles.css"),
+            body_gz: None,
+            body_zst: Some(include_bytes!("../../test_assets/dist/app.js.zst")),
+            body_br: None,
+            cache_busted: false,
+            immutable_aliases: ImmutableAliases::NONE,
+            cache_control: None,
+            headers: &[],
+            cors: &[],
+            lazy_body: None,
+            image_variants: &[],
+            media_variants: &[],
+            integrity: None,
+            stream_chunk_size: None,
+            basic_auth: None,
+            signed: false,
+            encoding_preference: &[],
+            source_map_gate: None,
+        }),
+    )];
+
+    static MISMATCHED_BROTLI: [(&str, StaticAsset); 1] = [(
+        "/styles.css",
+        StaticAsset::from_fields(StaticAssetFields {
+            content_type: "text/css",
+            etag: None,
+            last_modified: None,
+            body: include_bytes!("../../test_assets/big/styles.css"),
+            body_gz: None,
+            body_zst: None,
+            body_br: Some(include_bytes!("../../test_precompressed/app.js.br")),
+            cache_busted: false,
+            immutable_aliases: ImmutableAliases::NONE,
+            cache_control: None,
+            headers: &[],
+            cors: &[],
+            lazy_body: None,
+            image_variants: &[],
+            media_variants: &[],
+            integrity: None,
+            stream_chunk_size: None,
+            basic_auth: None,
+            signed: false,
+            encoding_preference: &[],
+            source_map_gate: None,
+        }),
+    )];
+
+    assert!(matches!(
+        verify_assets(&CORRUPTED_GZIP),
+        Err(VerifyError::Decompress {
+            web_path: "/app.js",
+            encoding: "gzip",
+            ..
+        })
+    ));
+    assert!(matches!(
+        verify_assets(&MISMATCHED_ZSTD),
+        Err(VerifyError::Mismatch {
+            web_path: "/styles.css",
+            encoding: "zstd",
+        })
+    ));
+    assert!(matches!(
+        verify_assets(&MISMATCHED_BROTLI),
+        Err(VerifyError::Mismatch {
+            web_path: "/styles.css",
+            encoding: "br",
+        })
+    ));
+}
+
+#[tokio::test]
+async fn serves_tarball_of_the_tree() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        tarball = "/bundle.tar.gz"
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/bundle.tar.gz", &Compression::Both);
+    let response = get_response(router, request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::OK);
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "application/gzip"
+    );
+    assert!(parts.headers.get(CONTENT_ENCODING).is_none());
+    assert!(parts.headers.contains_key("etag"));
+
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    let mut files = read_tar(&decompress_gzip(&collected_body_bytes));
+    files.sort();
+    let expected: Vec<(String, Vec<u8>)> = vec![
+        (
+            "app.js".to_owned(),
+            include_bytes!("../../test_assets/big/app.js").to_vec(),
+        ),
+        (
+            "immutable/app.js".to_owned(),
+            include_bytes!("../../test_assets/big/immutable/app.js").to_vec(),
+        ),
+        (
+            "immutable/styles.css".to_owned(),
+            include_bytes!("../../test_assets/big/immutable/styles.css").to_vec(),
+        ),
+        (
+            "styles.css".to_owned(),
+            include_bytes!("../../test_assets/big/styles.css").to_vec(),
+        ),
+    ];
+    assert_eq!(files, expected);
+}
+
+#[tokio::test]
+async fn writes_json_route_definitions() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        cache_busted_paths = ["immutable"],
+        route_definitions = "../static-serve/target/static-serve-test-routes.json"
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    let etag = response.headers().get(ETAG).unwrap().to_str().unwrap();
+    let hash = etag.trim_matches('"');
+
+    let definitions = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../target/static-serve-test-routes.json"
+    ))
+    .unwrap();
+    assert!(definitions.starts_with("{\n"));
+    assert!(definitions.contains(&format!(
+        "  \"/app.js\": {{\n    \"contentType\": \"text/javascript; charset=utf-8\",\n    \"hash\": \"{hash}\",\n    \"cacheBusted\": false\n  }}"
+    )));
+    assert!(definitions.contains(
+        "  \"/immutable/styles.css\": {\n    \"contentType\": \"text/css; charset=utf-8\",\n    \"hash\": \""
+    ));
+    assert!(definitions.contains("    \"cacheBusted\": true\n"));
+}
+
+#[tokio::test]
+async fn writes_csv_report() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        report = "../static-serve/target/static-serve-test-report.csv"
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/app.js", &Compression::Zstd);
+    let response = get_response(router, request).await;
+    let zstd_size = response
+        .into_body()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes()
+        .len();
+
+    let report = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../target/static-serve-test-report.csv"
+    ))
+    .unwrap();
+    let mut lines = report.lines();
+    assert_eq!(
+        lines.next(),
+        Some("route,content_type,size,gzip_size,zstd_size,brotli_size,encodings")
+    );
+    let app_js = lines
+        .find(|line| line.starts_with("/app.js,"))
+        .unwrap()
+        .split(',')
+        .collect::<Vec<_>>();
+    assert_eq!(app_js[1], "text/javascript; charset=utf-8");
+    assert_eq!(app_js[4], zstd_size.to_string());
+    assert_eq!(app_js[6], "gzip zstd");
+}
+
+#[tokio::test]
+async fn uses_full_hashes_as_etags() {
+    embed_assets!("../static-serve/test_assets/small", etag = "sha256");
+    let router: Router<()> = static_router();
+
+    let integrity = "sha256-/o9AgDola1tlyX9fTK6GL9VbN8uB8qgDxWw/ZUwi6e8=";
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(
+        response.headers().get(ETAG).unwrap(),
+        &format!("\"{integrity}\"")
+    );
+    let app_js = asset_manifest()
+        .iter()
+        .find(|info| info.path == "/app.js")
+        .unwrap();
+    assert_eq!(app_js.integrity, Some(integrity));
+
+    let salted = {
+        embed_assets!(
+            "../static-serve/test_assets/small",
+            etag = "sha512",
+            etag_salt = "v2"
+        );
+        get_asset("/styles.css").unwrap()
+    };
+    assert_eq!(
+        salted.integrity(),
+        Some(
+            "sha512-G0SSDAfx8/ht0ZnSx9txleOVH/dceJz+OpKovZ/Y0khVeAmHfH8qZa7k6T9tX0A1+3w1R/souFz0c5SKT6KQNg=="
+        )
+    );
+    assert!(salted.etag().unwrap().starts_with("\"sha512-"));
+    assert_ne!(salted.etag(), salted.integrity());
+
+    let folded = {
+        embed_assets!("../static-serve/test_assets/small");
+        get_asset("/app.js").unwrap()
+    };
+    assert_eq!(folded.integrity(), None);
+
+    let blake3 = {
+        embed_assets!("../static-serve/test_assets/small", etag = "blake3");
+        get_asset("/app.js").unwrap()
+    };
+    assert!(blake3.etag().unwrap().starts_with("\"blake3-"));
+    assert_eq!(blake3.integrity(), None);
+
+    let sha1 = {
+        embed_assets!("../static-serve/test_assets/small", etag = "sha1-64");
+        get_asset("/app.js").unwrap()
+    };
+    assert_eq!(sha1.etag().unwrap().len(), 18);
+    assert_ne!(sha1.etag(), folded.etag());
+}
+
+#[test]
+fn writes_expansion_to_file() {
+    embed_assets!(
+        "../static-serve/test_assets/small",
+        expand_to = "../static-serve/target/static-serve-test-expansion.rs"
+    );
+    let _router: Router<()> = static_router();
+
+    let expansion = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../target/static-serve-test-expansion.rs"
+    ))
+    .unwrap();
+    assert!(expansion.starts_with("// Generated by static-serve, do not edit.\n"));
+    assert!(expansion.contains("fn static_router"));
+    assert!(expansion.contains("\"/app.js\""));
+}
+
+#[test]
+fn writes_typescript_route_declarations() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        route_definitions = "../static-serve/target/static-serve-test-routes.d.ts"
+    );
+
+    let declarations = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../target/static-serve-test-routes.d.ts"
+    ))
+    .unwrap();
+    assert_eq!(
+        declarations,
+        r#"// Generated by static-serve, do not edit.
+
+export interface StaticRouteInfo {
+  readonly contentType: string;
+  readonly hash: string | null;
+  readonly cacheBusted: boolean;
+  readonly integrity?: string;
+}
+
+export type StaticRoute =
+  | "/app.js"
+  | "/immutable/app.js"
+  | "/immutable/styles.css"
+  | "/styles.css";
+"#
+    );
+}
+
+#[tokio::test]
+async fn serves_import_map() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        cache_busted_paths = ["immutable"],
+        import_map = "/importmap.json"
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    let etag = response.headers().get(ETAG).unwrap().to_str().unwrap();
+    let hash = etag.trim_matches('"');
+
+    let request = create_request("/importmap.json", &Compression::None);
+    let response = get_response(router, request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::OK);
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "application/importmap+json"
+    );
+    assert!(parts.headers.contains_key(ETAG));
+
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        collected_body_bytes,
+        format!("{{\n  \"imports\": {{\n    \"/app.js\": \"/app.js?v={hash}\"\n  }}\n}}\n")
+    );
+}
+
+#[tokio::test]
+async fn injects_import_map_into_html() {
+    embed_assets!(
+        "../static-serve/test_assets/with_html",
+        import_map = "/importmap.json",
+        inject_import_map = true
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/index.html", &Compression::None);
+    let response = get_response(router, request).await;
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    let html = std::str::from_utf8(&collected_body_bytes).unwrap();
+    assert!(html.starts_with(
+        "<!DOCTYPE html>\n<html>\n<head><script type=\"importmap\">\n{\n  \"imports\": {}\n}\n</script>\n    <meta charset=\"UTF-8\" />"
+    ));
+}
+
+#[tokio::test]
+async fn negotiates_not_found_responses() {
+    embed_assets!(
+        "../static-serve/test_assets/with_html",
+        compress = true,
+        not_found = "index.html"
+    );
+    let router: Router<()> = static_router();
+
+    let request = Request::builder()
+        .uri("/missing")
+        .header(
+            "accept",
+            "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8",
+        )
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
+    assert_eq!(
+        parts.headers.get("vary").unwrap(),
+        "Accept, Accept-Encoding"
+    );
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        collected_body_bytes,
+        &include_bytes!("../../test_assets/with_html/index.html")[..]
+    );
+
+    let request = Request::builder()
+        .uri("/missing")
+        .header("accept", "application/json")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::NOT_FOUND);
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "application/problem+json"
+    );
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        collected_body_bytes,
+        r#"{"type":"about:blank","title":"Not Found","status":404}"#
+    );
+}
+
+#[tokio::test]
+async fn serves_assets_from_manifest() {
+    embed_assets!(manifest = "../static-serve/test_manifest/assets.toml");
+    let router: Router<()> = static_router();
+
+    let request = create_request("/js/app.js", &Compression::Zstd);
+    let response = get_response(router.clone(), request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::OK);
+    assert_eq!(parts.headers.get(CONTENT_ENCODING).unwrap(), "zstd");
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/javascript; charset=utf-8"
+    );
+    assert!(parts.headers.get("cache-control").is_none());
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        decompress_zstd(&collected_body_bytes),
+        include_bytes!("../../test_assets/big/app.js")
+    );
+
+    let request = create_request("/", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
+    assert_eq!(
+        response.headers().get("cache-control").unwrap(),
+        "public, max-age=31536000, immutable"
+    );
+
+    let request = create_request("/notes", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/plain; charset=utf-8"
+    );
+
+    // Files which aren't listed in the manifest are not embedded
+    let request = create_request("/styles.css", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn selects_compression_per_group() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        compression_groups = {
+            "immutable/*.js" => ["zstd"],
+            "immutable/*" => ["gzip"],
+            "styles.css" => [],
+        }
+    );
+    let router: Router<()> = static_router();
+
+    let cases = [
+        ("/app.js", Some("zstd")),
+        ("/immutable/app.js", Some("zstd")),
+        ("/immutable/styles.css", Some("gzip")),
+        ("/styles.css", None),
+    ];
+    for (path, expected_encoding) in cases {
+        let request = create_request(path, &Compression::Both);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(
+            response
+                .headers()
+                .get(CONTENT_ENCODING)
+                .map(|encoding| encoding.to_str().unwrap()),
+            expected_encoding,
+            "{path}"
+        );
+    }
+
+    // The zstd-only group has no gzip variant to fall back to
+    let request = create_request("/immutable/app.js", &Compression::Gzip);
+    let response = get_response(router, request).await;
+    assert!(response.headers().get(CONTENT_ENCODING).is_none());
+}
+
+#[tokio::test]
+async fn serves_brotli_variants() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = ["gzip", "zstd", "br"]
+    );
+    let router: Router<()> = static_router();
+    let asset = get_asset("/app.js").unwrap();
+
+    let request = create_request("/app.js", &Compression::Brotli);
+    let response = get_response(router.clone(), request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.headers.get(CONTENT_ENCODING).unwrap(), "br");
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        *collected_body_bytes,
+        *asset.compressed_body(ContentEncoding::Brotli).unwrap()
+    );
+
+    // zstd is preferred when both are accepted
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(ACCEPT_ENCODING, "br, zstd")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "zstd");
+
+    let handler = embed_asset!("../static-serve/test_assets/big/app.js", compress = ["br"]);
+    let router: Router<()> = Router::new().route("/app.js", handler);
+    let request = create_request("/app.js", &Compression::Both);
+    let response = get_response(router.clone(), request).await;
+    assert!(response.headers().get(CONTENT_ENCODING).is_none());
+    let request = create_request("/app.js", &Compression::Brotli);
+    let response = get_response(router, request).await;
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "br");
+}
+
+#[tokio::test]
+async fn embeds_selected_compression_formats() {
+    embed_assets!("../static-serve/test_assets/big", compress = ["zstd"]);
+    let router: Router<()> = static_router();
+    let asset = get_asset("/app.js").unwrap();
+    assert!(asset.compressed_body(ContentEncoding::Zstd).is_some());
+    assert!(asset.compressed_body(ContentEncoding::Gzip).is_none());
+
+    let request = create_request("/app.js", &Compression::Gzip);
+    let response = get_response(router, request).await;
+    assert!(response.headers().get(CONTENT_ENCODING).is_none());
+}
+
+#[tokio::test]
+async fn embeds_precompressed_siblings() {
+    embed_assets!(
+        "../static-serve/test_precompressed",
+        compress = true,
+        use_precompressed = true
+    );
+    let router: Router<()> = static_router();
+    assert!(get_asset("/app.js.gz").is_none());
+    assert!(get_asset("/app.js.br").is_none());
+
+    let app_js = get_asset("/app.js").unwrap();
+    assert_eq!(
+        app_js.compressed_body(ContentEncoding::Gzip).unwrap(),
+        include_bytes!("../../test_precompressed/app.js.gz")
+    );
+    assert_eq!(
+        app_js.compressed_body(ContentEncoding::Zstd).unwrap(),
+        include_bytes!("../../test_precompressed/app.js.zst")
+    );
+    assert_eq!(
+        app_js.compressed_body(ContentEncoding::Brotli).unwrap(),
+        include_bytes!("../../test_precompressed/app.js.br")
+    );
+
+    // Files without siblings are compressed as usual
+    let styles_css = get_asset("/styles.css").unwrap();
+    assert!(styles_css.compressed_body(ContentEncoding::Gzip).is_some());
+    assert!(
+        styles_css
+            .compressed_body(ContentEncoding::Brotli)
+            .is_none()
+    );
+
+    let request = create_request("/app.js", &Compression::Brotli);
+    let response = get_response(router, request).await;
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "br");
+}
+
+#[tokio::test]
+async fn serves_files_from_disk_in_debug_builds() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        cache_busted_paths = ["immutable"],
+        debug_from_disk = true
+    );
+    let router: Router<()> = static_router();
+    let asset = get_asset("/immutable/app.js").unwrap();
+
+    let request = create_request("/immutable/app.js", &Compression::Zstd);
+    let response = get_response(router.clone(), request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::OK);
+    if cfg!(debug_assertions) {
+        assert!(parts.headers.get(CONTENT_ENCODING).is_none());
+        assert!(parts.headers.get(CACHE_CONTROL).is_none());
+        // The unchanged file has the `ETag` it was embedded with
+        assert_eq!(parts.headers.get(ETAG).unwrap(), asset.etag().unwrap());
+        let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+        assert_eq!(
+            *collected_body_bytes,
+            *include_bytes!("../../test_assets/big/immutable/app.js")
+        );
+
+        let request = Request::builder()
+            .uri("/immutable/app.js")
+            .header(IF_NONE_MATCH, parts.headers.get(ETAG).unwrap())
+            .body(Body::empty())
+            .unwrap();
+        let response = get_response(router, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    } else {
+        assert_eq!(parts.headers.get(CONTENT_ENCODING).unwrap(), "zstd");
+    }
+}
+
+#[tokio::test]
+async fn serves_spa_fallback() {
+    embed_assets!(
+        "../static-serve/test_assets/with_html",
+        spa_fallback = "index.html"
+    );
+    let router: Router<()> = static_router();
+    let index = get_asset("/index.html").unwrap();
+
+    let request = create_request("/users/42/settings", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::OK);
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
+    assert_eq!(parts.headers.get(ETAG).unwrap(), index.etag().unwrap());
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        collected_body_bytes,
+        &include_bytes!("../../test_assets/with_html/index.html")[..]
+    );
+
+    let request = Request::builder()
+        .uri("/users/42/settings")
+        .header(IF_NONE_MATCH, index.etag().unwrap())
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/api/users")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn mounts_routes_under_prefix() {
+    embed_assets!(
+        "../static-serve/test_assets/with_html",
+        strip_html_ext = true,
+        prefix = "/static/"
+    );
+    let router: Router<()> = static_router();
+    assert!(get_asset("/static/").is_some());
+    assert!(get_asset("/static/index2").is_some());
+    assert!(get_asset("/index2").is_none());
+
+    let request = create_request("/static/", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(
+        collected_body_bytes,
+        &include_bytes!("../../test_assets/with_html/index.html")[..]
+    );
+
+    let request = create_request("/index2", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let import_map = {
+        embed_assets!(
+            "../static-serve/test_assets/big",
+            import_map = "/importmap.json",
+            prefix = "/static"
+        );
+        get_asset("/static/importmap.json").unwrap()
+    };
+    let import_map = std::str::from_utf8(import_map.uncompressed_body()).unwrap();
+    assert!(import_map.contains(r#""/static/app.js": "/static/app.js?v="#));
+}
+
+#[tokio::test]
+async fn handles_head_requests() {
+    embed_assets!("../static-serve/test_assets/big", compress = true);
+    let router: Router<()> = static_router();
+    let handler = embed_asset!("../static-serve/test_assets/big/app.js", compress = true);
+    let single_router: Router<()> = Router::new().route("/app.js", handler);
+
+    for router in [router, single_router] {
+        let request = Request::builder()
+            .method("HEAD")
+            .uri("/app.js")
+            .header(ACCEPT_ENCODING, "zstd")
+            .body(Body::empty())
+            .unwrap();
+        let response = get_response(router.clone(), request).await;
+        let (parts, body) = response.into_parts();
+        assert_eq!(parts.status, StatusCode::OK);
+        assert_eq!(parts.headers.get(CONTENT_ENCODING).unwrap(), "zstd");
+        assert_eq!(
+            parts.headers.get("content-type").unwrap(),
+            "text/javascript; charset=utf-8"
+        );
+        assert!(parts.headers.contains_key(ETAG));
+        assert!(parts.headers.contains_key(CONTENT_LENGTH));
+        let get_request = create_request("/app.js", &Compression::Zstd);
+        let get_response_parts = get_response(router, get_request).await.into_parts().0;
+        assert_eq!(
+            parts.headers.get(CONTENT_LENGTH),
+            get_response_parts.headers.get(CONTENT_LENGTH)
+        );
+        let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+        assert!(collected_body_bytes.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn serves_assets_from_tower_service() {
+    embed_assets!("../static-serve/test_assets/big", compress = true);
+    let service = static_service();
+
+    // Requests of any body type are accepted
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(ACCEPT_ENCODING, "zstd")
+        .body(())
+        .unwrap();
+    let response = service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "zstd");
+    let etag = response.headers().get(ETAG).unwrap().clone();
+    let collected_body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        collected_body_bytes,
+        get_asset("/app.js")
+            .unwrap()
+            .compressed_body(ContentEncoding::Zstd)
+            .unwrap()
+    );
+
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(IF_NONE_MATCH, etag)
+        .body(String::new())
+        .unwrap();
+    let response = service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    let request = Request::builder()
+        .method("HEAD")
+        .uri("/app.js")
+        .header(ACCEPT_ENCODING, "zstd")
+        .body(())
+        .unwrap();
+    let response = service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // The length of the body which would have been sent
+    let body_zst = get_asset("/app.js")
+        .unwrap()
+        .compressed_body(ContentEncoding::Zstd)
+        .unwrap();
+    assert_eq!(
+        response.headers().get(CONTENT_LENGTH).unwrap(),
+        &body_zst.len().to_string()
+    );
+    let collected_body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert!(collected_body_bytes.is_empty());
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/app.js")
+        .body(())
+        .unwrap();
+    let response = service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+
+    let request = Request::builder().uri("/missing.js").body(()).unwrap();
+    let response = service.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn falls_back_in_tower_service() {
+    embed_assets!(
+        "../static-serve/test_assets/with_html",
+        not_found = "index.html",
+        fn_name = not_found_assets
+    );
+    embed_assets!(
+        "../static-serve/test_assets/with_html",
+        spa_fallback = "index.html",
+        fn_name = spa_assets
+    );
+    let index_html = not_found_assets::get_asset("/index.html")
+        .unwrap()
+        .uncompressed_body();
+
+    let request = Request::builder()
+        .uri("/missing")
+        .header("accept", "text/html")
+        .body(())
+        .unwrap();
+    let response = not_found_assets::static_service()
+        .oneshot(request)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
+    let collected_body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(collected_body_bytes, index_html);
+
+    let request = Request::builder()
+        .uri("/missing")
+        .header("accept", "application/json")
+        .body(())
+        .unwrap();
+    let response = not_found_assets::static_service()
+        .oneshot(request)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/problem+json"
+    );
+
+    let request = Request::builder()
+        .uri("/some/client/route")
+        .body(())
+        .unwrap();
+    let response = spa_assets::static_service().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let collected_body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(collected_body_bytes, index_html);
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/some/client/route")
+        .body(())
+        .unwrap();
+    let response = spa_assets::static_service().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn answers_options_and_other_methods() {
+    embed_assets!(
+        "../static-serve/test_assets/small",
+        fn_name = answering_assets
+    );
+    embed_assets!(
+        "../static-serve/test_assets/small",
+        fall_through_methods = true,
+        fn_name = falling_through_assets
+    );
+    let router: Router<()> = answering_assets();
+
+    let request = Request::builder()
+        .method("OPTIONS")
+        .uri("/app.js")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(response.headers()["allow"], "GET, HEAD");
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/app.js")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()["allow"], "GET, HEAD");
+
+    // The other methods are handled by the routes merged with the assets
+    let router: Router<()> =
+        falling_through_assets().route("/app.js", axum::routing::post(|| async { "posted" }));
+    let request = Request::builder()
+        .method("POST")
+        .uri("/app.js")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(*collected_body_bytes, *b"posted");
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn hands_routes_to_custom_backends() {
+    /// A backend listing the routes it is given
+    struct Listing;
+
+    impl StaticAssetBackend for Listing {
+        type Router = (Vec<&'static str>, Option<&'static str>);
+
+        fn router(
+            assets: &[Route],
+            _redirects: &[Redirect],
+            fallback: Option<Fallback>,
+        ) -> Self::Router {
+            let web_paths = assets.iter().map(|route| route.web_path).collect();
+            let fallback = fallback.map(|fallback| match fallback {
+                Fallback::NotFound(asset) | Fallback::Spa(asset) => asset.content_type(),
+            });
+            (web_paths, fallback)
+        }
+    }
+
+    embed_assets!(
+        "../static-serve/test_assets/with_html",
+        not_found = "index.html"
+    );
+    let (mut web_paths, fallback) = static_router_for::<Listing>();
+    web_paths.sort_unstable();
+    assert_eq!(web_paths, ["/index.html", "/index2.htm"]);
+    assert_eq!(fallback, Some("text/html; charset=utf-8"));
+}
+
+/// Add `X-Wrapped: true` to the responses of the scripts
+fn wrap_scripts<S>(route: &Route, method_router: MethodRouter<S>) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    if Path::new(route.web_path)
+        .extension()
+        .is_none_or(|extension| extension != "js")
+    {
+        return method_router;
+    }
+    method_router.layer(axum::middleware::map_response(
+        |mut response: axum::response::Response| async move {
+            response
+                .headers_mut()
+                .insert("x-wrapped", HeaderValue::from_static("true"));
+            response
+        },
+    ))
+}
+
+#[tokio::test]
+async fn wraps_the_route_of_each_asset() {
+    embed_assets!("../static-serve/test_assets/small", wrap = wrap_scripts);
+    embed_assets!(
+        "../static-serve/test_assets/small",
+        wrap = self::wrap_scripts,
+        fn_name = wrapped_assets
+    );
+
+    let routers: [Router<()>; 2] = [static_router(), wrapped_assets()];
+    for router in routers {
+        let request = create_request("/app.js", &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-wrapped"], "true");
+
+        let request = create_request("/styles.css", &Compression::None);
+        let response = get_response(router, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get("x-wrapped").is_none());
+    }
+}
+
+#[tokio::test]
+async fn sets_custom_cache_control_values() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        cache_busted_paths = ["immutable"],
+        cache_control = [
+            ("immutable", "public, max-age=60"),
+            ("immutable/app.js", "no-cache"),
+        ]
+    );
+    let router: Router<()> = static_router();
+
+    for (route, cache_control) in [
+        ("/immutable/app.js", Some("no-cache")),
+        ("/immutable/styles.css", Some("public, max-age=60")),
+        ("/app.js", None),
+    ] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(
+            response
+                .headers()
+                .get(CACHE_CONTROL)
+                .map(|cache_control| cache_control.to_str().unwrap()),
+            cache_control,
+            "{route}"
+        );
+    }
+
+    // The custom value is also sent on `304 Not Modified` responses
+    let etag = get_asset("/immutable/app.js").unwrap().etag().unwrap();
+    let request = Request::builder()
+        .uri("/immutable/app.js")
+        .header(IF_NONE_MATCH, etag)
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers().get(CACHE_CONTROL).unwrap(), "no-cache");
+}
+
+#[tokio::test]
+async fn sets_max_age_of_cache_busted_assets() {
+    let handler = embed_asset!(
+        "../static-serve/test_assets/big/app.js",
+        cache_bust = true,
+        max_age = 3600
+    );
+    let router: Router<()> = Router::new().route("/app.js", handler);
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(
+        response.headers().get(CACHE_CONTROL).unwrap(),
+        "public, max-age=3600, immutable"
+    );
+
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        cache_busted_paths = ["immutable"],
+        max_age = 600,
+        cache_control = [("immutable/app.js", "no-cache")]
+    );
+    let router: Router<()> = static_router();
+
+    for (route, cache_control) in [
+        (
+            "/immutable/styles.css",
+            Some("public, max-age=600, immutable"),
+        ),
+        ("/immutable/app.js", Some("no-cache")),
+        ("/app.js", None),
+    ] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(
+            response
+                .headers()
+                .get(CACHE_CONTROL)
+                .map(|cache_control| cache_control.to_str().unwrap()),
+            cache_control,
+            "{route}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn compresses_with_configured_levels() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        gzip_level = 1,
+        zstd_level = 1
+    );
+    let router: Router<()> = static_router();
+    let fast = get_asset("/app.js").unwrap();
+    let best = {
+        embed_assets!("../static-serve/test_assets/big", compress = true);
+        get_asset("/app.js").unwrap()
+    };
+
+    // The fastest gzip level trades some compression ratio
+    let fast_gzip = fast.compressed_body(ContentEncoding::Gzip).unwrap();
+    let fast_zstd = fast.compressed_body(ContentEncoding::Zstd).unwrap();
+    assert!(fast_gzip.len() > best.compressed_body(ContentEncoding::Gzip).unwrap().len());
+    assert_eq!(
+        decompress_gzip(fast_gzip),
+        include_bytes!("../../test_assets/big/app.js")
+    );
+    assert_eq!(
+        decompress_zstd(fast_zstd),
+        include_bytes!("../../test_assets/big/app.js")
+    );
+
+    let request = create_request("/app.js", &Compression::Zstd);
+    let response = get_response(router, request).await;
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(*collected_body_bytes, *fast_zstd);
+
+    let handler = embed_asset!(
+        "../static-serve/test_assets/big/app.js",
+        compress = true,
+        gzip_level = 1,
+        zstd_level = 1
+    );
+    let router: Router<()> = Router::new().route("/app.js", handler);
+    let request = create_request("/app.js", &Compression::Gzip);
+    let response = get_response(router, request).await;
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(*collected_body_bytes, *fast_gzip);
+}
+
+#[tokio::test]
+async fn configures_the_router_at_runtime() {
+    embed_assets!("../static-serve/test_assets/big", compress = true);
+    let config = StaticServeConfig::new()
+        .cache_control("/immutable/", "no-store")
+        .unwrap()
+        .header("/", "X-Frame-Options", "DENY")
+        .unwrap()
+        .header("/immutable/", "x-frame-options", "SAMEORIGIN")
+        .unwrap()
+        .encodings(&[ContentEncoding::Gzip])
+        .not_found("/styles.css");
+    let router: Router<()> = static_router_with_config(&config).unwrap();
+
+    let request = create_request("/immutable/app.js", &Compression::Zstd);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CACHE_CONTROL], "no-store");
+    assert_eq!(response.headers()["x-frame-options"], "SAMEORIGIN");
+    assert!(response.headers().get(CONTENT_ENCODING).is_none());
+
+    let request = create_request("/app.js", &Compression::Both);
+    let response = get_response(router.clone(), request).await;
+    assert!(response.headers().get(CACHE_CONTROL).is_none());
+    assert_eq!(response.headers()["x-frame-options"], "DENY");
+    assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+
+    let request = Request::builder()
+        .uri("/missing")
+        .header("accept", "text/html")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        body,
+        &include_bytes!("../../test_assets/big/styles.css")[..]
+    );
+
+    // Without settings, the assets are served as embedded
+    let router: Router<()> = static_router_with_config(&StaticServeConfig::new()).unwrap();
+    let request = create_request("/app.js", &Compression::Zstd);
+    let response = get_response(router, request).await;
+    assert_eq!(response.headers()[CONTENT_ENCODING], "zstd");
+}
+
+#[test]
+fn rejects_invalid_runtime_settings() {
+    embed_assets!("../static-serve/test_assets/big");
+    let error = StaticServeConfig::new()
+        .cache_control("/", "no-store\n")
+        .unwrap_err();
+    assert!(matches!(error, ConfigError::HeaderValue { .. }));
+    assert_eq!(error.to_string(), r#"Invalid header value "no-store\n""#);
+    let error = StaticServeConfig::new()
+        .header("/", "x frame options", "DENY")
+        .unwrap_err();
+    assert!(matches!(error, ConfigError::HeaderName { .. }));
+    assert!(error.source().is_some());
+
+    let config = StaticServeConfig::new().not_found("/missing.html");
+    let error = static_router_with_config::<()>(&config).unwrap_err();
+    assert!(matches!(error, ConfigError::NotFound { .. }));
+    assert_eq!(
+        error.to_string(),
+        "Cannot serve /missing.html as the 404 page: no asset is embedded at this route"
+    );
+}
+
+#[tokio::test]
+async fn prefers_the_configured_encodings() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        encoding_preference = ["gzip", "zstd"]
+    );
+    let router: Router<()> = static_router();
+
+    // `zstd, gzip` accepts both with the same quality
+    let request = create_request("/app.js", &Compression::Both);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.headers()[CONTENT_ENCODING], "gzip");
+    let request = create_request("/app.js", &Compression::Zstd);
+    let response = get_response(router, request).await;
+    assert_eq!(response.headers()[CONTENT_ENCODING], "zstd");
+
+    let config =
+        StaticServeConfig::new().encodings(&[ContentEncoding::Zstd, ContentEncoding::Gzip]);
+    let router: Router<()> = static_router_with_config(&config).unwrap();
+    let request = create_request("/app.js", &Compression::Both);
+    let response = get_response(router, request).await;
+    assert_eq!(response.headers()[CONTENT_ENCODING], "zstd");
+}
+
+#[tokio::test]
+async fn layer_serves_assets_as_fallback() {
+    embed_assets!("../static-serve/test_assets/big");
+    let api = tower::service_fn(|request: Request<Body>| async move {
+        let status = if request.uri().path() == "/api" {
+            StatusCode::OK
+        } else {
+            StatusCode::NOT_FOUND
+        };
+        Ok::<_, Infallible>(
+            Response::builder()
+                .status(status)
+                .body(Body::from("from the api"))
+                .unwrap(),
+        )
+    });
+    let service = StaticServeLayer::new(static_router()).layer(api);
+
+    let request = create_request("/api", &Compression::None);
+    let response = service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let collected_body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(collected_body_bytes, "from the api");
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = service.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let collected_body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        collected_body_bytes,
+        &include_bytes!("../../test_assets/big/app.js")[..]
+    );
+
+    // Without a matching asset, the response of the inner service is kept
+    let request = create_request("/missing", &Compression::None);
+    let response = service.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let collected_body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(collected_body_bytes, "from the api");
+}
+
+#[tokio::test]
+async fn responds_with_asset_from_custom_handler() {
+    embed_assets!("../static-serve/test_assets/big", compress = true);
+    let router: Router<()> = Router::new().route(
+        "/secret",
+        axum::routing::get(|parts: axum::http::request::Parts| async move {
+            if parts.headers.get("authorization").is_none() {
+                return StatusCode::UNAUTHORIZED.into_response();
+            }
+            get_asset("/app.js")
+                .unwrap()
+                .respond_to(&parts)
+                .into_response()
+        }),
+    );
+    assert!(get_asset("/missing.js").is_none());
+
+    let request = create_request("/secret", &Compression::Zstd);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let request = Request::builder()
+        .uri("/secret")
+        .header("authorization", "Bearer token")
+        .header(ACCEPT_ENCODING, "zstd")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::OK);
+    assert_eq!(parts.headers.get(CONTENT_ENCODING).unwrap(), "zstd");
+    let etag = parts.headers.get(ETAG).unwrap().clone();
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        decompress_zstd(&collected_body_bytes),
+        include_bytes!("../../test_assets/big/app.js")
+    );
+
+    let request = Request::builder()
+        .uri("/secret")
+        .header("authorization", "Bearer token")
+        .header(IF_NONE_MATCH, etag)
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+}
+
+#[test]
+fn resolves_paths_relative_to_manifest_dir() {
+    let web_paths: Vec<_> = {
+        embed_assets!("../test_assets/small", manifest_relative = true);
+        asset_manifest().iter().map(|info| info.path).collect()
+    };
+    assert_eq!(web_paths, ["/app.js", "/styles.css"]);
+
+    let _handler: axum::routing::MethodRouter =
+        embed_asset!("../test_assets/small/app.js", manifest_relative = true);
+}
+
+#[test]
+fn expands_environment_variables_in_paths() {
+    let web_paths: Vec<_> = {
+        embed_assets!("${CARGO_MANIFEST_DIR}/../test_assets/small");
+        asset_manifest().iter().map(|info| info.path).collect()
+    };
+    assert_eq!(web_paths, ["/app.js", "/styles.css"]);
+
+    let _handler: axum::routing::MethodRouter =
+        embed_asset!("$CARGO_MANIFEST_DIR/../test_assets/small/app.js");
+}
+
+#[tokio::test]
+async fn serves_files_at_custom_routes() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        route_map = [("immutable/app.js", "/app.min.js")]
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/app.min.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::OK);
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    assert_eq!(
+        collected_body_bytes,
+        include_bytes!("../../test_assets/big/immutable/app.js").as_slice()
+    );
+
+    let request = create_request("/immutable/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(get_asset("/app.min.js").is_some());
+}
+
+#[tokio::test]
+async fn names_the_generated_functions() {
+    embed_assets!("../static-serve/test_assets/small", fn_name = small_assets);
+    embed_assets!(
+        "../static-serve/test_assets/with_html",
+        fn_name = html_assets,
+        vis = pub(crate)
+    );
+    let router: Router<()> = small_assets().merge(html_assets());
+
+    for route in ["/app.js", "/index.html"] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    assert!(small_assets::get_asset("/styles.css").is_some());
+    assert!(html_assets::get_asset("/styles.css").is_none());
+}
+
+#[tokio::test]
+async fn merges_several_assets_directories() {
+    embed_assets!([
+        "../static-serve/test_assets/big",
+        "../static-serve/test_assets/small"
+    ]);
+    let web_paths: Vec<_> = asset_manifest().iter().map(|info| info.path).collect();
+    assert_eq!(
+        web_paths,
+        [
+            "/app.js",
+            "/immutable/app.js",
+            "/immutable/styles.css",
+            "/styles.css"
+        ]
+    );
+
+    let router: Router<()> = static_router();
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(
+        collected_body_bytes,
+        include_bytes!("../../test_assets/small/app.js").as_slice()
+    );
+}
+
+#[tokio::test]
+async fn generates_directory_index_pages() {
+    embed_assets!("../static-serve/test_assets/big", auto_index = true);
+    let router: Router<()> = static_router();
+
+    let request = create_request("/", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    let (parts, body) = response.into_parts();
+    assert_eq!(parts.status, StatusCode::OK);
+    assert_eq!(
+        parts.headers.get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
+    let collected_body_bytes = body.into_data_stream().collect().await.unwrap().to_bytes();
+    let index = std::str::from_utf8(&collected_body_bytes).unwrap();
+    assert!(index.contains("<a href=\"./immutable/\">immutable/</a>"));
+    assert!(index.contains("<a href=\"./app.js\">app.js</a>"));
+
+    let request = create_request("/immutable/", &Compression::None);
+    let response = get_response(router, request).await;
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    let index = std::str::from_utf8(&collected_body_bytes).unwrap();
+    assert!(index.contains("<a href=\"../\">../</a>"));
+    assert!(index.contains("<a href=\"./styles.css\">styles.css</a>"));
+}
+
+#[tokio::test]
+async fn handles_trailing_slashes_of_pages() {
+    let redirecting: Router<()> = {
+        embed_assets!(
+            "../static-serve/test_assets/big",
+            auto_index = true,
+            trailing_slash = "redirect"
+        );
+        static_router()
+    };
+    let request = create_request("/immutable", &Compression::None);
+    let response = get_response(redirecting, request).await;
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.headers().get(LOCATION).unwrap(), "/immutable/");
+
+    let stripping: Router<()> = {
+        embed_assets!(
+            "../static-serve/test_assets/big",
+            auto_index = true,
+            trailing_slash = "strip"
+        );
+        static_router()
+    };
+    for (route, status) in [
+        ("/immutable", StatusCode::OK),
+        ("/immutable/", StatusCode::NOT_FOUND),
+    ] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(stripping.clone(), request).await;
+        assert_eq!(response.status(), status);
+    }
+
+    embed_assets!(
+        "../static-serve/test_assets/with_html",
+        strip_html_ext = true,
+        trailing_slash = "both"
+    );
+    let router: Router<()> = static_router();
+    for route in ["/index2", "/index2/"] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+}
+
+#[tokio::test]
+async fn redirects_original_routes_of_stripped_pages() {
+    embed_assets!(
+        "../static-serve/test_assets/with_html",
+        strip_html_ext = true,
+        html_redirects = true
+    );
+    let router: Router<()> = static_router();
+
+    for (route, location) in [("/index.html", "/"), ("/index2.htm", "/index2")] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(response.headers().get(LOCATION).unwrap(), location);
+    }
+
+    let request = create_request("/index2.htm", &Compression::None);
+    let response = static_service().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.headers().get(LOCATION).unwrap(), "/index2");
+}
+
+#[test]
+fn lists_static_routes() {
+    embed_assets!(
+        "../static-serve/test_assets/with_html",
+        strip_html_ext = true,
+        html_redirects = true
+    );
+    assert_eq!(
+        STATIC_ROUTES,
+        ["/", "/index.html", "/index2", "/index2.htm"]
+    );
+}
+
+#[tokio::test]
+async fn sends_extra_headers() {
+    embed_assets!(
+        "../static-serve/test_assets/small",
+        headers = [
+            ("X-Frame-Options", "DENY"),
+            ("styles.css", "Cross-Origin-Resource-Policy", "same-origin")
+        ]
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("x-frame-options").unwrap(), "DENY");
+    assert!(
+        response
+            .headers()
+            .get("cross-origin-resource-policy")
+            .is_none()
+    );
+
+    let request = create_request("/styles.css", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.headers().get("x-frame-options").unwrap(), "DENY");
+    assert_eq!(
+        response
+            .headers()
+            .get("cross-origin-resource-policy")
+            .unwrap(),
+        "same-origin"
+    );
+}
+
+#[tokio::test]
+async fn protects_subtrees_with_basic_auth() {
+    // Cargo sets `CARGO_PKG_NAME` when running the tests, while
+    // `STATIC_SERVE_UNSET_CREDS` is never set, rejecting every request
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        protected = [("immutable", "env:CARGO_PKG_NAME")],
+        tarball = "/assets.tar.gz"
+    );
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        protected = [("immutable", "env:STATIC_SERVE_UNSET_CREDS")],
+        fn_name = unset_creds_assets
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/immutable/app.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert_eq!(
+        response.headers()["www-authenticate"],
+        "Basic realm=\"immutable\", charset=\"UTF-8\""
+    );
+
+    let request = Request::builder()
+        .uri("/immutable/app.js")
+        .header("authorization", "Basic c3RhdGljLXNlcnZl")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CACHE_CONTROL], "private, no-cache");
+
+    let request = Request::builder()
+        .uri("/immutable/app.js")
+        .header("authorization", "Basic d3Jvbmc6Y3JlZHM=")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // The other files are public, and the protected ones aren't in the tarball
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let tarball = get_asset("/assets.tar.gz").unwrap();
+    let mut tar = Vec::new();
+    flate2::read::GzDecoder::new(tarball.uncompressed_body())
+        .read_to_end(&mut tar)
+        .unwrap();
+    assert!(!tar.windows(9).any(|name| name == b"immutable"));
+
+    let request = Request::builder()
+        .uri("/immutable/app.js")
+        .header("authorization", "Basic c3RhdGljLXNlcnZl")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(unset_creds_assets(), request).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn serves_signed_assets_to_signed_urls() {
+    embed_assets!("../static-serve/test_assets/big", signed = ["immutable"]);
+    let signer = UrlSigner::new("downloads key");
+    let router: Router<()> = static_router().layer(Extension(signer.clone()));
+    let in_an_hour = SystemTime::now() + Duration::from_secs(3600);
+
+    let url = signer.sign("/immutable/app.js", in_an_hour);
+    let request = create_request(&url, &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CACHE_CONTROL], "private, no-cache");
+
+    // Unsigned, signed for another route, signed with another key, or expired
+    let expired = signer.sign(
+        "/immutable/app.js",
+        SystemTime::now() - Duration::from_secs(1),
+    );
+    let other_route = signer.sign("/immutable/styles.css", in_an_hour);
+    let (_, other_route_query) = other_route.split_once('?').unwrap();
+    for url in [
+        "/immutable/app.js".to_owned(),
+        format!("/immutable/app.js?{other_route_query}"),
+        UrlSigner::new("other key").sign("/immutable/app.js", in_an_hour),
+        expired,
+    ] {
+        let request = create_request(&url, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    // Without the key, the signed files are never served
+    let request = create_request(&url, &Compression::None);
+    let response = get_response(static_router(), request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn verifies_the_signed_urls_of_nested_routers() {
+    embed_assets!("../static-serve/test_assets/big", signed = ["immutable"]);
+    let signer = UrlSigner::new("downloads key");
+    let router: Router<()> = Router::new()
+        .nest("/files", static_router())
+        .layer(Extension(signer.clone()));
+    let in_an_hour = SystemTime::now() + Duration::from_secs(3600);
+
+    // The URLs are signed for the path the client requests
+    let url = signer.sign("/files/immutable/app.js", in_an_hour);
+    let request = create_request(&url, &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let url = signer.sign("/immutable/app.js", in_an_hour);
+    let (_, query) = url.split_once('?').unwrap();
+    let request = create_request(
+        &format!("/files/immutable/app.js?{query}"),
+        &Compression::None,
+    );
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn applies_the_source_maps_policy() {
+    let router: Router<()> = {
+        embed_assets!("../static-serve/test_source_maps");
+        static_router()
+    };
+    let request = create_request("/app.js.map", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let router: Router<()> = {
+        embed_assets!("../static-serve/test_source_maps", source_maps = "skip");
+        static_router()
+    };
+    let request = create_request("/app.js.map", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let router: Router<()> = {
+        embed_assets!(
+            "../static-serve/test_source_maps",
+            source_maps = "header-only",
+            source_maps_header = "X-Debug",
+            source_maps_cookie = "debug"
+        );
+        static_router()
+    };
+    let request = create_request("/app.js.map", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    for (name, value) in [("x-debug", "1"), ("cookie", "theme=dark; debug=1")] {
+        let request = Request::builder()
+            .uri("/app.js.map")
+            .header(name, value)
+            .body(Body::empty())
+            .unwrap();
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK, "{name}: {value}");
+        assert_eq!(response.headers()[CACHE_CONTROL], "private, no-cache");
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(*body, *include_bytes!("../../test_source_maps/app.js.map"));
+    }
+    let request = Request::builder()
+        .uri("/app.js.map")
+        .header("cookie", "notdebug=1")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The other files are served to anyone
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(CACHE_CONTROL).is_none());
+}
+
+#[tokio::test]
+async fn sends_security_headers() {
+    embed_assets!(
+        "../static-serve/test_assets/small",
+        security_headers = true,
+        referrer_policy = "strict-origin-when-cross-origin",
+        cross_origin_opener_policy = "same-origin",
+        headers = [("styles.css", "Referrer-Policy", "no-referrer")]
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    let headers = response.headers();
+    assert_eq!(headers["x-content-type-options"], "nosniff");
+    assert_eq!(
+        headers["referrer-policy"],
+        "strict-origin-when-cross-origin"
+    );
+    assert_eq!(headers["cross-origin-opener-policy"], "same-origin");
+
+    // The headers set with `headers` take precedence
+    let request = create_request("/styles.css", &Compression::None);
+    let response = get_response(router, request).await;
+    let headers = response.headers();
+    assert_eq!(headers["x-content-type-options"], "nosniff");
+    assert_eq!(
+        headers
+            .get_all("referrer-policy")
+            .iter()
+            .collect::<Vec<_>>(),
+        ["no-referrer"]
+    );
+
+    let router: Router<()> = Router::new().route(
+        "/app.js",
+        embed_asset!(
+            "../static-serve/test_assets/small/app.js",
+            security_headers = true
+        ),
+    );
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.headers()["x-content-type-options"], "nosniff");
+    assert!(response.headers().get("referrer-policy").is_none());
+}
+
+#[tokio::test]
+async fn sends_cors_headers_to_allowed_origins() {
+    embed_assets!(
+        "../static-serve/test_assets/small",
+        cors = ["https://app.example.com"]
+    );
+    let router: Router<()> = static_router();
+
+    let request = Request::builder()
+        .uri("/app.js")
+        .header("origin", "https://app.example.com")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response
+            .headers()
+            .get("access-control-allow-origin")
+            .unwrap(),
+        "https://app.example.com"
+    );
+    assert!(
+        response
+            .headers()
+            .get_all("vary")
+            .iter()
+            .any(|vary| vary == "Origin")
+    );
+
+    let request = Request::builder()
+        .uri("/app.js")
+        .header("origin", "https://evil.example.com")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(
+        response
+            .headers()
+            .get("access-control-allow-origin")
+            .is_none()
+    );
+
+    let request = Request::builder()
+        .method("OPTIONS")
+        .uri("/app.js")
+        .header("origin", "https://app.example.com")
+        .header("access-control-request-method", "GET")
+        .header("access-control-request-headers", "range")
+        .body(Body::empty())
+        .unwrap();
+    let response = static_service().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        response
+            .headers()
+            .get("access-control-allow-methods")
+            .unwrap(),
+        "GET, HEAD"
+    );
+    assert_eq!(
+        response
+            .headers()
+            .get("access-control-allow-headers")
+            .unwrap(),
+        "range"
+    );
+
+    let handler = embed_asset!("../static-serve/test_assets/small/styles.css", cors = "*");
+    let router: Router<()> = Router::new().route("/styles.css", handler);
+    let request = Request::builder()
+        .method("OPTIONS")
+        .uri("/styles.css")
+        .header("origin", "https://other.example.com")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    assert_eq!(
+        response
+            .headers()
+            .get("access-control-allow-origin")
+            .unwrap(),
+        "*"
+    );
+}
+
+#[test]
+fn lists_csp_hashes_of_scripts_and_styles() {
+    let hashes = {
+        embed_assets!(
+            "../static-serve/test_assets/with_html",
+            strip_html_ext = true
+        );
+        csp_hashes()
+    };
+    assert!(hashes.script_src.is_empty());
+    assert!(hashes.style_src.is_empty());
+
+    let hashes = {
+        embed_assets!("../static-serve/test_assets/small");
+        csp_hashes()
+    };
+    assert_eq!(hashes.script_src.len(), 1);
+    assert_eq!(hashes.style_src.len(), 1);
+    for source in hashes.script_src.iter().chain(hashes.style_src) {
+        assert!(source.starts_with("'sha256-"));
+        assert!(source.ends_with("='"));
+    }
+}
+
+#[tokio::test]
+async fn sends_preload_links_with_html_pages() {
+    embed_assets!(
+        [
+            "../static-serve/test_assets/with_html",
+            "../static-serve/test_assets/small"
+        ],
+        prefix = "/static",
+        preload = ["app.js", "styles.css"]
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/static/index.html", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("link").unwrap(),
+        "</static/app.js>; rel=preload; as=script, </static/styles.css>; rel=preload; as=style"
+    );
+
+    let request = create_request("/static/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("link").is_none());
+}
+
+#[tokio::test]
+async fn decompresses_dictionary_compressed_bodies() {
+    let router: Router<()> = {
+        embed_assets!(
+            "../static-serve/test_assets/big",
+            compress = true,
+            zstd_dictionary = true
+        );
+        assert!(debug_verify().is_ok());
+        static_router()
+    };
+
+    for (route, expected_body_bytes) in [
+        (
+            "/app.js",
+            include_bytes!("../../test_assets/big/app.js").as_slice(),
+        ),
+        (
+            "/immutable/styles.css",
+            include_bytes!("../../test_assets/big/immutable/styles.css").as_slice(),
+        ),
+    ] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().get(CONTENT_ENCODING).is_none());
+        let collected_body_bytes = response
+            .into_body()
+            .into_data_stream()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(*collected_body_bytes, *expected_body_bytes);
+    }
+
+    let request = create_request("/app.js", &Compression::Zstd);
+    let response = get_response(router, request).await;
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "zstd");
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(
+        decompress_zstd(&collected_body_bytes),
+        include_bytes!("../../test_assets/big/app.js")
+    );
+}
+
+#[tokio::test]
+async fn substitutes_template_placeholders() {
+    embed_assets!(
+        "../static-serve/test_templates",
+        template_vars = [("API_URL", "https://api.example.com")]
+    );
+    let router: Router<()> = static_router();
+
+    for (route, expected_body) in [
+        (
+            "/config.js",
+            "export const API_URL = \"https://api.example.com\";\nexport const PACKAGE = \"static-serve\";\nexport const TEMPLATE = \"{{name}}\";\n",
+        ),
+        // Binary files are left as they are
+        ("/logo.png", "PNG {{API_URL}}"),
+    ] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        let collected_body_bytes = response
+            .into_body()
+            .into_data_stream()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(collected_body_bytes, expected_body);
+    }
+}
+
+#[tokio::test]
+async fn negotiates_image_variants_with_accept() {
+    embed_assets!("../static-serve/test_images", image_variants = true);
+    let router: Router<()> = static_router();
+
+    let avif = include_bytes!("../../test_images/photo.avif").as_slice();
+    let webp = include_bytes!("../../test_images/photo.webp").as_slice();
+    let png = include_bytes!("../../test_images/photo.png").as_slice();
+    for (accept, content_type, body) in [
+        (
+            Some("image/avif,image/webp,image/*,*/*;q=0.8"),
+            "image/avif",
+            avif,
+        ),
+        (Some("image/avif;q=0.5, image/webp"), "image/webp", webp),
+        (Some("image/png, image/webp;q=0.1"), "image/png", png),
+        (
+            Some("image/png;q=0.5, image/webp;q=0.5"),
+            "image/webp",
+            webp,
+        ),
+        (Some("image/*"), "image/png", png),
+        (None, "image/png", png),
+    ] {
+        let mut request = Request::builder().uri("/photo.png");
+        if let Some(accept) = accept {
+            request = request.header("accept", accept);
+        }
+        let response = get_response(router.clone(), request.body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], content_type);
+        let vary: Vec<_> = response.headers().get_all("vary").iter().collect();
+        assert!(vary.contains(&&HeaderValue::from_static("Accept")));
+        let collected_body_bytes = response
+            .into_body()
+            .into_data_stream()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(collected_body_bytes, body);
+    }
+
+    for route in ["/photo.avif", "/photo.webp"] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{route}");
+    }
+
+    // Without the encoder, the images without siblings have no variants
+    #[cfg(not(feature = "image-encoding"))]
+    {
+        let request = create_request("/plain.jpg", &Compression::None);
+        let response = get_response(router, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(
+            response
+                .headers()
+                .get_all("vary")
+                .iter()
+                .all(|vary| vary != "Accept")
+        );
+    }
+}
+
+#[cfg(feature = "image-encoding")]
+#[tokio::test]
+async fn encodes_image_variants() {
+    embed_assets!(
+        "../static-serve/test_images",
+        image_variants = true,
+        ignore_paths = ["photo.avif", "photo.webp"]
+    );
+    let router: Router<()> = static_router();
+
+    for (route, accept, content_type, magic) in [
+        (
+            "/photo.png",
+            "image/avif,image/webp",
+            "image/avif",
+            &b"ftypavif"[..],
+        ),
+        ("/photo.png", "image/webp", "image/webp", b"WEBP"),
+        ("/plain.jpg", "image/webp", "image/webp", b"WEBP"),
+    ] {
+        let request = Request::builder()
+            .uri(route)
+            .header("accept", accept)
+            .body(Body::empty())
+            .unwrap();
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], content_type);
+        assert!(response.headers().contains_key("etag"));
+        let collected_body_bytes = response
+            .into_body()
+            .into_data_stream()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+        // The brand of the ISOBMFF or RIFF header
+        assert!(
+            collected_body_bytes[..12]
+                .windows(magic.len())
+                .any(|window| window == magic),
+            "{route}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn serves_woff2_fonts_cache_busted_to_all_origins() {
+    embed_assets!("../static-serve/test_fonts", fonts = true);
+    let router: Router<()> = static_router();
+
+    for route in ["/icons.woff", "/icons.ttf"] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{route}");
+    }
+
+    for route in ["/icons.woff2", "/other.ttf"] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK, "{route}");
+        assert_eq!(
+            response.headers().get(CACHE_CONTROL).unwrap(),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(
+            response
+                .headers()
+                .get("access-control-allow-origin")
+                .unwrap(),
+            "*"
+        );
+    }
+}
+
+#[tokio::test]
+async fn minifies_html_css_and_js() {
+    let router: Router<()> = {
+        embed_assets!("../static-serve/test_assets/with_html", minify = true);
+        static_router()
+    };
+    let request = create_request("/index.html", &Compression::None);
+    let response = get_response(router, request).await;
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(
+        collected_body_bytes,
+        "<!doctype html><html><head><meta charset=UTF-8><title>title</title></head><body>Hello, I'm index!</body></html>"
+    );
+
+    let router: Router<()> = {
+        embed_assets!("../static-serve/test_assets/big", minify = true);
+        static_router()
+    };
+    let request = create_request("/styles.css", &Compression::None);
+    let response = get_response(router, request).await;
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(collected_body_bytes, "body{background:#000}");
+}
+
+#[test]
+fn compresses_only_large_files_of_compressed_types() {
+    let large_enough = {
+        embed_assets!(
+            "../static-serve/test_assets/big",
+            compress = true,
+            compress_min_size = 400
+        );
+        asset_manifest()
+    };
+    let of_compressed_types = {
+        embed_assets!(
+            "../static-serve/test_assets/big",
+            compress = true,
+            compress_types = ["text/css", "image/*"]
+        );
+        asset_manifest()
+    };
+
+    for (manifest, compressed_path) in [
+        (large_enough, "/app.js"),
+        (of_compressed_types, "/styles.css"),
+    ] {
+        for info in manifest {
+            let is_compressed = info.path.ends_with(compressed_path);
+            assert_eq!(info.gzip_size.is_some(), is_compressed, "{}", info.path);
+            assert_eq!(info.zstd_size.is_some(), is_compressed, "{}", info.path);
+        }
+    }
+}
+
+#[tokio::test]
+async fn decompresses_lazy_bodies_on_first_access() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        lazy_decompression = true
+    );
+    assert!(debug_verify().is_ok());
+    let router: Router<()> = static_router();
+
+    let app_js = include_bytes!("../../test_assets/big/app.js");
+    let manifest = asset_manifest();
+    let info = manifest.iter().find(|info| info.path == "/app.js").unwrap();
+    assert_eq!(info.size, app_js.len());
+    assert!(info.gzip_size.is_none());
+
+    for (compression, content_encoding) in [
+        (Compression::None, None),
+        (Compression::Gzip, Some("gzip")),
+        (Compression::Zstd, Some("zstd")),
+    ] {
+        let request = create_request("/app.js", &compression);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response
+                .headers()
+                .get(CONTENT_ENCODING)
+                .map(|value| value.to_str().unwrap()),
+            content_encoding
+        );
+        let collected_body_bytes = response
+            .into_body()
+            .into_data_stream()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+        let body = match compression {
+            Compression::Gzip => decompress_gzip(&collected_body_bytes),
+            Compression::Zstd => decompress_zstd(&collected_body_bytes),
+            _ => collected_body_bytes.to_vec(),
+        };
+        assert_eq!(body, app_js);
+    }
+}
+
+#[tokio::test]
+async fn answers_not_modified_without_materializing_lazy_bodies() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        lazy_decompression = true
+    );
+    let router: Router<()> = static_router();
+    let etag = get_asset("/app.js").unwrap().etag().unwrap();
+
+    let not_modified = |router: Router<()>| async move {
+        let mut request = create_request("/app.js", &Compression::Gzip);
+        request
+            .headers_mut()
+            .insert(IF_NONE_MATCH, HeaderValue::from_static(etag));
+        let response = get_response(router, request).await;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        response.headers().get(CONTENT_LENGTH).cloned()
+    };
+
+    // The length of the gzip variant isn't known before it's compressed
+    assert_eq!(not_modified(router.clone()).await, None);
+
+    let request = create_request("/app.js", &Compression::Gzip);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+
+    assert_eq!(
+        not_modified(router).await,
+        Some(HeaderValue::from(collected_body_bytes.len()))
+    );
+}
+
+#[tokio::test]
+async fn decompresses_bodies_embedded_without_identity() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        identity = false
+    );
+    assert!(debug_verify().is_ok());
+    let router: Router<()> = static_router();
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(CONTENT_ENCODING).is_none());
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(
+        *collected_body_bytes,
+        *include_bytes!("../../test_assets/big/app.js")
+    );
+
+    let request = create_request("/app.js", &Compression::Gzip);
+    let response = get_response(router, request).await;
+    assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(
+        decompress_gzip(&collected_body_bytes),
+        include_bytes!("../../test_assets/big/app.js")
+    );
+}
+
+#[tokio::test]
+async fn streams_large_bodies_in_chunks() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        stream_min_size = 500,
+        stream_chunk_size = 128
+    );
+    let router: Router<()> = static_router();
+
+    for (route, contents, chunks) in [
+        (
+            "/app.js",
+            &include_bytes!("../../test_assets/big/app.js")[..],
+            5,
+        ),
+        (
+            "/styles.css",
+            &include_bytes!("../../test_assets/big/styles.css")[..],
+            1,
+        ),
+    ] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(CONTENT_LENGTH).unwrap(),
+            &contents.len().to_string()
+        );
+
+        let mut body = response.into_body();
+        let mut collected = Vec::new();
+        let mut frames = 0;
+        while let Some(frame) = body.frame().await {
+            let data = frame.unwrap().into_data().unwrap();
+            assert!(data.len() <= 128 || chunks == 1);
+            collected.extend_from_slice(&data);
+            frames += 1;
+        }
+        assert_eq!(frames, chunks, "{route}");
+        assert_eq!(collected, contents);
+    }
+
+    let request = Request::builder()
+        .uri("/app.js")
+        .header(RANGE, "bytes=100-399")
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(
+        *collected_body_bytes,
+        include_bytes!("../../test_assets/big/app.js")[100..400]
+    );
+}
+
+#[tokio::test]
+async fn builds_sharded_asset_tables() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        shard_size = 3,
+        fn_name = sharded_assets
+    );
+    // Decompressed at startup rather than built at compile time
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        identity = false,
+        shard_size = 1,
+        fn_name = decompressed_assets
+    );
+    assert!(sharded_assets::debug_verify().is_ok());
+    assert!(decompressed_assets::debug_verify().is_ok());
+
+    for (manifest, router) in [
+        (sharded_assets::asset_manifest(), sharded_assets::<()>()),
+        (
+            decompressed_assets::asset_manifest(),
+            decompressed_assets::<()>(),
+        ),
+    ] {
+        let paths: Vec<_> = manifest.iter().map(|info| info.path).collect();
+        assert_eq!(
+            paths,
+            [
+                "/app.js",
+                "/immutable/app.js",
+                "/immutable/styles.css",
+                "/styles.css"
+            ]
+        );
+
+        for (route, expected_body_bytes) in [
+            (
+                "/app.js",
+                include_bytes!("../../test_assets/big/app.js").as_slice(),
+            ),
+            (
+                "/styles.css",
+                include_bytes!("../../test_assets/big/styles.css").as_slice(),
+            ),
+        ] {
+            let request = create_request(route, &Compression::None);
+            let response = get_response(router.clone(), request).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let collected_body_bytes = response
+                .into_body()
+                .into_data_stream()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes();
+            assert_eq!(*collected_body_bytes, *expected_body_bytes);
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn places_embedded_bytes_in_link_section() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        compress = true,
+        link_section = ".static_serve",
+        fn_name = sectioned_assets
+    );
+    assert!(sectioned_assets::debug_verify().is_ok());
+    let router: Router<()> = sectioned_assets();
+
+    let request = create_request("/app.js", &Compression::Zstd);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    let decompressed_body = decompress_zstd(&collected_body_bytes);
+    assert_eq!(
+        decompressed_body,
+        include_bytes!("../../test_assets/big/app.js")
+    );
+}
+
+#[tokio::test]
+async fn serves_index_files_at_directory_routes() {
+    embed_assets!(
+        "../static-serve/test_index_files",
+        index_files = ["index.html", "index.htm"]
+    );
+    let router: Router<()> = static_router();
+
+    for (route, expected_body_bytes) in [
+        (
+            "/",
+            include_bytes!("../../test_index_files/index.html").as_slice(),
+        ),
+        (
+            "/docs/",
+            include_bytes!("../../test_index_files/docs/index.html").as_slice(),
+        ),
+        (
+            "/blog/",
+            include_bytes!("../../test_index_files/blog/index.htm").as_slice(),
+        ),
+        (
+            "/docs/index.htm",
+            include_bytes!("../../test_index_files/docs/index.htm").as_slice(),
+        ),
+    ] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK, "{route}");
+        let collected_body_bytes = response
+            .into_body()
+            .into_data_stream()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(*collected_body_bytes, *expected_body_bytes);
+    }
+
+    let request = create_request("/docs", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    embed_assets!(
+        "../static-serve/test_index_files",
+        index_files = ["index.html"],
+        trailing_slash = "redirect",
+        fn_name = redirecting_assets
+    );
+    let request = create_request("/docs", &Compression::None);
+    let response = get_response(redirecting_assets(), request).await;
+    assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(response.headers().get(LOCATION).unwrap(), "/docs/");
+}
+
+#[tokio::test]
+async fn negotiates_accept_variants() {
+    embed_assets!(
+        "../static-serve/test_data",
+        accept_variants = ["json", "xml", "csv"]
+    );
+    let router: Router<()> = static_router();
+
+    for (accept, content_type, body) in [
+        (
+            None,
+            "application/json; charset=utf-8",
+            include_bytes!("../../test_data/data.json").as_slice(),
+        ),
+        (
+            Some("*/*"),
+            "application/json; charset=utf-8",
+            include_bytes!("../../test_data/data.json").as_slice(),
+        ),
+        (
+            Some("text/xml"),
+            "text/xml; charset=utf-8",
+            include_bytes!("../../test_data/data.xml").as_slice(),
+        ),
+        (
+            Some("text/*"),
+            "text/xml; charset=utf-8",
+            include_bytes!("../../test_data/data.xml").as_slice(),
+        ),
+        (
+            Some("application/xml;q=0.5, application/json"),
+            "application/json; charset=utf-8",
+            include_bytes!("../../test_data/data.json").as_slice(),
+        ),
+        (
+            Some("text/csv, */*;q=0.1"),
+            "text/csv; charset=utf-8",
+            include_bytes!("../../test_data/data.csv").as_slice(),
+        ),
+    ] {
+        let mut request = Request::builder().uri("/data");
+        if let Some(accept) = accept {
+            request = request.header("accept", accept);
+        }
+        let response = get_response(router.clone(), request.body(Body::empty()).unwrap()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], content_type);
+        let vary: Vec<_> = response.headers().get_all("vary").iter().collect();
+        assert!(vary.contains(&&HeaderValue::from_static("Accept")));
+        let collected_body_bytes = response
+            .into_body()
+            .into_data_stream()
+            .collect()
+            .await
+            .unwrap()
+            .to_bytes();
+        assert_eq!(*collected_body_bytes, *body);
+    }
+
+    for route in ["/data.json", "/data.xml", "/data.csv", "/config.json"] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{route}");
+    }
+
+    let request = create_request("/config", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("vary").is_none());
+}
+
+#[test]
+fn negotiates_without_a_backend() {
+    embed_assets!(
+        "../static-serve/test_data",
+        compress = true,
+        accept_variants = ["json", "xml"]
+    );
+    let asset = get_asset("/data").unwrap();
+
+    let mut headers = HeaderMap::new();
+    headers.insert("accept", HeaderValue::from_static("text/xml"));
+    let negotiated = asset.negotiate(&headers).unwrap();
+    assert_eq!(negotiated.asset.content_type(), "text/xml; charset=utf-8");
+    assert_eq!(negotiated.content_encoding, None);
+    assert_eq!(
+        negotiated.body,
+        include_bytes!("../../test_data/data.xml").as_slice()
+    );
+
+    headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity;q=0"));
+    assert!(asset.negotiate(&headers).is_none());
+}
+
+#[cfg(feature = "bundle")]
+#[tokio::test]
+async fn serves_assets_from_bundle() {
+    embed_assets!(
+        "../test_assets/big",
+        compress = true,
+        bundle = "../target/static-serve-test.bundle",
+        fn_name = bundled_assets,
+        manifest_relative = true
+    );
+    assert!(bundled_assets::debug_verify().is_ok());
+    let router: Router<()> = bundled_assets();
+
+    let request = create_request("/app.js", &Compression::Zstd);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[CONTENT_ENCODING], "zstd");
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(
+        *collected_body_bytes,
+        *include_bytes!("../../test_assets/dist/app.js.zst")
+    );
+
+    let request = create_request("/styles.css", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let collected_body_bytes = response
+        .into_body()
+        .into_data_stream()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes();
+    assert_eq!(
+        *collected_body_bytes,
+        *include_bytes!("../../test_assets/big/styles.css")
+    );
+
+    let bundle = std::fs::read(
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/static-serve-test.bundle"),
+    )
+    .unwrap();
+    assert!(
+        bundle
+            .windows(include_bytes!("../../test_assets/big/styles.css").len())
+            .any(|window| window == include_bytes!("../../test_assets/big/styles.css"))
+    );
+}
+
+#[cfg(feature = "dev")]
+#[tokio::test]
+async fn watch_router_serves_current_files() {
+    let dir = std::env::temp_dir().join(format!("static-serve-watch-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("docs")).unwrap();
+    std::fs::write(dir.join("docs/index.html"), "<p>first</p>".repeat(20)).unwrap();
+    let router: Router<()> = static_serve::dev::watch_router(&dir);
+
+    let request = create_request("/docs/", &Compression::Zstd);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/html; charset=utf-8"
+    );
+    assert_eq!(response.headers()[CONTENT_ENCODING], "zstd");
+    assert_eq!(response.headers()[CACHE_CONTROL], "no-cache");
+    assert!(response.headers().contains_key(LAST_MODIFIED));
+    let etag = response.headers()[ETAG].clone();
+
+    let request = Request::builder()
+        .uri("/docs/index.html")
+        .header(IF_NONE_MATCH, etag.clone())
+        .body(Body::empty())
+        .unwrap();
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // The change is picked up once the filesystem notifies it
+    std::fs::write(dir.join("docs/index.html"), "<p>second</p>").unwrap();
+    let mut collected_body_bytes = axum::body::Bytes::new();
+    for _ in 0..100 {
+        let request = create_request("/docs/index.html", &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        if response.headers()[ETAG] != etag {
+            collected_body_bytes = response
+                .into_body()
+                .into_data_stream()
+                .collect()
+                .await
+                .unwrap()
+                .to_bytes();
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    assert_eq!(collected_body_bytes, "<p>second</p>");
+
+    for route in ["/missing.html", "/../Cargo.toml", "/%2e%2e/Cargo.toml"] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{route}");
+    }
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn busts_cache_with_query() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        cache_busted_paths = ["immutable"],
+        cache_bust_query = true
+    );
+    let router: Router<()> = static_router();
+
+    let etag = get_asset("/app.js")
+        .unwrap()
+        .etag()
+        .unwrap()
+        .trim_matches('"');
+    let url = format!("/app.js?v={etag}");
+    assert_eq!(paths::APP_JS, url);
+    assert_eq!(asset_url("/app.js"), Some(url.as_str()));
+    // Already fingerprinted by their file names
+    assert_eq!(paths::IMMUTABLE_APP_JS, "/immutable/app.js");
+    assert_eq!(asset_url("/immutable/app.js"), Some("/immutable/app.js"));
+    assert_eq!(asset_url("/missing.js"), None);
+
+    for route in [url.as_str(), "/app.js?v=outdated", "/app.js?lang=en&v"] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK, "{route}");
+        assert_eq!(
+            response.headers()[CACHE_CONTROL],
+            "public, max-age=31536000, immutable",
+            "{route}"
+        );
+    }
+
+    for route in ["/app.js", "/app.js?version=1"] {
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK, "{route}");
+        assert!(response.headers().get(CACHE_CONTROL).is_none(), "{route}");
+    }
+}
+
+#[tokio::test]
+async fn serves_immutable_aliases_keyed_by_etag() {
+    embed_assets!(
+        "../static-serve/test_assets/big",
+        cache_busted_paths = ["immutable"],
+        immutable_aliases = true
+    );
+    let router: Router<()> = static_router();
+
+    let hash: String = get_asset("/app.js")
+        .unwrap()
+        .etag()
+        .unwrap()
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(16)
+        .collect();
+    let alias = format!("/_immutable/{hash}/app.js");
+    assert_eq!(paths::APP_JS, alias);
+    assert_eq!(asset_url("/app.js"), Some(alias.as_str()));
+    assert!(STATIC_ROUTES.contains(&alias.as_str()));
+    // Already fingerprinted by their file names
+    assert_eq!(asset_url("/immutable/app.js"), Some("/immutable/app.js"));
+
+    let request = create_request(&alias, &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[CACHE_CONTROL],
+        "public, max-age=31536000, immutable"
+    );
+    let alias_body = response.into_body().collect().await.unwrap().to_bytes();
+
+    let request = create_request("/app.js", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(CACHE_CONTROL).is_none());
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    assert_eq!(alias_body, body);
+}
+
+#[tokio::test]
+async fn gates_assets_with_cfg() {
+    embed_assets!(
+        "../static-serve/test_assets",
+        ignore_paths = ["dist"],
+        cfg_gate = [("small", "not(test)"), ("big/immutable", "test")]
+    );
+    let router: Router<()> = static_router();
+
+    for route in ["/small/app.js", "/small/styles.css"] {
+        assert!(get_asset(route).is_none(), "{route}");
+        assert!(!STATIC_ROUTES.contains(&route), "{route}");
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND, "{route}");
+    }
+    assert!(
+        asset_manifest()
+            .iter()
+            .all(|asset| !asset.path.starts_with("/small/"))
+    );
+
+    for route in ["/big/app.js", "/big/immutable/app.js"] {
+        assert!(get_asset(route).is_some(), "{route}");
+        assert!(STATIC_ROUTES.contains(&route), "{route}");
+        let request = create_request(route, &Compression::None);
+        let response = get_response(router.clone(), request).await;
+        assert_eq!(response.status(), StatusCode::OK, "{route}");
+    }
+    assert_eq!(paths::BIG_IMMUTABLE_APP_JS, "/big/immutable/app.js");
+}
+
+#[tokio::test]
+async fn leaves_gated_assets_out_of_the_tarball_and_the_index() {
+    embed_assets!(
+        "../static-serve/test_assets",
+        ignore_paths = ["dist"],
+        cfg_gate = [("small", "not(test)")],
+        tarball = "/assets.tar.gz",
+        auto_index = true
+    );
+    let router: Router<()> = static_router();
+
+    let request = create_request("/assets.tar.gz", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let collected_body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let files = read_tar(&decompress_gzip(&collected_body_bytes));
+    assert!(files.iter().any(|(path, _)| path == "big/app.js"));
+    assert!(files.iter().all(|(path, _)| !path.starts_with("small/")));
+
+    let request = create_request("/", &Compression::None);
+    let response = get_response(router.clone(), request).await;
+    let collected_body_bytes = response.into_body().collect().await.unwrap().to_bytes();
+    let index = std::str::from_utf8(&collected_body_bytes).unwrap();
+    assert!(index.contains("<a href=\"./big/\">big/</a>"));
+    assert!(!index.contains("small"));
+
+    let request = create_request("/small/", &Compression::None);
+    let response = get_response(router, request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
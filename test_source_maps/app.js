@@ -0,0 +1,2 @@
+console.log("Hello, world!");
+//# sourceMappingURL=app.js.map
@@ -0,0 +1,3 @@
+export const API_URL = "{{API_URL}}";
+export const PACKAGE = "{{CARGO_PKG_NAME}}";
+export const TEMPLATE = "{{name}}";